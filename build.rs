@@ -1,5 +1,7 @@
 fn main() {
     // `dxguid` is needed for many of the included GUID definitions. MSVC
     // reports undefined external symbols while linking without this.
-    println!("cargo:rustc-link-lib=dylib=dxguid");
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") {
+        println!("cargo:rustc-link-lib=dylib=dxguid");
+    }
 }
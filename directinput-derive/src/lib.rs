@@ -0,0 +1,582 @@
+//! `#[derive(DeviceState)]` for the `directinput` crate.
+//!
+//! Generates a `#[repr(C)]` raw state structure, the matching data format and the
+//! `FromDeviceState` conversion for a struct whose fields are annotated with the device object
+//! they read:
+//!
+//! ```ignore
+//! use directinput::DeviceState;
+//!
+//! #[derive(DeviceState)]
+//! struct Gamepad {
+//!     #[axis(x)]
+//!     x: i32,
+//!     #[axis(y)]
+//!     y: i32,
+//!     #[axis(slider)]
+//!     sliders: [i32; 2],
+//!     #[pov(0)]
+//!     hat: u32,
+//!     #[button(0..16)]
+//!     buttons: [bool; 16],
+//! }
+//! ```
+//!
+//! Axes are `i32` or `[i32; N]` and match any instance of the given axis type unless an
+//! `instance = N` argument is given. POVs are `u32` or `[u32; N]`. Buttons are `bool` or `u8`
+//! (the raw value whose high bit is set while pressed), or arrays of either. POVs and buttons
+//! take a single instance number for scalar fields and a range for arrays. Adding
+//! `#[device_state(relative_axes)]` to the struct requests relative axis data.
+//!
+//! The raw state structure is named after the struct with a `Raw` suffix (`GamepadRaw` above)
+//! and has the same visibility.
+
+extern crate proc_macro;
+
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Ident, Lit, LitInt, Result, Token,
+    Type,
+};
+
+#[proc_macro_derive(DeviceState, attributes(axis, button, pov, device_state))]
+pub fn derive_device_state(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+const AXES: [(&str, &str); 7] = [
+    ("x", "GUID_XAxis"),
+    ("y", "GUID_YAxis"),
+    ("z", "GUID_ZAxis"),
+    ("rx", "GUID_RxAxis"),
+    ("ry", "GUID_RyAxis"),
+    ("rz", "GUID_RzAxis"),
+    ("slider", "GUID_Slider"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Elem {
+    I32,
+    U32,
+    U8,
+    Bool,
+}
+
+enum Object {
+    Axis { guid: Ident, instance: Option<u16> },
+    Button(Instances),
+    Pov(Instances),
+}
+
+/// A single instance number (`3`) or a half-open range of instances (`0..16`).
+struct Instances {
+    start: u16,
+    end: Option<u16>,
+}
+
+struct AxisArgs {
+    name: Ident,
+    instance: Option<u16>,
+}
+
+struct Field {
+    ident: Ident,
+    object: Object,
+    elem: Elem,
+    len: Option<usize>,
+    offset: u32,
+}
+
+impl Elem {
+    fn size(self) -> u32 {
+        match self {
+            Self::I32 | Self::U32 => 4,
+            Self::U8 | Self::Bool => 1,
+        }
+    }
+
+    fn raw_type(self) -> TokenStream2 {
+        match self {
+            Self::I32 => quote!(i32),
+            Self::U32 => quote!(u32),
+            Self::U8 | Self::Bool => quote!(u8),
+        }
+    }
+}
+
+impl Parse for Instances {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let start: LitInt = input.parse()?;
+        let start = start.base10_parse()?;
+
+        let end = if input.peek(Token![..]) {
+            input.parse::<Token![..]>()?;
+            let end: LitInt = input.parse()?;
+            let value: u16 = end.base10_parse()?;
+
+            if value <= start {
+                return Err(Error::new(end.span(), "instance range is empty"));
+            }
+
+            Some(value)
+        } else {
+            None
+        };
+
+        Ok(Self { start, end })
+    }
+}
+
+impl Parse for AxisArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+
+        let instance = if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            let key: Ident = input.parse()?;
+            if key != "instance" {
+                return Err(Error::new(key.span(), "expected `instance = N`"));
+            }
+            input.parse::<Token![=]>()?;
+            let value: LitInt = input.parse()?;
+
+            Some(value.base10_parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { name, instance })
+    }
+}
+
+fn parse_elem(ty: &Type) -> Option<Elem> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            let path = &path.path;
+
+            if path.is_ident("i32") {
+                Some(Elem::I32)
+            } else if path.is_ident("u32") {
+                Some(Elem::U32)
+            } else if path.is_ident("u8") {
+                Some(Elem::U8)
+            } else if path.is_ident("bool") {
+                Some(Elem::Bool)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Splits a field type into its element type and, for arrays, the array length.
+fn parse_type(ty: &Type) -> Option<(Elem, Option<usize>)> {
+    match ty {
+        Type::Array(array) => {
+            let len = match &array.len {
+                Expr::Lit(lit) => match &lit.lit {
+                    Lit::Int(len) => len.base10_parse().ok()?,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+
+            Some((parse_elem(&array.elem)?, Some(len)))
+        }
+        ty => Some((parse_elem(ty)?, None)),
+    }
+}
+
+fn parse_object(field: &syn::Field) -> Result<Object> {
+    let mut object = None;
+
+    for attr in &field.attrs {
+        let parsed = if attr.path.is_ident("axis") {
+            let args: AxisArgs = attr.parse_args()?;
+            let guid = AXES
+                .iter()
+                .find(|(name, _)| args.name == name)
+                .map(|(_, guid)| format_ident!("{}", guid))
+                .ok_or_else(|| {
+                    Error::new(
+                        args.name.span(),
+                        format!(
+                            "unknown axis `{}`, expected one of `x`, `y`, `z`, `rx`, `ry`, `rz` \
+                             or `slider`",
+                            args.name
+                        ),
+                    )
+                })?;
+
+            Object::Axis {
+                guid,
+                instance: args.instance,
+            }
+        } else if attr.path.is_ident("button") {
+            Object::Button(attr.parse_args()?)
+        } else if attr.path.is_ident("pov") {
+            Object::Pov(attr.parse_args()?)
+        } else {
+            continue;
+        };
+
+        if object.is_some() {
+            return Err(Error::new_spanned(
+                attr,
+                "only one of `#[axis(...)]`, `#[button(...)]` or `#[pov(...)]` is allowed",
+            ));
+        }
+        object = Some(parsed);
+    }
+
+    object.ok_or_else(|| {
+        Error::new_spanned(
+            field,
+            "expected one of `#[axis(...)]`, `#[button(...)]` or `#[pov(...)]`",
+        )
+    })
+}
+
+/// Checks that the field type is valid for the object and that the number of instances matches
+/// the array length.
+fn check_field(field: &syn::Field, object: &Object) -> Result<(Elem, Option<usize>)> {
+    let (expected, allowed): (&str, &[Elem]) = match object {
+        Object::Axis { .. } => ("axes must be `i32` or `[i32; N]`", &[Elem::I32]),
+        Object::Button(_) => (
+            "buttons must be `bool`, `u8`, `[bool; N]` or `[u8; N]`",
+            &[Elem::Bool, Elem::U8],
+        ),
+        Object::Pov(_) => ("POVs must be `u32` or `[u32; N]`", &[Elem::U32]),
+    };
+
+    let (elem, len) = parse_type(&field.ty)
+        .filter(|(elem, _)| allowed.contains(elem))
+        .ok_or_else(|| Error::new_spanned(&field.ty, expected))?;
+
+    let instances = match object {
+        Object::Button(instances) | Object::Pov(instances) => instances,
+        Object::Axis { .. } => return Ok((elem, len)),
+    };
+
+    match (instances.end, len) {
+        (None, None) => {}
+        (Some(end), Some(len)) if (end - instances.start) as usize == len => {}
+        (Some(end), Some(len)) => {
+            return Err(Error::new_spanned(
+                field,
+                format!(
+                    "instance range {}..{} has {} instances but the array has {} elements",
+                    instances.start,
+                    end,
+                    end - instances.start,
+                    len
+                ),
+            ))
+        }
+        (None, Some(_)) => {
+            return Err(Error::new_spanned(
+                field,
+                "array fields require a range of instances, e.g. `0..4`",
+            ))
+        }
+        (Some(_), None) => {
+            return Err(Error::new_spanned(
+                field,
+                "a range of instances requires an array field",
+            ))
+        }
+    }
+
+    Ok((elem, len))
+}
+
+fn parse_relative_axes(input: &DeriveInput) -> Result<bool> {
+    let mut relative = false;
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("device_state"))
+    {
+        let arg: Ident = attr.parse_args()?;
+
+        if arg == "relative_axes" {
+            relative = true;
+        } else if arg == "absolute_axes" {
+            relative = false;
+        } else {
+            return Err(Error::new(
+                arg.span(),
+                "expected `relative_axes` or `absolute_axes`",
+            ));
+        }
+    }
+
+    Ok(relative)
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.ident.span(),
+                    "`DeviceState` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "`DeviceState` can only be derived for structs with named fields",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`DeviceState` cannot be derived for generic structs",
+        ));
+    }
+    if fields.is_empty() {
+        return Err(Error::new(
+            input.ident.span(),
+            "`DeviceState` requires at least one field",
+        ));
+    }
+
+    let relative = parse_relative_axes(input)?;
+
+    let mut parsed = Vec::with_capacity(fields.len());
+    let mut buttons = HashSet::new();
+    let mut povs = HashSet::new();
+    let mut end = 0;
+
+    for field in fields {
+        let object = parse_object(field)?;
+        let (elem, len) = check_field(field, &object)?;
+
+        let used = match &object {
+            Object::Button(instances) => Some((&mut buttons, instances)),
+            Object::Pov(instances) => Some((&mut povs, instances)),
+            Object::Axis { .. } => None,
+        };
+        if let Some((used, instances)) = used {
+            for instance in instances.start..instances.end.unwrap_or(instances.start + 1) {
+                if !used.insert(instance) {
+                    return Err(Error::new_spanned(
+                        field,
+                        format!("instance {} is used more than once", instance),
+                    ));
+                }
+            }
+        }
+
+        // `#[repr(C)]` aligns each field to the size of its element
+        let size = elem.size();
+        let offset = (end + size - 1) & !(size - 1);
+        end = offset + size * len.unwrap_or(1) as u32;
+
+        parsed.push(Field {
+            ident: field.ident.clone().unwrap(),
+            object,
+            elem,
+            len,
+            offset,
+        });
+    }
+
+    let data_size = Literal::u32_unsuffixed((end + 3) & !3);
+
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let raw_ident = format_ident!("{}Raw", ident);
+    let raw_doc = format!("Raw state read by the data format of [`{}`].", ident);
+
+    let raw_fields = parsed.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = field.elem.raw_type();
+
+        match field.len {
+            Some(len) => {
+                let len = Literal::usize_unsuffixed(len);
+                quote!(pub #ident: [#ty; #len])
+            }
+            None => quote!(pub #ident: #ty),
+        }
+    });
+
+    let objects = parsed.iter().flat_map(|field| {
+        (0..field.len.unwrap_or(1)).map(move |i| {
+            let offset = Literal::u32_unsuffixed(field.offset + field.elem.size() * i as u32);
+            let instance = |start: u16| {
+                let instance = Literal::u16_unsuffixed(start + i as u16);
+                quote!(Some(#instance))
+            };
+
+            match &field.object {
+                Object::Axis { guid, instance: n } => {
+                    let n = n.map_or_else(|| quote!(None), instance);
+                    quote!(.axis(::directinput::__private::#guid, #n, #offset))
+                }
+                Object::Button(instances) => {
+                    let n = instance(instances.start);
+                    quote!(.button(#n, #offset))
+                }
+                Object::Pov(instances) => {
+                    let n = instance(instances.start);
+                    quote!(.pov(#n, #offset))
+                }
+            }
+        })
+    });
+
+    let conversions = parsed.iter().map(|field| {
+        let ident = &field.ident;
+
+        match (field.elem, field.len) {
+            (Elem::Bool, None) => quote!(#ident: state.#ident & 0x80 != 0),
+            (Elem::Bool, Some(len)) => {
+                let len = Literal::usize_unsuffixed(len);
+                quote! {
+                    #ident: {
+                        let mut buttons = [false; #len];
+                        for (button, raw) in buttons.iter_mut().zip(state.#ident.iter()) {
+                            *button = raw & 0x80 != 0;
+                        }
+                        buttons
+                    }
+                }
+            }
+            _ => quote!(#ident: state.#ident),
+        }
+    });
+
+    let relative = if relative {
+        quote!(.relative_axes())
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+        #[doc = #raw_doc]
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        #vis struct #raw_ident {
+            #(#raw_fields,)*
+        }
+
+        const _: [(); #data_size] = [(); ::core::mem::size_of::<#raw_ident>()];
+
+        impl ::directinput::FromDeviceState for #ident {
+            type RawState = #raw_ident;
+
+            fn data_format() -> ::directinput::DataFormat {
+                ::directinput::DataFormat::builder()
+                    #relative
+                    #(#objects)*
+                    .data_size(#data_size)
+                    .build()
+                    .expect("derived data format is valid")
+            }
+
+            fn from_instance(state: Self::RawState) -> Self {
+                Self {
+                    #(#conversions,)*
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let input: DeriveInput = parse_quote! {
+            #[device_state(relative_axes)]
+            pub struct Pad {
+                #[axis(x)]
+                x: i32,
+                #[button(0..2)]
+                buttons: [bool; 2],
+                #[axis(slider, instance = 1)]
+                slider: [i32; 2],
+                #[pov(0)]
+                hat: u32,
+                #[button(2)]
+                extra: u8,
+            }
+        };
+
+        let expected = quote! {
+            #[doc = "Raw state read by the data format of [`Pad`]."]
+            #[repr(C)]
+            #[derive(Clone, Copy)]
+            pub struct PadRaw {
+                pub x: i32,
+                pub buttons: [u8; 2],
+                pub slider: [i32; 2],
+                pub hat: u32,
+                pub extra: u8,
+            }
+
+            const _: [(); 24] = [(); ::core::mem::size_of::<PadRaw>()];
+
+            impl ::directinput::FromDeviceState for Pad {
+                type RawState = PadRaw;
+
+                fn data_format() -> ::directinput::DataFormat {
+                    ::directinput::DataFormat::builder()
+                        .relative_axes()
+                        .axis(::directinput::__private::GUID_XAxis, None, 0)
+                        .button(Some(0), 4)
+                        .button(Some(1), 5)
+                        .axis(::directinput::__private::GUID_Slider, Some(1), 8)
+                        .axis(::directinput::__private::GUID_Slider, Some(2), 12)
+                        .pov(Some(0), 16)
+                        .button(Some(2), 20)
+                        .data_size(24)
+                        .build()
+                        .expect("derived data format is valid")
+                }
+
+                fn from_instance(state: Self::RawState) -> Self {
+                    Self {
+                        x: state.x,
+                        buttons: {
+                            let mut buttons = [false; 2];
+                            for (button, raw) in buttons.iter_mut().zip(state.buttons.iter()) {
+                                *button = raw & 0x80 != 0;
+                            }
+                            buttons
+                        },
+                        slider: state.slider,
+                        hat: state.hat,
+                        extra: state.extra,
+                    }
+                }
+            }
+        };
+
+        assert_eq!(
+            expand(&input).expect("Failed to expand").to_string(),
+            expected.to_string()
+        );
+    }
+}
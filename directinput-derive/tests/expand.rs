@@ -0,0 +1,181 @@
+//! Checks the code generated for derived structs against the `directinput` crate: the raw state
+//! layout, the data format objects pointing into it and the conversion back to the struct.
+
+use std::mem;
+
+use directinput::__private::{GUID_RzAxis, GUID_Slider, GUID_XAxis, GUID_YAxis};
+use directinput::{DataFormat, DeviceState, FromDeviceState, ObjectKind, DIDFT_OPTIONAL};
+
+// From `dinput.h`
+const DIDFT_ANYINSTANCE: u32 = 0x00ff_ff00;
+const DIDF_ABSAXIS: u32 = 0x1;
+const DIDF_RELAXIS: u32 = 0x2;
+
+#[derive(Debug, DeviceState, PartialEq)]
+pub struct Gamepad {
+    #[axis(x)]
+    x: i32,
+    #[axis(y)]
+    y: i32,
+    #[button(0..3)]
+    buttons: [bool; 3],
+    #[axis(slider, instance = 1)]
+    sliders: [i32; 2],
+    #[button(3)]
+    trigger: u8,
+    #[pov(0..2)]
+    hats: [u32; 2],
+    #[button(4)]
+    select: bool,
+}
+
+#[derive(DeviceState)]
+#[device_state(relative_axes)]
+struct Wheel {
+    #[button(0)]
+    shift: bool,
+    #[axis(rz)]
+    rz: i32,
+}
+
+/// Offset of a field of a raw state, in the same unit as `ObjectFormat::offset`.
+fn offset_of<T, F>(raw: &T, field: &F) -> u32 {
+    (field as *const F as usize - raw as *const T as usize) as u32
+}
+
+fn gamepad_raw() -> GamepadRaw {
+    GamepadRaw {
+        x: -100,
+        y: 200,
+        buttons: [0x80, 0x00, 0xff],
+        sliders: [7, -7],
+        trigger: 0x80,
+        hats: [9000, u32::MAX],
+        select: 0x7f,
+    }
+}
+
+#[test]
+fn test_raw_layout() {
+    let raw = gamepad_raw();
+
+    assert_eq!(offset_of(&raw, &raw.x), 0);
+    assert_eq!(offset_of(&raw, &raw.y), 4);
+    assert_eq!(offset_of(&raw, &raw.buttons), 8);
+    // Padded to the alignment of the axes
+    assert_eq!(offset_of(&raw, &raw.sliders), 12);
+    assert_eq!(offset_of(&raw, &raw.trigger), 20);
+    assert_eq!(offset_of(&raw, &raw.hats), 24);
+    assert_eq!(offset_of(&raw, &raw.select), 32);
+    assert_eq!(mem::size_of::<GamepadRaw>(), 36);
+
+    let raw = WheelRaw { shift: 0, rz: 0 };
+    assert_eq!(offset_of(&raw, &raw.shift), 0);
+    assert_eq!(offset_of(&raw, &raw.rz), 4);
+    assert_eq!(mem::size_of::<WheelRaw>(), 8);
+}
+
+#[test]
+fn test_data_format() {
+    let format = Gamepad::data_format();
+
+    assert_eq!(format.flags(), DIDF_ABSAXIS);
+    assert_eq!(format.data_size() as usize, mem::size_of::<GamepadRaw>());
+    assert_eq!(format.validate(), Ok(()));
+
+    let objects: Vec<_> = format
+        .objects()
+        .iter()
+        .map(|object| {
+            (
+                object.guid,
+                object.offset,
+                object.kind(),
+                object.ty & !DIDFT_OPTIONAL & !object.kind().unwrap().type_mask(),
+            )
+        })
+        .collect();
+
+    let instance = |n: u32| n << 8;
+    // Whatever the builder uses for POVs, which the derive goes through
+    let pov = DataFormat::builder()
+        .pov(None, 0)
+        .data_size(4)
+        .build()
+        .unwrap()
+        .objects()[0]
+        .guid;
+    assert_eq!(
+        objects,
+        [
+            (
+                Some(GUID_XAxis),
+                0,
+                Some(ObjectKind::Axis),
+                DIDFT_ANYINSTANCE
+            ),
+            (
+                Some(GUID_YAxis),
+                4,
+                Some(ObjectKind::Axis),
+                DIDFT_ANYINSTANCE
+            ),
+            (None, 8, Some(ObjectKind::Button), instance(0)),
+            (None, 9, Some(ObjectKind::Button), instance(1)),
+            (None, 10, Some(ObjectKind::Button), instance(2)),
+            (Some(GUID_Slider), 12, Some(ObjectKind::Axis), instance(1)),
+            (Some(GUID_Slider), 16, Some(ObjectKind::Axis), instance(2)),
+            (None, 20, Some(ObjectKind::Button), instance(3)),
+            (pov, 24, Some(ObjectKind::Pov), instance(0)),
+            (pov, 28, Some(ObjectKind::Pov), instance(1)),
+            (None, 32, Some(ObjectKind::Button), instance(4)),
+        ]
+    );
+
+    let format = Wheel::data_format();
+    assert_eq!(format.flags(), DIDF_RELAXIS);
+    assert_eq!(format.data_size(), 8);
+    assert_eq!(format.objects()[1].guid, Some(GUID_RzAxis));
+    assert_eq!(format.objects()[1].offset, 4);
+}
+
+#[test]
+fn test_decode_matches_raw() {
+    // The objects of the format read the values of the raw state's fields
+    let raw = gamepad_raw();
+    let bytes = unsafe {
+        std::slice::from_raw_parts(
+            &raw as *const GamepadRaw as *const u8,
+            mem::size_of::<GamepadRaw>(),
+        )
+    };
+
+    let decoded = Gamepad::data_format().decode(bytes);
+    assert_eq!(decoded.axes, [-100, 200, 7, -7]);
+    assert_eq!(decoded.buttons, [0x80, 0x00, 0xff, 0x80, 0x7f]);
+    assert_eq!(decoded.povs, [9000, u32::MAX]);
+}
+
+#[test]
+fn test_from_instance() {
+    assert_eq!(
+        Gamepad::from_instance(gamepad_raw()),
+        Gamepad {
+            x: -100,
+            y: 200,
+            buttons: [true, false, true],
+            sliders: [7, -7],
+            trigger: 0x80,
+            hats: [9000, u32::MAX],
+            // Only the high bit counts
+            select: false,
+        }
+    );
+
+    let wheel = Wheel::from_instance(WheelRaw {
+        shift: 0x80,
+        rz: -5,
+    });
+    assert!(wheel.shift);
+    assert_eq!(wheel.rz, -5);
+}
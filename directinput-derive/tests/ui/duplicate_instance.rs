@@ -0,0 +1,11 @@
+use directinput_derive::DeviceState;
+
+#[derive(DeviceState)]
+struct Pad {
+    #[button(0..4)]
+    buttons: [bool; 4],
+    #[button(3)]
+    extra: bool,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use directinput_derive::DeviceState;
+
+#[derive(DeviceState)]
+struct Pad {
+    #[axis(x)]
+    x: i32,
+    y: i32,
+}
+
+fn main() {}
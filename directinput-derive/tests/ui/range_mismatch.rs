@@ -0,0 +1,9 @@
+use directinput_derive::DeviceState;
+
+#[derive(DeviceState)]
+struct Pad {
+    #[button(0..8)]
+    buttons: [bool; 16],
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use directinput_derive::DeviceState;
+
+#[derive(DeviceState)]
+struct Pad(#[axis(x)] i32);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use directinput_derive::DeviceState;
+
+#[derive(DeviceState)]
+struct Pad {
+    #[axis(w)]
+    w: i32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use directinput_derive::DeviceState;
+
+#[derive(DeviceState)]
+struct Pad {
+    #[axis(x)]
+    x: f32,
+}
+
+fn main() {}
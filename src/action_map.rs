@@ -0,0 +1,641 @@
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use bitflags::bitflags;
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DIACTIONFORMATW, DIACTIONW, DIACTIONW_0, DIAH_APPREQUESTED, DIAH_DEFAULT, DIAH_ERROR,
+    DIAH_HWAPP, DIAH_HWDEFAULT, DIAH_USERCONFIG, DIA_APPFIXED, DIA_APPMAPPED, DIA_APPNOMAP,
+    DIA_FORCEFEEDBACK, DIA_NORANGE, DIDBAM_HWDEFAULTS, DIDBAM_INITIALIZE, DIDBAM_PRESERVE,
+    DIDSAM_FORCESAVE, DIDSAM_NOUSER, DIEDBSFL_AVAILABLEDEVICES, DIEDBSFL_FORCEFEEDBACK,
+    DIEDBSFL_MULTIMICEKEYBOARDS, DIEDBSFL_NONGAMINGDEVICES, DIEDBSFL_THISUSER, DIEDBS_MAPPEDPRI1,
+    DIEDBS_MAPPEDPRI2, DIEDBS_NEWDEVICE, DIEDBS_RECENTDEVICE,
+};
+use windows::Win32::Foundation::PWSTR;
+
+use crate::data_format::ObjectKind;
+use crate::device_guid::DeviceGuid;
+use crate::device_info::DirectInputDeviceInfo;
+use crate::util::{copy_to_wide, to_wide_nul};
+
+/// Axis range applied to mapped axes unless changed with [`ActionFormatBuilder::axis_range`].
+const DEFAULT_AXIS_RANGE: (i32, i32) = (0, 0xffff);
+
+/// `tszActionMap` holds `MAX_PATH` characters including the terminator.
+const MAX_NAME_LEN: usize = 259;
+
+/// Semantic genres that may be used with any action format genre: keyboard, mouse, voice and
+/// the `DIAXIS_ANY_*` family.
+const SHARED_GENRES: [u8; 4] = [0x81, 0x82, 0x83, 0xff];
+
+pub(crate) const GENRE_KEYBOARD: u8 = 0x81;
+
+bitflags! {
+    /// `DIA_*` flags of an [`Action`].
+    pub struct ActionFlags: u32 {
+        /// The action is an effect that requires a force-feedback capable axis.
+        const FORCE_FEEDBACK = DIA_FORCEFEEDBACK;
+        /// The application supplied the mapping in the [`ActionMap`] and it must be kept.
+        const APP_MAPPED = DIA_APPMAPPED;
+        /// The action is not mapped by DirectInput.
+        const APP_NO_MAP = DIA_APPNOMAP;
+        /// The axis range of the action format is not applied to the mapped axis.
+        const NO_RANGE = DIA_NORANGE;
+        /// The user cannot change the mapping in the configuration UI.
+        const APP_FIXED = DIA_APPFIXED;
+    }
+}
+
+bitflags! {
+    /// `DIAH_*` origin of an [`ActionMapping`]. Empty if the action is not mapped.
+    #[derive(Default)]
+    pub struct MappingSource: u32 {
+        const USER_CONFIG = DIAH_USERCONFIG;
+        const APP_REQUESTED = DIAH_APPREQUESTED;
+        const HW_APP = DIAH_HWAPP;
+        const HW_DEFAULT = DIAH_HWDEFAULT;
+        const DEFAULT = DIAH_DEFAULT;
+        /// The requested mapping could not be applied.
+        const ERROR = DIAH_ERROR;
+    }
+}
+
+bitflags! {
+    /// `DIDBAM_*` flags for [`Device::build_action_map`](crate::Device::build_action_map).
+    /// Empty flags (`DIDBAM_DEFAULT`) overwrite every mapping except those flagged
+    /// [`ActionFlags::APP_MAPPED`].
+    pub struct BuildActionMapFlags: u32 {
+        /// Keep the mappings already present in the map.
+        const PRESERVE = DIDBAM_PRESERVE;
+        /// Overwrite every mapping, including application-requested ones.
+        const INITIALIZE = DIDBAM_INITIALIZE;
+        /// Ignore user configuration and use the hardware defaults.
+        const HW_DEFAULTS = DIDBAM_HWDEFAULTS;
+    }
+}
+
+bitflags! {
+    /// `DIDSAM_*` flags for [`Device::set_action_map`](crate::Device::set_action_map).
+    pub struct SetActionMapFlags: u32 {
+        /// Clear the user that owns the device instead of assigning it to the given user.
+        const NO_USER = DIDSAM_NOUSER;
+        /// Save the configuration even if it has not changed.
+        const FORCE_SAVE = DIDSAM_FORCESAVE;
+    }
+}
+
+bitflags! {
+    /// `DIEDBSFL_*` filters for
+    /// [`DirectInputManager::enum_devices_by_semantics`](crate::DirectInputManager::enum_devices_by_semantics).
+    /// Empty flags enumerate the attached devices only.
+    pub struct SemanticEnumFlags: u32 {
+        /// Only devices assigned to the given user.
+        const THIS_USER = DIEDBSFL_THISUSER;
+        /// Only force-feedback capable devices.
+        const FORCE_FEEDBACK = DIEDBSFL_FORCEFEEDBACK;
+        /// Only devices that are not assigned to a user.
+        const AVAILABLE_DEVICES = DIEDBSFL_AVAILABLEDEVICES;
+        /// Report every mouse and keyboard instead of the system mouse and keyboard only.
+        const MULTI_MICE_KEYBOARDS = DIEDBSFL_MULTIMICEKEYBOARDS;
+        /// Include devices that are not game controllers.
+        const NON_GAMING_DEVICES = DIEDBSFL_NONGAMINGDEVICES;
+    }
+}
+
+bitflags! {
+    /// `DIEDBS_*` flags describing how well a [`SemanticDevice`] suits the action format.
+    pub struct SemanticDeviceFlags: u32 {
+        /// The device has objects for priority 1 actions.
+        const MAPPED_PRI1 = DIEDBS_MAPPEDPRI1;
+        /// The device has objects for priority 2 actions.
+        const MAPPED_PRI2 = DIEDBS_MAPPEDPRI2;
+        /// The device was used recently with this action format.
+        const RECENT_DEVICE = DIEDBS_RECENTDEVICE;
+        /// The device was installed after the last use of this action format.
+        const NEW_DEVICE = DIEDBS_NEWDEVICE;
+    }
+}
+
+/// An application action bound to a semantic, equivalent to `DIACTION`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Action {
+    /// Application-defined identifier reported as [`DeviceEvent::action`] for events of the
+    /// object the action is mapped to (`uAppData`).
+    ///
+    /// [`DeviceEvent::action`]: crate::DeviceEvent::action
+    pub id: usize,
+    /// `DIAXIS_*`, `DIBUTTON_*`, `DIHATSWITCH_*`, `DIKEYBOARD_*` or `DIMOUSE_*` semantic.
+    pub semantic: u32,
+    pub flags: ActionFlags,
+    /// Name shown in the device configuration UI.
+    pub name: String,
+}
+
+/// A validated set of actions for a game genre, equivalent to `DIACTIONFORMAT`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActionFormat {
+    guid: GUID,
+    genre: u32,
+    name: String,
+    actions: Vec<Action>,
+    buffer_size: u32,
+    axis_range: (i32, i32),
+}
+
+/// Builds a validated [`ActionFormat`].
+#[derive(Clone, Debug)]
+pub struct ActionFormatBuilder {
+    format: ActionFormat,
+}
+
+/// Reasons an action format is rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ActionFormatError {
+    /// The format declares no actions.
+    Empty,
+    /// The genre is not a `DIVIRTUAL_*` value.
+    InvalidGenre(u32),
+    /// The semantic does not describe an axis, a button or a POV.
+    InvalidSemantic { index: usize, semantic: u32 },
+    /// The semantic belongs to a genre other than the format's.
+    GenreMismatch { index: usize, semantic: u32 },
+    /// The format name is longer than 259 UTF-16 code units.
+    NameTooLong(usize),
+    /// The axis range minimum is not below the maximum.
+    InvalidAxisRange { min: i32, max: i32 },
+}
+
+/// Where an [`Action`] is mapped, as filled in by
+/// [`Device::build_action_map`](crate::Device::build_action_map).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ActionMapping {
+    /// Instance GUID of the device the action is mapped to.
+    pub device: Option<DeviceGuid>,
+    /// `DIDFT_*` type and instance ID of the object the action is mapped to.
+    pub object_id: u32,
+    pub source: MappingSource,
+}
+
+/// An [`ActionFormat`] together with the mapping of each of its actions to a device.
+///
+/// DirectInput maps actions to one device at a time, so each device needs its own map, created
+/// with [`ActionMap::new`] and filled in by
+/// [`Device::build_action_map`](crate::Device::build_action_map).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActionMap {
+    format: ActionFormat,
+    mappings: Vec<ActionMapping>,
+}
+
+/// A device reported by
+/// [`DirectInputManager::enum_devices_by_semantics`](crate::DirectInputManager::enum_devices_by_semantics).
+#[derive(Clone, Debug)]
+pub struct SemanticDevice {
+    pub info: DirectInputDeviceInfo,
+    pub flags: SemanticDeviceFlags,
+}
+
+/// A `DIACTIONFORMATW` built from an [`ActionMap`], owning the action array and names it
+/// points to.
+pub(crate) struct RawActionFormat {
+    format: DIACTIONFORMATW,
+    actions: Vec<DIACTIONW>,
+    _names: Vec<Vec<u16>>,
+}
+
+/// Kind of object a semantic maps to, from the `DISEM_TYPE` bits.
+pub(crate) fn semantic_kind(semantic: u32) -> Option<ObjectKind> {
+    match (semantic >> 9) & 3 {
+        1 => Some(ObjectKind::Axis),
+        2 => Some(ObjectKind::Button),
+        3 => Some(ObjectKind::Pov),
+        _ => None,
+    }
+}
+
+/// Genre of a semantic (`DISEM_GENRE`), the high byte of the `DIVIRTUAL_*` genres.
+pub(crate) fn semantic_genre(semantic: u32) -> u8 {
+    (semantic >> 24) as u8
+}
+
+/// Whether the semantic is a priority 2 action (`DISEM_PRI`).
+pub(crate) fn semantic_is_priority2(semantic: u32) -> bool {
+    semantic & 0x4000 != 0
+}
+
+impl Action {
+    pub fn new(id: usize, semantic: u32, name: &str) -> Self {
+        Self {
+            id,
+            semantic,
+            flags: ActionFlags::empty(),
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn with_flags(mut self, flags: ActionFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+}
+
+impl ActionFormat {
+    /// Starts a format identified by `guid`, which DirectInput uses to store the user's
+    /// configuration, for a `DIVIRTUAL_*` genre.
+    pub fn builder(guid: GUID, genre: u32) -> ActionFormatBuilder {
+        ActionFormatBuilder::new(guid, genre)
+    }
+
+    pub fn guid(&self) -> GUID {
+        self.guid
+    }
+
+    pub fn genre(&self) -> u32 {
+        self.genre
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub fn buffer_size(&self) -> u32 {
+        self.buffer_size
+    }
+
+    pub fn axis_range(&self) -> (i32, i32) {
+        self.axis_range
+    }
+
+    fn validate(&self) -> Result<(), ActionFormatError> {
+        if self.actions.is_empty() {
+            return Err(ActionFormatError::Empty);
+        }
+        if self.genre == 0 || self.genre & 0x00ff_ffff != 0 {
+            return Err(ActionFormatError::InvalidGenre(self.genre));
+        }
+
+        let name_len = self.name.encode_utf16().count();
+        if name_len > MAX_NAME_LEN {
+            return Err(ActionFormatError::NameTooLong(name_len));
+        }
+
+        let (min, max) = self.axis_range;
+        if min >= max {
+            return Err(ActionFormatError::InvalidAxisRange { min, max });
+        }
+
+        let genre = semantic_genre(self.genre);
+        for (index, action) in self.actions.iter().enumerate() {
+            let semantic = action.semantic;
+
+            if semantic_kind(semantic).is_none() {
+                return Err(ActionFormatError::InvalidSemantic { index, semantic });
+            }
+
+            let semantic_genre = semantic_genre(semantic);
+            if semantic_genre != genre && !SHARED_GENRES.contains(&semantic_genre) {
+                return Err(ActionFormatError::GenreMismatch { index, semantic });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ActionFormatBuilder {
+    pub fn new(guid: GUID, genre: u32) -> Self {
+        Self {
+            format: ActionFormat {
+                guid,
+                genre,
+                name: String::new(),
+                actions: Vec::new(),
+                buffer_size: 0,
+                axis_range: DEFAULT_AXIS_RANGE,
+            },
+        }
+    }
+
+    /// Sets the name of the format shown in the device configuration UI.
+    pub fn name(mut self, name: &str) -> Self {
+        self.format.name = name.to_owned();
+        self
+    }
+
+    /// Declares an action reported with `id` when the object mapped to `semantic` changes.
+    /// Several actions may share an id, e.g. to bind the same action on keyboard and gamepad.
+    pub fn action(self, id: usize, semantic: u32, name: &str) -> Self {
+        self.push(Action::new(id, semantic, name))
+    }
+
+    pub fn push(mut self, action: Action) -> Self {
+        self.format.actions.push(action);
+        self
+    }
+
+    /// Sets the buffer size of devices the format is applied to, in events. Defaults to zero,
+    /// which disables buffered events.
+    pub fn buffer_size(mut self, size: u32) -> Self {
+        self.format.buffer_size = size;
+        self
+    }
+
+    /// Sets the range of mapped axes. Defaults to `0..=0xffff`.
+    pub fn axis_range(mut self, min: i32, max: i32) -> Self {
+        self.format.axis_range = (min, max);
+        self
+    }
+
+    pub fn build(self) -> Result<ActionFormat, ActionFormatError> {
+        self.format.validate()?;
+
+        Ok(self.format)
+    }
+}
+
+impl ActionMapping {
+    /// Whether the action is mapped to an object without error.
+    pub fn is_mapped(&self) -> bool {
+        self.device.is_some()
+            && !self.source.is_empty()
+            && !self.source.contains(MappingSource::ERROR)
+    }
+}
+
+impl ActionMap {
+    /// Creates a map with every action unmapped.
+    pub fn new(format: ActionFormat) -> Self {
+        let mappings = vec![ActionMapping::default(); format.actions.len()];
+
+        Self { format, mappings }
+    }
+
+    pub fn format(&self) -> &ActionFormat {
+        &self.format
+    }
+
+    /// The mapping of each action, in the order of [`ActionFormat::actions`].
+    pub fn mappings(&self) -> &[ActionMapping] {
+        &self.mappings
+    }
+
+    /// Iterates over the actions that are mapped, along with their mapping.
+    pub fn mapped(&self) -> impl Iterator<Item = (&Action, &ActionMapping)> {
+        self.format
+            .actions
+            .iter()
+            .zip(&self.mappings)
+            .filter(|(_, mapping)| mapping.is_mapped())
+    }
+
+    /// Replaces the mapping of the action at `index`, e.g. to apply a mapping chosen by the
+    /// user before calling [`Device::set_action_map`](crate::Device::set_action_map).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_mapping(&mut self, index: usize, mapping: ActionMapping) {
+        self.mappings[index] = mapping;
+    }
+
+    pub(crate) fn to_raw(&self) -> RawActionFormat {
+        let format = &self.format;
+
+        let mut names: Vec<_> = format
+            .actions
+            .iter()
+            .map(|action| to_wide_nul(&action.name))
+            .collect();
+        let mut actions: Vec<_> = format
+            .actions
+            .iter()
+            .zip(&self.mappings)
+            .zip(&mut names)
+            .map(|((action, mapping), name)| DIACTIONW {
+                uAppData: action.id,
+                dwSemantic: action.semantic,
+                dwFlags: action.flags.bits(),
+                Anonymous: DIACTIONW_0 {
+                    lptszActionName: PWSTR(name.as_mut_ptr()),
+                },
+                guidInstance: mapping.device.map_or_else(GUID::zeroed, GUID::from),
+                dwObjID: mapping.object_id,
+                dwHow: mapping.source.bits(),
+            })
+            .collect();
+
+        let mut raw = DIACTIONFORMATW {
+            dwSize: mem::size_of::<DIACTIONFORMATW>() as _,
+            dwActionSize: mem::size_of::<DIACTIONW>() as _,
+            dwDataSize: (actions.len() * mem::size_of::<u32>()) as _,
+            dwNumActions: actions.len() as _,
+            rgoAction: actions.as_mut_ptr(),
+            guidActionMap: format.guid,
+            dwGenre: format.genre,
+            dwBufferSize: format.buffer_size,
+            lAxisMin: format.axis_range.0,
+            lAxisMax: format.axis_range.1,
+            ..Default::default()
+        };
+        copy_to_wide(&mut raw.tszActionMap, &format.name);
+
+        RawActionFormat {
+            format: raw,
+            actions,
+            _names: names,
+        }
+    }
+
+    /// Reads back the mappings DirectInput wrote into `raw`.
+    pub(crate) fn update_from_raw(&mut self, raw: &RawActionFormat) {
+        for (mapping, action) in self.mappings.iter_mut().zip(&raw.actions) {
+            let device = DeviceGuid::from_guid(action.guidInstance);
+
+            *mapping = ActionMapping {
+                device: if device.to_u128() == 0 {
+                    None
+                } else {
+                    Some(device)
+                },
+                object_id: action.dwObjID,
+                source: MappingSource::from_bits_truncate(action.dwHow),
+            };
+        }
+    }
+}
+
+impl RawActionFormat {
+    pub(crate) fn as_mut_raw(&mut self) -> &mut DIACTIONFORMATW {
+        &mut self.format
+    }
+}
+
+impl fmt::Display for ActionFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("action format has no actions"),
+            Self::InvalidGenre(genre) => write!(f, "invalid genre {:#010x}", genre),
+            Self::InvalidSemantic { index, semantic } => write!(
+                f,
+                "action {} has invalid semantic {:#010x}",
+                index, semantic
+            ),
+            Self::GenreMismatch { index, semantic } => write!(
+                f,
+                "semantic {:#010x} of action {} does not belong to the format genre",
+                semantic, index
+            ),
+            Self::NameTooLong(len) => write!(f, "name of {} characters is too long", len),
+            Self::InvalidAxisRange { min, max } => {
+                write!(f, "axis range {}..{} is empty", min, max)
+            }
+        }
+    }
+}
+
+impl Error for ActionFormatError {}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::{
+        DIAXIS_ANY_X_1, DIAXIS_DRIVINGR_ACCELERATE, DIAXIS_DRIVINGR_STEER, DIAXIS_FLYINGM_BANK,
+        DIBUTTON_DRIVINGR_PAUSE, DIBUTTON_DRIVINGR_SHIFTUP, DIHATSWITCH_DRIVINGR_GLANCE,
+        DIKEYBOARD_A, DIVIRTUAL_DRIVING_RACE,
+    };
+
+    use super::*;
+
+    const GUID_TEST_MAP: GUID = GUID::from_values(0x1234_5678, 0x9abc, 0xdef0, *b"ACTIONS\0");
+
+    fn builder() -> ActionFormatBuilder {
+        ActionFormat::builder(GUID_TEST_MAP, DIVIRTUAL_DRIVING_RACE)
+    }
+
+    #[test]
+    fn test_semantic_decoding() {
+        assert_eq!(semantic_kind(DIAXIS_DRIVINGR_STEER), Some(ObjectKind::Axis));
+        assert_eq!(
+            semantic_kind(DIBUTTON_DRIVINGR_SHIFTUP),
+            Some(ObjectKind::Button)
+        );
+        assert_eq!(
+            semantic_kind(DIHATSWITCH_DRIVINGR_GLANCE),
+            Some(ObjectKind::Pov)
+        );
+        assert_eq!(semantic_kind(DIKEYBOARD_A), Some(ObjectKind::Button));
+        assert_eq!(semantic_kind(0x0100_0001), None);
+
+        assert_eq!(semantic_genre(DIAXIS_DRIVINGR_STEER), 0x01);
+        assert_eq!(semantic_genre(DIKEYBOARD_A), GENRE_KEYBOARD);
+        assert!(!semantic_is_priority2(DIAXIS_DRIVINGR_STEER));
+        assert!(semantic_is_priority2(DIBUTTON_DRIVINGR_PAUSE));
+    }
+
+    #[test]
+    fn test_validation() {
+        let format = builder()
+            .name("Racing")
+            .action(1, DIAXIS_DRIVINGR_STEER, "Steer")
+            .action(2, DIAXIS_DRIVINGR_ACCELERATE, "Accelerate")
+            .action(3, DIKEYBOARD_A, "Accelerate (keyboard)")
+            .push(Action::new(4, DIAXIS_ANY_X_1, "Look").with_flags(ActionFlags::NO_RANGE))
+            .buffer_size(16)
+            .build()
+            .expect("Failed to build action format");
+        assert_eq!(format.actions().len(), 4);
+        assert_eq!(format.axis_range(), DEFAULT_AXIS_RANGE);
+
+        assert_eq!(builder().build(), Err(ActionFormatError::Empty));
+        assert_eq!(
+            ActionFormat::builder(GUID_TEST_MAP, 0x0100_0001)
+                .action(1, DIAXIS_DRIVINGR_STEER, "Steer")
+                .build(),
+            Err(ActionFormatError::InvalidGenre(0x0100_0001))
+        );
+        assert_eq!(
+            builder()
+                .action(1, DIAXIS_DRIVINGR_STEER, "Steer")
+                .action(2, 0x0100_0001, "Nothing")
+                .build(),
+            Err(ActionFormatError::InvalidSemantic {
+                index: 1,
+                semantic: 0x0100_0001
+            })
+        );
+        assert_eq!(
+            builder().action(1, DIAXIS_FLYINGM_BANK, "Bank").build(),
+            Err(ActionFormatError::GenreMismatch {
+                index: 0,
+                semantic: DIAXIS_FLYINGM_BANK
+            })
+        );
+        assert_eq!(
+            builder()
+                .action(1, DIAXIS_DRIVINGR_STEER, "Steer")
+                .axis_range(10, -10)
+                .build(),
+            Err(ActionFormatError::InvalidAxisRange { min: 10, max: -10 })
+        );
+        assert_eq!(
+            builder()
+                .name(&"x".repeat(260))
+                .action(1, DIAXIS_DRIVINGR_STEER, "Steer")
+                .build(),
+            Err(ActionFormatError::NameTooLong(260))
+        );
+    }
+
+    #[test]
+    fn test_raw_round_trip() {
+        let format = builder()
+            .name("Racing")
+            .action(7, DIAXIS_DRIVINGR_STEER, "Steer")
+            .action(8, DIBUTTON_DRIVINGR_SHIFTUP, "Shift up")
+            .axis_range(-100, 100)
+            .build()
+            .unwrap();
+        let mut map = ActionMap::new(format);
+        let device = DeviceGuid::from_u128(0x42);
+        map.set_mapping(
+            1,
+            ActionMapping {
+                device: Some(device),
+                object_id: 0x0104,
+                source: MappingSource::APP_REQUESTED,
+            },
+        );
+
+        let mut raw = map.to_raw();
+        let format = raw.as_mut_raw();
+        assert_eq!(format.dwNumActions, 2);
+        assert_eq!(format.dwDataSize, 8);
+        assert_eq!(format.dwGenre, DIVIRTUAL_DRIVING_RACE);
+        assert_eq!((format.lAxisMin, format.lAxisMax), (-100, 100));
+        assert_eq!(&format.tszActionMap[..7], &to_wide_nul("Racing")[..]);
+
+        let actions = unsafe { std::slice::from_raw_parts_mut(format.rgoAction, 2) };
+        assert_eq!(actions[0].uAppData, 7);
+        assert_eq!(actions[1].dwHow, DIAH_APPREQUESTED);
+        let name = unsafe { std::slice::from_raw_parts(actions[1].Anonymous.lptszActionName.0, 9) };
+        assert_eq!(name, &to_wide_nul("Shift up")[..]);
+
+        // DirectInput writes the mapping of the first action
+        actions[0].guidInstance = device.into();
+        actions[0].dwObjID = 0x0002;
+        actions[0].dwHow = DIAH_DEFAULT;
+        let mut updated = map.clone();
+        updated.update_from_raw(&raw);
+
+        let mapped: Vec<_> = updated
+            .mapped()
+            .map(|(action, mapping)| (action.id, mapping.object_id))
+            .collect();
+        assert_eq!(mapped, [(7, 0x0002), (8, 0x0104)]);
+        assert_eq!(updated.mappings()[1], map.mappings()[1]);
+    }
+}
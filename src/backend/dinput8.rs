@@ -0,0 +1,688 @@
+use std::convert::TryInto;
+use std::ffi::c_void;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::task::Waker;
+use std::time::Duration;
+
+use windows::core::{Handle, Interface, RawPtr, GUID, HRESULT};
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DirectInput8Create, IDirectInput8W, IDirectInputDevice8W, IDirectInputEffect, DIACTIONFORMATW,
+    DIDATAFORMAT, DIDEVCAPS, DIDEVICEINSTANCEW, DIDEVICEOBJECTDATA, DIDEVICEOBJECTINSTANCEW,
+    DIEFFECT, DIEFFECTINFOW, DIENUM_CONTINUE, DIENUM_STOP, DIPROPHEADER, DIRECTINPUT_VERSION,
+};
+use windows::Win32::Foundation::{
+    CloseHandle, GetLastError, BOOL, BOOLEAN, HANDLE, HWND, INVALID_HANDLE_VALUE, PWSTR,
+};
+use windows::Win32::System::Threading::{
+    CreateEventW, RegisterWaitForSingleObject, SetEvent, UnregisterWaitEx, WaitForSingleObject,
+    WAIT_OBJECT_0, WT_EXECUTEDEFAULT,
+};
+use windows::Win32::System::WindowsProgramming::INFINITE;
+
+use super::{Backend, DeviceBackend, EffectBackend};
+use crate::error::{DirectInputError, DirectInputStatus, HResultContext, Result};
+use crate::manager::IntoModuleInstance;
+use crate::util::to_wide_nul;
+
+/// [`Backend`] implementation using the system DirectInput 8 runtime.
+#[derive(Debug)]
+pub struct DirectInput8Backend {
+    iface: IDirectInput8W,
+}
+
+pub(crate) struct DirectInput8Device {
+    iface: IDirectInputDevice8W,
+    event: Option<HANDLE>,
+    // Behind a mutex so waiting on the event directly can release it through `&self`
+    event_wait: Mutex<Option<EventWait>>,
+}
+
+/// Thread pool wait on the notification event registered by `poll_event`, which wakes the task
+/// polling the device instead of blocking a thread on the event.
+struct EventWait {
+    handle: HANDLE,
+    // Boxed so the pointer passed to the callback stays valid while the wait is registered
+    shared: Box<EventWaitShared>,
+}
+
+#[derive(Default)]
+struct EventWaitShared {
+    signalled: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+struct DirectInput8Effect {
+    iface: IDirectInputEffect,
+}
+
+// The device interface is only ever used from the thread currently owning the `Device`.
+unsafe impl Send for DirectInput8Device {}
+
+// Same as the device, the effect interface is only used by the thread owning the `EffectHandle`.
+unsafe impl Send for DirectInput8Effect {}
+
+impl DirectInput8Backend {
+    pub fn new(instance: impl IntoModuleInstance) -> Result<Self> {
+        let mut iface: Option<IDirectInput8W> = None;
+
+        unsafe {
+            DirectInput8Create(
+                instance.into_instance(),
+                DIRECTINPUT_VERSION,
+                &IDirectInput8W::IID,
+                &mut iface as *mut _ as _,
+                None,
+            )?;
+        };
+
+        match iface {
+            Some(iface) => Ok(Self { iface }),
+            None => Err(DirectInputError::BadDriverVersion),
+        }
+    }
+}
+
+impl Backend for DirectInput8Backend {
+    fn enum_devices(
+        &self,
+        filter: u32,
+        flags: u32,
+        callback: &mut dyn FnMut(&DIDEVICEINSTANCEW) -> bool,
+    ) -> Result<()> {
+        type Callback<'a> = &'a mut dyn FnMut(&DIDEVICEINSTANCEW) -> bool;
+
+        extern "system" fn enumeration_callback(
+            device_instance: *mut DIDEVICEINSTANCEW,
+            ctx: *mut c_void,
+        ) -> BOOL {
+            let callback = unsafe { &mut *(ctx as *mut Callback) };
+
+            if device_instance.is_null() || callback(unsafe { &*device_instance }) {
+                BOOL(DIENUM_CONTINUE as _)
+            } else {
+                BOOL(DIENUM_STOP as _)
+            }
+        }
+
+        let mut callback: Callback = callback;
+
+        unsafe {
+            self.iface.EnumDevices(
+                filter,
+                Some(enumeration_callback),
+                &mut callback as *mut Callback as _,
+                flags,
+            )?;
+        };
+
+        Ok(())
+    }
+
+    fn create_device(&self, guid_instance: &GUID) -> Result<Box<dyn DeviceBackend>> {
+        let mut iface: Option<IDirectInputDevice8W> = None;
+
+        unsafe {
+            self.iface.CreateDevice(guid_instance, &mut iface, None)?;
+        };
+
+        match iface {
+            Some(iface) => Ok(Box::new(DirectInput8Device {
+                iface,
+                event: None,
+                event_wait: Mutex::new(None),
+            })),
+            None => Err(DirectInputError::InputLost),
+        }
+    }
+
+    fn get_device_status(&self, guid_instance: &GUID) -> Result<DirectInputStatus> {
+        // `GetDeviceStatus` reports `DI_NOTATTACHED` through a success `HRESULT`, which the
+        // generated wrapper discards
+        let hr =
+            unsafe { (self.iface.vtable().5)(mem::transmute_copy(&self.iface), guid_instance) };
+
+        DirectInput8Device::status(hr, HResultContext::GetDeviceStatus)
+    }
+
+    unsafe fn enum_devices_by_semantics(
+        &self,
+        user: Option<&str>,
+        format: &mut DIACTIONFORMATW,
+        flags: u32,
+        callback: &mut dyn FnMut(&DIDEVICEINSTANCEW, u32) -> bool,
+    ) -> Result<()> {
+        type Callback<'a> = &'a mut dyn FnMut(&DIDEVICEINSTANCEW, u32) -> bool;
+
+        // The device interface is only borrowed for the duration of the callback, so it is
+        // taken as a raw pointer instead of an `IDirectInputDevice8W` that would be released
+        unsafe extern "system" fn enumeration_callback(
+            device_instance: *mut DIDEVICEINSTANCEW,
+            _device: RawPtr,
+            flags: u32,
+            _remaining: u32,
+            ctx: *mut c_void,
+        ) -> BOOL {
+            let callback = &mut *(ctx as *mut Callback);
+
+            if device_instance.is_null() || callback(&*device_instance, flags) {
+                BOOL(DIENUM_CONTINUE as _)
+            } else {
+                BOOL(DIENUM_STOP as _)
+            }
+        }
+
+        let mut user = user.map(to_wide_nul);
+        let mut callback: Callback = callback;
+
+        self.iface.EnumDevicesBySemantics(
+            wide_ptr(&mut user),
+            format,
+            Some(enumeration_callback),
+            &mut callback as *mut Callback as _,
+            flags,
+        )?;
+
+        Ok(())
+    }
+}
+
+fn wide_ptr(value: &mut Option<Vec<u16>>) -> PWSTR {
+    PWSTR(
+        value
+            .as_mut()
+            .map_or(ptr::null_mut(), |value| value.as_mut_ptr()),
+    )
+}
+
+impl DirectInput8Device {
+    /// Converts an `HRESULT` returned by the method described by `context` into a status.
+    fn status(hr: HRESULT, context: HResultContext) -> Result<DirectInputStatus> {
+        if hr.is_ok() {
+            Ok(DirectInputStatus::from_hresult_or_ok(hr, context))
+        } else {
+            Err(DirectInputError::from_hresult_with(hr, context))
+        }
+    }
+}
+
+impl DeviceBackend for DirectInput8Device {
+    fn capabilities(&self) -> Result<DIDEVCAPS> {
+        let mut caps = DIDEVCAPS {
+            dwSize: mem::size_of::<DIDEVCAPS>() as _,
+            ..Default::default()
+        };
+
+        unsafe { self.iface.GetCapabilities(&mut caps)? };
+
+        Ok(caps)
+    }
+
+    fn enum_objects(&self, flags: u32) -> Result<Vec<DIDEVICEOBJECTINSTANCEW>> {
+        extern "system" fn enumerate_callback(
+            device_object_instance: *mut DIDEVICEOBJECTINSTANCEW,
+            ctx: *mut c_void,
+        ) -> BOOL {
+            let objects = unsafe { &mut *(ctx as *mut Vec<DIDEVICEOBJECTINSTANCEW>) };
+
+            if !device_object_instance.is_null() {
+                objects.push(unsafe { *device_object_instance });
+            }
+
+            BOOL(DIENUM_CONTINUE as _)
+        }
+
+        let mut objects = Vec::new();
+
+        unsafe {
+            self.iface.EnumObjects(
+                Some(enumerate_callback),
+                &mut objects as *mut Vec<DIDEVICEOBJECTINSTANCEW> as _,
+                flags,
+            )?
+        };
+
+        Ok(objects)
+    }
+
+    unsafe fn get_property(
+        &self,
+        property: *const GUID,
+        header: *mut DIPROPHEADER,
+    ) -> Result<DirectInputStatus> {
+        let hr = (self.iface.vtable().5)(mem::transmute_copy(&self.iface), property, header);
+
+        Self::status(hr, HResultContext::GetProperty)
+    }
+
+    unsafe fn set_property(
+        &self,
+        property: *const GUID,
+        header: *const DIPROPHEADER,
+    ) -> Result<DirectInputStatus> {
+        let hr =
+            (self.iface.vtable().6)(mem::transmute_copy(&self.iface), property, header as *mut _);
+
+        Self::status(hr, HResultContext::SetProperty)
+    }
+
+    fn set_data_format(&self, format: &DIDATAFORMAT) -> Result<()> {
+        unsafe {
+            self.iface
+                .SetDataFormat(format as *const DIDATAFORMAT as *mut _)?
+        };
+
+        Ok(())
+    }
+
+    fn set_cooperative_level(&self, hwnd: HWND, flags: u32) -> Result<()> {
+        unsafe { self.iface.SetCooperativeLevel(hwnd, flags)? };
+
+        Ok(())
+    }
+
+    fn init_event(&mut self) -> Result<DirectInputStatus> {
+        // The registered wait must be gone before its event is closed
+        *self.event_wait.get_mut().unwrap() = None;
+
+        self.event
+            .take()
+            .map(|event| unsafe {
+                let res = self.iface.SetEventNotification(None);
+
+                CloseHandle(event);
+
+                res
+            })
+            .unwrap_or(Ok(()))?;
+
+        let event = unsafe { CreateEventW(ptr::null(), false, false, None) };
+
+        if event == INVALID_HANDLE_VALUE {
+            return Err(DirectInputError::from_last_error());
+        }
+
+        // TODO: Use `SetEventNotification` directly when status `HRESULT` return values are
+        // exposed
+        let hr = unsafe { (self.iface.vtable().12)(mem::transmute_copy(&self.iface), event) };
+
+        // If the method succeeds, the return value is DI_OK or DI_POLLEDDEVICE
+        let status = Self::status(hr, HResultContext::SetEventNotification);
+
+        if status.is_err() {
+            unsafe { CloseHandle(event) };
+        } else {
+            self.event = Some(event);
+        }
+
+        status
+    }
+
+    fn wait(&self, timeout: Duration) -> Result<bool> {
+        if let Some(event) = self.event {
+            // The registered wait of `poll_event` would otherwise consume the signal
+            self.release_event_wait()?;
+
+            let millis: u32 = timeout.as_millis().try_into().unwrap_or(INFINITE);
+
+            let res = unsafe { WaitForSingleObject(event, millis) };
+
+            if res == WAIT_OBJECT_0 {
+                Ok(true)
+            } else {
+                let err = unsafe { GetLastError() };
+
+                if err.0 == 0 {
+                    Ok(false)
+                } else {
+                    Err(DirectInputError::from_hresult(HRESULT::from(err)))
+                }
+            }
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn poll_event(&mut self, waker: &Waker) -> Result<bool> {
+        let event = match self.event {
+            Some(event) => event,
+            None => return Ok(true),
+        };
+
+        let event_wait = self.event_wait.get_mut().unwrap();
+
+        if event_wait.is_none() {
+            *event_wait = Some(EventWait::register(event)?);
+        }
+
+        let shared = &event_wait.as_ref().unwrap().shared;
+
+        // Register the waker before checking the flag so a signal in between is not missed
+        *shared.waker.lock().unwrap() = Some(waker.clone());
+
+        Ok(shared.signalled.swap(false, Ordering::AcqRel))
+    }
+
+    fn event_handle(&self) -> Result<Option<HANDLE>> {
+        // Waiting on the handle directly would race with the registered wait of `poll_event`
+        self.release_event_wait()?;
+
+        Ok(self.event)
+    }
+
+    fn acquire(&self) -> Result<()> {
+        unsafe { self.iface.Acquire() }
+            .map_err(|e| DirectInputError::from_hresult_with(e.code(), HResultContext::Acquire))
+    }
+
+    fn unacquire(&self) -> Result<()> {
+        unsafe { self.iface.Unacquire()? };
+
+        Ok(())
+    }
+
+    fn poll(&self) -> Result<DirectInputStatus> {
+        // TODO(felix): replace with direct `Poll` call when the non-error part of the `HRESULT` is
+        // made available to API consumers
+        let hr = unsafe { (self.iface.vtable().25)(mem::transmute_copy(&self.iface)) };
+
+        Self::status(hr, HResultContext::Poll)
+    }
+
+    fn get_device_state(&self, data: &mut [u8]) -> Result<()> {
+        unsafe {
+            self.iface
+                .GetDeviceState(data.len() as _, data.as_mut_ptr().cast())?
+        };
+
+        Ok(())
+    }
+
+    fn get_device_data(
+        &self,
+        data: &mut [DIDEVICEOBJECTDATA],
+    ) -> Result<(usize, DirectInputStatus)> {
+        let mut count = data.len() as u32;
+
+        // `GetDeviceData` reports `DI_BUFFEROVERFLOW` through a success `HRESULT`, which the
+        // generated wrapper discards
+        let hr = unsafe {
+            (self.iface.vtable().10)(
+                mem::transmute_copy(&self.iface),
+                mem::size_of::<DIDEVICEOBJECTDATA>() as _,
+                data.as_mut_ptr(),
+                &mut count,
+                0,
+            )
+        };
+
+        let status = Self::status(hr, HResultContext::GetDeviceData)?;
+
+        Ok((count as usize, status))
+    }
+
+    fn enum_effects(&self, ty: u32) -> Result<Vec<DIEFFECTINFOW>> {
+        extern "system" fn enumerate_callback(
+            effect_info: *mut DIEFFECTINFOW,
+            ctx: *mut c_void,
+        ) -> BOOL {
+            let effects = unsafe { &mut *(ctx as *mut Vec<DIEFFECTINFOW>) };
+
+            if !effect_info.is_null() {
+                effects.push(unsafe { *effect_info });
+            }
+
+            BOOL(DIENUM_CONTINUE as _)
+        }
+
+        let mut effects = Vec::new();
+
+        unsafe {
+            self.iface.EnumEffects(
+                Some(enumerate_callback),
+                &mut effects as *mut Vec<DIEFFECTINFOW> as _,
+                ty,
+            )?
+        };
+
+        Ok(effects)
+    }
+
+    unsafe fn create_effect(
+        &self,
+        guid: &GUID,
+        params: &DIEFFECT,
+    ) -> Result<Box<dyn EffectBackend>> {
+        let mut iface: Option<IDirectInputEffect> = None;
+
+        self.iface
+            .CreateEffect(guid, params as *const DIEFFECT as *mut _, &mut iface, None)?;
+
+        match iface {
+            Some(iface) => Ok(Box::new(DirectInput8Effect { iface })),
+            None => Err(DirectInputError::Generic),
+        }
+    }
+
+    unsafe fn build_action_map(
+        &self,
+        format: &mut DIACTIONFORMATW,
+        user: Option<&str>,
+        flags: u32,
+    ) -> Result<DirectInputStatus> {
+        let mut user = user.map(to_wide_nul);
+
+        // `BuildActionMap` reports `DI_NOEFFECT` and `DI_WRITEPROTECT` through a success
+        // `HRESULT`, which the generated wrapper discards
+        let hr = (self.iface.vtable().29)(
+            mem::transmute_copy(&self.iface),
+            format,
+            wide_ptr(&mut user),
+            flags,
+        );
+
+        Self::status(hr, HResultContext::BuildActionMap)
+    }
+
+    unsafe fn set_action_map(
+        &self,
+        format: &mut DIACTIONFORMATW,
+        user: Option<&str>,
+        flags: u32,
+    ) -> Result<DirectInputStatus> {
+        let mut user = user.map(to_wide_nul);
+
+        // Likewise for `DI_SETTINGSNOTSAVED` and `DI_WRITEPROTECT`
+        let hr = (self.iface.vtable().30)(
+            mem::transmute_copy(&self.iface),
+            format,
+            wide_ptr(&mut user),
+            flags,
+        );
+
+        Self::status(hr, HResultContext::SetActionMap)
+    }
+}
+
+impl EffectBackend for DirectInput8Effect {
+    unsafe fn set_parameters(&self, params: &DIEFFECT, flags: u32) -> Result<DirectInputStatus> {
+        // `SetParameters` reports `DI_DOWNLOADSKIPPED` and `DI_EFFECTRESTARTED` through a success
+        // `HRESULT`, which the generated wrapper discards
+        let hr = (self.iface.vtable().6)(
+            mem::transmute_copy(&self.iface),
+            params as *const DIEFFECT as *mut _,
+            flags,
+        );
+
+        DirectInput8Device::status(hr, HResultContext::SetParameters)
+    }
+
+    fn start(&self, iterations: u32, flags: u32) -> Result<()> {
+        unsafe { self.iface.Start(iterations, flags)? };
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<()> {
+        unsafe { self.iface.Stop()? };
+
+        Ok(())
+    }
+
+    fn status(&self) -> Result<u32> {
+        let mut status = 0;
+
+        unsafe { self.iface.GetEffectStatus(&mut status)? };
+
+        Ok(status)
+    }
+
+    fn download(&self) -> Result<DirectInputStatus> {
+        // `Download` returns `DI_NOEFFECT` if the effect was already downloaded
+        let hr = unsafe { (self.iface.vtable().10)(mem::transmute_copy(&self.iface)) };
+
+        DirectInput8Device::status(hr, HResultContext::Download)
+    }
+
+    fn unload(&self) -> Result<()> {
+        unsafe { self.iface.Unload()? };
+
+        Ok(())
+    }
+}
+
+impl DirectInput8Device {
+    /// Releases the wait registered by `poll_event`, which is registered again the next time the
+    /// device is polled.
+    fn release_event_wait(&self) -> Result<()> {
+        let event_wait = self.event_wait.lock().unwrap().take();
+
+        match (event_wait, self.event) {
+            (Some(event_wait), Some(event)) => event_wait.release(event),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl EventWait {
+    fn register(event: HANDLE) -> Result<Self> {
+        let shared = Box::new(EventWaitShared::default());
+        let mut handle = HANDLE::default();
+
+        let res = unsafe {
+            RegisterWaitForSingleObject(
+                &mut handle,
+                event,
+                Some(Self::callback),
+                &*shared as *const EventWaitShared as *const c_void,
+                INFINITE,
+                WT_EXECUTEDEFAULT,
+            )
+        };
+
+        if res.as_bool() {
+            Ok(Self { handle, shared })
+        } else {
+            Err(DirectInputError::from_last_error())
+        }
+    }
+
+    /// Runs on a thread pool thread each time the auto-reset event is signalled, which also
+    /// resets it.
+    unsafe extern "system" fn callback(context: *mut c_void, _timed_out: BOOLEAN) {
+        let shared = &*(context as *const EventWaitShared);
+
+        shared.signalled.store(true, Ordering::Release);
+
+        if let Some(waker) = shared.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    /// Unregisters the wait on `event` and wakes the registered waker. A signal consumed by the
+    /// wait but not reported by `poll_event` yet is restored by setting the event again.
+    fn release(mut self, event: HANDLE) -> Result<()> {
+        self.unregister_wait();
+
+        if let Some(waker) = self.shared.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+
+        if self.shared.signalled.load(Ordering::Acquire) && !unsafe { SetEvent(event) }.as_bool() {
+            return Err(DirectInputError::from_last_error());
+        }
+
+        Ok(())
+    }
+
+    fn unregister_wait(&mut self) {
+        if self.handle.is_invalid() {
+            return;
+        }
+
+        // Blocks until running callbacks have returned, after which `shared` can be freed
+        unsafe {
+            UnregisterWaitEx(self.handle, INVALID_HANDLE_VALUE);
+        }
+        self.handle = HANDLE::default();
+    }
+}
+
+impl Drop for EventWait {
+    fn drop(&mut self) {
+        self.unregister_wait();
+    }
+}
+
+impl Drop for DirectInput8Device {
+    fn drop(&mut self) {
+        *self.event_wait.get_mut().unwrap() = None;
+
+        if let Some(event) = self.event.take() {
+            unsafe {
+                let _ = self.iface.SetEventNotification(None);
+
+                CloseHandle(event);
+            }
+        }
+    }
+}
+
+// The thread pool wait only needs a Win32 event, not a DirectInput device
+#[cfg(all(test, windows))]
+mod tests {
+    use std::thread;
+    use std::time::Instant;
+
+    use windows::Win32::Foundation::WAIT_TIMEOUT;
+
+    use super::*;
+
+    #[test]
+    fn test_event_wait_release_restores_signal() {
+        let event = unsafe { CreateEventW(ptr::null(), false, false, None) };
+        assert!(!event.is_invalid());
+
+        // Nothing to restore
+        EventWait::register(event).unwrap().release(event).unwrap();
+        assert_eq!(unsafe { WaitForSingleObject(event, 0) }, WAIT_TIMEOUT.0);
+
+        let event_wait = EventWait::register(event).unwrap();
+        unsafe { SetEvent(event) };
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !event_wait.shared.signalled.load(Ordering::Acquire) {
+            assert!(Instant::now() < deadline, "wait callback never ran");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // The callback consumed the auto-reset event, releasing the wait sets it again
+        assert_eq!(unsafe { WaitForSingleObject(event, 0) }, WAIT_TIMEOUT.0);
+        event_wait.release(event).unwrap();
+        assert_eq!(unsafe { WaitForSingleObject(event, 0) }, WAIT_OBJECT_0);
+
+        unsafe { CloseHandle(event) };
+    }
+}
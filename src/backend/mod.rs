@@ -0,0 +1,184 @@
+//! Abstraction over the DirectInput 8 COM interfaces.
+//!
+//! [`DirectInputManager`](crate::DirectInputManager) and [`Device`](crate::Device) only talk to
+//! DirectInput through the [`Backend`] and [`DeviceBackend`] traits. The default implementation
+//! forwards to `IDirectInput8W` and `IDirectInputDevice8W`, while [`sim`] provides an in-memory
+//! implementation with scriptable devices that runs on any platform.
+
+use std::task::Waker;
+use std::time::Duration;
+
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DIACTIONFORMATW, DIDATAFORMAT, DIDEVCAPS, DIDEVICEINSTANCEW, DIDEVICEOBJECTDATA,
+    DIDEVICEOBJECTINSTANCEW, DIEFFECT, DIEFFECTINFOW, DIPROPHEADER,
+};
+use windows::Win32::Foundation::{HANDLE, HWND};
+
+use crate::error::{DirectInputStatus, Result};
+
+mod dinput8;
+pub mod sim;
+
+pub use self::dinput8::DirectInput8Backend;
+
+/// Counterpart of `IDirectInput8W`.
+pub trait Backend {
+    /// Enumerates the devices of the given `DI8DEVCLASS_*` class or `DI8DEVTYPE_*` type, filtered
+    /// by `DIEDFL_*` flags. Enumeration stops early once `callback` returns `false`.
+    fn enum_devices(
+        &self,
+        filter: u32,
+        flags: u32,
+        callback: &mut dyn FnMut(&DIDEVICEINSTANCEW) -> bool,
+    ) -> Result<()>;
+
+    /// Creates a device instance for the device with the given instance GUID.
+    fn create_device(&self, guid_instance: &GUID) -> Result<Box<dyn DeviceBackend>>;
+
+    /// Checks whether the device with the given instance GUID is attached, returning
+    /// `DirectInputStatus::Ok` if it is and `DirectInputStatus::NotAttached` otherwise.
+    fn get_device_status(&self, guid_instance: &GUID) -> Result<DirectInputStatus>;
+
+    /// Enumerates the devices that best match the actions of `format`, filtered by
+    /// `DIEDBSFL_*` flags. `callback` receives each device with its `DIEDBS_*` flags and stops
+    /// the enumeration by returning `false`.
+    ///
+    /// # Safety
+    ///
+    /// `format.rgoAction` must point to `format.dwNumActions` valid actions.
+    unsafe fn enum_devices_by_semantics(
+        &self,
+        user: Option<&str>,
+        format: &mut DIACTIONFORMATW,
+        flags: u32,
+        callback: &mut dyn FnMut(&DIDEVICEINSTANCEW, u32) -> bool,
+    ) -> Result<()>;
+}
+
+/// Counterpart of `IDirectInputDevice8W`.
+pub trait DeviceBackend: Send {
+    fn capabilities(&self) -> Result<DIDEVCAPS>;
+
+    /// Enumerates the device objects matching the given `DIDFT_*` flags.
+    fn enum_objects(&self, flags: u32) -> Result<Vec<DIDEVICEOBJECTINSTANCEW>>;
+
+    /// # Safety
+    ///
+    /// `header` must be the header of a complete `DIPROP*` structure of `header.dwSize` bytes
+    /// matching `property`.
+    unsafe fn get_property(
+        &self,
+        property: *const GUID,
+        header: *mut DIPROPHEADER,
+    ) -> Result<DirectInputStatus>;
+
+    /// # Safety
+    ///
+    /// `header` must be the header of a complete `DIPROP*` structure of `header.dwSize` bytes
+    /// matching `property`.
+    unsafe fn set_property(
+        &self,
+        property: *const GUID,
+        header: *const DIPROPHEADER,
+    ) -> Result<DirectInputStatus>;
+
+    fn set_data_format(&self, format: &DIDATAFORMAT) -> Result<()>;
+
+    fn set_cooperative_level(&self, hwnd: HWND, flags: u32) -> Result<()>;
+
+    /// Creates (or replaces) the notification event signalled when the device state changes.
+    fn init_event(&mut self) -> Result<DirectInputStatus>;
+
+    /// Waits for the notification event. Returns `true` if no event was configured.
+    fn wait(&self, timeout: Duration) -> Result<bool>;
+
+    /// Checks the notification event without blocking, resetting it if it was signalled.
+    /// Otherwise `waker` is woken the next time the event is signalled. Returns `true` if the
+    /// event was signalled or if no event was configured.
+    fn poll_event(&mut self, waker: &Waker) -> Result<bool>;
+
+    /// The Win32 notification event created by `init_event`, used to wait on several devices
+    /// with `WaitForMultipleObjects`. Backends without a real event return `None` and are waited
+    /// on through `poll_event` instead.
+    ///
+    /// Anything watching the event on behalf of `poll_event` is stopped first so it cannot
+    /// consume signals meant for the caller. A signal it already consumed is restored on the
+    /// event, and a registered waker is woken so its task polls again and resumes watching.
+    fn event_handle(&self) -> Result<Option<HANDLE>>;
+
+    fn acquire(&self) -> Result<()>;
+
+    fn unacquire(&self) -> Result<()>;
+
+    fn poll(&self) -> Result<DirectInputStatus>;
+
+    /// Fills `data` with the device state in the layout of the current data format.
+    fn get_device_state(&self, data: &mut [u8]) -> Result<()>;
+
+    /// Removes up to `data.len()` buffered input records from the device buffer, returning the
+    /// number of records read and `DirectInputStatus::BufferOverflow` if records were lost.
+    fn get_device_data(
+        &self,
+        data: &mut [DIDEVICEOBJECTDATA],
+    ) -> Result<(usize, DirectInputStatus)>;
+
+    /// Enumerates the effects of the given `DIEFT_*` type supported by the device.
+    fn enum_effects(&self, ty: u32) -> Result<Vec<DIEFFECTINFOW>>;
+
+    /// # Safety
+    ///
+    /// Every pointer in `params` must be valid for the duration of the call.
+    unsafe fn create_effect(
+        &self,
+        guid: &GUID,
+        params: &DIEFFECT,
+    ) -> Result<Box<dyn EffectBackend>>;
+
+    /// Maps the actions of `format` to objects of the device, writing the result into the
+    /// actions, with `DIDBAM_*` flags.
+    ///
+    /// # Safety
+    ///
+    /// `format.rgoAction` must point to `format.dwNumActions` valid actions.
+    unsafe fn build_action_map(
+        &self,
+        format: &mut DIACTIONFORMATW,
+        user: Option<&str>,
+        flags: u32,
+    ) -> Result<DirectInputStatus>;
+
+    /// Applies the mappings of `format` to the device with `DIDSAM_*` flags, replacing the data
+    /// format and buffer size.
+    ///
+    /// # Safety
+    ///
+    /// `format.rgoAction` must point to `format.dwNumActions` valid actions.
+    unsafe fn set_action_map(
+        &self,
+        format: &mut DIACTIONFORMATW,
+        user: Option<&str>,
+        flags: u32,
+    ) -> Result<DirectInputStatus>;
+}
+
+/// Counterpart of `IDirectInputEffect`.
+pub trait EffectBackend: Send {
+    /// # Safety
+    ///
+    /// Every pointer in `params` must be valid for the duration of the call.
+    unsafe fn set_parameters(&self, params: &DIEFFECT, flags: u32) -> Result<DirectInputStatus>;
+
+    /// Starts the effect for `iterations` playbacks (`INFINITE` to repeat until stopped) with
+    /// `DIES_*` flags.
+    fn start(&self, iterations: u32, flags: u32) -> Result<()>;
+
+    fn stop(&self) -> Result<()>;
+
+    /// Returns the `DIEGES_*` status flags.
+    fn status(&self) -> Result<u32>;
+
+    fn download(&self) -> Result<DirectInputStatus>;
+
+    fn unload(&self) -> Result<()>;
+}
@@ -0,0 +1,1578 @@
+//! In-memory DirectInput backend for tests and for running on platforms without DirectInput.
+//!
+//! A [`SimulatedDevice`] is a cheaply clonable handle: one clone is registered with a
+//! [`SimulatedBackend`] (or wrapped directly with [`Device::with_backend`]) while the test keeps
+//! another to script the device state, inject errors and inspect what the library did.
+//!
+//! [`Device::with_backend`]: crate::Device::with_backend
+
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::slice;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    GUID_Button, GUID_RxAxis, GUID_RyAxis, GUID_RzAxis, GUID_Slider, GUID_XAxis, GUID_YAxis,
+    GUID_ZAxis, DI8DEVCLASS_ALL, DI8DEVCLASS_DEVICE, DI8DEVCLASS_GAMECTRL, DI8DEVCLASS_KEYBOARD,
+    DI8DEVCLASS_POINTER, DI8DEVTYPEGAMEPAD_STANDARD, DI8DEVTYPE_1STPERSON, DI8DEVTYPE_GAMEPAD,
+    DI8DEVTYPE_JOYSTICK, DI8DEVTYPE_KEYBOARD, DI8DEVTYPE_MOUSE, DI8DEVTYPE_SCREENPOINTER,
+    DI8DEVTYPE_SUPPLEMENTAL, DIACTIONFORMATW, DIACTIONW, DIAH_APPREQUESTED, DIAH_DEFAULT,
+    DIAH_ERROR, DIAH_UNMAPPED, DIA_APPMAPPED, DIA_APPNOMAP, DIA_NORANGE, DIDATAFORMAT,
+    DIDBAM_INITIALIZE, DIDBAM_PRESERVE, DIDC_ALIAS, DIDC_ATTACHED, DIDC_FORCEFEEDBACK, DIDC_HIDDEN,
+    DIDC_PHANTOM, DIDC_POLLEDDEVICE, DIDEVCAPS, DIDEVICEINSTANCEW, DIDEVICEOBJECTDATA,
+    DIDEVICEOBJECTINSTANCEW, DIDFT_ABSAXIS, DIDFT_AXIS, DIDFT_POV, DIDFT_PSHBUTTON,
+    DIEDBSFL_FORCEFEEDBACK, DIEDBS_MAPPEDPRI1, DIEDBS_MAPPEDPRI2, DIEDFL_ATTACHEDONLY,
+    DIEDFL_FORCEFEEDBACK, DIEDFL_INCLUDEALIASES, DIEDFL_INCLUDEHIDDEN, DIEDFL_INCLUDEPHANTOMS,
+    DIEFFECT, DIEFFECTINFOW, DIEFT_ALL, DIEFT_CONDITION, DIEFT_CONSTANTFORCE, DIEFT_CUSTOMFORCE,
+    DIEFT_PERIODIC, DIEFT_RAMPFORCE, DIEGES_PLAYING, DIEP_ALLPARAMS, DIEP_AXES, DIEP_DURATION,
+    DIEP_GAIN, DIEP_NODOWNLOAD, DIEP_NORESTART, DIEP_START, DIEP_TYPESPECIFICPARAMS,
+    DIES_NODOWNLOAD, DIES_SOLO, DIPH_BYID, DIPH_BYOFFSET, DIPH_BYUSAGE, DIPH_DEVICE,
+    DIPROPAUTOCENTER_ON, DIPROPAXISMODE_ABS, DIPROPAXISMODE_REL, DIPROPCALIBRATIONMODE_COOKED,
+    DIPROPCALIBRATIONMODE_RAW, DIPROPDWORD, DIPROPGUIDANDPATH, DIPROPHEADER, DIPROPRANGE,
+    DIPROPSTRING, DISCL_EXCLUSIVE, GUID_POV, HID_USAGE_GENERIC_HATSWITCH, HID_USAGE_PAGE_BUTTON,
+    HID_USAGE_PAGE_GENERIC,
+};
+use windows::Win32::Foundation::{HANDLE, HWND};
+
+use super::{Backend, DeviceBackend, EffectBackend};
+use crate::action_map::{semantic_genre, semantic_is_priority2, semantic_kind, GENRE_KEYBOARD};
+use crate::effect::EffectKind;
+use crate::error::{DirectInputError, DirectInputStatus, Result};
+use crate::property::{
+    DIPROP_AUTOCENTER, DIPROP_AXISMODE, DIPROP_BUFFERSIZE, DIPROP_CALIBRATIONMODE, DIPROP_DEADZONE,
+    DIPROP_FFGAIN, DIPROP_GRANULARITY, DIPROP_GUIDANDPATH, DIPROP_INSTANCENAME, DIPROP_JOYSTICKID,
+    DIPROP_PRODUCTNAME, DIPROP_RANGE, DIPROP_SATURATION, DIPROP_TYPENAME, DIPROP_VIDPID,
+};
+use crate::util::{copy_to_wide, os_string_from_wide};
+
+/// Default range reported for axes before any `DIPROP_RANGE` is applied, matching DirectInput.
+const DEFAULT_RANGE: (i32, i32) = (0, 0xffff);
+
+/// `GUID_DEVCLASS_HIDCLASS`, reported as the class of every simulated device by
+/// `DIPROP_GUIDANDPATH`.
+const GUID_DEVCLASS_HIDCLASS: GUID = GUID::from_values(
+    0x745a17a0,
+    0x74d3,
+    0x11d0,
+    [0xb6, 0xfe, 0x00, 0xa0, 0xc9, 0x0f, 0x57, 0xda],
+);
+
+/// Properties using a `DIPROPDWORD` payload.
+const DWORD_PROPERTIES: [*const GUID; 10] = [
+    DIPROP_BUFFERSIZE,
+    DIPROP_AXISMODE,
+    DIPROP_GRANULARITY,
+    DIPROP_DEADZONE,
+    DIPROP_SATURATION,
+    DIPROP_FFGAIN,
+    DIPROP_AUTOCENTER,
+    DIPROP_CALIBRATIONMODE,
+    DIPROP_JOYSTICKID,
+    DIPROP_VIDPID,
+];
+
+/// Calls on a [`SimulatedDevice`] that can be made to fail with [`SimulatedDevice::fail_next`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SimulatedCall {
+    Acquire,
+    BuildActionMap,
+    Capabilities,
+    CreateEffect,
+    EnumEffects,
+    EnumObjects,
+    GetDeviceData,
+    GetDeviceState,
+    GetProperty,
+    InitEvent,
+    Poll,
+    PollEvent,
+    SetActionMap,
+    SetCooperativeLevel,
+    SetDataFormat,
+    SetProperty,
+    StartEffect,
+    Wait,
+}
+
+/// [`Backend`] holding a list of [`SimulatedDevice`]s.
+#[derive(Clone, Default)]
+pub struct SimulatedBackend {
+    devices: Arc<Mutex<Vec<SimulatedDevice>>>,
+}
+
+/// A scriptable device shared between a [`SimulatedBackend`] and the code driving the test.
+#[derive(Clone)]
+pub struct SimulatedDevice {
+    shared: Arc<Shared>,
+}
+
+/// Snapshot of an effect created on a [`SimulatedDevice`], as returned by
+/// [`SimulatedDevice::effects`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatedEffect {
+    pub guid: GUID,
+    /// Axes (data format offsets) the effect applies to.
+    pub axes: Vec<u32>,
+    /// `dwDuration` in microseconds.
+    pub duration: u32,
+    pub gain: u32,
+    /// Size in bytes of the type-specific parameters.
+    pub type_specific_size: u32,
+    pub downloaded: bool,
+    pub playing: bool,
+    /// Iteration count passed to the last `Start` call.
+    pub iterations: u32,
+}
+
+/// Effect backend handed out by [`SimulatedDevice`]. It is removed from the device when dropped.
+struct SimulatedEffectHandle {
+    device: SimulatedDevice,
+    id: u32,
+}
+
+struct Shared {
+    state: Mutex<SimState>,
+    changed: Condvar,
+}
+
+enum Step {
+    State(Vec<u8>),
+    Error(DirectInputError),
+}
+
+struct SimState {
+    instance: DIDEVICEINSTANCEW,
+    caps: DIDEVCAPS,
+    objects: Vec<DIDEVICEOBJECTINSTANCEW>,
+    data_size: Option<u32>,
+    cooperative_level: Option<u32>,
+    acquired: bool,
+    event: bool,
+    signalled: bool,
+    /// Task to wake when the event is signalled, registered by `poll_event`.
+    waker: Option<Waker>,
+    steps: VecDeque<Step>,
+    current: Vec<u8>,
+    ranges: HashMap<u32, (i32, i32)>,
+    dead_zones: HashMap<u32, u32>,
+    saturations: HashMap<u32, u32>,
+    calibration_modes: HashMap<u32, u32>,
+    axis_mode: u32,
+    auto_center: u32,
+    ff_gain: u32,
+    joystick_id: u32,
+    /// Object ID and application data of each action of the applied action map.
+    action_map: Vec<(u32, usize)>,
+    effect_kinds: Vec<EffectKind>,
+    effects: Vec<(u32, SimulatedEffect)>,
+    next_effect: u32,
+    buffer_size: u32,
+    buffer: VecDeque<DIDEVICEOBJECTDATA>,
+    overflowed: bool,
+    sequence: u32,
+    created: Instant,
+    failures: HashMap<SimulatedCall, VecDeque<DirectInputError>>,
+}
+
+impl SimulatedBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `device` visible to enumeration and `create_device`.
+    pub fn add_device(&self, device: SimulatedDevice) {
+        self.devices.lock().unwrap().push(device);
+    }
+
+    /// Removes the device with the given instance GUID, as if it were unplugged and its driver
+    /// unloaded.
+    pub fn remove_device(&self, guid_instance: &GUID) -> Option<SimulatedDevice> {
+        let mut devices = self.devices.lock().unwrap();
+        let index = devices
+            .iter()
+            .position(|device| device.guid_instance() == *guid_instance)?;
+
+        Some(devices.remove(index))
+    }
+}
+
+impl Backend for SimulatedBackend {
+    fn enum_devices(
+        &self,
+        filter: u32,
+        flags: u32,
+        callback: &mut dyn FnMut(&DIDEVICEINSTANCEW) -> bool,
+    ) -> Result<()> {
+        // Take a snapshot so that the callback can add and remove devices
+        let instances: Vec<_> = self
+            .devices
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|device| device.lock())
+            .filter(|state| filter_matches(filter, state.instance.dwDevType))
+            .filter(|state| flags_match(flags, state.caps.dwFlags))
+            .map(|state| state.instance)
+            .collect();
+
+        for instance in &instances {
+            if !callback(instance) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_device(&self, guid_instance: &GUID) -> Result<Box<dyn DeviceBackend>> {
+        let devices = self.devices.lock().unwrap();
+
+        devices
+            .iter()
+            .find(|device| device.guid_instance() == *guid_instance)
+            .map(|device| Box::new(device.clone()) as Box<dyn DeviceBackend>)
+            .ok_or(DirectInputError::DeviceNotReg)
+    }
+
+    fn get_device_status(&self, guid_instance: &GUID) -> Result<DirectInputStatus> {
+        let devices = self.devices.lock().unwrap();
+        let device = devices
+            .iter()
+            .find(|device| device.guid_instance() == *guid_instance)
+            .ok_or(DirectInputError::DeviceNotReg)?;
+
+        Ok(if device.lock().attached() {
+            DirectInputStatus::Ok
+        } else {
+            DirectInputStatus::NotAttached
+        })
+    }
+
+    unsafe fn enum_devices_by_semantics(
+        &self,
+        _user: Option<&str>,
+        format: &mut DIACTIONFORMATW,
+        flags: u32,
+        callback: &mut dyn FnMut(&DIDEVICEINSTANCEW, u32) -> bool,
+    ) -> Result<()> {
+        let actions = raw_actions(format);
+
+        let matches: Vec<_> = self
+            .devices
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|device| device.lock())
+            .filter(|state| state.attached())
+            .filter(|state| {
+                flags & DIEDBSFL_FORCEFEEDBACK == 0 || state.caps.dwFlags & DIDC_FORCEFEEDBACK != 0
+            })
+            .filter_map(|state| {
+                let device_flags = actions
+                    .iter()
+                    .filter(|action| state.find_semantic_object(action.dwSemantic, &[]).is_some())
+                    .fold(0, |device_flags, action| {
+                        device_flags
+                            | if semantic_is_priority2(action.dwSemantic) {
+                                DIEDBS_MAPPEDPRI2
+                            } else {
+                                DIEDBS_MAPPEDPRI1
+                            }
+                    });
+
+                if device_flags == 0 {
+                    None
+                } else {
+                    Some((state.instance, device_flags))
+                }
+            })
+            .collect();
+
+        for (instance, device_flags) in &matches {
+            if !callback(instance, *device_flags) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The actions of a `DIACTIONFORMATW`.
+///
+/// # Safety
+///
+/// `format.rgoAction` must point to `format.dwNumActions` valid actions.
+unsafe fn raw_actions(format: &mut DIACTIONFORMATW) -> &mut [DIACTIONW] {
+    if format.dwNumActions == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(format.rgoAction, format.dwNumActions as usize)
+    }
+}
+
+fn filter_matches(filter: u32, dev_type: u32) -> bool {
+    let ty = dev_type & 0xff;
+
+    match filter {
+        DI8DEVCLASS_ALL => true,
+        DI8DEVCLASS_POINTER => ty == DI8DEVTYPE_MOUSE || ty == DI8DEVTYPE_SCREENPOINTER,
+        DI8DEVCLASS_KEYBOARD => ty == DI8DEVTYPE_KEYBOARD,
+        DI8DEVCLASS_GAMECTRL => {
+            (DI8DEVTYPE_JOYSTICK..=DI8DEVTYPE_1STPERSON).contains(&ty)
+                || ty == DI8DEVTYPE_SUPPLEMENTAL
+        }
+        DI8DEVCLASS_DEVICE => {
+            !filter_matches(DI8DEVCLASS_POINTER, dev_type)
+                && !filter_matches(DI8DEVCLASS_KEYBOARD, dev_type)
+                && !filter_matches(DI8DEVCLASS_GAMECTRL, dev_type)
+        }
+        // Any other value is a `DI8DEVTYPE_*` device type
+        _ => ty == filter,
+    }
+}
+
+fn flags_match(flags: u32, caps_flags: u32) -> bool {
+    let excluded = [
+        (DIEDFL_INCLUDEALIASES, DIDC_ALIAS),
+        (DIEDFL_INCLUDEHIDDEN, DIDC_HIDDEN),
+        (DIEDFL_INCLUDEPHANTOMS, DIDC_PHANTOM),
+    ];
+
+    (flags & DIEDFL_ATTACHEDONLY == 0 || caps_flags & DIDC_ATTACHED != 0)
+        && (flags & DIEDFL_FORCEFEEDBACK == 0 || caps_flags & DIDC_FORCEFEEDBACK != 0)
+        && excluded
+            .iter()
+            .all(|&(include, cap)| flags & include != 0 || caps_flags & cap == 0)
+}
+
+impl SimulatedDevice {
+    /// Creates an attached gamepad with no objects and unique instance and product GUIDs.
+    pub fn new(product_name: &str) -> Self {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dev_type = DI8DEVTYPE_GAMEPAD | (DI8DEVTYPEGAMEPAD_STANDARD << 8);
+
+        let mut instance = DIDEVICEINSTANCEW {
+            dwSize: mem::size_of::<DIDEVICEINSTANCEW>() as _,
+            guidInstance: GUID::from_values(id, 0x5349, 0x4d44, *b"INSTANCE"),
+            guidProduct: GUID::from_values(id, 0x5349, 0x4d44, *b"PRODUCT\0"),
+            dwDevType: dev_type,
+            ..Default::default()
+        };
+        copy_to_wide(&mut instance.tszInstanceName, product_name);
+        copy_to_wide(&mut instance.tszProductName, product_name);
+
+        let caps = DIDEVCAPS {
+            dwSize: mem::size_of::<DIDEVCAPS>() as _,
+            dwFlags: DIDC_ATTACHED,
+            dwDevType: dev_type,
+            ..Default::default()
+        };
+
+        let state = SimState {
+            instance,
+            caps,
+            objects: Vec::new(),
+            data_size: None,
+            cooperative_level: None,
+            acquired: false,
+            event: false,
+            signalled: false,
+            waker: None,
+            steps: VecDeque::new(),
+            current: Vec::new(),
+            ranges: HashMap::new(),
+            dead_zones: HashMap::new(),
+            saturations: HashMap::new(),
+            calibration_modes: HashMap::new(),
+            axis_mode: DIPROPAXISMODE_ABS,
+            auto_center: DIPROPAUTOCENTER_ON,
+            ff_gain: 10_000,
+            joystick_id: 0,
+            action_map: Vec::new(),
+            effect_kinds: Vec::new(),
+            effects: Vec::new(),
+            next_effect: 0,
+            buffer_size: 0,
+            buffer: VecDeque::new(),
+            overflowed: false,
+            sequence: 0,
+            created: Instant::now(),
+            failures: HashMap::new(),
+        };
+
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(state),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn with_guid_instance(self, guid: GUID) -> Self {
+        self.lock().instance.guidInstance = guid;
+        self
+    }
+
+    pub fn with_guid_product(self, guid: GUID) -> Self {
+        self.lock().instance.guidProduct = guid;
+        self
+    }
+
+    /// Sets the product GUID the way DirectInput builds it for HID devices, which is what
+    /// `DIPROP_VIDPID` and `DIPROP_GUIDANDPATH` are derived from.
+    pub fn with_vid_pid(self, vendor_id: u16, product_id: u16) -> Self {
+        let data1 = (product_id as u32) << 16 | vendor_id as u32;
+
+        self.with_guid_product(GUID::from_values(data1, 0, 0, *b"\0\0PIDVID"))
+    }
+
+    /// Sets the top-level HID usage reported by enumeration.
+    pub fn with_usage(self, usage_page: u16, usage: u16) -> Self {
+        {
+            let mut state = self.lock();
+            state.instance.wUsagePage = usage_page;
+            state.instance.wUsage = usage;
+        }
+        self
+    }
+
+    /// Sets the `DIPROP_JOYSTICKID` reported by the device.
+    pub fn with_joystick_id(self, id: u32) -> Self {
+        self.lock().joystick_id = id;
+        self
+    }
+
+    pub fn with_instance_name(self, name: &str) -> Self {
+        copy_to_wide(&mut self.lock().instance.tszInstanceName, name);
+        self
+    }
+
+    /// Sets the `DI8DEVTYPE_*` type (and subtype) reported by enumeration and capabilities.
+    pub fn with_dev_type(self, dev_type: u32) -> Self {
+        {
+            let mut state = self.lock();
+            state.instance.dwDevType = dev_type;
+            state.caps.dwDevType = dev_type;
+        }
+        self
+    }
+
+    /// Sets the `DIDC_*` capability flags.
+    pub fn with_flags(self, flags: u32) -> Self {
+        self.lock().caps.dwFlags = flags;
+        self
+    }
+
+    /// Adds absolute axes, in `DIJOYSTATE2` order (X, Y, Z, Rx, Ry, Rz, then sliders).
+    pub fn with_axes(self, count: u32) -> Self {
+        const AXES: [(GUID, &str, u16); 8] = [
+            (GUID_XAxis, "X Axis", 0x30),
+            (GUID_YAxis, "Y Axis", 0x31),
+            (GUID_ZAxis, "Z Axis", 0x32),
+            (GUID_RxAxis, "X Rotation", 0x33),
+            (GUID_RyAxis, "Y Rotation", 0x34),
+            (GUID_RzAxis, "Z Rotation", 0x35),
+            (GUID_Slider, "Slider", 0x36),
+            (GUID_Slider, "Slider", 0x36),
+        ];
+
+        for (guid, name, usage) in AXES.iter().take(count as usize) {
+            self.add_object(*guid, DIDFT_ABSAXIS, name, (HID_USAGE_PAGE_GENERIC, *usage));
+        }
+        self
+    }
+
+    /// Makes the device support the given force feedback effects, which also sets
+    /// `DIDC_FORCEFEEDBACK`.
+    pub fn with_effects(self, kinds: &[EffectKind]) -> Self {
+        {
+            let mut state = self.lock();
+            state.effect_kinds.extend_from_slice(kinds);
+            state.caps.dwFlags |= DIDC_FORCEFEEDBACK;
+        }
+        self
+    }
+
+    pub fn with_buttons(self, count: u32) -> Self {
+        for i in 0..count {
+            let usage = (HID_USAGE_PAGE_BUTTON, i as u16 + 1);
+            self.add_object(
+                GUID_Button,
+                DIDFT_PSHBUTTON,
+                &format!("Button {}", i),
+                usage,
+            );
+        }
+        self
+    }
+
+    pub fn with_povs(self, count: u32) -> Self {
+        for i in 0..count {
+            let usage = (HID_USAGE_PAGE_GENERIC, HID_USAGE_GENERIC_HATSWITCH);
+            self.add_object(GUID_POV, DIDFT_POV, &format!("Hat Switch {}", i), usage);
+        }
+        self
+    }
+
+    /// Adds a fully specified object. Its `dwType` is used as-is and must contain a unique
+    /// instance number for its type.
+    pub fn with_object(self, object: DIDEVICEOBJECTINSTANCEW) -> Self {
+        {
+            let mut state = self.lock();
+            state.count_object(object.dwType);
+            state.objects.push(object);
+        }
+        self
+    }
+
+    fn add_object(&self, guid: GUID, ty: u32, name: &str, (usage_page, usage): (u16, u16)) {
+        let mut state = self.lock();
+        let instance = state
+            .objects
+            .iter()
+            .filter(|object| object.dwType & ty & 0xff != 0)
+            .count() as u32;
+        let offset = state.objects.len() as u32 * 4;
+
+        let mut object = DIDEVICEOBJECTINSTANCEW {
+            dwSize: mem::size_of::<DIDEVICEOBJECTINSTANCEW>() as _,
+            guidType: guid,
+            dwOfs: offset,
+            dwType: ty | (instance << 8),
+            wUsagePage: usage_page,
+            wUsage: usage,
+            ..Default::default()
+        };
+        copy_to_wide(&mut object.tszName, name);
+
+        state.count_object(object.dwType);
+        state.objects.push(object);
+    }
+
+    pub fn guid_instance(&self) -> GUID {
+        self.lock().instance.guidInstance
+    }
+
+    /// Queues a raw device state returned by subsequent `GetDeviceState` calls. Queued states are
+    /// consumed one per call; the last one keeps being returned once the queue is empty.
+    pub fn push_state(&self, data: &[u8]) {
+        let mut state = self.lock();
+        state.steps.push_back(Step::State(data.to_vec()));
+        state.signal();
+        self.shared.changed.notify_all();
+    }
+
+    /// Queues a raw state structure such as `DIJOYSTATE2`.
+    pub fn push_raw<T: Copy>(&self, raw: &T) {
+        let data =
+            unsafe { slice::from_raw_parts(raw as *const T as *const u8, mem::size_of::<T>()) };
+
+        self.push_state(data);
+    }
+
+    /// Queues an error returned by `GetDeviceState` in sequence with the queued states.
+    pub fn push_error(&self, err: DirectInputError) {
+        let mut state = self.lock();
+        state.steps.push_back(Step::Error(err));
+        state.signal();
+        self.shared.changed.notify_all();
+    }
+
+    /// Records a buffered input event for the object at `offset` in the data format, as read by
+    /// `GetDeviceData`. Like DirectInput, events are only buffered while the device is acquired
+    /// and a buffer size is set; once the buffer is full the oldest events are dropped.
+    pub fn push_event(&self, offset: u32, value: u32) {
+        let mut state = self.lock();
+        let sequence = state.sequence.wrapping_add(1);
+        let timestamp = state.created.elapsed().as_millis() as u32;
+
+        state.sequence = sequence;
+        state.push_record(DIDEVICEOBJECTDATA {
+            dwOfs: offset,
+            dwData: value,
+            dwTimeStamp: timestamp,
+            dwSequence: sequence,
+            uAppData: 0,
+        });
+        self.shared.changed.notify_all();
+    }
+
+    /// Queues a buffered input record for every action mapped to the object with the given
+    /// `DIDFT_*` identifier by the action map applied with `set_action_map`, tagged with the
+    /// action's application data. Objects without an action are ignored, like DirectInput does.
+    pub fn push_object_event(&self, object_id: u32, value: u32) {
+        let mut state = self.lock();
+        let sequence = state.sequence.wrapping_add(1);
+        let timestamp = state.created.elapsed().as_millis() as u32;
+
+        state.sequence = sequence;
+        let records: Vec<_> = state
+            .action_map
+            .iter()
+            .enumerate()
+            .filter(|(_, &(id, _))| id == object_id)
+            .map(|(index, &(_, app_data))| DIDEVICEOBJECTDATA {
+                dwOfs: (index * mem::size_of::<u32>()) as u32,
+                dwData: value,
+                dwTimeStamp: timestamp,
+                dwSequence: sequence,
+                uAppData: app_data,
+            })
+            .collect();
+        for record in records {
+            state.push_record(record);
+        }
+        self.shared.changed.notify_all();
+    }
+
+    /// Makes the next `call` fail with `err`. Failures for the same call are returned in order.
+    pub fn fail_next(&self, call: SimulatedCall, err: DirectInputError) {
+        self.lock().failures.entry(call).or_default().push_back(err);
+    }
+
+    /// Detaches the device. Reads fail with `InputLost` and acquiring fails with `Unplugged`
+    /// until [`plug`](Self::plug) is called.
+    pub fn unplug(&self) {
+        let mut state = self.lock();
+        state.caps.dwFlags &= !DIDC_ATTACHED;
+        state.signal();
+        self.shared.changed.notify_all();
+    }
+
+    pub fn plug(&self) {
+        self.lock().caps.dwFlags |= DIDC_ATTACHED;
+    }
+
+    /// Signals the notification event without changing the device state.
+    pub fn signal(&self) {
+        self.lock().signal();
+        self.shared.changed.notify_all();
+    }
+
+    pub fn is_acquired(&self) -> bool {
+        self.lock().acquired
+    }
+
+    /// The `DISCL_*` flags passed to the last successful `SetCooperativeLevel` call.
+    pub fn cooperative_level(&self) -> Option<u32> {
+        self.lock().cooperative_level
+    }
+
+    /// The `DIPROP_RANGE` currently applied to the object with the given `DIDFT_*` identifier.
+    pub fn range(&self, object_id: u32) -> Option<(i32, i32)> {
+        let state = self.lock();
+
+        state
+            .objects
+            .iter()
+            .find(|object| object.dwType == object_id && object.dwType & DIDFT_AXIS != 0)
+            .map(|object| {
+                state
+                    .ranges
+                    .get(&object.dwType)
+                    .copied()
+                    .unwrap_or(DEFAULT_RANGE)
+            })
+    }
+
+    /// The effects currently created on the device, in creation order.
+    pub fn effects(&self) -> Vec<SimulatedEffect> {
+        self.lock()
+            .effects
+            .iter()
+            .map(|(_, effect)| effect.clone())
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SimState> {
+        self.shared.state.lock().unwrap()
+    }
+}
+
+impl SimulatedEffect {
+    /// Copies the parameters selected by the `DIEP_*` flags.
+    unsafe fn update(&mut self, params: &DIEFFECT, flags: u32) {
+        if flags & DIEP_DURATION != 0 {
+            self.duration = params.dwDuration;
+        }
+        if flags & DIEP_GAIN != 0 {
+            self.gain = params.dwGain;
+        }
+        if flags & DIEP_AXES != 0 {
+            self.axes = if params.cAxes == 0 {
+                Vec::new()
+            } else {
+                slice::from_raw_parts(params.rgdwAxes, params.cAxes as usize).to_vec()
+            };
+        }
+        if flags & DIEP_TYPESPECIFICPARAMS != 0 {
+            self.type_specific_size = params.cbTypeSpecificParams;
+        }
+    }
+}
+
+impl SimState {
+    fn attached(&self) -> bool {
+        self.caps.dwFlags & DIDC_ATTACHED != 0
+    }
+
+    fn signal(&mut self) {
+        if self.event {
+            self.signalled = true;
+
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    fn push_record(&mut self, record: DIDEVICEOBJECTDATA) {
+        if !self.acquired || self.buffer_size == 0 {
+            return;
+        }
+
+        if self.buffer.len() == self.buffer_size as usize {
+            self.buffer.pop_front();
+            self.overflowed = true;
+        }
+        self.buffer.push_back(record);
+        self.signal();
+    }
+
+    fn count_object(&mut self, ty: u32) {
+        if ty & DIDFT_AXIS != 0 {
+            self.caps.dwAxes += 1;
+        } else if ty & DIDFT_POV != 0 {
+            self.caps.dwPOVs += 1;
+        } else {
+            self.caps.dwButtons += 1;
+        }
+    }
+
+    fn take_failure(&mut self, call: SimulatedCall) -> Result<()> {
+        match self.failures.get_mut(&call).and_then(VecDeque::pop_front) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Mirrors DirectInput's behaviour of reporting `InputLost` once to the acquirer of a device
+    /// that was unplugged and `NotAcquired` afterwards.
+    fn check_acquired(&mut self) -> Result<()> {
+        if !self.attached() && self.acquired {
+            self.acquired = false;
+            return Err(DirectInputError::InputLost);
+        }
+        if !self.acquired {
+            return Err(DirectInputError::NotAcquired);
+        }
+
+        Ok(())
+    }
+
+    fn exclusively_acquired(&self) -> bool {
+        self.acquired
+            && matches!(self.cooperative_level, Some(flags) if flags & DISCL_EXCLUSIVE != 0)
+    }
+
+    /// Finds an object for `semantic` that is not in `used`. Keyboard semantics map to the key
+    /// with the scan code of the semantic's index on keyboards, genre and `DIAXIS_ANY_*`
+    /// semantics to the first free object of the right kind on game controllers.
+    fn find_semantic_object(&self, semantic: u32, used: &[u32]) -> Option<u32> {
+        let kind = semantic_kind(semantic)?;
+        let keyboard = semantic_genre(semantic) == GENRE_KEYBOARD;
+        let dev_type = self.instance.dwDevType;
+
+        if keyboard != filter_matches(DI8DEVCLASS_KEYBOARD, dev_type)
+            || (!keyboard && !filter_matches(DI8DEVCLASS_GAMECTRL, dev_type))
+        {
+            return None;
+        }
+
+        self.objects
+            .iter()
+            .map(|object| object.dwType)
+            .filter(|&ty| ty & kind.type_mask() != 0 && !used.contains(&ty))
+            .find(|&ty| !keyboard || (ty >> 8) & 0xffff == semantic & 0xff)
+    }
+
+    fn has_object(&self, object_id: u32) -> bool {
+        self.objects.iter().any(|object| object.dwType == object_id)
+    }
+
+    fn effect_mut(&mut self, id: u32) -> &mut SimulatedEffect {
+        self.effects
+            .iter_mut()
+            .find(|(effect_id, _)| *effect_id == id)
+            .map(|(_, effect)| effect)
+            .expect("simulated effect is alive while its handle exists")
+    }
+
+    /// Downloads an effect like `IDirectInputEffect::Download`.
+    fn download_effect(&mut self, id: u32) -> Result<DirectInputStatus> {
+        let exclusive = self.exclusively_acquired();
+        let effect = self.effect_mut(id);
+
+        if effect.downloaded {
+            return Ok(DirectInputStatus::NoEffect);
+        }
+        if !exclusive {
+            return Err(DirectInputError::NotExclusiveAcquired);
+        }
+        if effect.axes.is_empty() || effect.type_specific_size == 0 {
+            return Err(DirectInputError::IncompleteEffect);
+        }
+
+        effect.downloaded = true;
+
+        Ok(DirectInputStatus::Ok)
+    }
+
+    /// Resolves a property header to the axes it applies to. Offsets are matched against the
+    /// object offsets rather than a data format.
+    fn find_axis(&self, how: u32, obj: u32) -> Result<Vec<u32>> {
+        let mut axes = self
+            .objects
+            .iter()
+            .filter(|object| object.dwType & DIDFT_AXIS != 0);
+
+        let found = match how {
+            DIPH_DEVICE => return Ok(axes.map(|object| object.dwType).collect()),
+            DIPH_BYID => axes.find(|object| object.dwType == obj),
+            DIPH_BYOFFSET => axes.find(|object| object.dwOfs == obj),
+            DIPH_BYUSAGE => axes
+                .clone()
+                .find(|object| (object.wUsagePage as u32) << 16 | object.wUsage as u32 == obj),
+            _ => return Err(DirectInputError::InvalidParam),
+        };
+
+        found
+            .map(|object| vec![object.dwType])
+            .ok_or(DirectInputError::ObjectNotFound)
+    }
+
+    /// Resolves a property header to a single axis, as needed to read per-axis properties.
+    fn find_single_axis(&self, header: &DIPROPHEADER) -> Result<u32> {
+        if header.dwHow == DIPH_DEVICE {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        Ok(self.find_axis(header.dwHow, header.dwObj)?[0])
+    }
+
+    fn vid_pid(&self) -> (u16, u16) {
+        let data1 = self.instance.guidProduct.data1;
+
+        (data1 as u16, (data1 >> 16) as u16)
+    }
+
+    /// Per-axis `DWORD` property storage and its default value.
+    fn axis_dwords(&mut self, property: *const GUID) -> Option<(&mut HashMap<u32, u32>, u32)> {
+        if property == DIPROP_DEADZONE {
+            Some((&mut self.dead_zones, 0))
+        } else if property == DIPROP_SATURATION {
+            Some((&mut self.saturations, 10_000))
+        } else if property == DIPROP_CALIBRATIONMODE {
+            Some((&mut self.calibration_modes, DIPROPCALIBRATIONMODE_COOKED))
+        } else {
+            None
+        }
+    }
+
+    fn get_dword(&mut self, property: *const GUID, header: &DIPROPHEADER) -> Result<u32> {
+        if self.axis_dwords(property).is_some() {
+            let ty = self.find_single_axis(header)?;
+            let (values, default) = self.axis_dwords(property).unwrap();
+
+            return Ok(values.get(&ty).copied().unwrap_or(default));
+        }
+        if property == DIPROP_GRANULARITY {
+            self.find_single_axis(header)?;
+
+            return Ok(1);
+        }
+        if header.dwHow != DIPH_DEVICE {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        Ok(if property == DIPROP_BUFFERSIZE {
+            self.buffer_size
+        } else if property == DIPROP_AXISMODE {
+            self.axis_mode
+        } else if property == DIPROP_AUTOCENTER {
+            self.auto_center
+        } else if property == DIPROP_FFGAIN {
+            self.ff_gain
+        } else if property == DIPROP_JOYSTICKID {
+            self.joystick_id
+        } else {
+            let (vid, pid) = self.vid_pid();
+            (pid as u32) << 16 | vid as u32
+        })
+    }
+
+    fn set_dword(&mut self, property: *const GUID, prop: &DIPROPDWORD) -> Result<()> {
+        let value = prop.dwData;
+
+        if property == DIPROP_GRANULARITY
+            || property == DIPROP_JOYSTICKID
+            || property == DIPROP_VIDPID
+        {
+            return Err(DirectInputError::ReadOnly);
+        }
+
+        if property == DIPROP_DEADZONE || property == DIPROP_SATURATION {
+            if value > 10_000 {
+                return Err(DirectInputError::InvalidParam);
+            }
+        } else if property == DIPROP_CALIBRATIONMODE
+            && value != DIPROPCALIBRATIONMODE_COOKED
+            && value != DIPROPCALIBRATIONMODE_RAW
+        {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        if self.axis_dwords(property).is_some() {
+            let axes = self.find_axis(prop.diph.dwHow, prop.diph.dwObj)?;
+            let (values, _) = self.axis_dwords(property).unwrap();
+            for ty in axes {
+                values.insert(ty, value);
+            }
+
+            return Ok(());
+        }
+        if prop.diph.dwHow != DIPH_DEVICE {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        if property == DIPROP_BUFFERSIZE {
+            self.buffer_size = value;
+            self.buffer.clear();
+            self.overflowed = false;
+        } else if property == DIPROP_AXISMODE {
+            if value != DIPROPAXISMODE_ABS && value != DIPROPAXISMODE_REL {
+                return Err(DirectInputError::InvalidParam);
+            }
+            self.axis_mode = value;
+        } else if property == DIPROP_AUTOCENTER {
+            if value > DIPROPAUTOCENTER_ON {
+                return Err(DirectInputError::InvalidParam);
+            }
+            self.auto_center = value;
+        } else {
+            if value > 10_000 {
+                return Err(DirectInputError::InvalidParam);
+            }
+            self.ff_gain = value;
+        }
+
+        Ok(())
+    }
+}
+
+impl DeviceBackend for SimulatedDevice {
+    fn capabilities(&self) -> Result<DIDEVCAPS> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::Capabilities)?;
+
+        Ok(state.caps)
+    }
+
+    fn enum_objects(&self, flags: u32) -> Result<Vec<DIDEVICEOBJECTINSTANCEW>> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::EnumObjects)?;
+
+        let ty = flags & 0xff;
+
+        Ok(state
+            .objects
+            .iter()
+            .filter(|object| ty == 0 || object.dwType & ty != 0)
+            .copied()
+            .collect())
+    }
+
+    unsafe fn get_property(
+        &self,
+        property: *const GUID,
+        header: *mut DIPROPHEADER,
+    ) -> Result<DirectInputStatus> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::GetProperty)?;
+
+        if DWORD_PROPERTIES.contains(&property) {
+            (*(header as *mut DIPROPDWORD)).dwData = state.get_dword(property, &*header)?;
+
+            return Ok(DirectInputStatus::Ok);
+        }
+        if property == DIPROP_RANGE {
+            let range = &mut *(header as *mut DIPROPRANGE);
+            let ty = state.find_single_axis(&range.diph)?;
+            let (min, max) = state.ranges.get(&ty).copied().unwrap_or(DEFAULT_RANGE);
+            range.lMin = min;
+            range.lMax = max;
+
+            return Ok(DirectInputStatus::Ok);
+        }
+        if property == DIPROP_GUIDANDPATH {
+            let (vid, pid) = state.vid_pid();
+            let prop = &mut *(header as *mut DIPROPGUIDANDPATH);
+            let path = format!(
+                "\\\\?\\hid#vid_{:04x}&pid_{:04x}#{:08x}",
+                vid, pid, state.instance.guidInstance.data1
+            );
+            prop.guidClass = GUID_DEVCLASS_HIDCLASS;
+            copy_to_wide(&mut prop.wszPath, &path);
+
+            return Ok(DirectInputStatus::Ok);
+        }
+
+        let name = if property == DIPROP_PRODUCTNAME {
+            os_string_from_wide(&state.instance.tszProductName)
+        } else if property == DIPROP_INSTANCENAME {
+            os_string_from_wide(&state.instance.tszInstanceName)
+        } else if property == DIPROP_TYPENAME {
+            let (vid, pid) = state.vid_pid();
+            format!("VID_{:04X}&PID_{:04X}", vid, pid).into()
+        } else {
+            return Err(DirectInputError::Unsupported);
+        };
+
+        let prop = &mut *(header as *mut DIPROPSTRING);
+        copy_to_wide(&mut prop.wsz, &name.to_string_lossy());
+
+        Ok(DirectInputStatus::Ok)
+    }
+
+    unsafe fn set_property(
+        &self,
+        property: *const GUID,
+        header: *const DIPROPHEADER,
+    ) -> Result<DirectInputStatus> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::SetProperty)?;
+
+        if state.acquired {
+            return Err(DirectInputError::Acquired);
+        }
+
+        if DWORD_PROPERTIES.contains(&property) {
+            state.set_dword(property, &*(header as *const DIPROPDWORD))?;
+
+            return Ok(DirectInputStatus::Ok);
+        }
+        if property == DIPROP_RANGE {
+            let range = &*(header as *const DIPROPRANGE);
+            if range.lMin > range.lMax {
+                return Err(DirectInputError::InvalidParam);
+            }
+
+            for ty in state.find_axis(range.diph.dwHow, range.diph.dwObj)? {
+                state.ranges.insert(ty, (range.lMin, range.lMax));
+            }
+
+            return Ok(DirectInputStatus::Ok);
+        }
+        if property == DIPROP_PRODUCTNAME || property == DIPROP_INSTANCENAME {
+            let prop = &*(header as *const DIPROPSTRING);
+            if prop.diph.dwHow != DIPH_DEVICE {
+                return Err(DirectInputError::InvalidParam);
+            }
+
+            let dst = if property == DIPROP_PRODUCTNAME {
+                &mut state.instance.tszProductName
+            } else {
+                &mut state.instance.tszInstanceName
+            };
+            *dst = prop.wsz;
+
+            return Ok(DirectInputStatus::Ok);
+        }
+        if property == DIPROP_GUIDANDPATH || property == DIPROP_TYPENAME {
+            return Err(DirectInputError::ReadOnly);
+        }
+
+        Err(DirectInputError::Unsupported)
+    }
+
+    fn set_data_format(&self, format: &DIDATAFORMAT) -> Result<()> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::SetDataFormat)?;
+
+        if state.acquired {
+            return Err(DirectInputError::Acquired);
+        }
+        if format.dwDataSize == 0 || format.dwDataSize & 3 != 0 {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        state.data_size = Some(format.dwDataSize);
+
+        Ok(())
+    }
+
+    fn set_cooperative_level(&self, _hwnd: HWND, flags: u32) -> Result<()> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::SetCooperativeLevel)?;
+
+        state.cooperative_level = Some(flags);
+
+        Ok(())
+    }
+
+    fn init_event(&mut self) -> Result<DirectInputStatus> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::InitEvent)?;
+
+        state.event = true;
+        state.signalled = false;
+
+        Ok(if state.caps.dwFlags & DIDC_POLLEDDEVICE != 0 {
+            DirectInputStatus::PolledDevice
+        } else {
+            DirectInputStatus::Ok
+        })
+    }
+
+    fn wait(&self, timeout: Duration) -> Result<bool> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::Wait)?;
+
+        if !state.event {
+            return Ok(true);
+        }
+
+        let (mut state, _) = self
+            .shared
+            .changed
+            .wait_timeout_while(state, timeout, |state| !state.signalled)
+            .unwrap();
+
+        Ok(mem::replace(&mut state.signalled, false))
+    }
+
+    fn poll_event(&mut self, waker: &Waker) -> Result<bool> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::PollEvent)?;
+
+        if !state.event || mem::replace(&mut state.signalled, false) {
+            return Ok(true);
+        }
+
+        state.waker = Some(waker.clone());
+
+        Ok(false)
+    }
+
+    fn event_handle(&self) -> Result<Option<HANDLE>> {
+        Ok(None)
+    }
+
+    fn acquire(&self) -> Result<()> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::Acquire)?;
+
+        if !state.attached() {
+            return Err(DirectInputError::Unplugged);
+        }
+        if state.data_size.is_none() {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        state.acquired = true;
+
+        Ok(())
+    }
+
+    fn unacquire(&self) -> Result<()> {
+        let mut state = self.lock();
+        state.acquired = false;
+        state.buffer.clear();
+        state.overflowed = false;
+
+        // Effects are unloaded from the device when it is unacquired
+        for (_, effect) in &mut state.effects {
+            effect.downloaded = false;
+            effect.playing = false;
+        }
+
+        Ok(())
+    }
+
+    fn poll(&self) -> Result<DirectInputStatus> {
+        let mut state = self.lock();
+        state.check_acquired()?;
+        state.take_failure(SimulatedCall::Poll)?;
+
+        if state.caps.dwFlags & DIDC_POLLEDDEVICE != 0 {
+            state.signal();
+            self.shared.changed.notify_all();
+
+            Ok(DirectInputStatus::Ok)
+        } else {
+            Ok(DirectInputStatus::NoEffect)
+        }
+    }
+
+    fn get_device_state(&self, data: &mut [u8]) -> Result<()> {
+        let mut state = self.lock();
+
+        match state.data_size {
+            Some(size) if size as usize == data.len() => {}
+            Some(_) => return Err(DirectInputError::InvalidParam),
+            None => return Err(DirectInputError::NotInitialized),
+        };
+
+        state.check_acquired()?;
+        state.take_failure(SimulatedCall::GetDeviceState)?;
+
+        match state.steps.pop_front() {
+            Some(Step::State(bytes)) => state.current = bytes,
+            Some(Step::Error(err)) => {
+                if err == DirectInputError::InputLost {
+                    state.acquired = false;
+                }
+
+                return Err(err);
+            }
+            None => {}
+        };
+
+        let len = state.current.len().min(data.len());
+        data[..len].copy_from_slice(&state.current[..len]);
+        data[len..].iter_mut().for_each(|b| *b = 0);
+
+        Ok(())
+    }
+
+    fn get_device_data(
+        &self,
+        data: &mut [DIDEVICEOBJECTDATA],
+    ) -> Result<(usize, DirectInputStatus)> {
+        let mut state = self.lock();
+
+        if state.data_size.is_none() {
+            return Err(DirectInputError::NotInitialized);
+        }
+        if state.buffer_size == 0 {
+            return Err(DirectInputError::NotBuffered);
+        }
+
+        state.check_acquired()?;
+        state.take_failure(SimulatedCall::GetDeviceData)?;
+
+        let count = data.len().min(state.buffer.len());
+        for (dst, src) in data.iter_mut().zip(state.buffer.drain(..count)) {
+            *dst = src;
+        }
+
+        let status = if mem::replace(&mut state.overflowed, false) {
+            DirectInputStatus::BufferOverflow
+        } else {
+            DirectInputStatus::Ok
+        };
+
+        Ok((count, status))
+    }
+
+    fn enum_effects(&self, ty: u32) -> Result<Vec<DIEFFECTINFOW>> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::EnumEffects)?;
+
+        Ok(state
+            .effect_kinds
+            .iter()
+            .map(|&kind| effect_info(kind))
+            .filter(|info| ty == DIEFT_ALL || info.dwEffType & 0xff == ty)
+            .collect())
+    }
+
+    unsafe fn create_effect(
+        &self,
+        guid: &GUID,
+        params: &DIEFFECT,
+    ) -> Result<Box<dyn EffectBackend>> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::CreateEffect)?;
+
+        if !state.effect_kinds.iter().any(|kind| kind.guid() == *guid) {
+            return Err(DirectInputError::DeviceNotReg);
+        }
+
+        let mut effect = SimulatedEffect {
+            guid: *guid,
+            axes: Vec::new(),
+            duration: 0,
+            gain: 0,
+            type_specific_size: 0,
+            downloaded: false,
+            playing: false,
+            iterations: 0,
+        };
+        effect.update(params, DIEP_ALLPARAMS);
+
+        let id = state.next_effect;
+        state.next_effect += 1;
+        state.effects.push((id, effect));
+
+        // Like DirectInput, complete effects are downloaded right away if possible
+        let _ = state.download_effect(id);
+
+        Ok(Box::new(SimulatedEffectHandle {
+            device: self.clone(),
+            id,
+        }))
+    }
+
+    unsafe fn build_action_map(
+        &self,
+        format: &mut DIACTIONFORMATW,
+        _user: Option<&str>,
+        flags: u32,
+    ) -> Result<DirectInputStatus> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::BuildActionMap)?;
+
+        let guid = state.instance.guidInstance;
+        let mut used = Vec::new();
+
+        // Keep the mappings to this device that must be preserved and clear the others
+        for action in raw_actions(format).iter_mut() {
+            let keep = action.guidInstance == guid
+                && action.dwHow != DIAH_UNMAPPED
+                && action.dwFlags & DIA_APPNOMAP == 0
+                && (flags & DIDBAM_PRESERVE != 0
+                    || (action.dwFlags & DIA_APPMAPPED != 0 && flags & DIDBAM_INITIALIZE == 0));
+
+            if keep && state.has_object(action.dwObjID) {
+                if action.dwFlags & DIA_APPMAPPED != 0 {
+                    action.dwHow = DIAH_APPREQUESTED;
+                }
+                used.push(action.dwObjID);
+            } else if keep {
+                action.dwHow = DIAH_ERROR;
+            } else {
+                action.guidInstance = GUID::zeroed();
+                action.dwObjID = 0;
+                action.dwHow = DIAH_UNMAPPED;
+            }
+        }
+
+        let mut mapped = !used.is_empty();
+        for action in raw_actions(format).iter_mut() {
+            if action.dwHow != DIAH_UNMAPPED || action.dwFlags & DIA_APPNOMAP != 0 {
+                continue;
+            }
+
+            if let Some(object_id) = state.find_semantic_object(action.dwSemantic, &used) {
+                action.guidInstance = guid;
+                action.dwObjID = object_id;
+                action.dwHow = DIAH_DEFAULT;
+                used.push(object_id);
+                mapped = true;
+            }
+        }
+
+        Ok(if mapped {
+            DirectInputStatus::Ok
+        } else {
+            DirectInputStatus::NoEffect
+        })
+    }
+
+    unsafe fn set_action_map(
+        &self,
+        format: &mut DIACTIONFORMATW,
+        _user: Option<&str>,
+        _flags: u32,
+    ) -> Result<DirectInputStatus> {
+        let mut state = self.lock();
+        state.take_failure(SimulatedCall::SetActionMap)?;
+
+        if state.acquired {
+            return Err(DirectInputError::Acquired);
+        }
+
+        let guid = state.instance.guidInstance;
+        let range = (format.lAxisMin, format.lAxisMax);
+        let (data_size, buffer_size) = (format.dwDataSize, format.dwBufferSize);
+        let actions = raw_actions(format);
+        let mapped = |action: &DIACTIONW| {
+            action.guidInstance == guid
+                && action.dwHow != DIAH_UNMAPPED
+                && action.dwHow & DIAH_ERROR == 0
+        };
+
+        if actions
+            .iter()
+            .any(|action| mapped(action) && !state.has_object(action.dwObjID))
+        {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        state.action_map = actions
+            .iter()
+            .map(|action| {
+                if mapped(action) {
+                    (action.dwObjID, action.uAppData)
+                } else {
+                    // Never matches an object
+                    (0, action.uAppData)
+                }
+            })
+            .collect();
+        for action in actions.iter().filter(|action| mapped(action)) {
+            if action.dwObjID & DIDFT_AXIS != 0 && action.dwFlags & DIA_NORANGE == 0 {
+                state.ranges.insert(action.dwObjID, range);
+            }
+        }
+        state.data_size = Some(data_size);
+        state.buffer_size = buffer_size;
+        state.buffer.clear();
+
+        Ok(DirectInputStatus::Ok)
+    }
+}
+
+impl EffectBackend for SimulatedEffectHandle {
+    unsafe fn set_parameters(&self, params: &DIEFFECT, flags: u32) -> Result<DirectInputStatus> {
+        let mut state = self.device.lock();
+        let exclusive = state.exclusively_acquired();
+        let effect = state.effect_mut(self.id);
+
+        if effect.playing && flags & DIEP_AXES != 0 && flags & DIEP_NORESTART != 0 {
+            return Err(DirectInputError::EffectPlaying);
+        }
+
+        effect.update(params, flags);
+
+        if flags & DIEP_NODOWNLOAD != 0 {
+            return Ok(DirectInputStatus::Ok);
+        }
+        if !exclusive {
+            effect.downloaded = false;
+            effect.playing = false;
+
+            return Ok(DirectInputStatus::DownloadSkipped);
+        }
+
+        let restarted = effect.playing && flags & DIEP_AXES != 0;
+        effect.downloaded = false;
+        state.download_effect(self.id)?;
+
+        let effect = state.effect_mut(self.id);
+        if flags & DIEP_START != 0 {
+            effect.playing = true;
+        }
+
+        Ok(if restarted {
+            DirectInputStatus::EffectRestarted
+        } else {
+            DirectInputStatus::Ok
+        })
+    }
+
+    fn start(&self, iterations: u32, flags: u32) -> Result<()> {
+        let mut state = self.device.lock();
+        state.take_failure(SimulatedCall::StartEffect)?;
+
+        if !state.effect_mut(self.id).downloaded {
+            if flags & DIES_NODOWNLOAD != 0 {
+                return Err(DirectInputError::NotDownloaded);
+            }
+
+            state.download_effect(self.id)?;
+        }
+
+        if flags & DIES_SOLO != 0 {
+            for (_, effect) in &mut state.effects {
+                effect.playing = false;
+            }
+        }
+
+        let effect = state.effect_mut(self.id);
+        effect.playing = true;
+        effect.iterations = iterations;
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<()> {
+        let mut state = self.device.lock();
+        let effect = state.effect_mut(self.id);
+
+        if !effect.downloaded {
+            return Err(DirectInputError::NotDownloaded);
+        }
+        effect.playing = false;
+
+        Ok(())
+    }
+
+    fn status(&self) -> Result<u32> {
+        let mut state = self.device.lock();
+        let effect = state.effect_mut(self.id);
+
+        if !effect.downloaded {
+            return Err(DirectInputError::NotDownloaded);
+        }
+
+        Ok(if effect.playing { DIEGES_PLAYING } else { 0 })
+    }
+
+    fn download(&self) -> Result<DirectInputStatus> {
+        self.device.lock().download_effect(self.id)
+    }
+
+    fn unload(&self) -> Result<()> {
+        let mut state = self.device.lock();
+        let effect = state.effect_mut(self.id);
+        effect.downloaded = false;
+        effect.playing = false;
+
+        Ok(())
+    }
+}
+
+impl Drop for SimulatedEffectHandle {
+    fn drop(&mut self) {
+        let mut state = self.device.lock();
+        state.effects.retain(|(id, _)| *id != self.id);
+    }
+}
+
+fn effect_info(kind: EffectKind) -> DIEFFECTINFOW {
+    let (ty, name) = match kind {
+        EffectKind::ConstantForce => (DIEFT_CONSTANTFORCE, "Constant Force"),
+        EffectKind::RampForce => (DIEFT_RAMPFORCE, "Ramp Force"),
+        EffectKind::Square => (DIEFT_PERIODIC, "Square"),
+        EffectKind::Sine => (DIEFT_PERIODIC, "Sine"),
+        EffectKind::Triangle => (DIEFT_PERIODIC, "Triangle"),
+        EffectKind::SawtoothUp => (DIEFT_PERIODIC, "Sawtooth Up"),
+        EffectKind::SawtoothDown => (DIEFT_PERIODIC, "Sawtooth Down"),
+        EffectKind::Spring => (DIEFT_CONDITION, "Spring"),
+        EffectKind::Damper => (DIEFT_CONDITION, "Damper"),
+        EffectKind::Inertia => (DIEFT_CONDITION, "Inertia"),
+        EffectKind::Friction => (DIEFT_CONDITION, "Friction"),
+        EffectKind::CustomForce => (DIEFT_CUSTOMFORCE, "Custom Force"),
+    };
+
+    let mut info = DIEFFECTINFOW {
+        dwSize: mem::size_of::<DIEFFECTINFOW>() as _,
+        guid: kind.guid(),
+        dwEffType: ty,
+        dwStaticParams: DIEP_ALLPARAMS,
+        dwDynamicParams: DIEP_ALLPARAMS,
+        ..Default::default()
+    };
+    copy_to_wide(&mut info.tszName, name);
+
+    info
+}
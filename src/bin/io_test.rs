@@ -3,7 +3,10 @@ use std::fmt;
 use std::thread;
 use std::time::Duration;
 
-use directinput::{CooperativeLevel, Device, DirectInputError, DirectInputManager, JoyState};
+use directinput::{
+    CooperativeLevel, Device, DirectInputError, DirectInputManager, JoyAxis, JoyState, StateDiff,
+    StateEvent,
+};
 use winit::event::Event;
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::WindowBuilder;
@@ -84,25 +87,23 @@ fn main() {
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
 
-        match event {
-            Event::UserEvent(e) => {
-                eprintln!("Error: {}", e);
+        if let Event::UserEvent(e) = event {
+            eprintln!("Error: {}", e);
 
-                if let Some(e) = e.source() {
-                    eprintln!();
-                    eprintln!("Caused by:");
-                    eprintln!("    {}", e);
-                }
-
-                *control_flow = ControlFlow::Exit;
+            if let Some(e) = e.source() {
+                eprintln!();
+                eprintln!("Caused by:");
+                eprintln!("    {}", e);
             }
-            _ => {}
+
+            *control_flow = ControlFlow::Exit;
         }
     });
 }
 
 fn input_thread(device: Device) -> Result<(), Error> {
-    let mut previous_state: Option<JoyState> = None;
+    // Ignore jitter of a few units on the axes, which span the i16 range
+    let mut diff = StateDiff::new().default_threshold(64);
 
     loop {
         println!(
@@ -119,22 +120,20 @@ fn input_thread(device: Device) -> Result<(), Error> {
         let state = device
             .get_state::<JoyState>()
             .map_err(|source| Error::new(source, "Failed to get device state"))?;
-        let last_state = previous_state.as_ref().unwrap_or(&state);
-
-        // Detect negative-to-positive range rollover
-        let change = state.x as i32 - last_state.x as i32;
-        let rollover_detect = if change.abs() > i16::MAX as i32 {
-            ", roll-over detected!"
-        } else {
-            ""
-        };
-
-        println!(
-            "Axes: X: {} (change: {}), Y: {}, Z: {}{}",
-            state.x, change, state.y, state.z, rollover_detect
-        );
 
-        previous_state = Some(state);
+        for event in diff.update(state) {
+            match event {
+                // Detect negative-to-positive range rollover
+                StateEvent::AxisMoved {
+                    axis: JoyAxis::X,
+                    old,
+                    new,
+                } if (i64::from(new) - i64::from(old)).abs() > i64::from(i16::MAX) => {
+                    println!("{:?}, roll-over detected!", event)
+                }
+                _ => println!("{:?}", event),
+            }
+        }
 
         std::thread::sleep(Duration::from_millis(25));
     }
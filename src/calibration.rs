@@ -0,0 +1,477 @@
+use std::collections::BTreeMap;
+
+use crate::device_guid::DeviceGuid;
+use crate::device_info::DirectInputDeviceInfo;
+use crate::joy_state::JoyState;
+use crate::property::Range;
+
+/// Range DirectInput reports absolute axes in until another range is set with
+/// [`Device::set_axes_range`](crate::Device::set_axes_range).
+pub const DEFAULT_AXIS_RANGE: Range = Range {
+    min: 0,
+    max: 0xffff,
+};
+
+/// An axis or slider of a [`JoyState`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JoyAxis {
+    X,
+    Y,
+    Z,
+    Rx,
+    Ry,
+    Rz,
+    Slider0,
+    Slider1,
+}
+
+/// Range a calibrated axis is mapped to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AxisPolarity {
+    /// `[-1, 1]`, with the center at 0. Used for sticks and wheels.
+    Bipolar,
+    /// `[0, 1]`, ignoring the center. Used for sliders, throttles and pedals.
+    Unipolar,
+}
+
+/// Calibration record of a single axis: the raw values at both ends and at rest.
+///
+/// Raw values outside `min..=max` are clamped. A bipolar axis is scaled separately on each side
+/// of `center`, so an off-center stick still reads 0 at rest and reaches both ends.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisCalibration {
+    pub min: i32,
+    pub center: i32,
+    pub max: i32,
+    pub inverted: bool,
+    pub polarity: AxisPolarity,
+}
+
+/// [`JoyState`] axes and sliders normalized by a [`DeviceCalibration`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NormalizedAxes {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub rx: f32,
+    pub ry: f32,
+    pub rz: f32,
+    pub slider: [f32; 2],
+}
+
+/// Calibration of every axis and slider of a device.
+///
+/// Axes without a record use the DirectInput default range, bipolar for axes and unipolar for
+/// sliders.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct DeviceCalibration {
+    axes: BTreeMap<JoyAxis, AxisCalibration>,
+}
+
+/// Device calibrations keyed by product GUID, so that a calibration applies to every device of
+/// the same model. With the `serde` feature it is (de)serialized as a map from GUID strings.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct CalibrationStore {
+    devices: BTreeMap<DeviceGuid, DeviceCalibration>,
+}
+
+impl JoyAxis {
+    pub const ALL: [Self; 8] = [
+        Self::X,
+        Self::Y,
+        Self::Z,
+        Self::Rx,
+        Self::Ry,
+        Self::Rz,
+        Self::Slider0,
+        Self::Slider1,
+    ];
+
+    pub fn is_slider(self) -> bool {
+        matches!(self, Self::Slider0 | Self::Slider1)
+    }
+
+    /// The raw value of the axis in `state`.
+    pub fn value(self, state: &JoyState) -> i32 {
+        match self {
+            Self::X => state.x,
+            Self::Y => state.y,
+            Self::Z => state.z,
+            Self::Rx => state.rx,
+            Self::Ry => state.ry,
+            Self::Rz => state.rz,
+            Self::Slider0 => state.slider[0],
+            Self::Slider1 => state.slider[1],
+        }
+    }
+}
+
+impl AxisCalibration {
+    /// Creates a bipolar calibration centered between `min` and `max`.
+    pub fn new(min: i32, max: i32) -> Self {
+        let center = (i64::from(min) + i64::from(max)) / 2;
+
+        Self {
+            min,
+            center: center as i32,
+            max,
+            inverted: false,
+            polarity: AxisPolarity::Bipolar,
+        }
+    }
+
+    /// Creates a calibration covering `range`, as set with `Device::set_axes_range`.
+    pub fn from_range(range: Range) -> Self {
+        Self::new(range.min, range.max)
+    }
+
+    /// The calibration of `axis` before any record was made: the default range, with sliders
+    /// being unipolar.
+    pub fn default_for(axis: JoyAxis) -> Self {
+        let calibration = Self::from_range(DEFAULT_AXIS_RANGE);
+
+        if axis.is_slider() {
+            calibration.polarity(AxisPolarity::Unipolar)
+        } else {
+            calibration
+        }
+    }
+
+    pub fn center(mut self, center: i32) -> Self {
+        self.center = center;
+        self
+    }
+
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    pub fn polarity(mut self, polarity: AxisPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Widens the range to include `raw`, e.g. while the user moves the axis to both ends.
+    pub fn record(&mut self, raw: i32) {
+        self.min = self.min.min(raw);
+        self.max = self.max.max(raw);
+    }
+
+    /// Maps `raw` to `[-1, 1]` or `[0, 1]` depending on the polarity. Degenerate ranges, where
+    /// `max` is not above `min` or the center is at one end, map that side to the center value.
+    pub fn normalize(&self, raw: i32) -> f32 {
+        let (min, center, max) = (
+            f64::from(self.min),
+            f64::from(self.center),
+            f64::from(self.max),
+        );
+        let raw = f64::from(raw);
+
+        let value = match self.polarity {
+            AxisPolarity::Bipolar if raw > center && max > center => {
+                (raw - center) / (max - center)
+            }
+            AxisPolarity::Bipolar if raw < center && center > min => {
+                (raw - center) / (center - min)
+            }
+            AxisPolarity::Bipolar => 0.0,
+            AxisPolarity::Unipolar if max > min => (raw - min) / (max - min),
+            AxisPolarity::Unipolar => 0.0,
+        };
+
+        let value = match self.polarity {
+            AxisPolarity::Bipolar => value.clamp(-1.0, 1.0),
+            AxisPolarity::Unipolar => value.clamp(0.0, 1.0),
+        };
+
+        let value = match (self.polarity, self.inverted) {
+            (_, false) => value,
+            (AxisPolarity::Bipolar, true) => -value,
+            (AxisPolarity::Unipolar, true) => 1.0 - value,
+        };
+
+        value as f32
+    }
+}
+
+impl DeviceCalibration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a calibration with every axis covering `range`, as set with
+    /// `Device::set_axes_range`. Sliders are unipolar.
+    pub fn from_range(range: Range) -> Self {
+        let axes = JoyAxis::ALL
+            .iter()
+            .map(|&axis| {
+                let polarity = AxisCalibration::default_for(axis).polarity;
+
+                (axis, AxisCalibration::from_range(range).polarity(polarity))
+            })
+            .collect();
+
+        Self { axes }
+    }
+
+    pub fn get(&self, axis: JoyAxis) -> AxisCalibration {
+        self.axes
+            .get(&axis)
+            .copied()
+            .unwrap_or_else(|| AxisCalibration::default_for(axis))
+    }
+
+    pub fn set(&mut self, axis: JoyAxis, calibration: AxisCalibration) {
+        self.axes.insert(axis, calibration);
+    }
+
+    /// Resets `axis` to its default calibration.
+    pub fn reset(&mut self, axis: JoyAxis) {
+        self.axes.remove(&axis);
+    }
+
+    pub fn normalize_axis(&self, axis: JoyAxis, state: &JoyState) -> f32 {
+        self.get(axis).normalize(axis.value(state))
+    }
+
+    pub fn normalize(&self, state: &JoyState) -> NormalizedAxes {
+        let axis = |axis| self.normalize_axis(axis, state);
+
+        NormalizedAxes {
+            x: axis(JoyAxis::X),
+            y: axis(JoyAxis::Y),
+            z: axis(JoyAxis::Z),
+            rx: axis(JoyAxis::Rx),
+            ry: axis(JoyAxis::Ry),
+            rz: axis(JoyAxis::Rz),
+            slider: [axis(JoyAxis::Slider0), axis(JoyAxis::Slider1)],
+        }
+    }
+}
+
+impl CalibrationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, guid_product: &DeviceGuid) -> Option<&DeviceCalibration> {
+        self.devices.get(guid_product)
+    }
+
+    pub fn get_mut(&mut self, guid_product: &DeviceGuid) -> Option<&mut DeviceCalibration> {
+        self.devices.get_mut(guid_product)
+    }
+
+    /// The calibration of the product of `info`, if one was stored.
+    pub fn for_device(&self, info: &DirectInputDeviceInfo) -> Option<&DeviceCalibration> {
+        self.get(&info.guid_product())
+    }
+
+    pub fn insert(
+        &mut self,
+        guid_product: DeviceGuid,
+        calibration: DeviceCalibration,
+    ) -> Option<DeviceCalibration> {
+        self.devices.insert(guid_product, calibration)
+    }
+
+    pub fn remove(&mut self, guid_product: &DeviceGuid) -> Option<DeviceCalibration> {
+        self.devices.remove(guid_product)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&DeviceGuid, &DeviceCalibration)> {
+        self.devices.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::DIJOYSTATE2;
+
+    use super::*;
+    use crate::device::FromDeviceState;
+
+    fn joy_state(raw: DIJOYSTATE2) -> JoyState {
+        JoyState::from_instance(raw)
+    }
+
+    #[test]
+    fn test_normalize_bipolar() {
+        let axis = AxisCalibration::new(-100, 100);
+        assert_eq!(axis.center, 0);
+
+        assert_eq!(axis.normalize(-100), -1.0);
+        assert_eq!(axis.normalize(-50), -0.5);
+        assert_eq!(axis.normalize(0), 0.0);
+        assert_eq!(axis.normalize(100), 1.0);
+        assert_eq!(axis.normalize(i32::MIN), -1.0);
+        assert_eq!(axis.normalize(i32::MAX), 1.0);
+
+        // Each side of an off-center rest position is scaled on its own
+        let axis = axis.center(50);
+        assert_eq!(axis.normalize(50), 0.0);
+        assert_eq!(axis.normalize(-25), -0.5);
+        assert_eq!(axis.normalize(75), 0.5);
+
+        let axis = axis.inverted(true);
+        assert_eq!(axis.normalize(-100), 1.0);
+        assert_eq!(axis.normalize(75), -0.5);
+
+        let full = AxisCalibration::new(i32::MIN, i32::MAX);
+        assert_eq!(full.normalize(i32::MIN), -1.0);
+        assert_eq!(full.normalize(i32::MAX), 1.0);
+    }
+
+    #[test]
+    fn test_normalize_unipolar() {
+        let axis = AxisCalibration::new(0, 200).polarity(AxisPolarity::Unipolar);
+
+        assert_eq!(axis.normalize(0), 0.0);
+        assert_eq!(axis.normalize(50), 0.25);
+        assert_eq!(axis.normalize(250), 1.0);
+        assert_eq!(axis.inverted(true).normalize(50), 0.75);
+    }
+
+    #[test]
+    fn test_degenerate_ranges() {
+        let axis = AxisCalibration::new(10, 10);
+        assert_eq!(axis.normalize(0), 0.0);
+        assert_eq!(axis.normalize(20), 0.0);
+        assert_eq!(axis.polarity(AxisPolarity::Unipolar).normalize(20), 0.0);
+
+        // Center at the upper end, only the lower side is scaled
+        let axis = AxisCalibration::new(0, 100).center(100);
+        assert_eq!(axis.normalize(50), -0.5);
+        assert_eq!(axis.normalize(100), 0.0);
+    }
+
+    #[test]
+    fn test_record() {
+        let mut axis = AxisCalibration::new(0, 0);
+
+        for &raw in &[300, -100, 200] {
+            axis.record(raw);
+        }
+        assert_eq!((axis.min, axis.max), (-100, 300));
+    }
+
+    #[test]
+    fn test_device_calibration() {
+        let state = joy_state(DIJOYSTATE2 {
+            lX: 0xffff,
+            lY: 0,
+            lZ: 0x7fff,
+            rglSlider: [0xffff, 0],
+            ..Default::default()
+        });
+
+        let mut calibration = DeviceCalibration::new();
+        let axes = calibration.normalize(&state);
+        assert_eq!((axes.x, axes.y), (1.0, -1.0));
+        assert!(axes.z.abs() < 1e-4);
+        assert_eq!(axes.slider, [1.0, 0.0]);
+
+        calibration.set(
+            JoyAxis::Y,
+            AxisCalibration::default_for(JoyAxis::Y).inverted(true),
+        );
+        assert_eq!(calibration.normalize_axis(JoyAxis::Y, &state), 1.0);
+        calibration.reset(JoyAxis::Y);
+        assert_eq!(calibration.normalize_axis(JoyAxis::Y, &state), -1.0);
+
+        let calibration = DeviceCalibration::from_range(Range {
+            min: -1000,
+            max: 1000,
+        });
+        let state = joy_state(DIJOYSTATE2 {
+            lRx: 500,
+            rglSlider: [0, 0],
+            ..Default::default()
+        });
+        let axes = calibration.normalize(&state);
+        assert_eq!(axes.rx, 0.5);
+        assert_eq!(axes.slider, [0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_store() {
+        let guid: DeviceGuid = "c216046d-0000-0000-0000-504944564944".parse().unwrap();
+        let mut store = CalibrationStore::new();
+        assert!(store.get(&guid).is_none());
+
+        let mut calibration = DeviceCalibration::new();
+        calibration.set(JoyAxis::X, AxisCalibration::new(-10, 10));
+        assert!(store.insert(guid, calibration.clone()).is_none());
+
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(&guid), Some(&calibration));
+        assert_eq!(store.remove(&guid), Some(calibration));
+        assert!(store.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use serde_test::{assert_tokens, Token};
+
+        let guid: DeviceGuid = "c216046d-0000-0000-0000-504944564944".parse().unwrap();
+        let mut calibration = DeviceCalibration::new();
+        calibration.set(
+            JoyAxis::Slider0,
+            AxisCalibration::new(0, 100)
+                .inverted(true)
+                .polarity(AxisPolarity::Unipolar),
+        );
+        let mut store = CalibrationStore::new();
+        store.insert(guid, calibration);
+
+        assert_tokens(
+            &store,
+            &[
+                Token::Map { len: Some(1) },
+                Token::Str("c216046d-0000-0000-0000-504944564944"),
+                Token::Map { len: Some(1) },
+                Token::UnitVariant {
+                    name: "JoyAxis",
+                    variant: "Slider0",
+                },
+                Token::Struct {
+                    name: "AxisCalibration",
+                    len: 5,
+                },
+                Token::Str("min"),
+                Token::I32(0),
+                Token::Str("center"),
+                Token::I32(50),
+                Token::Str("max"),
+                Token::I32(100),
+                Token::Str("inverted"),
+                Token::Bool(true),
+                Token::Str("polarity"),
+                Token::UnitVariant {
+                    name: "AxisPolarity",
+                    variant: "Unipolar",
+                },
+                Token::StructEnd,
+                Token::MapEnd,
+                Token::MapEnd,
+            ],
+        );
+    }
+}
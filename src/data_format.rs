@@ -0,0 +1,970 @@
+use std::any::TypeId;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
+use std::ptr;
+use std::slice;
+use std::sync::Mutex;
+
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    GUID_Key, GUID_RxAxis, GUID_RyAxis, GUID_RzAxis, GUID_Slider, GUID_XAxis, GUID_YAxis,
+    GUID_ZAxis, DIDATAFORMAT, DIDFT_ANYINSTANCE, DIDFT_AXIS, DIDFT_BUTTON, DIDFT_POV, DIDF_ABSAXIS,
+    DIDF_RELAXIS, DIDOI_ASPECTACCEL, DIDOI_ASPECTFORCE, DIDOI_ASPECTPOSITION, DIDOI_ASPECTVELOCITY,
+    DIJOYSTATE2, DIMOUSESTATE2, DIOBJECTDATAFORMAT, GUID_POV,
+};
+
+use crate::device::FromDeviceState;
+
+/// Not exported by the `windows` crate. Marks an object in a data format as optional so that
+/// `SetDataFormat` succeeds on devices that do not have a matching object.
+pub const DIDFT_OPTIONAL: u32 = 0x8000_0000;
+
+/// A single entry in a [`DataFormat`], equivalent to `DIOBJECTDATAFORMAT` but owning its GUID.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ObjectFormat {
+    /// Object type GUID (e.g. `GUID_XAxis`), or `None` to match any object type.
+    pub guid: Option<GUID>,
+    /// Offset of the object's value in the device state buffer.
+    pub offset: u32,
+    /// `DIDFT_*` type and instance flags.
+    pub ty: u32,
+    /// `DIDOI_*` flags.
+    pub flags: u32,
+}
+
+/// Kind of value an [`ObjectFormat`] writes into the state buffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObjectKind {
+    /// A `LONG` axis position.
+    Axis,
+    /// A single `BYTE` whose high bit is set while the button is pressed.
+    Button,
+    /// A `DWORD` angle in hundredths of a degree.
+    Pov,
+}
+
+/// An owned DirectInput data format describing how device objects map onto a state buffer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataFormat {
+    flags: u32,
+    data_size: u32,
+    objects: Vec<ObjectFormat>,
+}
+
+/// Builds a validated [`DataFormat`] for a custom state layout.
+///
+/// Objects declared with any instance are matched against the device in declaration order, so
+/// the first axis declared receives the first axis of the device of that type, and so on.
+#[derive(Clone, Debug)]
+pub struct DataFormatBuilder {
+    flags: u32,
+    data_size: Option<u32>,
+    objects: Vec<ObjectFormat>,
+}
+
+/// Reasons a data format layout is rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DataFormatError {
+    /// The format declares no objects.
+    Empty,
+    /// The state buffer size is zero or not a multiple of four bytes.
+    InvalidDataSize(u32),
+    /// The object's type is not exactly one of axis, button or POV.
+    InvalidObjectType { offset: u32, ty: u32 },
+    /// An axis or POV is not aligned to four bytes.
+    Misaligned { offset: u32 },
+    /// The object does not fit in the state buffer.
+    OutOfBounds { offset: u32, data_size: u32 },
+    /// Two objects write to overlapping bytes of the state buffer.
+    Overlap { first: u32, second: u32 },
+    /// The raw state a format is read into is not exactly the format's data size.
+    BufferSize { data_size: u32, buffer_size: usize },
+}
+
+/// Device state read with a runtime [`DataFormat`], with values grouped by object kind in
+/// declaration order.
+///
+/// `CustomState` cannot implement [`FromDeviceState`] itself as the trait describes a layout fixed
+/// at compile time, while a [`DataFormat`] can be built at runtime. A state read with any format
+/// is returned by [`Device::get_custom_state`](crate::Device::get_custom_state). Layouts whose
+/// size is known at compile time can instead implement [`CustomLayout`] to be read through
+/// [`Device::get_state`](crate::Device::get_state) as a [`LayoutState`], and
+/// `#[derive(DeviceState)]` generates a dedicated state type.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CustomState {
+    pub axes: Vec<i32>,
+    pub buttons: Vec<u8>,
+    pub povs: Vec<u32>,
+}
+
+/// A layout built with [`DataFormatBuilder`] whose size is known at compile time.
+///
+/// ```ignore
+/// struct Throttle;
+///
+/// impl CustomLayout for Throttle {
+///     type Buffer = [u32; 2];
+///
+///     fn data_format() -> DataFormat {
+///         DataFormat::builder()
+///             .axis(GUID_ZAxis, None, 0)
+///             .button(Some(0), 4)
+///             .build()
+///             .unwrap()
+///     }
+/// }
+///
+/// let state = device.get_state::<LayoutState<Throttle>>()?;
+/// ```
+pub trait CustomLayout {
+    /// Plain buffer the state is read into, exactly [`DataFormat::data_size`] bytes long, e.g.
+    /// `[u32; N]` for a data size of `4 * N` bytes. Initializing a device with a mismatched
+    /// buffer fails with [`DataFormatError::BufferSize`].
+    type Buffer: Copy;
+
+    /// Builds the format of the layout, which is only called once per layout.
+    fn data_format() -> DataFormat;
+}
+
+/// A [`CustomState`] read with the layout `L`, which carries the format so it implements
+/// [`FromDeviceState`].
+pub struct LayoutState<L> {
+    state: CustomState,
+    layout: PhantomData<L>,
+}
+
+/// A `DIDATAFORMAT` borrowed from a [`DataFormat`], valid for as long as the `DataFormat` lives.
+pub(crate) struct RawDataFormat<'a> {
+    format: DIDATAFORMAT,
+    _objects: Vec<DIOBJECTDATAFORMAT>,
+    _parent: &'a DataFormat,
+}
+
+impl ObjectFormat {
+    pub fn kind(&self) -> Option<ObjectKind> {
+        ObjectKind::from_type(self.ty)
+    }
+
+    pub fn is_optional(&self) -> bool {
+        self.ty & DIDFT_OPTIONAL != 0
+    }
+}
+
+impl ObjectKind {
+    /// Determines the kind from `DIDFT_*` type flags, or `None` if the flags do not describe
+    /// exactly one of an axis, a button or a POV.
+    pub fn from_type(ty: u32) -> Option<Self> {
+        let axis = ty & DIDFT_AXIS != 0;
+        let button = ty & DIDFT_BUTTON != 0;
+        let pov = ty & DIDFT_POV != 0;
+
+        match (axis, button, pov) {
+            (true, false, false) => Some(Self::Axis),
+            (false, true, false) => Some(Self::Button),
+            (false, false, true) => Some(Self::Pov),
+            _ => None,
+        }
+    }
+
+    /// `DIDFT_*` flags matching all objects of this kind.
+    pub fn type_mask(self) -> u32 {
+        match self {
+            Self::Axis => DIDFT_AXIS,
+            Self::Button => DIDFT_BUTTON,
+            Self::Pov => DIDFT_POV,
+        }
+    }
+
+    /// Number of bytes the object occupies in the state buffer.
+    pub fn size(self) -> u32 {
+        match self {
+            Self::Axis | Self::Pov => 4,
+            Self::Button => 1,
+        }
+    }
+}
+
+impl DataFormat {
+    pub fn builder() -> DataFormatBuilder {
+        DataFormatBuilder::new()
+    }
+
+    /// Equivalent to the `c_dfDIJoystick2` predefined data format, producing a `DIJOYSTATE2`.
+    pub fn joystick2() -> Self {
+        let mut format = Self {
+            flags: DIDF_ABSAXIS,
+            data_size: mem::size_of::<DIJOYSTATE2>() as _,
+            objects: Vec::with_capacity(164),
+        };
+
+        format.push_axes(0, DIDOI_ASPECTPOSITION);
+        for i in 0..4 {
+            format.objects.push(ObjectFormat {
+                guid: Some(GUID_POV),
+                offset: 32 + i * 4,
+                ty: DIDFT_OPTIONAL | DIDFT_POV | DIDFT_ANYINSTANCE,
+                flags: 0,
+            });
+        }
+        for i in 0..128 {
+            format.objects.push(ObjectFormat {
+                guid: None,
+                offset: 48 + i,
+                ty: DIDFT_OPTIONAL | DIDFT_BUTTON | DIDFT_ANYINSTANCE,
+                flags: 0,
+            });
+        }
+        format.push_axes(176, DIDOI_ASPECTVELOCITY);
+        format.push_axes(208, DIDOI_ASPECTACCEL);
+        format.push_axes(240, DIDOI_ASPECTFORCE);
+
+        format
+    }
+
+    /// Equivalent to the `c_dfDIKeyboard` predefined data format, producing an array of 256 key
+    /// states indexed by scan code.
+    pub fn keyboard() -> Self {
+        Self {
+            flags: DIDF_RELAXIS,
+            data_size: 256,
+            objects: (0..256)
+                .map(|i| ObjectFormat {
+                    guid: Some(GUID_Key),
+                    offset: i,
+                    ty: DIDFT_OPTIONAL | DIDFT_BUTTON | make_instance(Some(i as u16)),
+                    flags: 0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Equivalent to the `c_dfDIMouse2` predefined data format, producing a `DIMOUSESTATE2`.
+    pub fn mouse2() -> Self {
+        let mut objects = vec![
+            ObjectFormat {
+                guid: Some(GUID_XAxis),
+                offset: 0,
+                ty: DIDFT_AXIS | DIDFT_ANYINSTANCE,
+                flags: 0,
+            },
+            ObjectFormat {
+                guid: Some(GUID_YAxis),
+                offset: 4,
+                ty: DIDFT_AXIS | DIDFT_ANYINSTANCE,
+                flags: 0,
+            },
+            ObjectFormat {
+                guid: Some(GUID_ZAxis),
+                offset: 8,
+                ty: DIDFT_OPTIONAL | DIDFT_AXIS | DIDFT_ANYINSTANCE,
+                flags: 0,
+            },
+        ];
+        // The first two buttons are required, the rest are optional
+        for i in 0..8 {
+            let optional = if i < 2 { 0 } else { DIDFT_OPTIONAL };
+
+            objects.push(ObjectFormat {
+                guid: None,
+                offset: 12 + i,
+                ty: optional | DIDFT_BUTTON | DIDFT_ANYINSTANCE,
+                flags: 0,
+            });
+        }
+
+        Self {
+            flags: DIDF_RELAXIS,
+            data_size: mem::size_of::<DIMOUSESTATE2>() as _,
+            objects,
+        }
+    }
+
+    /// Pushes the six axes and two sliders that make up each block of `DIJOYSTATE2`.
+    fn push_axes(&mut self, base: u32, aspect: u32) {
+        const AXES: [GUID; 8] = [
+            GUID_XAxis,
+            GUID_YAxis,
+            GUID_ZAxis,
+            GUID_RxAxis,
+            GUID_RyAxis,
+            GUID_RzAxis,
+            GUID_Slider,
+            GUID_Slider,
+        ];
+
+        for (i, guid) in AXES.iter().enumerate() {
+            self.objects.push(ObjectFormat {
+                guid: Some(*guid),
+                offset: base + i as u32 * 4,
+                ty: DIDFT_OPTIONAL | DIDFT_AXIS | DIDFT_ANYINSTANCE,
+                flags: aspect,
+            });
+        }
+    }
+
+    /// `DIDF_ABSAXIS` or `DIDF_RELAXIS`.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Size in bytes of the device state buffer described by this format.
+    pub fn data_size(&self) -> u32 {
+        self.data_size
+    }
+
+    pub fn objects(&self) -> &[ObjectFormat] {
+        &self.objects
+    }
+
+    /// Checks that every object fits in the state buffer without overlapping another object.
+    pub fn validate(&self) -> Result<(), DataFormatError> {
+        if self.objects.is_empty() {
+            return Err(DataFormatError::Empty);
+        }
+        let sizes = self
+            .objects
+            .iter()
+            .map(|object| {
+                object
+                    .kind()
+                    .map(ObjectKind::size)
+                    .ok_or(DataFormatError::InvalidObjectType {
+                        offset: object.offset,
+                        ty: object.ty,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.data_size == 0 || self.data_size & 3 != 0 {
+            return Err(DataFormatError::InvalidDataSize(self.data_size));
+        }
+
+        let mut ranges = Vec::with_capacity(self.objects.len());
+
+        for (object, size) in self.objects.iter().zip(sizes) {
+            if object.offset & (size - 1) != 0 {
+                return Err(DataFormatError::Misaligned {
+                    offset: object.offset,
+                });
+            }
+            match object.offset.checked_add(size) {
+                Some(end) if end <= self.data_size => ranges.push((object.offset, end)),
+                _ => {
+                    return Err(DataFormatError::OutOfBounds {
+                        offset: object.offset,
+                        data_size: self.data_size,
+                    })
+                }
+            }
+        }
+
+        ranges.sort_unstable();
+        for pair in ranges.windows(2) {
+            if pair[1].0 < pair[0].1 {
+                return Err(DataFormatError::Overlap {
+                    first: pair[0].0,
+                    second: pair[1].0,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes a state buffer of [`data_size`](Self::data_size) bytes read with this format.
+    pub fn decode(&self, data: &[u8]) -> CustomState {
+        let read_u32 = |offset: u32| {
+            let offset = offset as usize;
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&data[offset..offset + 4]);
+            u32::from_ne_bytes(bytes)
+        };
+
+        let mut state = CustomState::default();
+
+        for object in &self.objects {
+            match object.kind() {
+                Some(ObjectKind::Axis) => state.axes.push(read_u32(object.offset) as i32),
+                Some(ObjectKind::Button) => state.buttons.push(data[object.offset as usize]),
+                Some(ObjectKind::Pov) => state.povs.push(read_u32(object.offset)),
+                None => {}
+            }
+        }
+
+        state
+    }
+
+    pub(crate) fn to_raw(&self) -> RawDataFormat<'_> {
+        let mut objects: Vec<DIOBJECTDATAFORMAT> = self
+            .objects
+            .iter()
+            .map(|object| DIOBJECTDATAFORMAT {
+                pguid: object
+                    .guid
+                    .as_ref()
+                    .map_or(ptr::null_mut(), |guid| guid as *const GUID as *mut GUID),
+                dwOfs: object.offset,
+                dwType: object.ty,
+                dwFlags: object.flags,
+            })
+            .collect();
+
+        let format = DIDATAFORMAT {
+            dwSize: mem::size_of::<DIDATAFORMAT>() as _,
+            dwObjSize: mem::size_of::<DIOBJECTDATAFORMAT>() as _,
+            dwFlags: self.flags,
+            dwDataSize: self.data_size,
+            dwNumObjs: objects.len() as _,
+            rgodf: objects.as_mut_ptr(),
+        };
+
+        RawDataFormat {
+            format,
+            _objects: objects,
+            _parent: self,
+        }
+    }
+}
+
+impl<'a> RawDataFormat<'a> {
+    pub(crate) fn as_raw(&self) -> &DIDATAFORMAT {
+        &self.format
+    }
+}
+
+impl DataFormatBuilder {
+    /// Creates a builder for a format reporting absolute axis positions.
+    pub fn new() -> Self {
+        Self {
+            flags: DIDF_ABSAXIS,
+            data_size: None,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Report axis positions as absolute values (`DIDF_ABSAXIS`). This is the default.
+    pub fn absolute_axes(mut self) -> Self {
+        self.flags = DIDF_ABSAXIS;
+        self
+    }
+
+    /// Report axis positions as changes since the last read (`DIDF_RELAXIS`).
+    pub fn relative_axes(mut self) -> Self {
+        self.flags = DIDF_RELAXIS;
+        self
+    }
+
+    /// Sets the size of the state buffer. Defaults to the end of the last object rounded up to a
+    /// multiple of four bytes.
+    pub fn data_size(mut self, size: u32) -> Self {
+        self.data_size = Some(size);
+        self
+    }
+
+    /// Declares an optional axis of the given type (e.g. `GUID_XAxis`) at `offset`, matching the
+    /// given instance or any instance if `None`.
+    pub fn axis(self, guid: GUID, instance: Option<u16>, offset: u32) -> Self {
+        self.object(ObjectFormat {
+            guid: Some(guid),
+            offset,
+            ty: DIDFT_OPTIONAL | DIDFT_AXIS | make_instance(instance),
+            flags: 0,
+        })
+    }
+
+    /// Declares an optional button at `offset`, matching the given instance or any instance if
+    /// `None`.
+    pub fn button(self, instance: Option<u16>, offset: u32) -> Self {
+        self.object(ObjectFormat {
+            guid: None,
+            offset,
+            ty: DIDFT_OPTIONAL | DIDFT_BUTTON | make_instance(instance),
+            flags: 0,
+        })
+    }
+
+    /// Declares an optional POV hat at `offset`, matching the given instance or any instance if
+    /// `None`.
+    pub fn pov(self, instance: Option<u16>, offset: u32) -> Self {
+        self.object(ObjectFormat {
+            guid: Some(GUID_POV),
+            offset,
+            ty: DIDFT_OPTIONAL | DIDFT_POV | make_instance(instance),
+            flags: 0,
+        })
+    }
+
+    /// Declares an object with explicit `DIDFT_*` and `DIDOI_*` flags.
+    pub fn object(mut self, object: ObjectFormat) -> Self {
+        self.objects.push(object);
+        self
+    }
+
+    pub fn build(self) -> Result<DataFormat, DataFormatError> {
+        let data_size = self.data_size.unwrap_or_else(|| {
+            let end = self
+                .objects
+                .iter()
+                .map(|object| {
+                    object
+                        .offset
+                        .saturating_add(object.kind().map_or(0, ObjectKind::size))
+                })
+                .max()
+                .unwrap_or(0);
+
+            end.saturating_add(3) & !3
+        });
+
+        let format = DataFormat {
+            flags: self.flags,
+            data_size,
+            objects: self.objects,
+        };
+        format.validate()?;
+
+        Ok(format)
+    }
+}
+
+impl Default for DataFormatBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Equivalent to `DIDFT_MAKEINSTANCE`, or `DIDFT_ANYINSTANCE` for `None`.
+fn make_instance(instance: Option<u16>) -> u32 {
+    instance.map_or(DIDFT_ANYINSTANCE, |n| (n as u32) << 8)
+}
+
+impl fmt::Display for DataFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("data format has no objects"),
+            Self::InvalidDataSize(size) => {
+                write!(f, "data size {} is not a non-zero multiple of 4", size)
+            }
+            Self::InvalidObjectType { offset, ty } => write!(
+                f,
+                "object at offset {} has invalid type {:#010x}",
+                offset, ty
+            ),
+            Self::Misaligned { offset } => write!(f, "object at offset {} is misaligned", offset),
+            Self::OutOfBounds { offset, data_size } => write!(
+                f,
+                "object at offset {} does not fit in {} bytes",
+                offset, data_size
+            ),
+            Self::Overlap { first, second } => {
+                write!(f, "objects at offsets {} and {} overlap", first, second)
+            }
+            Self::BufferSize {
+                data_size,
+                buffer_size,
+            } => write!(
+                f,
+                "state buffer of {} bytes does not match data size {}",
+                buffer_size, data_size
+            ),
+        }
+    }
+}
+
+impl Error for DataFormatError {}
+
+impl<L> LayoutState<L> {
+    pub fn into_inner(self) -> CustomState {
+        self.state
+    }
+}
+
+impl<L: CustomLayout + 'static> LayoutState<L> {
+    /// The format of `L`, built the first time the layout is used as `from_instance` has nowhere
+    /// else to keep it.
+    fn layout_format() -> &'static DataFormat {
+        static FORMATS: Mutex<Vec<(TypeId, &'static DataFormat)>> = Mutex::new(Vec::new());
+
+        let mut formats = FORMATS.lock().unwrap();
+        let id = TypeId::of::<L>();
+
+        match formats.iter().find(|(layout, _)| *layout == id) {
+            Some(&(_, format)) => format,
+            None => {
+                let format = Box::leak(Box::new(L::data_format()));
+                formats.push((id, format));
+                format
+            }
+        }
+    }
+}
+
+impl<L: CustomLayout + 'static> FromDeviceState for LayoutState<L> {
+    type RawState = L::Buffer;
+
+    fn data_format() -> DataFormat {
+        Self::layout_format().clone()
+    }
+
+    fn from_instance(state: Self::RawState) -> Self {
+        let format = Self::layout_format();
+        let data = unsafe {
+            slice::from_raw_parts(
+                &state as *const L::Buffer as *const u8,
+                mem::size_of::<L::Buffer>(),
+            )
+        };
+
+        // `Device::get_state` only fills buffers of the size of the device's format, which
+        // `Device::init_with` checked against the layout
+        Self {
+            state: format.decode(data),
+            layout: PhantomData,
+        }
+    }
+}
+
+impl<L> Deref for LayoutState<L> {
+    type Target = CustomState;
+
+    fn deref(&self) -> &CustomState {
+        &self.state
+    }
+}
+
+impl<L> fmt::Debug for LayoutState<L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.state.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use windows::Win32::Devices::HumanInterfaceDevice::{GUID_XAxis, GUID_YAxis};
+
+    use super::*;
+    use crate::backend::sim::SimulatedDevice;
+    use crate::device::Device;
+    use crate::error::DirectInputError;
+    use crate::joy_state::JoyState;
+
+    #[test]
+    fn test_joystick2_is_valid() {
+        let format = DataFormat::joystick2();
+
+        assert_eq!(format.validate(), Ok(()));
+        assert_eq!(format.data_size(), 272);
+        assert_eq!(format.objects().len(), 164);
+    }
+
+    #[test]
+    fn test_keyboard_and_mouse_are_valid() {
+        let keyboard = DataFormat::keyboard();
+        assert_eq!(keyboard.validate(), Ok(()));
+        assert_eq!(
+            keyboard.objects()[0x1e].ty,
+            DIDFT_OPTIONAL | DIDFT_BUTTON | 0x1e00
+        );
+
+        let mouse = DataFormat::mouse2();
+        assert_eq!(mouse.validate(), Ok(()));
+        assert_eq!(mouse.data_size(), 20);
+        assert_eq!(mouse.flags(), DIDF_RELAXIS);
+        assert!(!mouse.objects()[0].is_optional());
+        assert!(mouse.objects()[2].is_optional());
+    }
+
+    #[test]
+    fn test_builder_layout() {
+        let format = DataFormat::builder()
+            .axis(GUID_XAxis, None, 0)
+            .axis(GUID_YAxis, Some(1), 4)
+            .pov(None, 8)
+            .button(Some(0), 12)
+            .button(None, 13)
+            .build()
+            .expect("Failed to build format");
+
+        assert_eq!(format.data_size(), 16);
+        assert_eq!(format.flags(), DIDF_ABSAXIS);
+        assert_eq!(
+            format.objects()[0].ty,
+            DIDFT_OPTIONAL | DIDFT_AXIS | DIDFT_ANYINSTANCE
+        );
+        assert_eq!(format.objects()[1].ty, DIDFT_OPTIONAL | DIDFT_AXIS | 0x100);
+
+        let mut data = [0u8; 16];
+        data[0..4].copy_from_slice(&(-5i32).to_ne_bytes());
+        data[4..8].copy_from_slice(&7i32.to_ne_bytes());
+        data[8..12].copy_from_slice(&9000u32.to_ne_bytes());
+        data[13] = 0x80;
+
+        assert_eq!(
+            format.decode(&data),
+            CustomState {
+                axes: vec![-5, 7],
+                buttons: vec![0, 0x80],
+                povs: vec![9000],
+            }
+        );
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert_eq!(DataFormat::builder().build(), Err(DataFormatError::Empty));
+        assert_eq!(
+            DataFormat::builder().axis(GUID_XAxis, None, 2).build(),
+            Err(DataFormatError::Misaligned { offset: 2 })
+        );
+        assert_eq!(
+            DataFormat::builder()
+                .axis(GUID_XAxis, None, 0)
+                .button(None, 2)
+                .build(),
+            Err(DataFormatError::Overlap {
+                first: 0,
+                second: 2
+            })
+        );
+        assert_eq!(
+            DataFormat::builder()
+                .axis(GUID_XAxis, None, 4)
+                .data_size(4)
+                .build(),
+            Err(DataFormatError::OutOfBounds {
+                offset: 4,
+                data_size: 4
+            })
+        );
+        assert_eq!(
+            DataFormat::builder().button(None, 0).data_size(6).build(),
+            Err(DataFormatError::InvalidDataSize(6))
+        );
+        assert_eq!(
+            DataFormat::builder()
+                .object(ObjectFormat {
+                    guid: None,
+                    offset: 0,
+                    ty: DIDFT_AXIS | DIDFT_BUTTON,
+                    flags: 0,
+                })
+                .build(),
+            Err(DataFormatError::InvalidObjectType {
+                offset: 0,
+                ty: DIDFT_AXIS | DIDFT_BUTTON
+            })
+        );
+        assert_eq!(
+            DataFormat::builder()
+                .relative_axes()
+                .axis(GUID_XAxis, None, 0)
+                .build()
+                .map(|format| format.flags()),
+            Ok(DIDF_RELAXIS)
+        );
+    }
+
+    #[test]
+    fn test_simulated_custom_format() {
+        let sim = SimulatedDevice::new("Simulated Pad")
+            .with_axes(2)
+            .with_buttons(2);
+        let mut device = Device::with_backend(sim.clone());
+
+        let format = DataFormat::builder()
+            .axis(GUID_YAxis, None, 0)
+            .axis(GUID_XAxis, None, 4)
+            .button(None, 8)
+            .button(None, 9)
+            .build()
+            .expect("Failed to build format");
+        device
+            .set_data_format(&format)
+            .expect("Failed to set data format");
+        device.acquire().expect("Failed to acquire device");
+
+        let mut data = [0u8; 12];
+        data[0..4].copy_from_slice(&100i32.to_ne_bytes());
+        data[4..8].copy_from_slice(&200i32.to_ne_bytes());
+        data[9] = 0x80;
+        sim.push_state(&data);
+
+        assert_eq!(
+            device
+                .get_custom_state(&format)
+                .expect("Failed to get state"),
+            CustomState {
+                axes: vec![100, 200],
+                buttons: vec![0, 0x80],
+                povs: vec![],
+            }
+        );
+        assert_eq!(
+            device.get_state::<JoyState>().err(),
+            Some(DirectInputError::InvalidParam)
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_simulated_derived_state() {
+        use crate::device::FromDeviceState;
+        use crate::DeviceState;
+
+        #[derive(DeviceState)]
+        struct Pad {
+            #[axis(x)]
+            x: i32,
+            #[axis(slider)]
+            sliders: [i32; 2],
+            #[pov(0)]
+            hat: u32,
+            #[button(0..3)]
+            buttons: [bool; 3],
+        }
+
+        assert_eq!(Pad::data_format().data_size(), 20);
+        assert_eq!(Pad::data_format().objects().len(), 7);
+
+        let sim = SimulatedDevice::new("Simulated Pad")
+            .with_axes(8)
+            .with_povs(1)
+            .with_buttons(3);
+        let mut device = Device::with_backend(sim.clone());
+        device
+            .init_with::<Pad>()
+            .expect("Failed to set data format");
+        device.acquire().expect("Failed to acquire device");
+
+        sim.push_raw(&PadRaw {
+            x: -10,
+            sliders: [1, 2],
+            hat: 27000,
+            buttons: [0x80, 0, 0x80],
+        });
+
+        let state = device.get_state::<Pad>().expect("Failed to get state");
+        assert_eq!(state.x, -10);
+        assert_eq!(state.sliders, [1, 2]);
+        assert_eq!(state.hat, 27000);
+        assert_eq!(state.buttons, [true, false, true]);
+    }
+
+    struct Throttle;
+
+    impl CustomLayout for Throttle {
+        type Buffer = [u32; 3];
+
+        fn data_format() -> DataFormat {
+            DataFormat::builder()
+                .axis(GUID_ZAxis, None, 0)
+                .pov(Some(0), 4)
+                .button(Some(0), 8)
+                .button(Some(1), 9)
+                .build()
+                .unwrap()
+        }
+    }
+
+    struct Mismatched;
+
+    impl CustomLayout for Mismatched {
+        type Buffer = [u32; 2];
+
+        fn data_format() -> DataFormat {
+            Throttle::data_format()
+        }
+    }
+
+    static COUNTED_BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+    /// [`Throttle`] counting how many times its format is built.
+    struct CountedThrottle;
+
+    impl CustomLayout for CountedThrottle {
+        type Buffer = [u32; 3];
+
+        fn data_format() -> DataFormat {
+            COUNTED_BUILDS.fetch_add(1, Ordering::Relaxed);
+            Throttle::data_format()
+        }
+    }
+
+    #[test]
+    fn test_layout_state() {
+        let format = LayoutState::<Throttle>::data_format();
+        assert_eq!(format, Throttle::data_format());
+
+        let mut buffer = [0u32; 3];
+        buffer[0] = -300i32 as u32;
+        buffer[1] = 18000;
+        buffer[2] = u32::from_ne_bytes([0, 0x80, 0, 0]);
+
+        let state = LayoutState::<Throttle>::from_instance(buffer);
+        assert_eq!(
+            state.into_inner(),
+            CustomState {
+                axes: vec![-300],
+                buttons: vec![0, 0x80],
+                povs: vec![18000],
+            }
+        );
+    }
+
+    #[test]
+    fn test_layout_state_size_mismatch() {
+        let sim = SimulatedDevice::new("Simulated Throttle")
+            .with_axes(3)
+            .with_povs(1)
+            .with_buttons(2);
+        let mut device = Device::with_backend(sim);
+
+        assert_eq!(
+            device.init_with::<LayoutState<Mismatched>>(),
+            Err(DirectInputError::DataFormat(DataFormatError::BufferSize {
+                data_size: 12,
+                buffer_size: 8,
+            }))
+        );
+        assert_eq!(
+            device.get_state::<LayoutState<Mismatched>>().err(),
+            Some(DirectInputError::NotInitialized)
+        );
+    }
+
+    #[test]
+    fn test_layout_state_get_state() {
+        let sim = SimulatedDevice::new("Simulated Throttle")
+            .with_axes(3)
+            .with_povs(1)
+            .with_buttons(2);
+        let mut device = Device::with_backend(sim.clone());
+        device.init_with::<LayoutState<CountedThrottle>>().unwrap();
+        device.acquire().unwrap();
+
+        let mut data = [0u8; 12];
+        data[0..4].copy_from_slice(&50i32.to_ne_bytes());
+        data[4..8].copy_from_slice(&u32::MAX.to_ne_bytes());
+        data[9] = 0x80;
+        sim.push_state(&data);
+
+        for _ in 0..3 {
+            let state = device.get_state::<LayoutState<CountedThrottle>>().unwrap();
+            assert_eq!(
+                *state,
+                CustomState {
+                    axes: vec![50],
+                    buttons: vec![0, 0x80],
+                    povs: vec![u32::MAX],
+                }
+            );
+        }
+
+        // The format is built once for the initialization and every read
+        assert_eq!(COUNTED_BUILDS.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,329 @@
+use crate::calibration::NormalizedAxes;
+
+/// Shape of a software dead zone applied to a stick.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeadZoneMode {
+    /// Each axis has its own dead zone, rescaled so the output starts at 0 on the dead zone edge.
+    /// Snaps the stick to the axes, which suits digital-like movement.
+    Axial,
+    /// Inputs inside a circle are zeroed and left unchanged outside. The output jumps from 0 to
+    /// the dead zone size on the edge.
+    Radial,
+    /// Like `Radial`, but the magnitude is rescaled so the output starts at 0 on the edge,
+    /// keeping the direction of the stick.
+    ScaledRadial,
+    /// `ScaledRadial` followed by an axial dead zone of the same size on the rescaled values,
+    /// snapping directions close to an axis without a square dead zone in the center.
+    Hybrid,
+}
+
+/// Software dead zone of a stick, i.e. an axis pair normalized to `[-1, 1]`.
+///
+/// Unlike `DIPROP_DEADZONE`, which is per-axis and left to the driver, the dead zone is applied to
+/// both axes together. Magnitudes below `inner` read 0, magnitudes from `outer` read 1
+/// (saturation) and `anti` is the smallest magnitude read outside the dead zone, to cancel out
+/// a dead zone the application applies on its own.
+///
+/// The output is continuous for every mode but `Radial` as long as `anti` is 0, and each output
+/// axis never decreases as its input axis increases.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StickDeadZone {
+    pub mode: DeadZoneMode,
+    pub inner: f32,
+    pub outer: f32,
+    pub anti: f32,
+}
+
+/// Dead zones of the axis pairs of a [`JoyState`](crate::JoyState), applied to the axes
+/// normalized by a [`DeviceCalibration`](crate::DeviceCalibration). Sliders are left unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeadZones {
+    /// `x` and `y`, usually the left stick.
+    pub xy: StickDeadZone,
+    /// `rx` and `ry`, usually the right stick.
+    pub rx_ry: StickDeadZone,
+    /// `z` and `rz`, the right stick of some gamepads or a twist axis and throttle.
+    pub z_rz: StickDeadZone,
+}
+
+impl StickDeadZone {
+    /// A dead zone of `inner` without saturation or anti-dead-zone.
+    pub fn new(mode: DeadZoneMode, inner: f32) -> Self {
+        Self {
+            mode,
+            inner,
+            outer: 1.0,
+            anti: 0.0,
+        }
+    }
+
+    /// A dead zone that leaves the input unchanged.
+    pub fn none() -> Self {
+        Self::new(DeadZoneMode::Axial, 0.0)
+    }
+
+    pub fn outer(mut self, outer: f32) -> Self {
+        self.outer = outer;
+        self
+    }
+
+    pub fn anti(mut self, anti: f32) -> Self {
+        self.anti = anti;
+        self
+    }
+
+    /// Applies the dead zone to a stick position. Out of range settings are clamped: `inner` and
+    /// `anti` to `[0, 1]` and `outer` to `[inner, 1]`.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let inner = self.inner.clamp(0.0, 1.0);
+        let outer = self.outer.clamp(inner, 1.0);
+        let anti = self.anti.clamp(0.0, 1.0);
+
+        match self.mode {
+            DeadZoneMode::Axial => (axial(x, inner, outer, anti), axial(y, inner, outer, anti)),
+            DeadZoneMode::Radial => radial(x, y, |magnitude| {
+                if magnitude <= inner {
+                    0.0
+                } else {
+                    anti + (1.0 - anti) * saturate(magnitude, 0.0, outer)
+                }
+            }),
+            DeadZoneMode::ScaledRadial => {
+                radial(x, y, |magnitude| rescale(magnitude, inner, outer, anti))
+            }
+            DeadZoneMode::Hybrid => {
+                let (x, y) = radial(x, y, |magnitude| rescale(magnitude, inner, outer, anti));
+
+                (axial(x, inner, 1.0, 0.0), axial(y, inner, 1.0, 0.0))
+            }
+        }
+    }
+}
+
+impl Default for StickDeadZone {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl DeadZones {
+    /// Uses the same dead zone for every stick.
+    pub fn uniform(dead_zone: StickDeadZone) -> Self {
+        Self {
+            xy: dead_zone,
+            rx_ry: dead_zone,
+            z_rz: dead_zone,
+        }
+    }
+
+    pub fn apply(&self, axes: &NormalizedAxes) -> NormalizedAxes {
+        let (x, y) = self.xy.apply(axes.x, axes.y);
+        let (rx, ry) = self.rx_ry.apply(axes.rx, axes.ry);
+        let (z, rz) = self.z_rz.apply(axes.z, axes.rz);
+
+        NormalizedAxes {
+            x,
+            y,
+            z,
+            rx,
+            ry,
+            rz,
+            slider: axes.slider,
+        }
+    }
+}
+
+/// Position of `value` between `inner` and `outer` in `[0, 1]`. A degenerate range acts as a
+/// step at `inner`.
+fn saturate(value: f32, inner: f32, outer: f32) -> f32 {
+    if outer > inner {
+        ((value - inner) / (outer - inner)).clamp(0.0, 1.0)
+    } else if value > inner {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Maps a magnitude to 0 inside the dead zone and to `[anti, 1]` outside of it.
+fn rescale(magnitude: f32, inner: f32, outer: f32, anti: f32) -> f32 {
+    if magnitude <= inner {
+        0.0
+    } else {
+        anti + (1.0 - anti) * saturate(magnitude, inner, outer)
+    }
+}
+
+fn axial(value: f32, inner: f32, outer: f32, anti: f32) -> f32 {
+    rescale(value.abs(), inner, outer, anti).copysign(value)
+}
+
+/// Scales the position to the magnitude returned by `map`, keeping its direction.
+fn radial(x: f32, y: f32, map: impl FnOnce(f32) -> f32) -> (f32, f32) {
+    let magnitude = x.hypot(y);
+
+    if magnitude == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let scale = map(magnitude) / magnitude;
+
+    (x * scale, y * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const MODES: [DeadZoneMode; 4] = [
+        DeadZoneMode::Axial,
+        DeadZoneMode::Radial,
+        DeadZoneMode::ScaledRadial,
+        DeadZoneMode::Hybrid,
+    ];
+
+    fn assert_close((x, y): (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (x - expected.0).abs() < 1e-6 && (y - expected.1).abs() < 1e-6,
+            "{:?} != {:?}",
+            (x, y),
+            expected
+        );
+    }
+
+    fn dead_zone() -> impl Strategy<Value = StickDeadZone> {
+        (
+            prop::sample::select(&MODES[..]),
+            0.0f32..0.5,
+            0.1f32..0.5,
+            0.0f32..0.5,
+        )
+            .prop_map(|(mode, inner, width, anti)| {
+                StickDeadZone::new(mode, inner)
+                    .outer((inner + width).min(1.0))
+                    .anti(anti)
+            })
+    }
+
+    #[test]
+    fn test_none() {
+        for &(x, y) in &[(0.0, 0.0), (0.3, -0.7), (-1.0, 1.0)] {
+            assert_eq!(StickDeadZone::none().apply(x, y), (x, y));
+        }
+    }
+
+    #[test]
+    fn test_modes() {
+        let axial = StickDeadZone::new(DeadZoneMode::Axial, 0.2);
+        assert_close(axial.apply(0.1, 0.6), (0.0, 0.5));
+        assert_close(axial.apply(-1.0, 0.15), (-1.0, 0.0));
+
+        let radial = StickDeadZone::new(DeadZoneMode::Radial, 0.2);
+        assert_close(radial.apply(0.1, 0.1), (0.0, 0.0));
+        assert_close(radial.apply(0.3, 0.4), (0.3, 0.4));
+
+        let scaled = StickDeadZone::new(DeadZoneMode::ScaledRadial, 0.2);
+        assert_close(scaled.apply(0.1, 0.1), (0.0, 0.0));
+        assert_close(scaled.apply(0.6, 0.0), (0.5, 0.0));
+        assert_close(scaled.apply(0.36, 0.48), (0.3, 0.4));
+
+        // The radial stage keeps (0.1, 0.6) alive, the axial stage snaps it to the y axis
+        let hybrid = StickDeadZone::new(DeadZoneMode::Hybrid, 0.2);
+        assert_close(hybrid.apply(0.1, 0.1), (0.0, 0.0));
+        assert_eq!(hybrid.apply(0.1, 0.6).0, 0.0);
+        assert!(hybrid.apply(0.1, 0.6).1 > 0.0);
+    }
+
+    #[test]
+    fn test_saturation_and_anti_dead_zone() {
+        let dead_zone = StickDeadZone::new(DeadZoneMode::ScaledRadial, 0.2).outer(0.8);
+        assert_close(dead_zone.apply(0.5, 0.0), (0.5, 0.0));
+        assert_close(dead_zone.apply(0.0, -0.9), (0.0, -1.0));
+
+        let dead_zone = dead_zone.anti(0.25);
+        assert_close(dead_zone.apply(0.2, 0.0), (0.0, 0.0));
+        assert_close(dead_zone.apply(0.5, 0.0), (0.625, 0.0));
+        assert_close(dead_zone.apply(0.8, 0.0), (1.0, 0.0));
+
+        let dead_zone = StickDeadZone::new(DeadZoneMode::Axial, 0.5).outer(0.2);
+        assert_close(dead_zone.apply(0.4, 0.6), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_dead_zones() {
+        let dead_zones = DeadZones {
+            xy: StickDeadZone::new(DeadZoneMode::ScaledRadial, 0.5),
+            ..DeadZones::default()
+        };
+        let axes = NormalizedAxes {
+            x: 0.25,
+            y: 0.25,
+            rx: 0.25,
+            slider: [0.1, 0.2],
+            ..NormalizedAxes::default()
+        };
+
+        let applied = dead_zones.apply(&axes);
+        assert_eq!((applied.x, applied.y), (0.0, 0.0));
+        assert_eq!(applied.rx, 0.25);
+        assert_eq!(applied.slider, [0.1, 0.2]);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_monotonic(
+            dead_zone in dead_zone(),
+            a in -1.0f32..1.0,
+            b in -1.0f32..1.0,
+            other in -1.0f32..1.0,
+        ) {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+
+            let x = (dead_zone.apply(low, other).0, dead_zone.apply(high, other).0);
+            let y = (dead_zone.apply(other, low).1, dead_zone.apply(other, high).1);
+
+            prop_assert!(x.0 <= x.1 + 1e-6, "x: {:?}", x);
+            prop_assert!(y.0 <= y.1 + 1e-6, "y: {:?}", y);
+        }
+
+        #[test]
+        fn prop_continuous(
+            dead_zone in dead_zone(),
+            x in -1.0f32..1.0,
+            y in -1.0f32..1.0,
+            dx in -1e-3f32..1e-3,
+            dy in -1e-3f32..1e-3,
+        ) {
+            prop_assume!(dead_zone.mode != DeadZoneMode::Radial);
+            let dead_zone = dead_zone.anti(0.0);
+
+            let a = dead_zone.apply(x, y);
+            let b = dead_zone.apply(x + dx, y + dy);
+
+            // Bounded by the steepest slope, from rescaling `inner..outer` then `inner..1`
+            let slope = 2.0 / ((dead_zone.outer - dead_zone.inner) * (1.0 - dead_zone.inner));
+            let bound = slope * dx.hypot(dy) + 1e-5;
+
+            prop_assert!((a.0 - b.0).abs() <= bound, "{:?} -> {:?}", a, b);
+            prop_assert!((a.1 - b.1).abs() <= bound, "{:?} -> {:?}", a, b);
+        }
+
+        #[test]
+        fn prop_bounded(dead_zone in dead_zone(), x in -1.0f32..1.0, y in -1.0f32..1.0) {
+            let (out_x, out_y) = dead_zone.apply(x, y);
+
+            prop_assert!(out_x.abs() <= 1.0 && out_y.abs() <= 1.0);
+            if dead_zone.mode != DeadZoneMode::Axial {
+                prop_assert!(out_x.hypot(out_y) <= 1.0 + 1e-6);
+            }
+            if x.hypot(y) <= dead_zone.inner {
+                prop_assert_eq!((out_x, out_y), (0.0, 0.0));
+            }
+        }
+    }
+}
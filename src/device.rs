@@ -1,187 +1,180 @@
-use std::convert::TryInto;
-//use std::ffi::OsString;
-use std::ffi::c_void;
 use std::mem::{self, MaybeUninit};
-//use std::os::windows::ffi::OsStringExt;
-use std::ptr;
+use std::slice;
+use std::task::Waker;
 use std::time::Duration;
 
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
-use winapi::um::dinput::{
-    c_dfDIJoystick2, DIERR_OTHERAPPHASPRIO, DIPH_BYID, DIPROP_RANGE, DI_NOEFFECT, DI_OK,
-    DI_POLLEDDEVICE,
-};
-use windows::core::{Interface, HRESULT};
-use windows::Win32::Devices::HumanInterfaceDevice::{
-    IDirectInputDevice8W, DIDATAFORMAT, DIDEVCAPS, DIDEVICEOBJECTINSTANCEW, DIDFT_AXIS,
-    DIENUM_CONTINUE, DIPROPHEADER, DIPROPRANGE,
-};
-use windows::Win32::Foundation::{
-    CloseHandle, GetLastError, BOOL, HANDLE, HWND, INVALID_HANDLE_VALUE,
-};
-use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject, WAIT_OBJECT_0};
-use windows::Win32::System::WindowsProgramming::INFINITE;
+use windows::Win32::Devices::HumanInterfaceDevice::{DIDEVICEOBJECTDATA, DIDFT_ALL, DIEFT_ALL};
+use windows::Win32::Foundation::{HANDLE, HWND};
 
+use crate::action_map::{ActionMap, BuildActionMapFlags, SetActionMapFlags};
+use crate::backend::DeviceBackend;
 use crate::cooperative_level::CooperativeLevel;
+use crate::data_format::{CustomState, DataFormat, DataFormatError, ObjectKind};
 use crate::device_capabilities::DeviceCapabilities;
+use crate::device_event::{DeviceEvent, DeviceEvents};
+use crate::device_object::DeviceObject;
+use crate::device_type::DeviceType;
+use crate::effect::{Effect, EffectHandle, EffectInfo};
 use crate::error::{DirectInputError, DirectInputStatus, Result};
+use crate::joy_state::JoyState;
+use crate::keyboard_state::KeyboardState;
+use crate::mouse_state::MouseState;
+use crate::property::{BufferSize, Property, PropertyTarget, Range, RawProperty, SettableProperty};
 
 pub struct Device {
-    iface: IDirectInputDevice8W,
-    event: Option<HANDLE>,
+    backend: Box<dyn DeviceBackend>,
 }
 
+/// A device state type read with [`Device::get_state`].
+///
+/// `RawState` must be a plain `#[repr(C)]` structure whose layout matches the format returned
+/// by `data_format`.
 pub trait FromDeviceState {
     type RawState;
 
+    /// Data format to pass to [`Device::set_data_format`] before reading this state.
+    fn data_format() -> DataFormat;
+
     fn from_instance(state: Self::RawState) -> Self;
 }
 
-unsafe impl Send for Device {}
-
 impl Device {
-    pub(crate) fn new(iface: IDirectInputDevice8W) -> Self {
-        Self { iface, event: None }
+    pub(crate) fn new(backend: Box<dyn DeviceBackend>) -> Self {
+        Self { backend }
     }
 
-    pub fn capabilities(&self) -> Result<DeviceCapabilities> {
-        let mut caps = DIDEVCAPS::default();
-        caps.dwSize = mem::size_of::<DIDEVCAPS>() as _;
+    /// Wraps a device backend directly, e.g. a
+    /// [`SimulatedDevice`](crate::backend::sim::SimulatedDevice), without going through a
+    /// [`DirectInputManager`](crate::DirectInputManager).
+    pub fn with_backend(backend: impl DeviceBackend + 'static) -> Self {
+        Self::new(Box::new(backend))
+    }
 
-        unsafe { self.iface.GetCapabilities(&mut caps)? };
+    pub fn capabilities(&self) -> Result<DeviceCapabilities> {
+        let caps = self.backend.capabilities()?;
 
         Ok(DeviceCapabilities::from_instance(caps))
     }
 
-    pub fn init(&mut self) -> Result<()> {
-        // Clone the `c_dfDIJoystick2` data format with the axis value set to relative
-        let mut data_format: DIDATAFORMAT = unsafe { mem::transmute(c_dfDIJoystick2) };
-        eprintln!("dwSize: {}", data_format.dwSize);
-        eprintln!("dwObjSize: {}", data_format.dwObjSize);
-        eprintln!("dwFlags: {}", data_format.dwFlags);
-        eprintln!("dwDataSize: {}", data_format.dwDataSize);
-        eprintln!("dwNumObjs: {}", data_format.dwNumObjs);
+    /// Lists all objects of the device.
+    pub fn objects(&self) -> Result<Vec<DeviceObject>> {
+        self.enum_objects(DIDFT_ALL)
+    }
 
-        //data_format.dwFlags = DIDF_RELAXIS;
+    /// Lists the axes, buttons or POVs of the device.
+    pub fn objects_of(&self, kind: ObjectKind) -> Result<Vec<DeviceObject>> {
+        self.enum_objects(kind.type_mask())
+    }
 
-        self.set_data_format(&mut data_format)?;
+    fn enum_objects(&self, flags: u32) -> Result<Vec<DeviceObject>> {
+        let objects = self.backend.enum_objects(flags)?;
 
-        Ok(())
+        Ok(objects.iter().map(DeviceObject::from_instance).collect())
     }
 
-    pub fn init_event(&mut self) -> Result<DirectInputStatus> {
-        self.event
-            .take()
-            .map(|event| unsafe {
-                let res = self.iface.SetEventNotification(None);
+    /// Lists the force feedback effects supported by the device.
+    pub fn effects(&self) -> Result<Vec<EffectInfo>> {
+        let effects = self.backend.enum_effects(DIEFT_ALL)?;
 
-                CloseHandle(event);
+        Ok(effects.iter().map(EffectInfo::from_instance).collect())
+    }
 
-                res
-            })
-            .unwrap_or(Ok(()))?;
+    /// Creates a force feedback effect. If the device is acquired exclusively and the effect
+    /// parameters are complete, the effect is downloaded to the device right away, otherwise it
+    /// is downloaded when started.
+    pub fn create_effect(&self, effect: &Effect) -> Result<EffectHandle> {
+        let kind = effect.kind();
+        let raw = effect.to_raw()?;
 
-        let event = unsafe { CreateEventW(ptr::null(), false, false, None) };
+        let backend = unsafe { self.backend.create_effect(&kind.guid(), raw.as_raw())? };
 
-        if event == INVALID_HANDLE_VALUE {
-            return Err(DirectInputError::from_last_error());
-        }
+        Ok(EffectHandle::new(backend, kind))
+    }
 
-        // TODO: Use `SetEventNotification` directly when status `HRESULT` return values are
-        // exposed
-        let hr = unsafe { (self.iface.vtable().12)(mem::transmute_copy(&self.iface), event) };
+    /// Sets the predefined data format matching the device type: [`KeyboardState`] for
+    /// keyboards, [`MouseState`] for mice and [`JoyState`] for everything else.
+    pub fn init(&mut self) -> Result<()> {
+        let caps = self.backend.capabilities()?;
 
-        if let Err(e) = hr.ok() {
-            unsafe { CloseHandle(event) };
+        match DeviceType::from_bits(caps.dwDevType) {
+            DeviceType::Keyboard(_) => self.init_with::<KeyboardState>(),
+            DeviceType::Mouse(_) | DeviceType::ScreenPointer(_) => self.init_with::<MouseState>(),
+            _ => self.init_with::<JoyState>(),
+        }
+    }
 
-            return Err(e.into());
+    /// Sets the data format to the one required by `T`.
+    ///
+    /// Fails with [`DataFormatError::BufferSize`] if `T::RawState` is not exactly the size of the
+    /// format's state buffer.
+    pub fn init_with<T: FromDeviceState>(&mut self) -> Result<()> {
+        let format = T::data_format();
+        let buffer_size = mem::size_of::<T::RawState>();
+
+        if format.data_size() as usize != buffer_size {
+            return Err(DataFormatError::BufferSize {
+                data_size: format.data_size(),
+                buffer_size,
+            }
+            .into());
         }
 
-        self.event = Some(event);
+        self.set_data_format(&format)
+    }
 
-        Ok(match hr.0 as i32 {
-            // If the method succeeds, the return value is DI_OK
-            DI_OK => DirectInputStatus::Ok,
-            // or DI_POLLEDDEVICE
-            DI_POLLEDDEVICE => DirectInputStatus::PolledDevice,
-            _ => DirectInputStatus::from_hresult_or_ok(hr),
-        })
+    pub fn init_event(&mut self) -> Result<DirectInputStatus> {
+        self.backend.init_event()
+    }
+
+    /// Reads property `P` from the device or one of its objects, e.g.
+    /// `device.property::<DeadZone>(PropertyTarget::Offset(0))`.
+    pub fn property<P: Property>(&self, target: PropertyTarget) -> Result<P> {
+        let mut raw: P::Raw = target.new_raw();
+
+        unsafe { self.backend.get_property(P::id(), raw.header_mut())? };
+
+        Ok(P::from_raw(&raw))
+    }
+
+    /// Writes property `P` to the device or one of its objects. Most properties can only be set
+    /// while the device is not acquired.
+    ///
+    /// Returns `DirectInputStatus::PropNoEffect` if the device does not use the property.
+    pub fn set_property<P: SettableProperty>(
+        &mut self,
+        target: PropertyTarget,
+        value: P,
+    ) -> Result<DirectInputStatus> {
+        let mut raw: P::Raw = target.new_raw();
+        value.to_raw(&mut raw);
+
+        unsafe { self.backend.set_property(P::id(), raw.header_mut()) }
     }
 
     pub fn set_axes_range(&mut self, min: i32, max: i32) -> Result<()> {
-        struct SetAxesRangeContext<'parent> {
-            device: &'parent Device,
-            min: i32,
-            max: i32,
-        }
+        for object in self.objects_of(ObjectKind::Axis)? {
+            let range = Range { min, max };
 
-        extern "system" fn enumerate_callback(
-            device_object_instance: *mut DIDEVICEOBJECTINSTANCEW,
-            ctx: *mut c_void,
-        ) -> BOOL {
-            let ctx = unsafe { &*(ctx as *const SetAxesRangeContext) };
-
-            if !device_object_instance.is_null() {
-                let device_object_instance = unsafe { &*device_object_instance };
-
-                /*
-                let end = device_object_instance
-                    .tszName
-                    .iter()
-                    .position(|&ch| ch == 0)
-                    .unwrap_or(device_object_instance.tszName.len());
-                let name = OsString::from_wide(&device_object_instance.tszName[..end]);
-                println!("name: {:?}", name);
-                */
-
-                let prop_range = DIPROPRANGE {
-                    diph: DIPROPHEADER {
-                        dwSize: mem::size_of::<DIPROPRANGE>() as _,
-                        dwHeaderSize: mem::size_of::<DIPROPHEADER>() as _,
-                        dwHow: DIPH_BYID,
-                        dwObj: device_object_instance.dwType,
-                    },
-                    lMin: ctx.min,
-                    lMax: ctx.max,
-                };
-                unsafe {
-                    if let Err(e) = ctx
-                        .device
-                        .iface
-                        .SetProperty(DIPROP_RANGE.cast(), &prop_range as *const _ as _)
-                    {
-                        eprintln!("Failed to set device range: {:?}", e);
-                    }
-                };
+            if let Err(e) = self.set_property(PropertyTarget::Id(object.ty), range) {
+                eprintln!("Failed to set device range: {:?}", e);
             }
-
-            BOOL(DIENUM_CONTINUE as _)
         }
 
-        let ctx = SetAxesRangeContext {
-            device: self,
-            min,
-            max,
-        };
-        unsafe {
-            self.iface.EnumObjects(
-                Some(enumerate_callback),
-                &ctx as *const SetAxesRangeContext as *mut _,
-                DIDFT_AXIS,
-            )?
-        };
+        Ok(())
+    }
+
+    /// Enables buffered input by setting the number of records the device keeps for
+    /// [`events`](Self::events). A size of zero disables buffering. The device must not be
+    /// acquired.
+    pub fn set_buffer_size(&mut self, size: u32) -> Result<()> {
+        self.set_property(PropertyTarget::Device, BufferSize(size))?;
 
         Ok(())
     }
 
     pub fn acquire(&self) -> Result<()> {
-        match unsafe { self.iface.Acquire() } {
-            Ok(()) => Ok(()),
-            Err(e) => match e.code().0 as i32 {
-                DIERR_OTHERAPPHASPRIO => Err(DirectInputError::OtherAppHasPrio),
-                _ => Err(e.into()),
-            },
-        }
+        self.backend.acquire()
     }
 
     pub fn set_cooperative_level<H: HasRawWindowHandle>(
@@ -194,14 +187,12 @@ impl Device {
             _ => return Err(DirectInputError::Handle),
         };
 
-        Ok(unsafe {
-            self.iface
-                .SetCooperativeLevel(HWND(hwnd as _), flags.bits())?
-        })
+        self.backend
+            .set_cooperative_level(HWND(hwnd as _), flags.bits())
     }
 
-    pub fn set_data_format(&mut self, format: &mut DIDATAFORMAT) -> Result<()> {
-        Ok(unsafe { self.iface.SetDataFormat(format)? })
+    pub fn set_data_format(&mut self, format: &DataFormat) -> Result<()> {
+        self.backend.set_data_format(format.to_raw().as_raw())
     }
 
     /// From MSDN:
@@ -215,30 +206,18 @@ impl Device {
     /// > require polling. If the method fails, the return value can be one of the following error
     /// > values: DIERR_INPUTLOST, DIERR_NOTACQUIRED, DIERR_NOTINITIALIZED.
     pub fn poll(&self) -> Result<DirectInputStatus> {
-        // TODO(felix): replace with direct `Poll` call when the non-error part of the `HRESULT` is
-        // made available to API consumers
-        let hr = unsafe { (self.iface.vtable().25)(mem::transmute_copy(&self.iface)) };
-
-        if hr.is_ok() {
-            Ok(match hr.0 as i32 {
-                // If the method succeeds, the return value is DI_OK
-                DI_OK => DirectInputStatus::Ok,
-                // or `DI_NOEFFECT` if the device does not require polling.
-                DI_NOEFFECT => DirectInputStatus::NoEffect,
-                // Check for other status codes or fallback to `Ok`
-                _ => DirectInputStatus::from_hresult_or_ok(hr),
-            })
-        } else {
-            Err(DirectInputError::from_hresult(hr))
-        }
+        self.backend.poll()
     }
 
     pub fn get_state<T: FromDeviceState>(&self) -> Result<T> {
         let mut data: MaybeUninit<T::RawState> = MaybeUninit::zeroed();
 
         unsafe {
-            self.iface
-                .GetDeviceState(mem::size_of::<T::RawState>() as _, data.as_mut_ptr().cast())?;
+            let buf = slice::from_raw_parts_mut(
+                data.as_mut_ptr().cast::<u8>(),
+                mem::size_of::<T::RawState>(),
+            );
+            self.backend.get_device_state(buf)?;
 
             let state = data.assume_init();
 
@@ -246,38 +225,109 @@ impl Device {
         }
     }
 
+    /// Reads the device state using a runtime data format previously passed to
+    /// [`set_data_format`](Self::set_data_format).
+    pub fn get_custom_state(&self, format: &DataFormat) -> Result<CustomState> {
+        let mut data = vec![0; format.data_size() as usize];
+
+        self.backend.get_device_state(&mut data)?;
+
+        Ok(format.decode(&data))
+    }
+
+    /// Drains all buffered input records received since the last call. Requires a buffer size to
+    /// have been configured with [`set_buffer_size`](Self::set_buffer_size).
+    ///
+    /// If the buffer overflowed since the last call, the returned events are still valid but
+    /// [`DeviceEvents::status`] reports `DirectInputStatus::BufferOverflow`.
+    pub fn events(&self) -> Result<DeviceEvents> {
+        const CHUNK_SIZE: usize = 64;
+
+        let mut data = [DIDEVICEOBJECTDATA::default(); CHUNK_SIZE];
+        let mut events = Vec::new();
+        let mut status = DirectInputStatus::Ok;
+
+        loop {
+            let (count, chunk_status) = self.backend.get_device_data(&mut data)?;
+
+            if chunk_status == DirectInputStatus::BufferOverflow {
+                status = chunk_status;
+            }
+            events.extend(data[..count].iter().map(DeviceEvent::from_instance));
+
+            if count < CHUNK_SIZE {
+                break;
+            }
+        }
+
+        Ok(DeviceEvents::new(events, status))
+    }
+
+    /// Maps the actions of `map` to objects of this device, replacing the mappings in `map`
+    /// according to `flags`. Returns `DirectInputStatus::NoEffect` if no action could be mapped.
+    ///
+    /// `user` selects the user whose saved configuration is applied, defaulting to the current
+    /// user.
+    pub fn build_action_map(
+        &self,
+        map: &mut ActionMap,
+        user: Option<&str>,
+        flags: BuildActionMapFlags,
+    ) -> Result<DirectInputStatus> {
+        let mut raw = map.to_raw();
+
+        let status = unsafe {
+            self.backend
+                .build_action_map(raw.as_mut_raw(), user, flags.bits())?
+        };
+        map.update_from_raw(&raw);
+
+        Ok(status)
+    }
+
+    /// Applies the mappings of `map` built for this device, replacing the data format and the
+    /// buffer size. Buffered [`events`](Self::events) then report the
+    /// [`action`](DeviceEvent::action) of each mapped object. The device must not be acquired.
+    pub fn set_action_map(
+        &mut self,
+        map: &ActionMap,
+        user: Option<&str>,
+        flags: SetActionMapFlags,
+    ) -> Result<DirectInputStatus> {
+        let mut raw = map.to_raw();
+
+        unsafe {
+            self.backend
+                .set_action_map(raw.as_mut_raw(), user, flags.bits())
+        }
+    }
+
     /// If event polling is enabled using `init_event`, this will wait for up to the duration
     /// specified for an event update to arrive.
     ///
     /// Return value is `true` if an event arrived or `false` if the timeout expired. If no event
     /// handle is configured, this method returns `true`.
+    ///
+    /// To wait on several devices at once, use a [`DeviceSet`](crate::DeviceSet). With the
+    /// `async` feature, `StateStream` and `EventStream` wait for the event without blocking a
+    /// thread.
     pub fn wait(&self, timeout: Duration) -> Result<bool> {
-        if let Some(event) = self.event {
-            let millis: u32 = timeout.as_millis().try_into().unwrap_or(INFINITE);
-
-            let res = unsafe { WaitForSingleObject(event, millis) };
+        self.backend.wait(timeout)
+    }
 
-            if res == WAIT_OBJECT_0 {
-                Ok(true)
-            } else {
-                let err = unsafe { GetLastError() };
+    /// Non-blocking counterpart of [`wait`](Self::wait) that registers `waker` to be woken when
+    /// the event is signalled.
+    pub(crate) fn poll_event(&mut self, waker: &Waker) -> Result<bool> {
+        self.backend.poll_event(waker)
+    }
 
-                if err.0 == 0 {
-                    Ok(false)
-                } else {
-                    Err(DirectInputError::from_hresult(HRESULT::from(err)))
-                }
-            }
-        } else {
-            Ok(true)
-        }
+    pub(crate) fn event_handle(&self) -> Result<Option<HANDLE>> {
+        self.backend.event_handle()
     }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
-        unsafe {
-            let _ = self.iface.Unacquire();
-        };
+        let _ = self.backend.unacquire();
     }
 }
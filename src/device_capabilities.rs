@@ -1,9 +1,57 @@
-use windows::Win32::Devices::HumanInterfaceDevice::DIDEVCAPS;
+use bitflags::bitflags;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DIDC_ALIAS, DIDC_ATTACHED, DIDC_DEADBAND, DIDC_EMULATED, DIDC_FFATTACK, DIDC_FFFADE,
+    DIDC_FORCEFEEDBACK, DIDC_HIDDEN, DIDC_PHANTOM, DIDC_POLLEDDATAFORMAT, DIDC_POLLEDDEVICE,
+    DIDC_POSNEGCOEFFICIENTS, DIDC_POSNEGSATURATION, DIDC_SATURATION, DIDC_STARTDELAY, DIDEVCAPS,
+};
+
+use crate::device_type::{is_hid, DeviceType};
+
+bitflags! {
+    /// `DIDC_*` flags describing a device in [`DeviceCapabilities`].
+    pub struct CapabilityFlags: u32 {
+        /// The device is physically attached.
+        const ATTACHED = DIDC_ATTACHED;
+        /// At least one object requires polling.
+        const POLLED_DEVICE = DIDC_POLLEDDEVICE;
+        /// The device is emulated in user mode instead of through a driver.
+        const EMULATED = DIDC_EMULATED;
+        /// At least one object in the current data format requires polling.
+        const POLLED_DATA_FORMAT = DIDC_POLLEDDATAFORMAT;
+        /// The device supports force feedback.
+        const FORCE_FEEDBACK = DIDC_FORCEFEEDBACK;
+        /// The force-feedback system supports the attack parameter of envelopes.
+        const FF_ATTACK = DIDC_FFATTACK;
+        /// The force-feedback system supports the fade parameter of envelopes.
+        const FF_FADE = DIDC_FFFADE;
+        /// The force-feedback system supports the saturation of condition effects.
+        const SATURATION = DIDC_SATURATION;
+        /// The force-feedback system supports two coefficient values for conditions.
+        const POS_NEG_COEFFICIENTS = DIDC_POSNEGCOEFFICIENTS;
+        /// The force-feedback system supports a maximum saturation for both positive and negative
+        /// force output.
+        const POS_NEG_SATURATION = DIDC_POSNEGSATURATION;
+        /// The force-feedback system supports the dead band of condition effects.
+        const DEADBAND = DIDC_DEADBAND;
+        /// The force-feedback system supports the start delay of effects.
+        const START_DELAY = DIDC_STARTDELAY;
+        /// The device is a duplicate of another device, such as a keyboard exposing its mouse
+        /// keys.
+        const ALIAS = DIDC_ALIAS;
+        /// The device does not correspond to real hardware.
+        const PHANTOM = DIDC_PHANTOM;
+        /// The device is not meant to be used directly, such as the system keyboard interface
+        /// of a multimedia keyboard.
+        const HIDDEN = DIDC_HIDDEN;
+    }
+}
 
 #[derive(Debug)]
 pub struct DeviceCapabilities {
-    pub flags: u32,
-    pub dev_type: u32,
+    pub flags: CapabilityFlags,
+    pub dev_type: DeviceType,
+    /// Whether the device is driven through HID (`DIDEVTYPE_HID`).
+    pub hid: bool,
     pub axes: u32,
     pub buttons: u32,
     pub povs: u32,
@@ -17,8 +65,9 @@ pub struct DeviceCapabilities {
 impl DeviceCapabilities {
     pub(crate) fn from_instance(caps: DIDEVCAPS) -> Self {
         Self {
-            flags: caps.dwFlags,
-            dev_type: caps.dwDevType,
+            flags: CapabilityFlags::from_bits_truncate(caps.dwFlags),
+            dev_type: DeviceType::from_bits(caps.dwDevType),
+            hid: is_hid(caps.dwDevType),
             axes: caps.dwAxes,
             buttons: caps.dwButtons,
             povs: caps.dwPOVs,
@@ -30,3 +79,65 @@ impl DeviceCapabilities {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::{
+        DI8DEVTYPEDRIVING_THREEPEDALS, DI8DEVTYPE_DRIVING, DIDEVTYPE_HID,
+    };
+
+    use super::*;
+    use crate::device_type::DrivingSubtype;
+
+    #[test]
+    fn test_decode_flags() {
+        let cases = [
+            (DIDC_ATTACHED, CapabilityFlags::ATTACHED),
+            (DIDC_POLLEDDEVICE, CapabilityFlags::POLLED_DEVICE),
+            (DIDC_EMULATED, CapabilityFlags::EMULATED),
+            (DIDC_POLLEDDATAFORMAT, CapabilityFlags::POLLED_DATA_FORMAT),
+            (DIDC_FORCEFEEDBACK, CapabilityFlags::FORCE_FEEDBACK),
+            (DIDC_FFATTACK, CapabilityFlags::FF_ATTACK),
+            (DIDC_FFFADE, CapabilityFlags::FF_FADE),
+            (DIDC_SATURATION, CapabilityFlags::SATURATION),
+            (
+                DIDC_POSNEGCOEFFICIENTS,
+                CapabilityFlags::POS_NEG_COEFFICIENTS,
+            ),
+            (DIDC_POSNEGSATURATION, CapabilityFlags::POS_NEG_SATURATION),
+            (DIDC_DEADBAND, CapabilityFlags::DEADBAND),
+            (DIDC_STARTDELAY, CapabilityFlags::START_DELAY),
+            (DIDC_ALIAS, CapabilityFlags::ALIAS),
+            (DIDC_PHANTOM, CapabilityFlags::PHANTOM),
+            (DIDC_HIDDEN, CapabilityFlags::HIDDEN),
+        ];
+
+        let mut all = CapabilityFlags::empty();
+        for &(bits, flag) in &cases {
+            assert_eq!(CapabilityFlags::from_bits(bits), Some(flag));
+            all |= flag;
+        }
+        assert_eq!(all, CapabilityFlags::all());
+    }
+
+    #[test]
+    fn test_from_instance() {
+        let caps = DeviceCapabilities::from_instance(DIDEVCAPS {
+            dwFlags: DIDC_ATTACHED | DIDC_FORCEFEEDBACK | 0x8000_0000,
+            dwDevType: DI8DEVTYPE_DRIVING | (DI8DEVTYPEDRIVING_THREEPEDALS << 8) | DIDEVTYPE_HID,
+            dwAxes: 4,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            caps.flags,
+            CapabilityFlags::ATTACHED | CapabilityFlags::FORCE_FEEDBACK
+        );
+        assert_eq!(
+            caps.dev_type,
+            DeviceType::Driving(DrivingSubtype::ThreePedals)
+        );
+        assert!(caps.hid);
+        assert_eq!(caps.axes, 4);
+    }
+}
@@ -0,0 +1,31 @@
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DI8DEVCLASS_ALL, DI8DEVCLASS_DEVICE, DI8DEVCLASS_GAMECTRL, DI8DEVCLASS_KEYBOARD,
+    DI8DEVCLASS_POINTER,
+};
+
+/// Device classes that can be enumerated, equivalent to the `DI8DEVCLASS_*` constants.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DeviceClass {
+    /// All devices.
+    All,
+    /// Devices that do not fall into another class.
+    Device,
+    /// Game controllers such as joysticks, gamepads and wheels.
+    GameController,
+    /// Keyboards and keyboard-like devices.
+    Keyboard,
+    /// Mice and screen pointers.
+    Pointer,
+}
+
+impl DeviceClass {
+    pub fn bits(self) -> u32 {
+        match self {
+            Self::All => DI8DEVCLASS_ALL,
+            Self::Device => DI8DEVCLASS_DEVICE,
+            Self::GameController => DI8DEVCLASS_GAMECTRL,
+            Self::Keyboard => DI8DEVCLASS_KEYBOARD,
+            Self::Pointer => DI8DEVCLASS_POINTER,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::vec;
+
+use windows::Win32::Devices::HumanInterfaceDevice::DIDEVICEOBJECTDATA;
+
+use crate::error::DirectInputStatus;
+
+/// A single buffered input record read with [`Device::events`](crate::Device::events).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DeviceEvent {
+    /// Offset of the object in the device's data format (e.g. `48` for button 0 of `JoyState`).
+    pub object: u32,
+    /// New value of the object. Axes report their position, POVs their angle in hundredths of a
+    /// degree and buttons use the high bit of the low byte to report being pressed.
+    pub value: u32,
+    /// System time in milliseconds at which the event was generated.
+    pub timestamp: u32,
+    /// Monotonically increasing sequence number. Events with the same sequence number happened
+    /// simultaneously.
+    pub sequence: u32,
+    /// [`Action::id`](crate::Action::id) of the action mapped to the object if an action map
+    /// was applied with [`Device::set_action_map`](crate::Device::set_action_map), otherwise 0.
+    pub action: usize,
+}
+
+/// Buffered input records drained from a device, in the order they occurred.
+#[derive(Debug)]
+pub struct DeviceEvents {
+    events: vec::IntoIter<DeviceEvent>,
+    status: DirectInputStatus,
+}
+
+impl DeviceEvent {
+    pub(crate) fn from_instance(data: &DIDEVICEOBJECTDATA) -> Self {
+        Self {
+            object: data.dwOfs,
+            value: data.dwData,
+            timestamp: data.dwTimeStamp,
+            sequence: data.dwSequence,
+            action: data.uAppData,
+        }
+    }
+
+    /// For button objects, whether the button was pressed (as opposed to released).
+    pub fn pressed(&self) -> bool {
+        self.value & 0x80 != 0
+    }
+}
+
+impl DeviceEvents {
+    pub(crate) fn new(events: Vec<DeviceEvent>, status: DirectInputStatus) -> Self {
+        Self {
+            events: events.into_iter(),
+            status,
+        }
+    }
+
+    /// `DirectInputStatus::BufferOverflow` if the device buffer filled up and records were lost
+    /// before they could be read, otherwise `DirectInputStatus::Ok`.
+    pub fn status(&self) -> DirectInputStatus {
+        self.status
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.status == DirectInputStatus::BufferOverflow
+    }
+}
+
+impl Iterator for DeviceEvents {
+    type Item = DeviceEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.size_hint()
+    }
+}
+
+impl ExactSizeIterator for DeviceEvents {}
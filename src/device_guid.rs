@@ -0,0 +1,309 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use windows::core::GUID;
+
+/// Identity of a device instance or product, such as [`DirectInputDeviceInfo::guid_instance`].
+///
+/// Formats as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` in lowercase, or in the braced registry form
+/// `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}` with `{:#}`. Parsing accepts both forms in either
+/// case, as well as the `xxxxxxxx-xxxx-xxxx-xxxxxxxxxxxxxxxx` form previously returned by
+/// `guid_instance_str`. With the `serde` feature it is (de)serialized as the unbraced string.
+///
+/// [`DirectInputDeviceInfo::guid_instance`]: crate::DirectInputDeviceInfo::guid_instance
+#[derive(Clone, Copy)]
+pub struct DeviceGuid(GUID);
+
+/// Error returned when parsing a [`DeviceGuid`] from a malformed string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseGuidError;
+
+impl DeviceGuid {
+    pub const fn from_guid(guid: GUID) -> Self {
+        Self(guid)
+    }
+
+    pub fn as_guid(&self) -> &GUID {
+        &self.0
+    }
+
+    /// The GUID as a 128-bit big-endian integer, which orders GUIDs by their string form.
+    pub fn to_u128(&self) -> u128 {
+        let guid = &self.0;
+
+        (u128::from(guid.data1) << 96)
+            | (u128::from(guid.data2) << 80)
+            | (u128::from(guid.data3) << 64)
+            | u128::from(u64::from_be_bytes(guid.data4))
+    }
+
+    pub fn from_u128(value: u128) -> Self {
+        let data4 = (value as u64).to_be_bytes();
+
+        Self(GUID::from_values(
+            (value >> 96) as u32,
+            (value >> 80) as u16,
+            (value >> 64) as u16,
+            data4,
+        ))
+    }
+}
+
+impl From<GUID> for DeviceGuid {
+    fn from(guid: GUID) -> Self {
+        Self(guid)
+    }
+}
+
+impl From<DeviceGuid> for GUID {
+    fn from(guid: DeviceGuid) -> Self {
+        guid.0
+    }
+}
+
+impl PartialEq for DeviceGuid {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_u128() == other.to_u128()
+    }
+}
+
+impl Eq for DeviceGuid {}
+
+impl Hash for DeviceGuid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_u128().hash(state);
+    }
+}
+
+impl PartialOrd for DeviceGuid {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DeviceGuid {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_u128().cmp(&other.to_u128())
+    }
+}
+
+impl fmt::Display for DeviceGuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let guid = &self.0;
+        let d = &guid.data4;
+
+        if f.alternate() {
+            write!(
+                f,
+                "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+                guid.data1, guid.data2, guid.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+            )
+        } else {
+            write!(
+                f,
+                "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+                guid.data1, guid.data2, guid.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+            )
+        }
+    }
+}
+
+impl fmt::Debug for DeviceGuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        fmt::Display::fmt(self, f)?;
+        f.write_char('"')
+    }
+}
+
+impl FromStr for DeviceGuid {
+    type Err = ParseGuidError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = match s.strip_prefix('{') {
+            Some(inner) => inner.strip_suffix('}').ok_or(ParseGuidError)?,
+            None => s,
+        };
+
+        // `guid_instance_str` used to omit the dash between the last two groups
+        let bytes = s.as_bytes();
+        let dashes: &[usize] = match bytes.len() {
+            36 => &[8, 13, 18, 23],
+            35 => &[8, 13, 18],
+            _ => return Err(ParseGuidError),
+        };
+
+        let mut value = 0u128;
+        for (i, &c) in bytes.iter().enumerate() {
+            if dashes.contains(&i) {
+                if c != b'-' {
+                    return Err(ParseGuidError);
+                }
+                continue;
+            }
+
+            let digit = (c as char).to_digit(16).ok_or(ParseGuidError)?;
+            value = (value << 4) | u128::from(digit);
+        }
+
+        Ok(Self::from_u128(value))
+    }
+}
+
+impl fmt::Display for ParseGuidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid GUID syntax")
+    }
+}
+
+impl Error for ParseGuidError {}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DeviceGuid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DeviceGuid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = DeviceGuid;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a GUID string")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<DeviceGuid, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const GUID_STR: &str = "6f1d2b61-d5a0-11cf-bfc7-444553540000";
+
+    fn guid() -> DeviceGuid {
+        DeviceGuid::from_guid(GUID::from_values(
+            0x6f1d2b61,
+            0xd5a0,
+            0x11cf,
+            [0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00],
+        ))
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(guid().to_string(), GUID_STR);
+        assert_eq!(
+            format!("{:#}", guid()),
+            "{6F1D2B61-D5A0-11CF-BFC7-444553540000}"
+        );
+        assert_eq!(format!("{:?}", guid()), format!("\"{}\"", GUID_STR));
+    }
+
+    #[test]
+    fn test_parse() {
+        for s in &[
+            GUID_STR,
+            "6F1D2B61-D5A0-11CF-BFC7-444553540000",
+            "{6f1d2b61-d5a0-11cf-bfc7-444553540000}",
+            "{6F1D2B61-D5A0-11CF-BFC7-444553540000}",
+            "6f1d2b61-d5a0-11cf-bfc7444553540000",
+            "{6f1d2b61-d5a0-11cf-bfc7444553540000}",
+        ] {
+            assert_eq!(s.parse::<DeviceGuid>(), Ok(guid()), "{}", s);
+        }
+
+        for s in &[
+            "",
+            "{}",
+            "6f1d2b61d5a011cfbfc7444553540000",
+            "6f1d2b61-d5a0-11cf-bfc7-44455354000",
+            "6f1d2b61-d5a0-11cf-bfc7-4445535400000",
+            "6f1d2b61-d5a0-11cf-bfc7-44455354000g",
+            "6f1d2b61-d5a0-11cf+bfc7-444553540000",
+            "{6f1d2b61-d5a0-11cf-bfc7-444553540000",
+            "6f1d2b61-d5a0-11cf-bfc7-444553540000}",
+            "+f1d2b61-d5a0-11cf-bfc7-444553540000",
+            "6f1d2b61-d5a011cf-bfc7-444553540000",
+            "6f1d2b61-d5a0-11cf-bfc744455354000g",
+        ] {
+            assert_eq!(s.parse::<DeviceGuid>(), Err(ParseGuidError), "{}", s);
+        }
+
+        let guid = GUID::from_values(0x1234, 0x5678, 0x9abc, *b"DIRINPUT");
+        let round_trip: DeviceGuid = DeviceGuid::from(guid).to_string().parse().unwrap();
+        assert_eq!(GUID::from(round_trip), guid);
+    }
+
+    #[test]
+    fn test_parse_legacy_instance_str() {
+        // The format of `guid_instance_str` before `DeviceGuid` was introduced
+        let guid = GUID::from_values(0x1234, 0x5678, 0x9abc, *b"DIRINPUT");
+        let d = guid.data4;
+        let legacy = format!(
+            "{:08x}-{:04x}-{:04x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            guid.data1, guid.data2, guid.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+        );
+        assert_eq!(legacy, "00001234-5678-9abc-444952494e505554");
+
+        let parsed: DeviceGuid = legacy.parse().unwrap();
+        assert_eq!(GUID::from(parsed), guid);
+    }
+
+    #[test]
+    fn test_ord_and_hash() {
+        let mut guids: Vec<DeviceGuid> = [
+            "00000002-0000-0000-0000-000000000000",
+            "00000001-0000-0000-0000-000000000001",
+            "00000001-0000-0000-0000-000000000000",
+            "00000001-0001-0000-0000-000000000000",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        guids.sort();
+
+        let sorted: Vec<_> = guids.iter().map(|guid| guid.to_string()).collect();
+        assert_eq!(
+            sorted,
+            [
+                "00000001-0000-0000-0000-000000000000",
+                "00000001-0000-0000-0000-000000000001",
+                "00000001-0001-0000-0000-000000000000",
+                "00000002-0000-0000-0000-000000000000",
+            ]
+        );
+
+        let set: HashSet<_> = guids.iter().chain(&guids).collect();
+        assert_eq!(set.len(), 4);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Token};
+
+        assert_tokens(&guid(), &[Token::Str(GUID_STR)]);
+        assert_de_tokens(
+            &guid(),
+            &[Token::Str("{6F1D2B61-D5A0-11CF-BFC7-444553540000}")],
+        );
+        assert_de_tokens_error::<DeviceGuid>(&[Token::Str("nope")], "invalid GUID syntax");
+    }
+}
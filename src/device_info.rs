@@ -1,15 +1,21 @@
 use std::ffi::OsString;
-use std::fmt::{self, Write};
-use std::os::windows::ffi::OsStringExt;
+use std::fmt;
 
 use windows::core::GUID;
 use windows::Win32::Devices::HumanInterfaceDevice::DIDEVICEINSTANCEW;
 
+use crate::device_guid::DeviceGuid;
+use crate::device_type::{is_hid, DeviceType};
+use crate::hid_usage::Usage;
+use crate::util::os_string_from_wide;
+
+#[derive(Clone)]
 pub struct DirectInputDeviceInfo {
     guid_instance: GUID,
     guid_product: GUID,
     instance_name: OsString,
     product_name: OsString,
+    dev_type: u32,
     force_feedback_driver: GUID,
     usage_page: u16,
     usage: u16,
@@ -17,86 +23,84 @@ pub struct DirectInputDeviceInfo {
 
 impl DirectInputDeviceInfo {
     pub(crate) fn from_instance(device_instance: &DIDEVICEINSTANCEW) -> Self {
-        let instance_name = {
-            let end = device_instance
-                .tszProductName
-                .iter()
-                .position(|&ch| ch == 0)
-                .unwrap_or(device_instance.tszInstanceName.len());
-            OsStringExt::from_wide(&device_instance.tszInstanceName[..end])
-        };
-        let product_name = {
-            let end = device_instance
-                .tszProductName
-                .iter()
-                .position(|&ch| ch == 0)
-                .unwrap_or(device_instance.tszProductName.len());
-            OsStringExt::from_wide(&device_instance.tszProductName[..end])
-        };
+        let instance_name = os_string_from_wide(&device_instance.tszInstanceName);
+        let product_name = os_string_from_wide(&device_instance.tszProductName);
 
         Self {
             guid_instance: device_instance.guidInstance,
             guid_product: device_instance.guidProduct,
             instance_name,
             product_name,
+            dev_type: device_instance.dwDevType,
             force_feedback_driver: device_instance.guidFFDriver,
             usage_page: device_instance.wUsagePage,
             usage: device_instance.wUsage,
         }
     }
 
-    pub(crate) fn guid_instance(&self) -> &GUID {
-        &self.guid_instance
+    pub fn guid_instance(&self) -> DeviceGuid {
+        DeviceGuid::from_guid(self.guid_instance)
+    }
+
+    pub fn guid_product(&self) -> DeviceGuid {
+        DeviceGuid::from_guid(self.guid_product)
     }
 
     pub fn guid_instance_str(&self) -> String {
-        GuidString(&self.guid_instance).to_string()
+        self.guid_instance().to_string()
     }
-}
 
-struct GuidString<'a>(&'a GUID);
+    pub fn device_type(&self) -> DeviceType {
+        DeviceType::from_bits(self.dev_type)
+    }
 
-impl<'a> fmt::Display for GuidString<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{:08x}-{:04x}-{:04x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
-            self.0.data1,
-            self.0.data2,
-            self.0.data3,
-            self.0.data4[0],
-            self.0.data4[1],
-            self.0.data4[2],
-            self.0.data4[3],
-            self.0.data4[4],
-            self.0.data4[5],
-            self.0.data4[6],
-            self.0.data4[7]
-        )
+    /// Whether the device is driven through HID (`DIDEVTYPE_HID`).
+    pub fn is_hid(&self) -> bool {
+        is_hid(self.dev_type)
     }
-}
 
-impl<'a> fmt::Debug for GuidString<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_char('"')?;
-        fmt::Display::fmt(self, f)?;
-        f.write_char('"')
+    /// Top-level HID usage of the device, e.g. "Generic Desktop / Joystick". Only meaningful
+    /// for HID devices.
+    pub fn hid_usage(&self) -> Usage {
+        Usage::new(self.usage_page, self.usage)
+    }
+
+    /// USB vendor ID, if the product GUID is in the `PIDVID` form DirectInput uses for HID
+    /// devices.
+    pub fn vendor_id(&self) -> Option<u16> {
+        self.pid_vid().map(|pid_vid| pid_vid as u16)
+    }
+
+    /// USB product ID, if the product GUID is in the `PIDVID` form DirectInput uses for HID
+    /// devices.
+    pub fn product_id(&self) -> Option<u16> {
+        self.pid_vid().map(|pid_vid| (pid_vid >> 16) as u16)
+    }
+
+    fn pid_vid(&self) -> Option<u32> {
+        let guid = &self.guid_product;
+
+        if guid.data2 == 0 && guid.data3 == 0 && guid.data4 == *b"\0\0PIDVID" {
+            Some(guid.data1)
+        } else {
+            None
+        }
     }
 }
 
 impl fmt::Debug for DirectInputDeviceInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("DirectInputDevice")
-            .field("guid_instance", &GuidString(&self.guid_instance))
-            .field("guid_product", &GuidString(&self.guid_product))
+            .field("guid_instance", &self.guid_instance())
+            .field("guid_product", &self.guid_product())
             .field("instance_name", &self.instance_name)
             .field("product_name", &self.product_name)
+            .field("device_type", &self.device_type())
             .field(
                 "force_feedback_driver",
-                &GuidString(&self.force_feedback_driver),
+                &DeviceGuid::from_guid(self.force_feedback_driver),
             )
-            .field("usage_page", &self.usage_page)
-            .field("usage", &self.usage)
+            .field("usage", &self.hid_usage())
             .finish()
     }
 }
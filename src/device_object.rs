@@ -0,0 +1,85 @@
+use std::ffi::OsString;
+
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DIDEVICEOBJECTINSTANCEW, DIDFT_FFACTUATOR, DIDFT_FFEFFECTTRIGGER,
+};
+
+use crate::data_format::ObjectKind;
+use crate::hid_usage::Usage;
+use crate::util::os_string_from_wide;
+
+/// An object (axis, button, POV, ...) of a device, as reported by `EnumObjects`.
+#[derive(Clone, Debug)]
+pub struct DeviceObject {
+    /// Name of the object as reported by the driver, e.g. "X Axis" or "Button 3".
+    pub name: OsString,
+    /// Object type GUID, e.g. `GUID_XAxis`.
+    pub guid_type: GUID,
+    /// Offset of the object in the current data format.
+    pub offset: u32,
+    /// `DIDFT_*` type flags including the instance number.
+    pub ty: u32,
+    /// `DIDOI_*` flags.
+    pub flags: u32,
+    pub ff_max_force: u32,
+    pub ff_force_resolution: u32,
+    pub collection_number: u16,
+    pub designator_index: u16,
+    pub usage_page: u16,
+    pub usage: u16,
+    /// HID unit code of the object's values.
+    pub dimension: u32,
+    /// Exponent to associate with `dimension`.
+    pub exponent: u16,
+    /// HID report ID containing the object.
+    pub report_id: u16,
+}
+
+impl DeviceObject {
+    pub(crate) fn from_instance(object: &DIDEVICEOBJECTINSTANCEW) -> Self {
+        Self {
+            name: os_string_from_wide(&object.tszName),
+            guid_type: object.guidType,
+            offset: object.dwOfs,
+            ty: object.dwType,
+            flags: object.dwFlags,
+            ff_max_force: object.dwFFMaxForce,
+            ff_force_resolution: object.dwFFForceResolution,
+            collection_number: object.wCollectionNumber,
+            designator_index: object.wDesignatorIndex,
+            usage_page: object.wUsagePage,
+            usage: object.wUsage,
+            dimension: object.dwDimension,
+            exponent: object.wExponent,
+            report_id: object.wReportId,
+        }
+    }
+
+    /// Whether the object is an axis, a button or a POV. Collections and objects without data
+    /// return `None`.
+    pub fn kind(&self) -> Option<ObjectKind> {
+        ObjectKind::from_type(self.ty)
+    }
+
+    /// Instance number of the object among objects of the same type, equivalent to
+    /// `DIDFT_GETINSTANCE`.
+    pub fn instance(&self) -> u16 {
+        (self.ty >> 8) as u16
+    }
+
+    /// HID usage of the object, e.g. "Generic Desktop / X" for an X axis.
+    pub fn hid_usage(&self) -> Usage {
+        Usage::new(self.usage_page, self.usage)
+    }
+
+    /// Whether a force feedback effect can be applied to the object.
+    pub fn is_ff_actuator(&self) -> bool {
+        self.ty & DIDFT_FFACTUATOR != 0
+    }
+
+    /// Whether the object can trigger force feedback effects.
+    pub fn is_ff_effect_trigger(&self) -> bool {
+        self.ty & DIDFT_FFEFFECTTRIGGER != 0
+    }
+}
@@ -0,0 +1,125 @@
+/// Identifiers decoded from a HID device interface path, as reported by
+/// [`GuidAndPath`](crate::GuidAndPath).
+///
+/// USB paths look like `\\?\hid#vid_045e&pid_028e&ig_00#...` or
+/// `\\?\HID#VID_046D&PID_C52B&MI_02&Col01#...`, while Bluetooth paths carry the IDs as
+/// `..._VID&0002045e_PID&02e0...`. Identifiers that are not present are `None`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DevicePath {
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    /// USB interface number of composite devices (`MI_xx`).
+    pub interface: Option<u8>,
+    /// Top-level collection of HID devices exposing several collections (`Colxx`).
+    pub collection: Option<u8>,
+    /// Index of the XInput slot (`IG_xx`) of devices that are also exposed through XInput.
+    pub xinput_index: Option<u8>,
+}
+
+impl DevicePath {
+    /// Decodes the identifiers of `path`. Matching is case-insensitive.
+    pub fn parse(path: &str) -> Self {
+        let path = path.to_ascii_uppercase();
+
+        Self {
+            vendor_id: field(&path, "VID_", 4)
+                .or_else(|| field(&path, "VID&", 8))
+                .map(|id| id as u16),
+            product_id: field(&path, "PID_", 4)
+                .or_else(|| field(&path, "PID&", 4))
+                .map(|id| id as u16),
+            interface: field(&path, "MI_", 2).map(|id| id as u8),
+            collection: field(&path, "COL", 2).map(|id| id as u8),
+            xinput_index: field(&path, "IG_", 2).map(|id| id as u8),
+        }
+    }
+
+    /// Whether the device is an XInput device that DirectInput also exposes, with its triggers
+    /// combined on a single axis.
+    pub fn is_xinput(&self) -> bool {
+        self.xinput_index.is_some()
+    }
+}
+
+/// Finds `key` at the start of a path component and parses the `digits` hex digits following
+/// it. Bluetooth vendor IDs are prefixed with the vendor ID source, which is dropped by the
+/// truncation to `u16`.
+fn field(path: &str, key: &str, digits: usize) -> Option<u32> {
+    path.match_indices(key)
+        .filter(|&(index, _)| {
+            matches!(
+                path[..index].chars().last(),
+                None | Some('#') | Some('&') | Some('\\') | Some('_')
+            )
+        })
+        .find_map(|(index, _)| {
+            let value = path.get(index + key.len()..index + key.len() + digits)?;
+
+            if value.chars().all(|c| c.is_ascii_hexdigit()) {
+                u32::from_str_radix(value, 16).ok()
+            } else {
+                None
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_usb_paths() {
+        let path = DevicePath::parse(
+            r"\\?\hid#vid_045e&pid_028e&ig_00#3&1b8e7f8d&0&0000#{4d1e55b2-f16f-11cf-88cb-001111000030}",
+        );
+        assert_eq!(
+            path,
+            DevicePath {
+                vendor_id: Some(0x045e),
+                product_id: Some(0x028e),
+                xinput_index: Some(0),
+                ..Default::default()
+            }
+        );
+        assert!(path.is_xinput());
+
+        let path = DevicePath::parse(
+            r"\\?\HID#VID_046D&PID_C52B&MI_02&Col01#8&2d4a5b3c&0&0000#{4d1e55b2-f16f-11cf-88cb-001111000030}",
+        );
+        assert_eq!(
+            path,
+            DevicePath {
+                vendor_id: Some(0x046d),
+                product_id: Some(0xc52b),
+                interface: Some(2),
+                collection: Some(1),
+                xinput_index: None,
+            }
+        );
+        assert!(!path.is_xinput());
+    }
+
+    #[test]
+    fn test_parse_bluetooth_path() {
+        let path = DevicePath::parse(
+            r"\\?\HID#{00001124-0000-1000-8000-00805f9b34fb}_VID&0002054c_PID&09cc&Col02#9&1ab2c3d4&0&0001#{4d1e55b2-f16f-11cf-88cb-001111000030}",
+        );
+        assert_eq!(path.vendor_id, Some(0x054c));
+        assert_eq!(path.product_id, Some(0x09cc));
+        assert_eq!(path.collection, Some(2));
+        assert_eq!(path.interface, None);
+    }
+
+    #[test]
+    fn test_parse_invalid_paths() {
+        assert_eq!(DevicePath::parse(""), DevicePath::default());
+        assert_eq!(
+            DevicePath::parse(r"\\?\ROOT#SYSTEM#0001#{4d1e55b2-f16f-11cf-88cb-001111000030}"),
+            DevicePath::default()
+        );
+
+        // Truncated or non-hex identifiers and keys inside other words are ignored
+        let path = DevicePath::parse(r"\\?\hid#vid_04&pid_zzzz&xmi_01&ig_0");
+        assert_eq!(path, DevicePath::default());
+    }
+}
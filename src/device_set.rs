@@ -0,0 +1,376 @@
+use std::convert::TryInto;
+use std::ptr;
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Wake, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use windows::core::Handle;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_TIMEOUT};
+use windows::Win32::System::Threading::{
+    CreateEventW, SetEvent, WaitForMultipleObjects, WaitForSingleObject, WAIT_OBJECT_0,
+};
+use windows::Win32::System::WindowsProgramming::INFINITE;
+
+use crate::device::Device;
+use crate::error::{DirectInputError, Result};
+
+// From `winnt.h`, which is not part of the enabled `windows` features
+const MAXIMUM_WAIT_OBJECTS: usize = 64;
+
+/// A group of devices whose notification events are waited on together.
+///
+/// When every device exposes a Win32 event handle, [`wait`](Self::wait) uses
+/// `WaitForMultipleObjects`, splitting sets of more than 64 devices into shards waited on by
+/// helper threads. Otherwise, e.g. with the simulated backend, it falls back to polling the
+/// events with a shared waker.
+#[derive(Default)]
+pub struct DeviceSet {
+    members: Vec<Member>,
+}
+
+struct Member {
+    device: Device,
+    // Devices whose notification event could not be created are never waited on
+    has_event: bool,
+}
+
+impl DeviceSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `device`, (re)creating its notification event with [`Device::init_event`], and
+    /// returns its index in the set.
+    ///
+    /// If the event cannot be created, the device is still added as the last device so that
+    /// indices keep matching the order devices were pushed in, but [`wait`](Self::wait) never
+    /// reports it.
+    pub fn push(&mut self, mut device: Device) -> Result<usize> {
+        let res = device.init_event();
+        self.members.push(Member {
+            device,
+            has_event: res.is_ok(),
+        });
+
+        res.map(|_| self.members.len() - 1)
+    }
+
+    /// Removes the device at `index`, shifting the index of every following device down by one.
+    pub fn remove(&mut self, index: usize) -> Device {
+        self.members.remove(index).device
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Device> {
+        self.members.get(index).map(|member| &member.device)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Device> {
+        self.members.get_mut(index).map(|member| &mut member.device)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Device> {
+        self.members.iter().map(|member| &member.device)
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn into_devices(self) -> Vec<Device> {
+        self.members
+            .into_iter()
+            .map(|member| member.device)
+            .collect()
+    }
+
+    /// Waits for up to `timeout` for at least one device to signal its notification event.
+    ///
+    /// Returns the indices of every device that signalled, in ascending order, resetting their
+    /// events. An empty list means the timeout expired.
+    pub fn wait(&mut self, timeout: Duration) -> Result<Vec<usize>> {
+        let indices: Vec<_> = (0..self.members.len())
+            .filter(|&index| self.members[index].has_event)
+            .collect();
+        let handles = indices
+            .iter()
+            .map(|&index| self.members[index].device.event_handle())
+            .collect::<Result<Vec<_>>>()?;
+
+        match handles.into_iter().collect::<Option<Vec<HANDLE>>>() {
+            Some(handles) if !handles.is_empty() => Ok(wait_handles(&handles, timeout)?
+                .into_iter()
+                .map(|index| indices[index])
+                .collect()),
+            _ => self.wait_portable(timeout),
+        }
+    }
+
+    /// Waits by polling every device with a waker that notifies a condition variable, which
+    /// works with any backend.
+    pub fn wait_portable(&mut self, timeout: Duration) -> Result<Vec<usize>> {
+        let waker = Arc::new(SetWaker::default());
+        let deadline = Instant::now().checked_add(timeout);
+
+        loop {
+            *waker.woken.lock().unwrap() = false;
+
+            let task_waker = Waker::from(waker.clone());
+            let mut signalled = Vec::new();
+
+            for (index, member) in self.members.iter_mut().enumerate() {
+                if member.has_event && member.device.poll_event(&task_waker)? {
+                    signalled.push(index);
+                }
+            }
+
+            if !signalled.is_empty() || self.members.is_empty() {
+                return Ok(signalled);
+            }
+
+            let woken = waker.woken.lock().unwrap();
+            let woken = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+
+                    waker
+                        .changed
+                        .wait_timeout_while(woken, remaining, |woken| !*woken)
+                        .unwrap()
+                        .0
+                }
+                None => waker.changed.wait_while(woken, |woken| !*woken).unwrap(),
+            };
+
+            if !*woken {
+                return Ok(Vec::new());
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct SetWaker {
+    woken: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl Wake for SetWaker {
+    fn wake(self: Arc<Self>) {
+        *self.woken.lock().unwrap() = true;
+        self.changed.notify_all();
+    }
+}
+
+/// Splits `count` handles into shards that fit in a single `WaitForMultipleObjects` call next to
+/// the extra cancellation event each helper thread waits on.
+fn shards(count: usize) -> impl Iterator<Item = std::ops::Range<usize>> {
+    let size = if count <= MAXIMUM_WAIT_OBJECTS {
+        MAXIMUM_WAIT_OBJECTS
+    } else {
+        MAXIMUM_WAIT_OBJECTS - 1
+    };
+
+    (0..count)
+        .step_by(size)
+        .map(move |start| start..count.min(start + size))
+}
+
+fn timeout_millis(timeout: Duration) -> u32 {
+    timeout.as_millis().try_into().unwrap_or(INFINITE)
+}
+
+/// Waits for any of `handles`, then collects every other handle that is already signalled.
+fn wait_handles(handles: &[HANDLE], timeout: Duration) -> Result<Vec<usize>> {
+    let mut signalled = if handles.len() <= MAXIMUM_WAIT_OBJECTS {
+        wait_any(handles, timeout_millis(timeout))?
+            .into_iter()
+            .collect()
+    } else {
+        wait_sharded(handles, timeout)?
+    };
+
+    if signalled.is_empty() {
+        return Ok(signalled);
+    }
+
+    // The events are auto-reset, so checking them also resets the ones that were signalled
+    for (index, &handle) in handles.iter().enumerate() {
+        if !signalled.contains(&index) && unsafe { WaitForSingleObject(handle, 0) } == WAIT_OBJECT_0
+        {
+            signalled.push(index);
+        }
+    }
+    signalled.sort_unstable();
+
+    Ok(signalled)
+}
+
+/// `WaitForMultipleObjects` on up to 64 handles, returning the lowest signalled index.
+fn wait_any(handles: &[HANDLE], millis: u32) -> Result<Option<usize>> {
+    let res =
+        unsafe { WaitForMultipleObjects(handles.len() as u32, handles.as_ptr(), false, millis) };
+
+    if res == WAIT_TIMEOUT.0 {
+        Ok(None)
+    } else if (WAIT_OBJECT_0..WAIT_OBJECT_0 + handles.len() as u32).contains(&res) {
+        Ok(Some((res - WAIT_OBJECT_0) as usize))
+    } else {
+        Err(DirectInputError::from_last_error())
+    }
+}
+
+/// Waits on more than 64 handles with one helper thread per shard, returning the handles whose
+/// event was consumed by a wait. Each thread also waits on a manual-reset event that is set to
+/// release the other threads once the first one returns.
+fn wait_sharded(handles: &[HANDLE], timeout: Duration) -> Result<Vec<usize>> {
+    let cancel = unsafe { CreateEventW(ptr::null(), true, false, None) };
+
+    if cancel.is_invalid() {
+        return Err(DirectInputError::from_last_error());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let threads: Vec<_> = shards(handles.len())
+        .map(|range| {
+            let start = range.start;
+            let mut shard = handles[range].to_vec();
+            shard.push(cancel);
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                let res = match wait_any(&shard, timeout_millis(timeout)) {
+                    // The cancellation event is the last handle of the shard
+                    Ok(Some(index)) if index == shard.len() - 1 => return,
+                    res => res.map(|index| index.map(|index| start + index)),
+                };
+
+                let _ = sender.send(res);
+            })
+        })
+        .collect();
+    drop(sender);
+
+    // Every thread times out on its own, so the first result is a signalled handle, an error or
+    // the timeout of the whole set
+    let first = receiver.recv().unwrap_or(Ok(None));
+
+    unsafe { SetEvent(cancel) };
+    for thread in threads {
+        let _ = thread.join();
+    }
+    unsafe { CloseHandle(cancel) };
+
+    // Other shards may have consumed an event before being cancelled
+    let mut signalled: Vec<usize> = first?.into_iter().collect();
+    signalled.extend(receiver.try_iter().filter_map(|res| res.ok().flatten()));
+
+    Ok(signalled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::sim::{SimulatedCall, SimulatedDevice};
+    use crate::joy_state::JoyState;
+
+    fn simulated(sim: &SimulatedDevice) -> Device {
+        let mut device = Device::with_backend(sim.clone());
+        device.init_with::<JoyState>().unwrap();
+        device.set_buffer_size(4).unwrap();
+        device.acquire().unwrap();
+        device
+    }
+
+    #[test]
+    fn test_shards() {
+        let collect = |count| shards(count).collect::<Vec<_>>();
+
+        assert_eq!(collect(0), []);
+        assert_eq!(collect(1), vec![0..1]);
+        assert_eq!(collect(64), vec![0..64]);
+        assert_eq!(collect(65), [0..63, 63..65]);
+        assert_eq!(collect(200), [0..63, 63..126, 126..189, 189..200]);
+    }
+
+    #[test]
+    fn test_wait_portable() {
+        let sims: Vec<_> = (0..3)
+            .map(|i| SimulatedDevice::new(&format!("Simulated Pad {}", i)).with_buttons(1))
+            .collect();
+
+        let mut set = DeviceSet::new();
+        for sim in &sims {
+            set.push(simulated(sim)).unwrap();
+        }
+        assert_eq!(set.len(), 3);
+
+        assert_eq!(set.wait(Duration::from_millis(10)).unwrap(), []);
+
+        sims[2].push_event(48, 0x80);
+        sims[0].push_event(48, 0x80);
+        assert_eq!(set.wait(Duration::from_millis(10)).unwrap(), [0, 2]);
+        assert_eq!(set.wait(Duration::ZERO).unwrap(), []);
+
+        let producer = {
+            let sim = sims[1].clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                sim.push_event(48, 0x80);
+            })
+        };
+        assert_eq!(set.wait(Duration::from_secs(10)).unwrap(), [1]);
+        producer.join().unwrap();
+
+        let events = set.get(1).unwrap().events().unwrap();
+        assert_eq!(events.count(), 1);
+    }
+
+    #[test]
+    fn test_push_without_event() {
+        let broken = SimulatedDevice::new("Simulated Pad 0").with_buttons(1);
+        broken.fail_next(SimulatedCall::InitEvent, DirectInputError::Generic);
+        let sim = SimulatedDevice::new("Simulated Pad 1").with_buttons(1);
+
+        // The device is kept so that the index of the next one is not shifted
+        let mut set = DeviceSet::new();
+        assert_eq!(set.push(simulated(&broken)), Err(DirectInputError::Generic));
+        assert_eq!(set.push(simulated(&sim)), Ok(1));
+        assert_eq!(set.len(), 2);
+
+        // A device without an event is never reported as signalled
+        assert_eq!(set.wait(Duration::from_millis(10)).unwrap(), []);
+        sim.push_event(48, 0x80);
+        assert_eq!(set.wait(Duration::from_millis(10)).unwrap(), [1]);
+        assert_eq!(set.into_devices().len(), 2);
+    }
+
+    #[test]
+    fn test_wait_after_poll_event() {
+        // A device polled through a stream before being waited on by the set
+        let sim = SimulatedDevice::new("Simulated Pad").with_buttons(1);
+        let mut set = DeviceSet::new();
+        set.push(simulated(&sim)).unwrap();
+
+        let waker = Arc::new(SetWaker::default());
+        let task_waker = Waker::from(waker.clone());
+        assert!(!set.get_mut(0).unwrap().poll_event(&task_waker).unwrap());
+
+        assert_eq!(set.wait(Duration::from_millis(10)).unwrap(), []);
+
+        sim.push_event(48, 0x80);
+        assert_eq!(set.wait(Duration::from_millis(10)).unwrap(), [0]);
+        assert_eq!(set.wait(Duration::ZERO).unwrap(), []);
+
+        // The stream can poll the device again afterwards
+        assert!(!set.get_mut(0).unwrap().poll_event(&task_waker).unwrap());
+        sim.push_event(48, 0x80);
+        assert!(*waker.woken.lock().unwrap());
+        assert!(set.get_mut(0).unwrap().poll_event(&task_waker).unwrap());
+    }
+}
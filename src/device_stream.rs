@@ -0,0 +1,304 @@
+use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::device::{Device, FromDeviceState};
+use crate::device_event::{DeviceEvent, DeviceEvents};
+use crate::error::{DirectInputError, DirectInputStatus, Result};
+
+/// A [`Stream`] of device states, yielding a new state each time the device signals its
+/// notification event.
+///
+/// The task is woken by the event itself rather than by a thread blocked in
+/// [`Device::wait`], so it works with any executor. The stream never ends; errors such as
+/// `DirectInputError::InputLost` are yielded and the stream can keep being polled after
+/// reacquiring the device through [`device`](Self::device).
+pub struct StateStream<T> {
+    device: Device,
+    _state: PhantomData<fn() -> T>,
+}
+
+/// A [`Stream`] of buffered [`DeviceEvent`]s, draining the device buffer each time the device
+/// signals its notification event.
+///
+/// Requires a buffer size to have been configured with [`Device::set_buffer_size`]. Records lost
+/// to a buffer overflow are skipped and reported by [`take_overflowed`](Self::take_overflowed).
+/// Like [`StateStream`], the stream never ends.
+pub struct EventStream {
+    device: Device,
+    pending: DeviceEvents,
+    overflowed: bool,
+}
+
+/// Creates the notification event of `device`. Polled devices only signal the event when
+/// polled, so nothing would ever wake the stream.
+fn init_event(device: &mut Device) -> Result<()> {
+    match device.init_event()? {
+        DirectInputStatus::PolledDevice => Err(DirectInputError::Unsupported),
+        _ => Ok(()),
+    }
+}
+
+impl<T: FromDeviceState> StateStream<T> {
+    /// Takes ownership of `device` and (re)creates its notification event, replacing any event
+    /// created with [`Device::init_event`]. The data format must match `T` and the device should
+    /// be acquired before polling the stream.
+    ///
+    /// Fails with `DirectInputError::Unsupported` for devices that require polling.
+    pub fn new(mut device: Device) -> Result<Self> {
+        init_event(&mut device)?;
+
+        Ok(Self {
+            device,
+            _state: PhantomData,
+        })
+    }
+}
+
+impl<T> StateStream<T> {
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn device_mut(&mut self) -> &mut Device {
+        &mut self.device
+    }
+
+    pub fn into_device(self) -> Device {
+        self.device
+    }
+}
+
+impl<T: FromDeviceState> Stream for StateStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match this.device.poll_event(cx.waker()) {
+            Ok(true) => Poll::Ready(Some(this.device.get_state())),
+            Ok(false) => Poll::Pending,
+            Err(e) => Poll::Ready(Some(Err(e))),
+        }
+    }
+}
+
+impl EventStream {
+    /// Takes ownership of `device` and (re)creates its notification event, replacing any event
+    /// created with [`Device::init_event`]. The device should be acquired before polling the
+    /// stream.
+    ///
+    /// Fails with `DirectInputError::Unsupported` for devices that require polling.
+    pub fn new(mut device: Device) -> Result<Self> {
+        init_event(&mut device)?;
+
+        Ok(Self {
+            device,
+            pending: DeviceEvents::new(Vec::new(), DirectInputStatus::Ok),
+            overflowed: false,
+        })
+    }
+
+    /// Returns `true` if the device buffer overflowed since the last call, in which case records
+    /// older than the events drained since then were lost.
+    pub fn take_overflowed(&mut self) -> bool {
+        mem::replace(&mut self.overflowed, false)
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn device_mut(&mut self) -> &mut Device {
+        &mut self.device
+    }
+
+    /// Returns the device. Events already drained from the device buffer but not yet yielded are
+    /// dropped.
+    pub fn into_device(self) -> Device {
+        self.device
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<DeviceEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(event) = this.pending.next() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            // The event may be signalled without new records, e.g. on acquisition, in which case
+            // the waker has to be registered again
+            match this.device.poll_event(cx.waker()) {
+                Ok(true) => match this.device.events() {
+                    Ok(events) => {
+                        this.overflowed |= events.overflowed();
+                        this.pending = events;
+                    }
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                },
+                Ok(false) => return Poll::Pending,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    use windows::Win32::Devices::HumanInterfaceDevice::{DIDC_POLLEDDEVICE, DIJOYSTATE2};
+
+    use super::*;
+    use crate::backend::sim::{SimulatedCall, SimulatedDevice};
+    use crate::joy_state::JoyState;
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            match Pin::new(&mut *stream).poll_next(&mut cx) {
+                Poll::Ready(item) => return item,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    fn simulated(sim: &SimulatedDevice) -> Device {
+        let mut device = Device::with_backend(sim.clone());
+        device.init_with::<JoyState>().unwrap();
+        device
+    }
+
+    #[test]
+    fn test_state_stream_wakes() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_axes(2);
+        let mut stream = StateStream::<JoyState>::new(simulated(&sim)).unwrap();
+        stream.device().acquire().unwrap();
+
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        sim.push_raw(&DIJOYSTATE2 {
+            lX: 1,
+            lY: 2,
+            ..Default::default()
+        });
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Ready(Some(Ok(state))) => assert_eq!((state.x, state.y), (1, 2)),
+            _ => panic!("Expected a state"),
+        }
+
+        // The waker is consumed by the signal, polling again registers it anew
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        sim.signal();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+
+        sim.fail_next(SimulatedCall::PollEvent, DirectInputError::InputLost);
+        assert!(matches!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(Err(DirectInputError::InputLost)))
+        ));
+    }
+
+    #[test]
+    fn test_event_stream_across_threads() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_buttons(2);
+        let mut device = simulated(&sim);
+        device.set_buffer_size(8).unwrap();
+        let mut stream = EventStream::new(device).unwrap();
+        stream.device().acquire().unwrap();
+
+        let producer = {
+            let sim = sim.clone();
+            thread::spawn(move || {
+                for &(offset, value) in &[(48, 0x80), (49, 0x80), (48, 0)] {
+                    thread::sleep(Duration::from_millis(5));
+                    sim.push_event(offset, value);
+                }
+            })
+        };
+
+        let events: Vec<_> = (0..3)
+            .map(|_| {
+                let event = next(&mut stream).unwrap().unwrap();
+                (event.object, event.value)
+            })
+            .collect();
+        assert_eq!(events, [(48, 0x80), (49, 0x80), (48, 0)]);
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn test_event_stream_overflow() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_buttons(1);
+        let mut device = simulated(&sim);
+        device.set_buffer_size(4).unwrap();
+        let mut stream = EventStream::new(device).unwrap();
+        stream.device().acquire().unwrap();
+        assert!(!stream.take_overflowed());
+
+        // The two oldest records are dropped from the full buffer
+        for value in 0..6 {
+            sim.push_event(48, value);
+        }
+
+        let mut values = Vec::new();
+        for _ in 0..4 {
+            values.push(next(&mut stream).unwrap().unwrap().value);
+        }
+        assert_eq!(values, [2, 3, 4, 5]);
+        assert!(stream.take_overflowed());
+        assert!(!stream.take_overflowed());
+
+        sim.push_event(48, 6);
+        assert_eq!(next(&mut stream).unwrap().unwrap().value, 6);
+        assert!(!stream.take_overflowed());
+    }
+
+    #[test]
+    fn test_polled_device_unsupported() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_flags(DIDC_POLLEDDEVICE);
+
+        assert_eq!(
+            StateStream::<JoyState>::new(simulated(&sim)).err(),
+            Some(DirectInputError::Unsupported)
+        );
+    }
+}
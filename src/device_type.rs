@@ -0,0 +1,605 @@
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DI8DEVTYPE1STPERSON_LIMITED, DI8DEVTYPE1STPERSON_SHOOTER, DI8DEVTYPE1STPERSON_SIXDOF,
+    DI8DEVTYPE1STPERSON_UNKNOWN, DI8DEVTYPEDEVICECTRL_COMMSSELECTION,
+    DI8DEVTYPEDEVICECTRL_COMMSSELECTION_HARDWIRED, DI8DEVTYPEDEVICECTRL_UNKNOWN,
+    DI8DEVTYPEDRIVING_COMBINEDPEDALS, DI8DEVTYPEDRIVING_DUALPEDALS, DI8DEVTYPEDRIVING_HANDHELD,
+    DI8DEVTYPEDRIVING_LIMITED, DI8DEVTYPEDRIVING_THREEPEDALS, DI8DEVTYPEFLIGHT_LIMITED,
+    DI8DEVTYPEFLIGHT_RC, DI8DEVTYPEFLIGHT_STICK, DI8DEVTYPEFLIGHT_YOKE, DI8DEVTYPEGAMEPAD_LIMITED,
+    DI8DEVTYPEGAMEPAD_STANDARD, DI8DEVTYPEGAMEPAD_TILT, DI8DEVTYPEJOYSTICK_LIMITED,
+    DI8DEVTYPEJOYSTICK_STANDARD, DI8DEVTYPEKEYBOARD_J3100, DI8DEVTYPEKEYBOARD_JAPAN106,
+    DI8DEVTYPEKEYBOARD_JAPANAX, DI8DEVTYPEKEYBOARD_NEC98, DI8DEVTYPEKEYBOARD_NEC98106,
+    DI8DEVTYPEKEYBOARD_NEC98LAPTOP, DI8DEVTYPEKEYBOARD_NOKIA1050, DI8DEVTYPEKEYBOARD_NOKIA9140,
+    DI8DEVTYPEKEYBOARD_OLIVETTI, DI8DEVTYPEKEYBOARD_PCAT, DI8DEVTYPEKEYBOARD_PCENH,
+    DI8DEVTYPEKEYBOARD_PCXT, DI8DEVTYPEKEYBOARD_UNKNOWN, DI8DEVTYPEMOUSE_ABSOLUTE,
+    DI8DEVTYPEMOUSE_FINGERSTICK, DI8DEVTYPEMOUSE_TOUCHPAD, DI8DEVTYPEMOUSE_TRACKBALL,
+    DI8DEVTYPEMOUSE_TRADITIONAL, DI8DEVTYPEMOUSE_UNKNOWN, DI8DEVTYPEREMOTE_UNKNOWN,
+    DI8DEVTYPESCREENPTR_LIGHTGUN, DI8DEVTYPESCREENPTR_LIGHTPEN, DI8DEVTYPESCREENPTR_TOUCH,
+    DI8DEVTYPESCREENPTR_UNKNOWN, DI8DEVTYPESUPPLEMENTAL_2NDHANDCONTROLLER,
+    DI8DEVTYPESUPPLEMENTAL_COMBINEDPEDALS, DI8DEVTYPESUPPLEMENTAL_DUALPEDALS,
+    DI8DEVTYPESUPPLEMENTAL_HANDTRACKER, DI8DEVTYPESUPPLEMENTAL_HEADTRACKER,
+    DI8DEVTYPESUPPLEMENTAL_RUDDERPEDALS, DI8DEVTYPESUPPLEMENTAL_SHIFTER,
+    DI8DEVTYPESUPPLEMENTAL_SHIFTSTICKGATE, DI8DEVTYPESUPPLEMENTAL_SPLITTHROTTLE,
+    DI8DEVTYPESUPPLEMENTAL_THREEPEDALS, DI8DEVTYPESUPPLEMENTAL_THROTTLE,
+    DI8DEVTYPESUPPLEMENTAL_UNKNOWN, DI8DEVTYPE_1STPERSON, DI8DEVTYPE_DEVICE, DI8DEVTYPE_DEVICECTRL,
+    DI8DEVTYPE_DRIVING, DI8DEVTYPE_FLIGHT, DI8DEVTYPE_GAMEPAD, DI8DEVTYPE_JOYSTICK,
+    DI8DEVTYPE_KEYBOARD, DI8DEVTYPE_MOUSE, DI8DEVTYPE_REMOTE, DI8DEVTYPE_SCREENPOINTER,
+    DI8DEVTYPE_SUPPLEMENTAL, DIDEVTYPE_HID,
+};
+
+use crate::device_class::DeviceClass;
+
+/// Defines a subtype enum for the second byte of `dwDevType`, with an `Other` variant for values
+/// DirectInput does not define.
+macro_rules! subtype {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:ident,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// A subtype not defined by DirectInput.
+            Other(u8),
+        }
+
+        impl $name {
+            pub fn from_bits(bits: u8) -> Self {
+                match u32::from(bits) {
+                    $($value => Self::$variant,)*
+                    _ => Self::Other(bits),
+                }
+            }
+
+            pub fn bits(self) -> u8 {
+                match self {
+                    $(Self::$variant => $value as u8,)*
+                    Self::Other(bits) => bits,
+                }
+            }
+        }
+    };
+}
+
+subtype! {
+    /// `DI8DEVTYPEMOUSE_*` subtypes.
+    pub enum MouseSubtype {
+        /// The subtype could not be determined.
+        Unknown = DI8DEVTYPEMOUSE_UNKNOWN,
+        Traditional = DI8DEVTYPEMOUSE_TRADITIONAL,
+        FingerStick = DI8DEVTYPEMOUSE_FINGERSTICK,
+        TouchPad = DI8DEVTYPEMOUSE_TOUCHPAD,
+        Trackball = DI8DEVTYPEMOUSE_TRACKBALL,
+        /// A mouse reporting absolute axis data.
+        Absolute = DI8DEVTYPEMOUSE_ABSOLUTE,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPEKEYBOARD_*` subtypes, identifying the keyboard layout.
+    pub enum KeyboardSubtype {
+        /// The subtype could not be determined.
+        Unknown = DI8DEVTYPEKEYBOARD_UNKNOWN,
+        /// IBM PC/XT 83-key keyboard.
+        PcXt = DI8DEVTYPEKEYBOARD_PCXT,
+        /// Olivetti 102-key keyboard.
+        Olivetti = DI8DEVTYPEKEYBOARD_OLIVETTI,
+        /// IBM PC/AT 84-key keyboard.
+        PcAt = DI8DEVTYPEKEYBOARD_PCAT,
+        /// IBM PC enhanced 101/102-key or Microsoft Natural keyboard.
+        PcEnhanced = DI8DEVTYPEKEYBOARD_PCENH,
+        Nokia1050 = DI8DEVTYPEKEYBOARD_NOKIA1050,
+        Nokia9140 = DI8DEVTYPEKEYBOARD_NOKIA9140,
+        /// Japanese NEC PC98 keyboard.
+        Nec98 = DI8DEVTYPEKEYBOARD_NEC98,
+        /// Japanese NEC PC98 laptop keyboard.
+        Nec98Laptop = DI8DEVTYPEKEYBOARD_NEC98LAPTOP,
+        /// Japanese NEC PC98 106-key keyboard.
+        Nec98106 = DI8DEVTYPEKEYBOARD_NEC98106,
+        /// Japanese 106-key keyboard.
+        Japan106 = DI8DEVTYPEKEYBOARD_JAPAN106,
+        /// Japanese AX keyboard.
+        JapanAx = DI8DEVTYPEKEYBOARD_JAPANAX,
+        /// Japanese J3100 keyboard.
+        J3100 = DI8DEVTYPEKEYBOARD_J3100,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPEJOYSTICK_*` subtypes.
+    pub enum JoystickSubtype {
+        /// Does not have the minimum controls for action mapping.
+        Limited = DI8DEVTYPEJOYSTICK_LIMITED,
+        Standard = DI8DEVTYPEJOYSTICK_STANDARD,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPEGAMEPAD_*` subtypes.
+    pub enum GamepadSubtype {
+        /// Does not have the minimum controls for action mapping.
+        Limited = DI8DEVTYPEGAMEPAD_LIMITED,
+        Standard = DI8DEVTYPEGAMEPAD_STANDARD,
+        /// Reports the x- and y-axis from the tilt of the controller.
+        Tilt = DI8DEVTYPEGAMEPAD_TILT,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPEDRIVING_*` subtypes for steering wheels.
+    pub enum DrivingSubtype {
+        /// Does not have the minimum controls for action mapping.
+        Limited = DI8DEVTYPEDRIVING_LIMITED,
+        /// Accelerator and brake pedals report a single combined axis.
+        CombinedPedals = DI8DEVTYPEDRIVING_COMBINEDPEDALS,
+        /// Accelerator and brake pedals report separate axes.
+        DualPedals = DI8DEVTYPEDRIVING_DUALPEDALS,
+        /// Accelerator, brake and clutch pedals report separate axes.
+        ThreePedals = DI8DEVTYPEDRIVING_THREEPEDALS,
+        /// Handheld steering device.
+        Handheld = DI8DEVTYPEDRIVING_HANDHELD,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPEFLIGHT_*` subtypes.
+    pub enum FlightSubtype {
+        /// Does not have the minimum controls for action mapping.
+        Limited = DI8DEVTYPEFLIGHT_LIMITED,
+        Stick = DI8DEVTYPEFLIGHT_STICK,
+        Yoke = DI8DEVTYPEFLIGHT_YOKE,
+        /// Radio-controlled model aircraft controller.
+        Rc = DI8DEVTYPEFLIGHT_RC,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPE1STPERSON_*` subtypes.
+    pub enum FirstPersonSubtype {
+        /// Does not have the minimum controls for action mapping.
+        Limited = DI8DEVTYPE1STPERSON_LIMITED,
+        /// The subtype could not be determined.
+        Unknown = DI8DEVTYPE1STPERSON_UNKNOWN,
+        /// Device with six degrees of freedom: three lateral and three rotational axes.
+        SixDof = DI8DEVTYPE1STPERSON_SIXDOF,
+        Shooter = DI8DEVTYPE1STPERSON_SHOOTER,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPEDEVICECTRL_*` subtypes.
+    pub enum DeviceControlSubtype {
+        /// The subtype could not be determined.
+        Unknown = DI8DEVTYPEDEVICECTRL_UNKNOWN,
+        /// Control used to make communications selections.
+        CommsSelection = DI8DEVTYPEDEVICECTRL_COMMSSELECTION,
+        /// Control that must use its default action mapping.
+        CommsSelectionHardwired = DI8DEVTYPEDEVICECTRL_COMMSSELECTION_HARDWIRED,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPESCREENPTR_*` subtypes.
+    pub enum ScreenPointerSubtype {
+        /// The subtype could not be determined.
+        Unknown = DI8DEVTYPESCREENPTR_UNKNOWN,
+        LightGun = DI8DEVTYPESCREENPTR_LIGHTGUN,
+        LightPen = DI8DEVTYPESCREENPTR_LIGHTPEN,
+        Touch = DI8DEVTYPESCREENPTR_TOUCH,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPEREMOTE_*` subtypes.
+    pub enum RemoteSubtype {
+        /// The subtype could not be determined.
+        Unknown = DI8DEVTYPEREMOTE_UNKNOWN,
+    }
+}
+
+subtype! {
+    /// `DI8DEVTYPESUPPLEMENTAL_*` subtypes for devices that complement a main controller.
+    pub enum SupplementalSubtype {
+        /// The subtype could not be determined.
+        Unknown = DI8DEVTYPESUPPLEMENTAL_UNKNOWN,
+        /// Controller held in the second hand, such as a throttle paired with a flight stick.
+        SecondHandController = DI8DEVTYPESUPPLEMENTAL_2NDHANDCONTROLLER,
+        HeadTracker = DI8DEVTYPESUPPLEMENTAL_HEADTRACKER,
+        HandTracker = DI8DEVTYPESUPPLEMENTAL_HANDTRACKER,
+        /// Shifter reporting gate positions as buttons.
+        ShiftStickGate = DI8DEVTYPESUPPLEMENTAL_SHIFTSTICKGATE,
+        /// Shifter reporting gear changes on an axis.
+        Shifter = DI8DEVTYPESUPPLEMENTAL_SHIFTER,
+        Throttle = DI8DEVTYPESUPPLEMENTAL_THROTTLE,
+        /// Two throttles on separate axes.
+        SplitThrottle = DI8DEVTYPESUPPLEMENTAL_SPLITTHROTTLE,
+        /// Accelerator and brake pedals on a single combined axis.
+        CombinedPedals = DI8DEVTYPESUPPLEMENTAL_COMBINEDPEDALS,
+        /// Accelerator and brake pedals on separate axes.
+        DualPedals = DI8DEVTYPESUPPLEMENTAL_DUALPEDALS,
+        /// Accelerator, brake and clutch pedals on separate axes.
+        ThreePedals = DI8DEVTYPESUPPLEMENTAL_THREEPEDALS,
+        RudderPedals = DI8DEVTYPESUPPLEMENTAL_RUDDERPEDALS,
+    }
+}
+
+/// Device type and subtype decoded from the `dwDevType` of a device, equivalent to the
+/// `DI8DEVTYPE_*` constants and their per-type subtypes.
+///
+/// The `DIDEVTYPE_HID` flag in the third byte is not part of the type; see
+/// [`DeviceCapabilities::hid`](crate::DeviceCapabilities::hid).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DeviceType {
+    /// A device that does not fall into another type.
+    Device,
+    Mouse(MouseSubtype),
+    Keyboard(KeyboardSubtype),
+    Joystick(JoystickSubtype),
+    Gamepad(GamepadSubtype),
+    /// Steering wheels and other driving controllers.
+    Driving(DrivingSubtype),
+    Flight(FlightSubtype),
+    FirstPerson(FirstPersonSubtype),
+    /// Controls for the device itself, such as communication selection.
+    DeviceControl(DeviceControlSubtype),
+    ScreenPointer(ScreenPointerSubtype),
+    Remote(RemoteSubtype),
+    Supplemental(SupplementalSubtype),
+    /// A type not defined by DirectInput, with its raw type and subtype bytes.
+    Other(u8, u8),
+}
+
+impl DeviceType {
+    /// Decodes the type (`GET_DIDEVICE_TYPE`) and subtype (`GET_DIDEVICE_SUBTYPE`) of a
+    /// `dwDevType` value.
+    pub fn from_bits(dev_type: u32) -> Self {
+        let subtype = (dev_type >> 8) as u8;
+
+        match dev_type & 0xff {
+            DI8DEVTYPE_DEVICE => Self::Device,
+            DI8DEVTYPE_MOUSE => Self::Mouse(MouseSubtype::from_bits(subtype)),
+            DI8DEVTYPE_KEYBOARD => Self::Keyboard(KeyboardSubtype::from_bits(subtype)),
+            DI8DEVTYPE_JOYSTICK => Self::Joystick(JoystickSubtype::from_bits(subtype)),
+            DI8DEVTYPE_GAMEPAD => Self::Gamepad(GamepadSubtype::from_bits(subtype)),
+            DI8DEVTYPE_DRIVING => Self::Driving(DrivingSubtype::from_bits(subtype)),
+            DI8DEVTYPE_FLIGHT => Self::Flight(FlightSubtype::from_bits(subtype)),
+            DI8DEVTYPE_1STPERSON => Self::FirstPerson(FirstPersonSubtype::from_bits(subtype)),
+            DI8DEVTYPE_DEVICECTRL => Self::DeviceControl(DeviceControlSubtype::from_bits(subtype)),
+            DI8DEVTYPE_SCREENPOINTER => {
+                Self::ScreenPointer(ScreenPointerSubtype::from_bits(subtype))
+            }
+            DI8DEVTYPE_REMOTE => Self::Remote(RemoteSubtype::from_bits(subtype)),
+            DI8DEVTYPE_SUPPLEMENTAL => Self::Supplemental(SupplementalSubtype::from_bits(subtype)),
+            ty => Self::Other(ty as u8, subtype),
+        }
+    }
+
+    /// The `dwDevType` value of the type and subtype, without `DIDEVTYPE_HID`.
+    pub fn bits(self) -> u32 {
+        let (ty, subtype) = match self {
+            Self::Device => (DI8DEVTYPE_DEVICE, 0),
+            Self::Mouse(subtype) => (DI8DEVTYPE_MOUSE, subtype.bits()),
+            Self::Keyboard(subtype) => (DI8DEVTYPE_KEYBOARD, subtype.bits()),
+            Self::Joystick(subtype) => (DI8DEVTYPE_JOYSTICK, subtype.bits()),
+            Self::Gamepad(subtype) => (DI8DEVTYPE_GAMEPAD, subtype.bits()),
+            Self::Driving(subtype) => (DI8DEVTYPE_DRIVING, subtype.bits()),
+            Self::Flight(subtype) => (DI8DEVTYPE_FLIGHT, subtype.bits()),
+            Self::FirstPerson(subtype) => (DI8DEVTYPE_1STPERSON, subtype.bits()),
+            Self::DeviceControl(subtype) => (DI8DEVTYPE_DEVICECTRL, subtype.bits()),
+            Self::ScreenPointer(subtype) => (DI8DEVTYPE_SCREENPOINTER, subtype.bits()),
+            Self::Remote(subtype) => (DI8DEVTYPE_REMOTE, subtype.bits()),
+            Self::Supplemental(subtype) => (DI8DEVTYPE_SUPPLEMENTAL, subtype.bits()),
+            Self::Other(ty, subtype) => (u32::from(ty), subtype),
+        };
+
+        ty | (u32::from(subtype) << 8)
+    }
+
+    /// The class the type is enumerated under.
+    pub fn class(self) -> DeviceClass {
+        match self {
+            Self::Mouse(_) | Self::ScreenPointer(_) => DeviceClass::Pointer,
+            Self::Keyboard(_) => DeviceClass::Keyboard,
+            Self::Joystick(_)
+            | Self::Gamepad(_)
+            | Self::Driving(_)
+            | Self::Flight(_)
+            | Self::FirstPerson(_)
+            | Self::Supplemental(_) => DeviceClass::GameController,
+            Self::Device | Self::DeviceControl(_) | Self::Remote(_) | Self::Other(..) => {
+                DeviceClass::Device
+            }
+        }
+    }
+}
+
+/// Whether a `dwDevType` value has the `DIDEVTYPE_HID` flag, set for devices driven through HID.
+pub(crate) fn is_hid(dev_type: u32) -> bool {
+    dev_type & DIDEVTYPE_HID != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_types() {
+        let cases = [
+            (DI8DEVTYPE_DEVICE, DeviceType::Device),
+            (
+                DI8DEVTYPE_MOUSE | (DI8DEVTYPEMOUSE_TRADITIONAL << 8),
+                DeviceType::Mouse(MouseSubtype::Traditional),
+            ),
+            (
+                DI8DEVTYPE_KEYBOARD | (DI8DEVTYPEKEYBOARD_PCENH << 8),
+                DeviceType::Keyboard(KeyboardSubtype::PcEnhanced),
+            ),
+            (
+                DI8DEVTYPE_JOYSTICK | (DI8DEVTYPEJOYSTICK_STANDARD << 8),
+                DeviceType::Joystick(JoystickSubtype::Standard),
+            ),
+            (
+                DI8DEVTYPE_GAMEPAD | (DI8DEVTYPEGAMEPAD_STANDARD << 8),
+                DeviceType::Gamepad(GamepadSubtype::Standard),
+            ),
+            (
+                DI8DEVTYPE_DRIVING | (DI8DEVTYPEDRIVING_DUALPEDALS << 8),
+                DeviceType::Driving(DrivingSubtype::DualPedals),
+            ),
+            (
+                DI8DEVTYPE_FLIGHT | (DI8DEVTYPEFLIGHT_STICK << 8),
+                DeviceType::Flight(FlightSubtype::Stick),
+            ),
+            (
+                DI8DEVTYPE_1STPERSON | (DI8DEVTYPE1STPERSON_SIXDOF << 8),
+                DeviceType::FirstPerson(FirstPersonSubtype::SixDof),
+            ),
+            (
+                DI8DEVTYPE_DEVICECTRL | (DI8DEVTYPEDEVICECTRL_COMMSSELECTION << 8),
+                DeviceType::DeviceControl(DeviceControlSubtype::CommsSelection),
+            ),
+            (
+                DI8DEVTYPE_SCREENPOINTER | (DI8DEVTYPESCREENPTR_LIGHTGUN << 8),
+                DeviceType::ScreenPointer(ScreenPointerSubtype::LightGun),
+            ),
+            (
+                DI8DEVTYPE_REMOTE | (DI8DEVTYPEREMOTE_UNKNOWN << 8),
+                DeviceType::Remote(RemoteSubtype::Unknown),
+            ),
+            (
+                DI8DEVTYPE_SUPPLEMENTAL | (DI8DEVTYPESUPPLEMENTAL_THROTTLE << 8),
+                DeviceType::Supplemental(SupplementalSubtype::Throttle),
+            ),
+            (0x3f | (0x07 << 8), DeviceType::Other(0x3f, 0x07)),
+            (
+                DI8DEVTYPE_GAMEPAD | (0x42 << 8),
+                DeviceType::Gamepad(GamepadSubtype::Other(0x42)),
+            ),
+        ];
+
+        for &(bits, ty) in &cases {
+            assert_eq!(DeviceType::from_bits(bits), ty, "{:#x}", bits);
+            assert_eq!(
+                DeviceType::from_bits(bits | DIDEVTYPE_HID),
+                ty,
+                "{:#x}",
+                bits
+            );
+            assert_eq!(ty.bits(), bits);
+        }
+    }
+
+    #[test]
+    fn test_decode_subtypes() {
+        fn check<T: Copy + Eq + std::fmt::Debug>(
+            from_bits: fn(u8) -> T,
+            bits: fn(T) -> u8,
+            cases: &[(u32, T)],
+        ) {
+            for &(value, subtype) in cases {
+                assert_eq!(from_bits(value as u8), subtype);
+                assert_eq!(bits(subtype), value as u8);
+            }
+        }
+
+        check(
+            MouseSubtype::from_bits,
+            MouseSubtype::bits,
+            &[
+                (DI8DEVTYPEMOUSE_UNKNOWN, MouseSubtype::Unknown),
+                (DI8DEVTYPEMOUSE_TRADITIONAL, MouseSubtype::Traditional),
+                (DI8DEVTYPEMOUSE_FINGERSTICK, MouseSubtype::FingerStick),
+                (DI8DEVTYPEMOUSE_TOUCHPAD, MouseSubtype::TouchPad),
+                (DI8DEVTYPEMOUSE_TRACKBALL, MouseSubtype::Trackball),
+                (DI8DEVTYPEMOUSE_ABSOLUTE, MouseSubtype::Absolute),
+                (0, MouseSubtype::Other(0)),
+            ],
+        );
+        check(
+            KeyboardSubtype::from_bits,
+            KeyboardSubtype::bits,
+            &[
+                (DI8DEVTYPEKEYBOARD_UNKNOWN, KeyboardSubtype::Unknown),
+                (DI8DEVTYPEKEYBOARD_PCXT, KeyboardSubtype::PcXt),
+                (DI8DEVTYPEKEYBOARD_OLIVETTI, KeyboardSubtype::Olivetti),
+                (DI8DEVTYPEKEYBOARD_PCAT, KeyboardSubtype::PcAt),
+                (DI8DEVTYPEKEYBOARD_PCENH, KeyboardSubtype::PcEnhanced),
+                (DI8DEVTYPEKEYBOARD_NOKIA1050, KeyboardSubtype::Nokia1050),
+                (DI8DEVTYPEKEYBOARD_NOKIA9140, KeyboardSubtype::Nokia9140),
+                (DI8DEVTYPEKEYBOARD_NEC98, KeyboardSubtype::Nec98),
+                (DI8DEVTYPEKEYBOARD_NEC98LAPTOP, KeyboardSubtype::Nec98Laptop),
+                (DI8DEVTYPEKEYBOARD_NEC98106, KeyboardSubtype::Nec98106),
+                (DI8DEVTYPEKEYBOARD_JAPAN106, KeyboardSubtype::Japan106),
+                (DI8DEVTYPEKEYBOARD_JAPANAX, KeyboardSubtype::JapanAx),
+                (DI8DEVTYPEKEYBOARD_J3100, KeyboardSubtype::J3100),
+                (13, KeyboardSubtype::Other(13)),
+            ],
+        );
+        check(
+            JoystickSubtype::from_bits,
+            JoystickSubtype::bits,
+            &[
+                (DI8DEVTYPEJOYSTICK_LIMITED, JoystickSubtype::Limited),
+                (DI8DEVTYPEJOYSTICK_STANDARD, JoystickSubtype::Standard),
+                (3, JoystickSubtype::Other(3)),
+            ],
+        );
+        check(
+            GamepadSubtype::from_bits,
+            GamepadSubtype::bits,
+            &[
+                (DI8DEVTYPEGAMEPAD_LIMITED, GamepadSubtype::Limited),
+                (DI8DEVTYPEGAMEPAD_STANDARD, GamepadSubtype::Standard),
+                (DI8DEVTYPEGAMEPAD_TILT, GamepadSubtype::Tilt),
+                (4, GamepadSubtype::Other(4)),
+            ],
+        );
+        check(
+            DrivingSubtype::from_bits,
+            DrivingSubtype::bits,
+            &[
+                (DI8DEVTYPEDRIVING_LIMITED, DrivingSubtype::Limited),
+                (
+                    DI8DEVTYPEDRIVING_COMBINEDPEDALS,
+                    DrivingSubtype::CombinedPedals,
+                ),
+                (DI8DEVTYPEDRIVING_DUALPEDALS, DrivingSubtype::DualPedals),
+                (DI8DEVTYPEDRIVING_THREEPEDALS, DrivingSubtype::ThreePedals),
+                (DI8DEVTYPEDRIVING_HANDHELD, DrivingSubtype::Handheld),
+                (6, DrivingSubtype::Other(6)),
+            ],
+        );
+        check(
+            FlightSubtype::from_bits,
+            FlightSubtype::bits,
+            &[
+                (DI8DEVTYPEFLIGHT_LIMITED, FlightSubtype::Limited),
+                (DI8DEVTYPEFLIGHT_STICK, FlightSubtype::Stick),
+                (DI8DEVTYPEFLIGHT_YOKE, FlightSubtype::Yoke),
+                (DI8DEVTYPEFLIGHT_RC, FlightSubtype::Rc),
+                (5, FlightSubtype::Other(5)),
+            ],
+        );
+        check(
+            FirstPersonSubtype::from_bits,
+            FirstPersonSubtype::bits,
+            &[
+                (DI8DEVTYPE1STPERSON_LIMITED, FirstPersonSubtype::Limited),
+                (DI8DEVTYPE1STPERSON_UNKNOWN, FirstPersonSubtype::Unknown),
+                (DI8DEVTYPE1STPERSON_SIXDOF, FirstPersonSubtype::SixDof),
+                (DI8DEVTYPE1STPERSON_SHOOTER, FirstPersonSubtype::Shooter),
+                (5, FirstPersonSubtype::Other(5)),
+            ],
+        );
+        check(
+            DeviceControlSubtype::from_bits,
+            DeviceControlSubtype::bits,
+            &[
+                (DI8DEVTYPEDEVICECTRL_UNKNOWN, DeviceControlSubtype::Unknown),
+                (
+                    DI8DEVTYPEDEVICECTRL_COMMSSELECTION,
+                    DeviceControlSubtype::CommsSelection,
+                ),
+                (
+                    DI8DEVTYPEDEVICECTRL_COMMSSELECTION_HARDWIRED,
+                    DeviceControlSubtype::CommsSelectionHardwired,
+                ),
+                (1, DeviceControlSubtype::Other(1)),
+            ],
+        );
+        check(
+            ScreenPointerSubtype::from_bits,
+            ScreenPointerSubtype::bits,
+            &[
+                (DI8DEVTYPESCREENPTR_UNKNOWN, ScreenPointerSubtype::Unknown),
+                (DI8DEVTYPESCREENPTR_LIGHTGUN, ScreenPointerSubtype::LightGun),
+                (DI8DEVTYPESCREENPTR_LIGHTPEN, ScreenPointerSubtype::LightPen),
+                (DI8DEVTYPESCREENPTR_TOUCH, ScreenPointerSubtype::Touch),
+                (6, ScreenPointerSubtype::Other(6)),
+            ],
+        );
+        check(
+            RemoteSubtype::from_bits,
+            RemoteSubtype::bits,
+            &[
+                (DI8DEVTYPEREMOTE_UNKNOWN, RemoteSubtype::Unknown),
+                (1, RemoteSubtype::Other(1)),
+            ],
+        );
+        check(
+            SupplementalSubtype::from_bits,
+            SupplementalSubtype::bits,
+            &[
+                (DI8DEVTYPESUPPLEMENTAL_UNKNOWN, SupplementalSubtype::Unknown),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_2NDHANDCONTROLLER,
+                    SupplementalSubtype::SecondHandController,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_HEADTRACKER,
+                    SupplementalSubtype::HeadTracker,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_HANDTRACKER,
+                    SupplementalSubtype::HandTracker,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_SHIFTSTICKGATE,
+                    SupplementalSubtype::ShiftStickGate,
+                ),
+                (DI8DEVTYPESUPPLEMENTAL_SHIFTER, SupplementalSubtype::Shifter),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_THROTTLE,
+                    SupplementalSubtype::Throttle,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_SPLITTHROTTLE,
+                    SupplementalSubtype::SplitThrottle,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_COMBINEDPEDALS,
+                    SupplementalSubtype::CombinedPedals,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_DUALPEDALS,
+                    SupplementalSubtype::DualPedals,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_THREEPEDALS,
+                    SupplementalSubtype::ThreePedals,
+                ),
+                (
+                    DI8DEVTYPESUPPLEMENTAL_RUDDERPEDALS,
+                    SupplementalSubtype::RudderPedals,
+                ),
+                (14, SupplementalSubtype::Other(14)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_class_and_hid() {
+        assert_eq!(
+            DeviceType::Mouse(MouseSubtype::Traditional).class(),
+            DeviceClass::Pointer
+        );
+        assert_eq!(
+            DeviceType::ScreenPointer(ScreenPointerSubtype::Touch).class(),
+            DeviceClass::Pointer
+        );
+        assert_eq!(
+            DeviceType::Keyboard(KeyboardSubtype::Unknown).class(),
+            DeviceClass::Keyboard
+        );
+        assert_eq!(
+            DeviceType::Supplemental(SupplementalSubtype::RudderPedals).class(),
+            DeviceClass::GameController
+        );
+        assert_eq!(DeviceType::Other(0x3f, 0).class(), DeviceClass::Device);
+
+        assert!(is_hid(DI8DEVTYPE_GAMEPAD | DIDEVTYPE_HID));
+        assert!(!is_hid(DI8DEVTYPE_GAMEPAD));
+    }
+}
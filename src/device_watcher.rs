@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::device_class::DeviceClass;
+use crate::device_guid::DeviceGuid;
+use crate::device_info::DirectInputDeviceInfo;
+use crate::enum_options::EnumOptions;
+use crate::error::Result;
+use crate::manager::DirectInputManager;
+
+// From `winuser.h` and `dbt.h`, which are not part of the enabled `windows` features
+const WM_DEVICECHANGE: u32 = 0x0219;
+const DBT_DEVNODES_CHANGED: usize = 0x0007;
+const DBT_DEVICEARRIVAL: usize = 0x8000;
+const DBT_DEVICEREMOVECOMPLETE: usize = 0x8004;
+
+/// A change in the set of attached devices reported by a [`DeviceWatcher`].
+#[derive(Clone, Debug)]
+pub enum DeviceChange {
+    /// A device was seen for the first time.
+    Added(DirectInputDeviceInfo),
+    /// A previously attached device is no longer attached.
+    Removed(DirectInputDeviceInfo),
+    /// A device that was removed earlier is attached again.
+    Reconnected(DirectInputDeviceInfo),
+}
+
+/// Tracks the attached devices of a class by instance GUID and reports devices being plugged in
+/// and out.
+///
+/// The watcher does not poll by itself: call [`refresh`](Self::refresh) periodically, or
+/// [`handle_message`](Self::handle_message) from the window procedure to refresh on
+/// `WM_DEVICECHANGE`. Changes are returned from these methods and also sent to every
+/// [`subscribe`](Self::subscribe)d channel and [`on_change`](Self::on_change) callback.
+pub struct DeviceWatcher {
+    class: DeviceClass,
+    devices: HashMap<DeviceGuid, TrackedDevice>,
+    senders: Vec<Sender<DeviceChange>>,
+    callbacks: Vec<ChangeCallback>,
+}
+
+type ChangeCallback = Box<dyn FnMut(&DeviceChange) + Send>;
+
+struct TrackedDevice {
+    info: DirectInputDeviceInfo,
+    attached: bool,
+    /// Order in which the device was first seen, to report devices in a stable order.
+    order: usize,
+}
+
+impl DeviceChange {
+    pub fn device(&self) -> &DirectInputDeviceInfo {
+        match self {
+            Self::Added(info) | Self::Removed(info) | Self::Reconnected(info) => info,
+        }
+    }
+}
+
+impl DeviceWatcher {
+    /// Creates a watcher for devices of `class`. The first refresh reports every attached device
+    /// as added.
+    pub fn new(class: DeviceClass) -> Self {
+        Self {
+            class,
+            devices: HashMap::new(),
+            senders: Vec::new(),
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// Returns a channel receiving every subsequent change.
+    pub fn subscribe(&mut self) -> Receiver<DeviceChange> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+
+        receiver
+    }
+
+    /// Registers a callback invoked for every subsequent change.
+    pub fn on_change(&mut self, callback: impl FnMut(&DeviceChange) + Send + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// The devices that were attached at the last refresh, in the order they were first seen.
+    pub fn devices(&self) -> Vec<&DirectInputDeviceInfo> {
+        let mut devices: Vec<_> = self
+            .devices
+            .values()
+            .filter(|device| device.attached)
+            .collect();
+        devices.sort_by_key(|device| device.order);
+
+        devices.into_iter().map(|device| &device.info).collect()
+    }
+
+    /// Enumerates the attached devices and reports the differences to the previous refresh.
+    pub fn refresh(&mut self, manager: &DirectInputManager) -> Result<Vec<DeviceChange>> {
+        let devices =
+            manager.enum_devices_with(&EnumOptions::new().class(self.class).attached_only())?;
+
+        Ok(self.update(devices))
+    }
+
+    /// Refreshes if `message` is a `WM_DEVICECHANGE` notifying that devices were added or
+    /// removed, otherwise returns no changes.
+    pub fn handle_message(
+        &mut self,
+        manager: &DirectInputManager,
+        message: u32,
+        wparam: usize,
+    ) -> Result<Vec<DeviceChange>> {
+        match (message, wparam) {
+            (WM_DEVICECHANGE, DBT_DEVNODES_CHANGED)
+            | (WM_DEVICECHANGE, DBT_DEVICEARRIVAL)
+            | (WM_DEVICECHANGE, DBT_DEVICEREMOVECOMPLETE) => self.refresh(manager),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Diffs `devices`, the complete list of currently attached devices, against the previous
+    /// list and reports the changes. Removals are reported before additions.
+    pub fn update(
+        &mut self,
+        devices: impl IntoIterator<Item = DirectInputDeviceInfo>,
+    ) -> Vec<DeviceChange> {
+        let mut added = Vec::new();
+        let mut seen = Vec::new();
+
+        for info in devices {
+            let guid = info.guid_instance();
+            let order = self.devices.len();
+
+            seen.push(guid);
+
+            match self.devices.get_mut(&guid) {
+                Some(device) if device.attached => device.info = info,
+                Some(device) => {
+                    device.info = info.clone();
+                    device.attached = true;
+                    added.push((device.order, DeviceChange::Reconnected(info)));
+                }
+                None => {
+                    self.devices.insert(
+                        guid,
+                        TrackedDevice {
+                            info: info.clone(),
+                            attached: true,
+                            order,
+                        },
+                    );
+                    added.push((order, DeviceChange::Added(info)));
+                }
+            }
+        }
+
+        let mut changes: Vec<_> = self
+            .devices
+            .iter_mut()
+            .filter(|(guid, device)| device.attached && !seen.contains(guid))
+            .map(|(_, device)| {
+                device.attached = false;
+                (device.order, DeviceChange::Removed(device.info.clone()))
+            })
+            .collect();
+        changes.sort_by_key(|(order, _)| *order);
+        added.sort_by_key(|(order, _)| *order);
+        changes.extend(added);
+
+        let changes: Vec<_> = changes.into_iter().map(|(_, change)| change).collect();
+        self.notify(&changes);
+
+        changes
+    }
+
+    fn notify(&mut self, changes: &[DeviceChange]) {
+        for change in changes {
+            // Drop the channels whose receiver is gone
+            self.senders
+                .retain(|sender| sender.send(change.clone()).is_ok());
+
+            for callback in &mut self.callbacks {
+                callback(change);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+    use std::sync::{Arc, Mutex};
+
+    use windows::core::GUID;
+    use windows::Win32::Devices::HumanInterfaceDevice::DIDEVICEINSTANCEW;
+
+    use super::*;
+    use crate::backend::sim::{SimulatedBackend, SimulatedDevice};
+
+    fn info(id: u32) -> DirectInputDeviceInfo {
+        DirectInputDeviceInfo::from_instance(&DIDEVICEINSTANCEW {
+            dwSize: mem::size_of::<DIDEVICEINSTANCEW>() as _,
+            guidInstance: GUID::from_values(id, 0, 0, [0; 8]),
+            ..Default::default()
+        })
+    }
+
+    fn summary(changes: &[DeviceChange]) -> Vec<(&'static str, u32)> {
+        changes
+            .iter()
+            .map(|change| {
+                let kind = match change {
+                    DeviceChange::Added(_) => "added",
+                    DeviceChange::Removed(_) => "removed",
+                    DeviceChange::Reconnected(_) => "reconnected",
+                };
+
+                (kind, change.device().guid_instance().as_guid().data1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_update_diffs_enumerations() {
+        let mut watcher = DeviceWatcher::new(DeviceClass::GameController);
+
+        let changes = watcher.update(vec![info(1), info(2)]);
+        assert_eq!(summary(&changes), [("added", 1), ("added", 2)]);
+
+        assert!(watcher.update(vec![info(2), info(1)]).is_empty());
+
+        let changes = watcher.update(vec![info(2), info(3)]);
+        assert_eq!(summary(&changes), [("removed", 1), ("added", 3)]);
+
+        let changes = watcher.update(vec![info(1)]);
+        assert_eq!(
+            summary(&changes),
+            [("removed", 2), ("removed", 3), ("reconnected", 1)]
+        );
+        assert_eq!(
+            watcher
+                .devices()
+                .iter()
+                .map(|info| info.guid_instance().as_guid().data1)
+                .collect::<Vec<_>>(),
+            [1]
+        );
+
+        let changes = watcher.update(vec![info(3), info(2), info(1)]);
+        assert_eq!(summary(&changes), [("reconnected", 2), ("reconnected", 3)]);
+    }
+
+    #[test]
+    fn test_refresh_notifies_listeners() {
+        let backend = SimulatedBackend::new();
+        let pad = SimulatedDevice::new("Simulated Pad");
+        backend.add_device(pad.clone());
+        let manager = DirectInputManager::with_backend(backend.clone());
+
+        let mut watcher = DeviceWatcher::new(DeviceClass::GameController);
+        let receiver = watcher.subscribe();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        {
+            let seen = seen.clone();
+            watcher.on_change(move |change| {
+                seen.lock()
+                    .unwrap()
+                    .push(summary(std::slice::from_ref(change)))
+            });
+        }
+
+        let changes = watcher.refresh(&manager).unwrap();
+        assert_eq!(summary(&changes), [("added", pad.guid_instance().data1)]);
+
+        // Messages other than device arrival and removal are ignored
+        pad.unplug();
+        assert!(watcher
+            .handle_message(&manager, 0x0010, 0)
+            .unwrap()
+            .is_empty());
+        let changes = watcher
+            .handle_message(&manager, WM_DEVICECHANGE, DBT_DEVICEREMOVECOMPLETE)
+            .unwrap();
+        assert_eq!(summary(&changes), [("removed", pad.guid_instance().data1)]);
+
+        pad.plug();
+        let changes = watcher.refresh(&manager).unwrap();
+        assert_eq!(
+            summary(&changes),
+            [("reconnected", pad.guid_instance().data1)]
+        );
+
+        let id = pad.guid_instance().data1;
+        let received: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            summary(&received),
+            [("added", id), ("removed", id), ("reconnected", id)]
+        );
+        assert_eq!(seen.lock().unwrap().concat(), summary(&received));
+    }
+}
@@ -0,0 +1,754 @@
+use std::convert::TryInto;
+use std::ffi::{c_void, OsString};
+use std::mem;
+use std::ptr;
+use std::time::Duration;
+
+use bitflags::bitflags;
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    GUID_ConstantForce, GUID_CustomForce, GUID_Damper, GUID_Friction, GUID_Inertia, GUID_RampForce,
+    GUID_SawtoothDown, GUID_SawtoothUp, GUID_Sine, GUID_Spring, GUID_Square, GUID_Triangle,
+    DICONDITION, DICONSTANTFORCE, DICUSTOMFORCE, DIEB_NOTRIGGER, DIEFFECT, DIEFFECTINFOW,
+    DIEFF_CARTESIAN, DIEFF_OBJECTOFFSETS, DIEFF_POLAR, DIEFF_SPHERICAL, DIEGES_EMULATED,
+    DIEGES_PLAYING, DIENVELOPE, DIEP_ALLPARAMS, DIEP_AXES, DIEP_DIRECTION, DIEP_DURATION,
+    DIEP_ENVELOPE, DIEP_GAIN, DIEP_NODOWNLOAD, DIEP_NORESTART, DIEP_SAMPLEPERIOD, DIEP_START,
+    DIEP_STARTDELAY, DIEP_TRIGGERBUTTON, DIEP_TRIGGERREPEATINTERVAL, DIEP_TYPESPECIFICPARAMS,
+    DIES_NODOWNLOAD, DIES_SOLO, DIPERIODIC, DIRAMPFORCE, DI_FFNOMINALMAX,
+};
+use windows::Win32::System::WindowsProgramming::INFINITE;
+
+use crate::backend::EffectBackend;
+use crate::error::{DirectInputError, DirectInputStatus, Result};
+use crate::util::os_string_from_wide;
+
+/// The predefined effect types, identified by their `GUID_*` effect GUIDs.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EffectKind {
+    ConstantForce,
+    RampForce,
+    Square,
+    Sine,
+    Triangle,
+    SawtoothUp,
+    SawtoothDown,
+    Spring,
+    Damper,
+    Inertia,
+    Friction,
+    CustomForce,
+}
+
+/// Waveform of a [`Periodic`] effect.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    SawtoothUp,
+    SawtoothDown,
+}
+
+/// Kind of a [`Condition`] effect, i.e. which quantity the force depends on.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ConditionKind {
+    /// Proportional to the distance of the axis from the offset.
+    Spring,
+    /// Proportional to the velocity of the axis.
+    Damper,
+    /// Proportional to the acceleration of the axis.
+    Inertia,
+    /// Constant while the axis is moving.
+    Friction,
+}
+
+/// `DICONSTANTFORCE`: a force of constant magnitude, from -10,000 to 10,000.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ConstantForce {
+    pub magnitude: i32,
+}
+
+/// `DIRAMPFORCE`: a force changing linearly from `start` to `end` over the effect duration.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RampForce {
+    pub start: i32,
+    pub end: i32,
+}
+
+/// `DIPERIODIC`: a force following a waveform.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Periodic {
+    pub waveform: Waveform,
+    /// Absolute magnitude, from 0 to 10,000.
+    pub magnitude: u32,
+    /// Offset of the waveform from zero.
+    pub offset: i32,
+    /// Phase at which playback begins, in hundredths of a degree.
+    pub phase: u32,
+    pub period: Duration,
+}
+
+/// `DICONDITION`: parameters of a condition effect for one axis.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Condition {
+    pub offset: i32,
+    pub positive_coefficient: i32,
+    pub negative_coefficient: i32,
+    pub positive_saturation: u32,
+    pub negative_saturation: u32,
+    pub dead_band: i32,
+}
+
+/// `DICUSTOMFORCE`: a force defined by samples played back at a fixed rate.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CustomForce {
+    /// Number of channels interleaved in `samples`, one per axis or a single one for all axes.
+    pub channels: u32,
+    pub sample_period: Duration,
+    pub samples: Vec<i32>,
+}
+
+/// Type-specific parameters of an effect, which also determine its [`EffectKind`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EffectType {
+    ConstantForce(ConstantForce),
+    RampForce(RampForce),
+    Periodic(Periodic),
+    /// Either one condition per axis, or a single condition applied to all axes.
+    Condition(ConditionKind, Vec<Condition>),
+    CustomForce(CustomForce),
+}
+
+/// `DIENVELOPE`: attack and fade applied to the start and end of an effect.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Envelope {
+    /// Magnitude at the start of the effect, from 0 to 10,000.
+    pub attack_level: u32,
+    pub attack_time: Duration,
+    /// Magnitude at the end of the effect, from 0 to 10,000.
+    pub fade_level: u32,
+    pub fade_time: Duration,
+}
+
+/// Direction of an effect, in the coordinate system selected by the variant.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EffectDirection {
+    /// One component per axis.
+    Cartesian(Vec<i32>),
+    /// Angle clockwise from north in hundredths of a degree, for effects on exactly two axes.
+    Polar(i32),
+    /// Angles in hundredths of a degree, one fewer than the number of axes.
+    Spherical(Vec<i32>),
+}
+
+/// Parameters of a force feedback effect, passed to [`Device::create_effect`] and
+/// [`EffectHandle::set_parameters`].
+///
+/// [`Device::create_effect`]: crate::Device::create_effect
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Effect {
+    pub ty: EffectType,
+    /// Offsets of the axes in the device's data format the effect is applied to.
+    pub axes: Vec<u32>,
+    pub direction: EffectDirection,
+    /// How long the effect plays, or `None` to play until stopped.
+    pub duration: Option<Duration>,
+    /// Playback sample period, or `None` to use the device default.
+    pub sample_period: Option<Duration>,
+    /// Gain applied to the effect, from 0 to 10,000.
+    pub gain: u32,
+    /// Offset of the button that triggers the effect, or `None` if only started explicitly.
+    pub trigger_button: Option<u32>,
+    /// Delay before a held trigger button replays the effect, or `None` to never repeat.
+    pub trigger_repeat_interval: Option<Duration>,
+    pub envelope: Option<Envelope>,
+    pub start_delay: Duration,
+}
+
+/// An effect supported by a device, as listed by [`Device::effects`].
+///
+/// [`Device::effects`]: crate::Device::effects
+#[derive(Clone, Debug)]
+pub struct EffectInfo {
+    pub guid: GUID,
+    /// `DIEFT_*` type and capability flags.
+    pub effect_type: u32,
+    /// Parameters the device supports for the effect.
+    pub static_params: EffectParams,
+    /// Parameters that can be changed while the effect is playing.
+    pub dynamic_params: EffectParams,
+    pub name: OsString,
+}
+
+bitflags! {
+    /// `DIEP_*` flags selecting which parameters [`EffectHandle::set_parameters`] updates, and how.
+    pub struct EffectParams: u32 {
+        const DURATION = DIEP_DURATION;
+        const SAMPLE_PERIOD = DIEP_SAMPLEPERIOD;
+        const GAIN = DIEP_GAIN;
+        const TRIGGER_BUTTON = DIEP_TRIGGERBUTTON;
+        const TRIGGER_REPEAT_INTERVAL = DIEP_TRIGGERREPEATINTERVAL;
+        const AXES = DIEP_AXES;
+        const DIRECTION = DIEP_DIRECTION;
+        const ENVELOPE = DIEP_ENVELOPE;
+        const TYPE_SPECIFIC_PARAMS = DIEP_TYPESPECIFICPARAMS;
+        const START_DELAY = DIEP_STARTDELAY;
+
+        /// Every parameter.
+        const ALL_PARAMS = DIEP_ALLPARAMS;
+
+        /// Restart the effect from the beginning after updating it.
+        const START = DIEP_START;
+
+        /// Fail with `DirectInputError::EffectPlaying` instead of restarting a playing effect if
+        /// the device cannot update it while it plays.
+        const NO_RESTART = DIEP_NORESTART;
+
+        /// Only update the parameters without downloading them to the device.
+        const NO_DOWNLOAD = DIEP_NODOWNLOAD;
+    }
+}
+
+bitflags! {
+    /// `DIES_*` flags passed to [`EffectHandle::start`].
+    pub struct EffectStartFlags: u32 {
+        /// Stop all other effects before starting this one.
+        const SOLO = DIES_SOLO;
+
+        /// Do not download the effect automatically if it is not downloaded yet.
+        const NO_DOWNLOAD = DIES_NODOWNLOAD;
+    }
+}
+
+bitflags! {
+    /// `DIEGES_*` flags returned by [`EffectHandle::status`].
+    pub struct EffectStatus: u32 {
+        const PLAYING = DIEGES_PLAYING;
+
+        /// The effect is emulated by the driver rather than played by the device.
+        const EMULATED = DIEGES_EMULATED;
+    }
+}
+
+/// A force feedback effect created on a device with [`Device::create_effect`].
+///
+/// The effect is unloaded from the device when the handle is dropped.
+///
+/// [`Device::create_effect`]: crate::Device::create_effect
+pub struct EffectHandle {
+    backend: Box<dyn EffectBackend>,
+    kind: EffectKind,
+}
+
+/// A `DIEFFECT` along with the buffers it points to.
+pub(crate) struct RawEffect<'a> {
+    effect: DIEFFECT,
+    _direction: Vec<i32>,
+    _envelope: Option<Box<DIENVELOPE>>,
+    _params: RawTypeSpecific,
+    _parent: &'a Effect,
+}
+
+enum RawTypeSpecific {
+    ConstantForce(Box<DICONSTANTFORCE>),
+    RampForce(Box<DIRAMPFORCE>),
+    Periodic(Box<DIPERIODIC>),
+    Condition(Vec<DICONDITION>),
+    CustomForce(Box<DICUSTOMFORCE>),
+}
+
+impl EffectKind {
+    pub fn guid(self) -> GUID {
+        match self {
+            Self::ConstantForce => GUID_ConstantForce,
+            Self::RampForce => GUID_RampForce,
+            Self::Square => GUID_Square,
+            Self::Sine => GUID_Sine,
+            Self::Triangle => GUID_Triangle,
+            Self::SawtoothUp => GUID_SawtoothUp,
+            Self::SawtoothDown => GUID_SawtoothDown,
+            Self::Spring => GUID_Spring,
+            Self::Damper => GUID_Damper,
+            Self::Inertia => GUID_Inertia,
+            Self::Friction => GUID_Friction,
+            Self::CustomForce => GUID_CustomForce,
+        }
+    }
+
+    /// Looks up the predefined effect with the given GUID, or `None` for device-specific
+    /// effects.
+    pub fn from_guid(guid: &GUID) -> Option<Self> {
+        const KINDS: [EffectKind; 12] = [
+            EffectKind::ConstantForce,
+            EffectKind::RampForce,
+            EffectKind::Square,
+            EffectKind::Sine,
+            EffectKind::Triangle,
+            EffectKind::SawtoothUp,
+            EffectKind::SawtoothDown,
+            EffectKind::Spring,
+            EffectKind::Damper,
+            EffectKind::Inertia,
+            EffectKind::Friction,
+            EffectKind::CustomForce,
+        ];
+
+        KINDS.iter().copied().find(|kind| kind.guid() == *guid)
+    }
+}
+
+impl EffectType {
+    pub fn kind(&self) -> EffectKind {
+        match self {
+            Self::ConstantForce(_) => EffectKind::ConstantForce,
+            Self::RampForce(_) => EffectKind::RampForce,
+            Self::Periodic(periodic) => match periodic.waveform {
+                Waveform::Square => EffectKind::Square,
+                Waveform::Sine => EffectKind::Sine,
+                Waveform::Triangle => EffectKind::Triangle,
+                Waveform::SawtoothUp => EffectKind::SawtoothUp,
+                Waveform::SawtoothDown => EffectKind::SawtoothDown,
+            },
+            Self::Condition(kind, _) => match kind {
+                ConditionKind::Spring => EffectKind::Spring,
+                ConditionKind::Damper => EffectKind::Damper,
+                ConditionKind::Inertia => EffectKind::Inertia,
+                ConditionKind::Friction => EffectKind::Friction,
+            },
+            Self::CustomForce(_) => EffectKind::CustomForce,
+        }
+    }
+
+    fn to_raw(&self) -> RawTypeSpecific {
+        match self {
+            Self::ConstantForce(force) => {
+                RawTypeSpecific::ConstantForce(Box::new(DICONSTANTFORCE {
+                    lMagnitude: force.magnitude,
+                }))
+            }
+            Self::RampForce(force) => RawTypeSpecific::RampForce(Box::new(DIRAMPFORCE {
+                lStart: force.start,
+                lEnd: force.end,
+            })),
+            Self::Periodic(periodic) => RawTypeSpecific::Periodic(Box::new(DIPERIODIC {
+                dwMagnitude: periodic.magnitude,
+                lOffset: periodic.offset,
+                dwPhase: periodic.phase,
+                dwPeriod: micros(periodic.period),
+            })),
+            Self::Condition(_, conditions) => RawTypeSpecific::Condition(
+                conditions
+                    .iter()
+                    .map(|condition| DICONDITION {
+                        lOffset: condition.offset,
+                        lPositiveCoefficient: condition.positive_coefficient,
+                        lNegativeCoefficient: condition.negative_coefficient,
+                        dwPositiveSaturation: condition.positive_saturation,
+                        dwNegativeSaturation: condition.negative_saturation,
+                        lDeadBand: condition.dead_band,
+                    })
+                    .collect(),
+            ),
+            Self::CustomForce(force) => RawTypeSpecific::CustomForce(Box::new(DICUSTOMFORCE {
+                cChannels: force.channels,
+                dwSamplePeriod: micros(force.sample_period),
+                cSamples: force.samples.len() as _,
+                rglForceData: force.samples.as_ptr() as *mut i32,
+            })),
+        }
+    }
+}
+
+impl RawTypeSpecific {
+    /// Pointer and size in bytes of the type-specific parameters.
+    fn as_raw(&self) -> (*mut c_void, u32) {
+        fn raw<T>(value: &T) -> (*mut c_void, u32) {
+            (value as *const T as *mut c_void, mem::size_of::<T>() as _)
+        }
+
+        match self {
+            Self::ConstantForce(force) => raw(&**force),
+            Self::RampForce(force) => raw(&**force),
+            Self::Periodic(periodic) => raw(&**periodic),
+            Self::Condition(conditions) => (
+                conditions.as_ptr() as *mut c_void,
+                (conditions.len() * mem::size_of::<DICONDITION>()) as _,
+            ),
+            Self::CustomForce(force) => raw(&**force),
+        }
+    }
+}
+
+impl EffectDirection {
+    /// `DIEFF_*` coordinate flag and the direction as one value per axis.
+    pub(crate) fn to_raw(&self) -> (u32, Vec<i32>) {
+        match self {
+            Self::Cartesian(values) => (DIEFF_CARTESIAN, values.clone()),
+            Self::Polar(angle) => (DIEFF_POLAR, vec![*angle, 0]),
+            Self::Spherical(angles) => {
+                let mut values = angles.clone();
+                values.push(0);
+
+                (DIEFF_SPHERICAL, values)
+            }
+        }
+    }
+}
+
+impl Effect {
+    /// Creates an effect with the given parameters that plays until stopped, at full gain and
+    /// without envelope. Its axes and direction still need to be set with
+    /// [`with_axes`](Self::with_axes).
+    pub fn new(ty: EffectType) -> Self {
+        Self {
+            ty,
+            axes: Vec::new(),
+            direction: EffectDirection::Cartesian(Vec::new()),
+            duration: None,
+            sample_period: None,
+            gain: DI_FFNOMINALMAX,
+            trigger_button: None,
+            trigger_repeat_interval: None,
+            envelope: None,
+            start_delay: Duration::ZERO,
+        }
+    }
+
+    pub fn with_axes(mut self, axes: &[u32], direction: EffectDirection) -> Self {
+        self.axes = axes.to_vec();
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn with_gain(mut self, gain: u32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    pub fn with_envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = Some(envelope);
+        self
+    }
+
+    pub fn with_trigger_button(mut self, offset: u32, repeat_interval: Option<Duration>) -> Self {
+        self.trigger_button = Some(offset);
+        self.trigger_repeat_interval = repeat_interval;
+        self
+    }
+
+    pub fn with_start_delay(mut self, delay: Duration) -> Self {
+        self.start_delay = delay;
+        self
+    }
+
+    pub fn kind(&self) -> EffectKind {
+        self.ty.kind()
+    }
+
+    /// Builds the `DIEFFECT` for this effect. Fails with `DirectInputError::InvalidParam` if the
+    /// direction does not match the number of axes.
+    pub(crate) fn to_raw(&self) -> Result<RawEffect<'_>> {
+        let (coordinates, mut direction) = self.direction.to_raw();
+
+        if !self.axes.is_empty() && direction.len() != self.axes.len() {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        let mut envelope = self.envelope.map(|envelope| {
+            Box::new(DIENVELOPE {
+                dwSize: mem::size_of::<DIENVELOPE>() as _,
+                dwAttackLevel: envelope.attack_level,
+                dwAttackTime: micros(envelope.attack_time),
+                dwFadeLevel: envelope.fade_level,
+                dwFadeTime: micros(envelope.fade_time),
+            })
+        });
+        let params = self.ty.to_raw();
+        let (params_ptr, params_size) = params.as_raw();
+
+        let effect = DIEFFECT {
+            dwSize: mem::size_of::<DIEFFECT>() as _,
+            dwFlags: DIEFF_OBJECTOFFSETS | coordinates,
+            dwDuration: self.duration.map_or(INFINITE, micros),
+            dwSamplePeriod: self.sample_period.map_or(0, micros),
+            dwGain: self.gain,
+            dwTriggerButton: self.trigger_button.unwrap_or(DIEB_NOTRIGGER),
+            dwTriggerRepeatInterval: self.trigger_repeat_interval.map_or(INFINITE, micros),
+            cAxes: self.axes.len() as _,
+            rgdwAxes: self.axes.as_ptr() as *mut u32,
+            rglDirection: direction.as_mut_ptr(),
+            lpEnvelope: envelope
+                .as_deref_mut()
+                .map_or(ptr::null_mut(), |envelope| envelope as *mut _),
+            cbTypeSpecificParams: params_size,
+            lpvTypeSpecificParams: params_ptr,
+            dwStartDelay: micros(self.start_delay),
+        };
+
+        Ok(RawEffect {
+            effect,
+            _direction: direction,
+            _envelope: envelope,
+            _params: params,
+            _parent: self,
+        })
+    }
+}
+
+impl<'a> RawEffect<'a> {
+    pub(crate) fn as_raw(&self) -> &DIEFFECT {
+        &self.effect
+    }
+}
+
+impl EffectInfo {
+    pub(crate) fn from_instance(info: &DIEFFECTINFOW) -> Self {
+        Self {
+            guid: info.guid,
+            effect_type: info.dwEffType,
+            static_params: EffectParams::from_bits_truncate(info.dwStaticParams),
+            dynamic_params: EffectParams::from_bits_truncate(info.dwDynamicParams),
+            name: os_string_from_wide(&info.tszName),
+        }
+    }
+
+    /// The predefined effect type, or `None` for device-specific effects.
+    pub fn kind(&self) -> Option<EffectKind> {
+        EffectKind::from_guid(&self.guid)
+    }
+}
+
+impl EffectHandle {
+    pub(crate) fn new(backend: Box<dyn EffectBackend>, kind: EffectKind) -> Self {
+        Self { backend, kind }
+    }
+
+    pub fn kind(&self) -> EffectKind {
+        self.kind
+    }
+
+    /// Starts playing the effect `iterations` times, or until stopped if `None`. The effect is
+    /// downloaded first if needed, unless `EffectStartFlags::NO_DOWNLOAD` is given.
+    pub fn start(&self, iterations: Option<u32>, flags: EffectStartFlags) -> Result<()> {
+        self.backend
+            .start(iterations.unwrap_or(INFINITE), flags.bits())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.backend.stop()
+    }
+
+    /// Updates the parameters of the effect selected by `params`. The effect type must be the
+    /// one the effect was created with.
+    ///
+    /// Returns `DirectInputStatus::DownloadSkipped` if the device is not acquired and the new
+    /// parameters could not be downloaded yet, or `DirectInputStatus::EffectRestarted` if the
+    /// effect had to be restarted to apply them.
+    pub fn set_parameters(
+        &mut self,
+        effect: &Effect,
+        params: EffectParams,
+    ) -> Result<DirectInputStatus> {
+        if effect.kind() != self.kind {
+            return Err(DirectInputError::InvalidParam);
+        }
+
+        let raw = effect.to_raw()?;
+
+        unsafe { self.backend.set_parameters(raw.as_raw(), params.bits()) }
+    }
+
+    pub fn status(&self) -> Result<EffectStatus> {
+        let status = self.backend.status()?;
+
+        Ok(EffectStatus::from_bits_truncate(status))
+    }
+
+    /// Downloads the effect to the device, which must be acquired exclusively.
+    pub fn download(&self) -> Result<DirectInputStatus> {
+        self.backend.download()
+    }
+
+    /// Stops the effect and frees it from the device. It is downloaded again when started.
+    pub fn unload(&self) -> Result<()> {
+        self.backend.unload()
+    }
+}
+
+impl Drop for EffectHandle {
+    fn drop(&mut self) {
+        let _ = self.backend.unload();
+    }
+}
+
+/// Converts a duration to the microseconds used by DirectInput, saturating below `INFINITE`.
+pub(crate) fn micros(duration: Duration) -> u32 {
+    duration
+        .as_micros()
+        .try_into()
+        .unwrap_or(INFINITE)
+        .min(INFINITE - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use raw_window_handle::{HasRawWindowHandle, RawWindowHandle, Win32Handle};
+
+    use super::*;
+    use crate::backend::sim::SimulatedDevice;
+    use crate::cooperative_level::CooperativeLevel;
+    use crate::device::Device;
+
+    fn simulated(sim: &SimulatedDevice) -> Device {
+        let mut device = Device::with_backend(sim.clone());
+        device.init().unwrap();
+        device
+    }
+
+    /// Window handle for `set_cooperative_level`, which the simulated backend ignores.
+    struct TestWindow;
+
+    unsafe impl HasRawWindowHandle for TestWindow {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            RawWindowHandle::Win32(Win32Handle::empty())
+        }
+    }
+
+    #[test]
+    fn test_simulated_effects() {
+        let sim = SimulatedDevice::new("Simulated Wheel")
+            .with_axes(2)
+            .with_buttons(2)
+            .with_effects(&[
+                EffectKind::ConstantForce,
+                EffectKind::Sine,
+                EffectKind::Spring,
+            ]);
+        let mut device = simulated(&sim);
+
+        let effects = device.effects().expect("Failed to enumerate effects");
+        assert_eq!(
+            effects.iter().map(|info| info.kind()).collect::<Vec<_>>(),
+            [
+                Some(EffectKind::ConstantForce),
+                Some(EffectKind::Sine),
+                Some(EffectKind::Spring)
+            ]
+        );
+        assert_eq!(effects[1].name, "Sine");
+        assert!(effects[0].dynamic_params.contains(EffectParams::GAIN));
+
+        let constant = Effect::new(EffectType::ConstantForce(ConstantForce {
+            magnitude: 5_000,
+        }))
+        .with_axes(&[0, 4], EffectDirection::Polar(9_000))
+        .with_duration(Duration::from_secs(1));
+
+        // Effects are only downloaded once the device is acquired exclusively
+        let mut handle = device.create_effect(&constant).unwrap();
+        assert_eq!(handle.kind(), EffectKind::ConstantForce);
+        assert_eq!(handle.status(), Err(DirectInputError::NotDownloaded));
+        assert_eq!(
+            handle.start(None, EffectStartFlags::empty()),
+            Err(DirectInputError::NotExclusiveAcquired)
+        );
+
+        device
+            .set_cooperative_level(
+                &TestWindow,
+                CooperativeLevel::EXCLUSIVE | CooperativeLevel::BACKGROUND,
+            )
+            .unwrap();
+        device.acquire().unwrap();
+        handle.start(Some(2), EffectStartFlags::empty()).unwrap();
+        assert_eq!(handle.status(), Ok(EffectStatus::PLAYING));
+
+        let effect = &sim.effects()[0];
+        assert_eq!(effect.axes, [0, 4]);
+        assert_eq!(effect.duration, 1_000_000);
+        assert_eq!(effect.gain, 10_000);
+        assert_eq!(effect.iterations, 2);
+
+        assert_eq!(
+            handle.set_parameters(&constant.clone().with_gain(5_000), EffectParams::GAIN),
+            Ok(DirectInputStatus::Ok)
+        );
+        assert_eq!(sim.effects()[0].gain, 5_000);
+        assert_eq!(
+            handle.set_parameters(&constant, EffectParams::AXES | EffectParams::NO_RESTART),
+            Err(DirectInputError::EffectPlaying)
+        );
+        assert_eq!(
+            handle.set_parameters(&constant, EffectParams::AXES),
+            Ok(DirectInputStatus::EffectRestarted)
+        );
+
+        // The effect type cannot change after creation
+        let sine = Effect::new(EffectType::Periodic(Periodic {
+            waveform: Waveform::Sine,
+            magnitude: 10_000,
+            offset: 0,
+            phase: 0,
+            period: Duration::from_millis(100),
+        }))
+        .with_axes(&[0], EffectDirection::Cartesian(vec![1]));
+        assert_eq!(
+            handle.set_parameters(&sine, EffectParams::ALL_PARAMS),
+            Err(DirectInputError::InvalidParam)
+        );
+
+        let sine = device.create_effect(&sine).unwrap();
+        sine.start(None, EffectStartFlags::SOLO).unwrap();
+        assert_eq!(handle.status(), Ok(EffectStatus::empty()));
+        assert_eq!(sine.status(), Ok(EffectStatus::PLAYING));
+        sine.stop().unwrap();
+        assert_eq!(sine.status(), Ok(EffectStatus::empty()));
+
+        // Effects without axes can be created but not downloaded
+        let spring = device
+            .create_effect(&Effect::new(EffectType::Condition(
+                ConditionKind::Spring,
+                vec![Condition {
+                    offset: 0,
+                    positive_coefficient: 10_000,
+                    negative_coefficient: 10_000,
+                    positive_saturation: 10_000,
+                    negative_saturation: 10_000,
+                    dead_band: 0,
+                }],
+            )))
+            .unwrap();
+        assert_eq!(spring.download(), Err(DirectInputError::IncompleteEffect));
+
+        assert_eq!(
+            device
+                .create_effect(&Effect::new(EffectType::RampForce(RampForce {
+                    start: 0,
+                    end: 10_000
+                })))
+                .err(),
+            Some(DirectInputError::DeviceNotReg)
+        );
+        assert_eq!(
+            device
+                .create_effect(
+                    &constant
+                        .clone()
+                        .with_axes(&[0, 4], EffectDirection::Cartesian(vec![1]))
+                )
+                .err(),
+            Some(DirectInputError::InvalidParam)
+        );
+
+        assert_eq!(sim.effects().len(), 3);
+        drop(handle);
+        drop(spring);
+        assert_eq!(sim.effects().len(), 1);
+        assert_eq!(sim.effects()[0].guid, EffectKind::Sine.guid());
+    }
+}
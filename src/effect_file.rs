@@ -0,0 +1,701 @@
+//! Reader and writer for the RIFF based `.ffe` files used by `EnumEffectsInFile` and
+//! `WriteEffectToFile`.
+//!
+//! A file is a `RIFF` chunk of form type `FORC` holding one `efct` list per effect, made of the
+//! following chunks (all values little-endian):
+//!
+//! - `name`: friendly name of the effect, NUL-terminated
+//! - `id  `: GUID of the effect type
+//! - `parm`: the `DIEFFECT` structure as laid out on 32-bit Windows, with zeroed pointers
+//! - `axes`: one `DWORD` per axis
+//! - `dirn`: one `LONG` per direction value
+//! - `envp`: the `DIENVELOPE` structure, only present if the effect has an envelope
+//! - `tspc`: the type-specific parameters. For custom forces the samples directly follow the
+//!   `DICUSTOMFORCE` header.
+//!
+//! Unknown chunks are skipped when reading.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DIEB_NOTRIGGER, DIEFF_CARTESIAN, DIEFF_OBJECTOFFSETS, DIEFF_POLAR, DIEFF_SPHERICAL,
+};
+use windows::Win32::System::WindowsProgramming::INFINITE;
+
+use crate::effect::{
+    micros, Condition, ConditionKind, ConstantForce, CustomForce, Effect, EffectDirection,
+    EffectKind, EffectType, Envelope, Periodic, RampForce, Waveform,
+};
+
+/// Size of `DIEFFECT` with 32-bit pointers.
+const PARM_SIZE: u32 = 56;
+/// Size of `DIENVELOPE`.
+const ENVELOPE_SIZE: u32 = 20;
+/// Size of `DICONDITION`.
+const CONDITION_SIZE: usize = 24;
+/// Size of `DICUSTOMFORCE` with a 32-bit pointer.
+const CUSTOM_FORCE_SIZE: u32 = 16;
+
+/// Contents of an `.ffe` file. The effects can be created on a device with
+/// [`Device::create_effect`](crate::Device::create_effect).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EffectFile {
+    pub effects: Vec<FileEffect>,
+}
+
+/// A named effect stored in an [`EffectFile`], the counterpart of `DIFILEEFFECT`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileEffect {
+    pub name: String,
+    pub effect: Effect,
+}
+
+/// Errors from reading or writing an [`EffectFile`].
+#[derive(Debug)]
+pub enum EffectFileError {
+    Io(io::Error),
+    /// The data does not start with a `RIFF` chunk of form type `FORC`.
+    NotEffectFile,
+    /// A chunk extends past the end of its parent.
+    Truncated,
+    /// An effect is missing a required chunk.
+    MissingChunk([u8; 4]),
+    /// A chunk has an invalid size or content.
+    InvalidChunk([u8; 4]),
+    /// The effect GUID is not one of the predefined effects.
+    UnknownEffect(GUID),
+    /// The `DIEFF_*` flags are not `DIEFF_OBJECTOFFSETS` with a single coordinate system.
+    UnsupportedFlags(u32),
+}
+
+struct Chunk<'a> {
+    id: [u8; 4],
+    data: &'a [u8],
+}
+
+/// Iterates over the chunks in the body of a `RIFF` or `LIST` chunk.
+struct Chunks<'a> {
+    data: &'a [u8],
+}
+
+impl EffectFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, EffectFileError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<Self, EffectFileError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Self::parse(&data)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, EffectFileError> {
+        if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"FORC" {
+            return Err(EffectFileError::NotEffectFile);
+        }
+
+        let riff = match Chunks::new(data).next() {
+            Some(chunk) => chunk?,
+            None => return Err(EffectFileError::NotEffectFile),
+        };
+
+        // The form type counts towards the size of the RIFF chunk
+        if riff.data.get(..4) != Some(&b"FORC"[..]) {
+            return Err(EffectFileError::NotEffectFile);
+        }
+
+        let mut effects = Vec::new();
+
+        for chunk in Chunks::new(&riff.data[4..]) {
+            let chunk = chunk?;
+
+            if &chunk.id == b"LIST" && chunk.data.starts_with(b"efct") {
+                effects.push(FileEffect::parse(&chunk.data[4..])?);
+            }
+        }
+
+        Ok(Self { effects })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), EffectFileError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<(), EffectFileError> {
+        writer.write_all(&self.to_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut body = b"FORC".to_vec();
+
+        for effect in &self.effects {
+            let mut list = b"efct".to_vec();
+            effect.write_chunks(&mut list);
+            write_chunk(&mut body, b"LIST", &list);
+        }
+
+        let mut data = Vec::new();
+        write_chunk(&mut data, b"RIFF", &body);
+
+        data
+    }
+}
+
+impl FileEffect {
+    fn parse(data: &[u8]) -> Result<Self, EffectFileError> {
+        let mut name = None;
+        let mut guid = None;
+        let mut parm = None;
+        let mut axes = None;
+        let mut direction = None;
+        let mut envelope = None;
+        let mut params = None;
+
+        for chunk in Chunks::new(data) {
+            let chunk = chunk?;
+
+            match &chunk.id {
+                b"name" => name = Some(chunk.data),
+                b"id  " => guid = Some(chunk),
+                b"parm" => parm = Some(chunk),
+                b"axes" => axes = Some(chunk),
+                b"dirn" => direction = Some(chunk),
+                b"envp" => envelope = Some(chunk),
+                b"tspc" => params = Some(chunk),
+                _ => {}
+            }
+        }
+
+        let name = name.ok_or(EffectFileError::MissingChunk(*b"name"))?;
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+        let guid = guid.ok_or(EffectFileError::MissingChunk(*b"id  "))?;
+        let guid = read_guid(&guid)?;
+        let kind = EffectKind::from_guid(&guid).ok_or(EffectFileError::UnknownEffect(guid))?;
+
+        let parm = parm.ok_or(EffectFileError::MissingChunk(*b"parm"))?;
+        let fields = read_u32s(&parm)?;
+        if fields.len() != PARM_SIZE as usize / 4 || fields[0] != PARM_SIZE {
+            return Err(EffectFileError::InvalidChunk(parm.id));
+        }
+        let flags = fields[1];
+
+        let axes = axes.ok_or(EffectFileError::MissingChunk(*b"axes"))?;
+        let axes = read_u32s(&axes)?;
+        if fields[7] as usize != axes.len() {
+            return Err(EffectFileError::InvalidChunk(parm.id));
+        }
+
+        let direction = direction.ok_or(EffectFileError::MissingChunk(*b"dirn"))?;
+        let direction = read_direction(flags, &direction)?;
+
+        let envelope = envelope.map(|chunk| read_envelope(&chunk)).transpose()?;
+
+        let params = params.ok_or(EffectFileError::MissingChunk(*b"tspc"))?;
+        let ty = read_type_specific(kind, fields[11], &params)?;
+
+        let effect = Effect {
+            ty,
+            axes,
+            direction,
+            duration: optional_duration(fields[2], INFINITE),
+            sample_period: optional_duration(fields[3], 0),
+            gain: fields[4],
+            trigger_button: Some(fields[5]).filter(|&button| button != DIEB_NOTRIGGER),
+            trigger_repeat_interval: optional_duration(fields[6], INFINITE),
+            envelope,
+            start_delay: duration(fields[13]),
+        };
+
+        Ok(Self {
+            name: String::from_utf8_lossy(name).into_owned(),
+            effect,
+        })
+    }
+
+    fn write_chunks(&self, out: &mut Vec<u8>) {
+        let effect = &self.effect;
+        let (coordinates, direction) = effect.direction.to_raw();
+        let params = type_specific_bytes(&effect.ty);
+        let params_size = match effect.ty {
+            EffectType::CustomForce(_) => CUSTOM_FORCE_SIZE,
+            _ => params.len() as u32,
+        };
+
+        let mut name = self.name.as_bytes().to_vec();
+        name.push(0);
+        write_chunk(out, b"name", &name);
+
+        write_chunk(out, b"id  ", &guid_bytes(&effect.kind().guid()));
+
+        let parm = [
+            PARM_SIZE,
+            DIEFF_OBJECTOFFSETS | coordinates,
+            effect.duration.map_or(INFINITE, micros),
+            effect.sample_period.map_or(0, micros),
+            effect.gain,
+            effect.trigger_button.unwrap_or(DIEB_NOTRIGGER),
+            effect.trigger_repeat_interval.map_or(INFINITE, micros),
+            effect.axes.len() as u32,
+            0,
+            0,
+            0,
+            params_size,
+            0,
+            micros(effect.start_delay),
+        ];
+        write_chunk(out, b"parm", &u32_bytes(&parm));
+        write_chunk(out, b"axes", &u32_bytes(&effect.axes));
+        write_chunk(out, b"dirn", &i32_bytes(&direction));
+
+        if let Some(envelope) = &effect.envelope {
+            let envp = [
+                ENVELOPE_SIZE,
+                envelope.attack_level,
+                micros(envelope.attack_time),
+                envelope.fade_level,
+                micros(envelope.fade_time),
+            ];
+            write_chunk(out, b"envp", &u32_bytes(&envp));
+        }
+
+        write_chunk(out, b"tspc", &params);
+    }
+}
+
+impl<'a> Chunks<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Result<Chunk<'a>, EffectFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        if self.data.len() < 8 {
+            self.data = &[];
+            return Some(Err(EffectFileError::Truncated));
+        }
+
+        let mut id = [0; 4];
+        id.copy_from_slice(&self.data[..4]);
+        let size = u32::from_le_bytes([self.data[4], self.data[5], self.data[6], self.data[7]]);
+        let size = size as usize;
+
+        if self.data.len() - 8 < size {
+            self.data = &[];
+            return Some(Err(EffectFileError::Truncated));
+        }
+
+        let data = &self.data[8..8 + size];
+        // Chunks are padded to an even size
+        let next = (8 + size + (size & 1)).min(self.data.len());
+        self.data = &self.data[next..];
+
+        Some(Ok(Chunk { id, data }))
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+
+    if data.len() & 1 != 0 {
+        out.push(0);
+    }
+}
+
+fn read_u32s(chunk: &Chunk<'_>) -> Result<Vec<u32>, EffectFileError> {
+    let values = chunk.data.chunks_exact(4);
+    if !values.remainder().is_empty() {
+        return Err(EffectFileError::InvalidChunk(chunk.id));
+    }
+
+    Ok(values
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn read_i32s(chunk: &Chunk<'_>) -> Result<Vec<i32>, EffectFileError> {
+    Ok(read_u32s(chunk)?.into_iter().map(|v| v as i32).collect())
+}
+
+fn u32_bytes(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn i32_bytes(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn read_guid(chunk: &Chunk<'_>) -> Result<GUID, EffectFileError> {
+    let b = chunk.data;
+    if b.len() != 16 {
+        return Err(EffectFileError::InvalidChunk(chunk.id));
+    }
+
+    let mut data4 = [0; 8];
+    data4.copy_from_slice(&b[8..]);
+
+    Ok(GUID::from_values(
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        u16::from_le_bytes([b[4], b[5]]),
+        u16::from_le_bytes([b[6], b[7]]),
+        data4,
+    ))
+}
+
+fn guid_bytes(guid: &GUID) -> Vec<u8> {
+    let mut bytes = guid.data1.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&guid.data2.to_le_bytes());
+    bytes.extend_from_slice(&guid.data3.to_le_bytes());
+    bytes.extend_from_slice(&guid.data4);
+
+    bytes
+}
+
+fn duration(micros: u32) -> Duration {
+    Duration::from_micros(micros as u64)
+}
+
+/// Reads a duration where `none` stands for "not set".
+fn optional_duration(micros: u32, none: u32) -> Option<Duration> {
+    Some(micros).filter(|&micros| micros != none).map(duration)
+}
+
+fn read_direction(flags: u32, chunk: &Chunk<'_>) -> Result<EffectDirection, EffectFileError> {
+    let mut values = read_i32s(chunk)?;
+
+    match flags {
+        _ if flags == DIEFF_OBJECTOFFSETS | DIEFF_CARTESIAN => {
+            Ok(EffectDirection::Cartesian(values))
+        }
+        // The last value of polar and spherical directions is always zero
+        _ if flags == DIEFF_OBJECTOFFSETS | DIEFF_POLAR => match values[..] {
+            [angle, 0] => Ok(EffectDirection::Polar(angle)),
+            _ => Err(EffectFileError::InvalidChunk(chunk.id)),
+        },
+        _ if flags == DIEFF_OBJECTOFFSETS | DIEFF_SPHERICAL => match values.pop() {
+            Some(0) => Ok(EffectDirection::Spherical(values)),
+            _ => Err(EffectFileError::InvalidChunk(chunk.id)),
+        },
+        _ => Err(EffectFileError::UnsupportedFlags(flags)),
+    }
+}
+
+fn read_envelope(chunk: &Chunk<'_>) -> Result<Envelope, EffectFileError> {
+    match read_u32s(chunk)?[..] {
+        [ENVELOPE_SIZE, attack_level, attack_time, fade_level, fade_time] => Ok(Envelope {
+            attack_level,
+            attack_time: duration(attack_time),
+            fade_level,
+            fade_time: duration(fade_time),
+        }),
+        _ => Err(EffectFileError::InvalidChunk(chunk.id)),
+    }
+}
+
+fn read_type_specific(
+    kind: EffectKind,
+    size: u32,
+    chunk: &Chunk<'_>,
+) -> Result<EffectType, EffectFileError> {
+    let invalid = || EffectFileError::InvalidChunk(chunk.id);
+
+    if kind != EffectKind::CustomForce && size as usize != chunk.data.len() {
+        return Err(invalid());
+    }
+
+    let values = read_i32s(chunk)?;
+    let waveform = |waveform| -> Result<EffectType, EffectFileError> {
+        match values[..] {
+            [magnitude, offset, phase, period] => Ok(EffectType::Periodic(Periodic {
+                waveform,
+                magnitude: magnitude as u32,
+                offset,
+                phase: phase as u32,
+                period: duration(period as u32),
+            })),
+            _ => Err(invalid()),
+        }
+    };
+    let condition = |kind| -> Result<EffectType, EffectFileError> {
+        let conditions = values.chunks_exact(CONDITION_SIZE / 4);
+        if values.is_empty() || !conditions.remainder().is_empty() {
+            return Err(invalid());
+        }
+
+        let conditions = conditions
+            .map(|v| Condition {
+                offset: v[0],
+                positive_coefficient: v[1],
+                negative_coefficient: v[2],
+                positive_saturation: v[3] as u32,
+                negative_saturation: v[4] as u32,
+                dead_band: v[5],
+            })
+            .collect();
+
+        Ok(EffectType::Condition(kind, conditions))
+    };
+
+    match kind {
+        EffectKind::ConstantForce => match values[..] {
+            [magnitude] => Ok(EffectType::ConstantForce(ConstantForce { magnitude })),
+            _ => Err(invalid()),
+        },
+        EffectKind::RampForce => match values[..] {
+            [start, end] => Ok(EffectType::RampForce(RampForce { start, end })),
+            _ => Err(invalid()),
+        },
+        EffectKind::Square => waveform(Waveform::Square),
+        EffectKind::Sine => waveform(Waveform::Sine),
+        EffectKind::Triangle => waveform(Waveform::Triangle),
+        EffectKind::SawtoothUp => waveform(Waveform::SawtoothUp),
+        EffectKind::SawtoothDown => waveform(Waveform::SawtoothDown),
+        EffectKind::Spring => condition(ConditionKind::Spring),
+        EffectKind::Damper => condition(ConditionKind::Damper),
+        EffectKind::Inertia => condition(ConditionKind::Inertia),
+        EffectKind::Friction => condition(ConditionKind::Friction),
+        EffectKind::CustomForce => match values[..] {
+            [channels, sample_period, count, _pointer, ref samples @ ..]
+                if size == CUSTOM_FORCE_SIZE && count as usize == samples.len() =>
+            {
+                Ok(EffectType::CustomForce(CustomForce {
+                    channels: channels as u32,
+                    sample_period: duration(sample_period as u32),
+                    samples: samples.to_vec(),
+                }))
+            }
+            _ => Err(invalid()),
+        },
+    }
+}
+
+fn type_specific_bytes(ty: &EffectType) -> Vec<u8> {
+    match ty {
+        EffectType::ConstantForce(force) => i32_bytes(&[force.magnitude]),
+        EffectType::RampForce(force) => i32_bytes(&[force.start, force.end]),
+        EffectType::Periodic(periodic) => u32_bytes(&[
+            periodic.magnitude,
+            periodic.offset as u32,
+            periodic.phase,
+            micros(periodic.period),
+        ]),
+        EffectType::Condition(_, conditions) => conditions
+            .iter()
+            .flat_map(|condition| {
+                u32_bytes(&[
+                    condition.offset as u32,
+                    condition.positive_coefficient as u32,
+                    condition.negative_coefficient as u32,
+                    condition.positive_saturation,
+                    condition.negative_saturation,
+                    condition.dead_band as u32,
+                ])
+            })
+            .collect(),
+        EffectType::CustomForce(force) => {
+            let mut bytes = u32_bytes(&[
+                force.channels,
+                micros(force.sample_period),
+                force.samples.len() as u32,
+                0,
+            ]);
+            bytes.extend(i32_bytes(&force.samples));
+
+            bytes
+        }
+    }
+}
+
+impl From<io::Error> for EffectFileError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl fmt::Display for EffectFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => fmt::Display::fmt(e, f),
+            Self::NotEffectFile => f.write_str("not a RIFF FORC effect file"),
+            Self::Truncated => f.write_str("chunk extends past the end of the file"),
+            Self::MissingChunk(id) => {
+                write!(
+                    f,
+                    "effect is missing the '{}' chunk",
+                    String::from_utf8_lossy(id)
+                )
+            }
+            Self::InvalidChunk(id) => write!(f, "invalid '{}' chunk", String::from_utf8_lossy(id)),
+            Self::UnknownEffect(guid) => write!(f, "unknown effect {:?}", guid),
+            Self::UnsupportedFlags(flags) => write!(f, "unsupported effect flags {:#x}", flags),
+        }
+    }
+}
+
+impl Error for EffectFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONSTANT_AND_SPRING: &[u8] = include_bytes!("../tests/fixtures/constant_and_spring.ffe");
+    const PERIODIC_AND_CUSTOM: &[u8] = include_bytes!("../tests/fixtures/periodic_and_custom.ffe");
+
+    fn condition(coefficient: i32) -> Condition {
+        Condition {
+            offset: 0,
+            positive_coefficient: coefficient,
+            negative_coefficient: coefficient,
+            positive_saturation: 10_000,
+            negative_saturation: 10_000,
+            dead_band: 500,
+        }
+    }
+
+    #[test]
+    fn test_read_fixtures() {
+        let file = EffectFile::parse(CONSTANT_AND_SPRING).expect("Failed to parse effect file");
+        assert_eq!(file.effects.len(), 2);
+
+        let constant = &file.effects[0];
+        assert_eq!(constant.name, "Push Left");
+        assert_eq!(
+            constant.effect,
+            Effect::new(EffectType::ConstantForce(ConstantForce {
+                magnitude: -7_500
+            }))
+            .with_axes(&[0, 4], EffectDirection::Polar(27_000))
+            .with_duration(Duration::from_millis(500))
+            .with_envelope(Envelope {
+                attack_level: 0,
+                attack_time: Duration::from_millis(100),
+                fade_level: 2_000,
+                fade_time: Duration::from_millis(50),
+            })
+        );
+
+        let spring = &file.effects[1];
+        assert_eq!(spring.name, "Center Spring");
+        assert_eq!(
+            spring.effect,
+            Effect::new(EffectType::Condition(
+                ConditionKind::Spring,
+                vec![condition(8_000), condition(6_000)]
+            ))
+            .with_axes(&[0, 4], EffectDirection::Cartesian(vec![1, 0]))
+            .with_gain(9_000)
+            .with_trigger_button(48, Some(Duration::from_millis(250)))
+        );
+
+        let file = EffectFile::parse(PERIODIC_AND_CUSTOM).expect("Failed to parse effect file");
+        assert_eq!(file.effects.len(), 2);
+        assert_eq!(
+            file.effects[0].effect,
+            Effect::new(EffectType::Periodic(Periodic {
+                waveform: Waveform::Sine,
+                magnitude: 5_000,
+                offset: -100,
+                phase: 9_000,
+                period: Duration::from_millis(20),
+            }))
+            .with_axes(&[0], EffectDirection::Cartesian(vec![1]))
+            .with_duration(Duration::from_secs(2))
+            .with_start_delay(Duration::from_millis(10))
+        );
+        assert_eq!(
+            file.effects[1].effect,
+            Effect::new(EffectType::CustomForce(CustomForce {
+                channels: 1,
+                sample_period: Duration::from_millis(10),
+                samples: vec![0, 2_500, 5_000, 2_500, 0, -2_500, -5_000],
+            }))
+            .with_axes(&[0, 4, 8], EffectDirection::Spherical(vec![9_000, 0]))
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for fixture in &[CONSTANT_AND_SPRING, PERIODIC_AND_CUSTOM] {
+            let file = EffectFile::parse(fixture).expect("Failed to parse effect file");
+
+            assert_eq!(file.to_bytes(), *fixture);
+            assert_eq!(EffectFile::parse(&file.to_bytes()).unwrap(), file);
+        }
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(matches!(
+            EffectFile::parse(b"RIFX\x04\0\0\0FORC"),
+            Err(EffectFileError::NotEffectFile)
+        ));
+        assert!(matches!(
+            EffectFile::parse(b"RIFF"),
+            Err(EffectFileError::NotEffectFile)
+        ));
+        assert!(matches!(
+            EffectFile::parse(&CONSTANT_AND_SPRING[..40]),
+            Err(EffectFileError::Truncated)
+        ));
+        assert!(matches!(
+            EffectFile::parse(b"RIFF\x10\0\0\0FORC"),
+            Err(EffectFileError::Truncated)
+        ));
+
+        // A RIFF size too small to hold the form type
+        assert!(matches!(
+            EffectFile::parse(b"RIFF\0\0\0\0FORC"),
+            Err(EffectFileError::NotEffectFile)
+        ));
+        assert!(matches!(
+            EffectFile::parse(b"RIFF\x02\0\0\0FORC"),
+            Err(EffectFileError::NotEffectFile)
+        ));
+
+        // An effect with an unknown GUID
+        let mut data = CONSTANT_AND_SPRING.to_vec();
+        let id = data.windows(4).position(|w| w == b"id  ").unwrap();
+        data[id + 8] ^= 0xff;
+        assert!(matches!(
+            EffectFile::parse(&data),
+            Err(EffectFileError::UnknownEffect(_))
+        ));
+
+        // A `DIEFFECT` with the wrong size
+        let mut data = CONSTANT_AND_SPRING.to_vec();
+        let parm = data.windows(4).position(|w| w == b"parm").unwrap();
+        data[parm + 8] = 52;
+        assert!(matches!(
+            EffectFile::parse(&data),
+            Err(EffectFileError::InvalidChunk(id)) if &id == b"parm"
+        ));
+    }
+}
@@ -0,0 +1,164 @@
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DIEDFL_ALLDEVICES, DIEDFL_ATTACHEDONLY, DIEDFL_FORCEFEEDBACK, DIEDFL_INCLUDEALIASES,
+    DIEDFL_INCLUDEHIDDEN, DIEDFL_INCLUDEPHANTOMS,
+};
+
+use crate::device_class::DeviceClass;
+use crate::device_type::DeviceType;
+
+/// Filters for [`DirectInputManager::enum_devices_with`], mapping to the `dwDevType` and
+/// `dwFlags` parameters of `IDirectInput8::EnumDevices`.
+///
+/// By default all devices of every class are enumerated, including detached ones.
+///
+/// [`DirectInputManager::enum_devices_with`]: crate::DirectInputManager::enum_devices_with
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EnumOptions {
+    filter: u32,
+    flags: u32,
+}
+
+impl EnumOptions {
+    pub fn new() -> Self {
+        Self {
+            filter: DeviceClass::All.bits(),
+            flags: DIEDFL_ALLDEVICES,
+        }
+    }
+
+    /// Only enumerates devices of the given class. Replaces any previous class or type filter.
+    pub fn class(mut self, class: DeviceClass) -> Self {
+        self.filter = class.bits();
+        self
+    }
+
+    /// Only enumerates devices of the given type. DirectInput does not filter on subtypes, so
+    /// only the `DI8DEVTYPE_*` byte is used. Replaces any previous class or type filter.
+    pub fn device_type(mut self, device_type: DeviceType) -> Self {
+        self.filter = device_type.bits() & 0xff;
+        self
+    }
+
+    /// Only enumerates devices that are currently attached (`DIEDFL_ATTACHEDONLY`).
+    pub fn attached_only(mut self) -> Self {
+        self.flags |= DIEDFL_ATTACHEDONLY;
+        self
+    }
+
+    /// Only enumerates devices that support force feedback (`DIEDFL_FORCEFEEDBACK`).
+    pub fn force_feedback(mut self) -> Self {
+        self.flags |= DIEDFL_FORCEFEEDBACK;
+        self
+    }
+
+    /// Includes devices that are aliases of other devices (`DIEDFL_INCLUDEALIASES`).
+    pub fn include_aliases(mut self) -> Self {
+        self.flags |= DIEDFL_INCLUDEALIASES;
+        self
+    }
+
+    /// Includes hidden devices (`DIEDFL_INCLUDEHIDDEN`).
+    pub fn include_hidden(mut self) -> Self {
+        self.flags |= DIEDFL_INCLUDEHIDDEN;
+        self
+    }
+
+    /// Includes placeholder devices that do not correspond to real hardware
+    /// (`DIEDFL_INCLUDEPHANTOMS`).
+    pub fn include_phantoms(mut self) -> Self {
+        self.flags |= DIEDFL_INCLUDEPHANTOMS;
+        self
+    }
+
+    /// The `DI8DEVCLASS_*` or `DI8DEVTYPE_*` value passed as `dwDevType`.
+    pub fn filter(&self) -> u32 {
+        self.filter
+    }
+
+    /// The `DIEDFL_*` flags passed as `dwFlags`.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+}
+
+impl Default for EnumOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<DeviceClass> for EnumOptions {
+    fn from(class: DeviceClass) -> Self {
+        Self::new().class(class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::{
+        DI8DEVTYPE_DRIVING, DIDC_ATTACHED, DIDC_HIDDEN, DIDC_PHANTOM,
+    };
+
+    use super::*;
+    use crate::backend::sim::{SimulatedBackend, SimulatedDevice};
+    use crate::device_type::GamepadSubtype;
+    use crate::effect::EffectKind;
+    use crate::manager::DirectInputManager;
+
+    #[test]
+    fn test_enum_options() {
+        let pad = SimulatedDevice::new("Simulated Pad");
+        let wheel = SimulatedDevice::new("Simulated Wheel")
+            .with_dev_type(DI8DEVTYPE_DRIVING)
+            .with_effects(&[EffectKind::ConstantForce]);
+        let phantom = SimulatedDevice::new("Simulated Phantom").with_flags(DIDC_PHANTOM);
+        let hidden =
+            SimulatedDevice::new("Simulated Hidden").with_flags(DIDC_ATTACHED | DIDC_HIDDEN);
+        let unplugged = SimulatedDevice::new("Simulated Unplugged");
+        unplugged.unplug();
+
+        let backend = SimulatedBackend::new();
+        for device in [&pad, &wheel, &phantom, &hidden, &unplugged] {
+            backend.add_device(device.clone());
+        }
+
+        let manager = DirectInputManager::with_backend(backend);
+        let ids = |options: EnumOptions| {
+            manager
+                .enum_devices_with(&options)
+                .expect("Failed to enumerate devices")
+                .iter()
+                .map(|info| info.guid_instance().as_guid().data1)
+                .collect::<Vec<_>>()
+        };
+        let id = |device: &SimulatedDevice| device.guid_instance().data1;
+
+        assert_eq!(
+            ids(EnumOptions::new()),
+            [id(&pad), id(&wheel), id(&unplugged)]
+        );
+        assert_eq!(
+            ids(EnumOptions::new().attached_only()),
+            [id(&pad), id(&wheel)]
+        );
+        assert_eq!(ids(EnumOptions::new().force_feedback()), [id(&wheel)]);
+        assert_eq!(
+            ids(EnumOptions::new().device_type(DeviceType::Gamepad(GamepadSubtype::Standard))),
+            [id(&pad), id(&unplugged)]
+        );
+        assert_eq!(
+            ids(EnumOptions::new()
+                .class(DeviceClass::GameController)
+                .include_hidden()
+                .include_phantoms()),
+            [
+                id(&pad),
+                id(&wheel),
+                id(&phantom),
+                id(&hidden),
+                id(&unplugged)
+            ]
+        );
+        assert!(ids(EnumOptions::new().class(DeviceClass::Keyboard)).is_empty());
+    }
+}
@@ -1,23 +1,25 @@
 use std::error::Error;
 use std::fmt;
+use std::io;
 
-use winapi::um::dinput::{
-    DIERR_ACQUIRED, DIERR_ALREADYINITIALIZED, DIERR_BADDRIVERVER, DIERR_BETADIRECTINPUTVERSION,
-    DIERR_DEVICEFULL, DIERR_DEVICENOTREG, DIERR_EFFECTPLAYING, DIERR_HASEFFECTS,
-    DIERR_INCOMPLETEEFFECT, DIERR_INPUTLOST, DIERR_INSUFFICIENTPRIVS, DIERR_INVALIDPARAM,
-    DIERR_MAPFILEFAIL, DIERR_MOREDATA, DIERR_NOAGGREGATION, DIERR_NOINTERFACE, DIERR_NOTACQUIRED,
-    DIERR_NOTBUFFERED, DIERR_NOTDOWNLOADED, DIERR_NOTEXCLUSIVEACQUIRED, DIERR_NOTINITIALIZED,
-    DIERR_OBJECTNOTFOUND, DIERR_OLDDIRECTINPUTVERSION, DIERR_OUTOFMEMORY, DIERR_REPORTFULL,
-    DIERR_UNPLUGGED, DIERR_UNSUPPORTED,
-};
 use windows::core::HRESULT;
 use windows::Win32::Devices::HumanInterfaceDevice::{
-    DI_DOWNLOADSKIPPED, DI_EFFECTRESTARTED, DI_POLLEDDEVICE, DI_SETTINGSNOTSAVED, DI_TRUNCATED,
-    DI_TRUNCATEDANDRESTARTED, DI_WRITEPROTECT,
+    DIERR_ACQUIRED, DIERR_ALREADYINITIALIZED, DIERR_BADDRIVERVER, DIERR_BETADIRECTINPUTVERSION,
+    DIERR_DEVICEFULL, DIERR_DEVICENOTREG, DIERR_EFFECTPLAYING, DIERR_GENERIC, DIERR_HANDLEEXISTS,
+    DIERR_HASEFFECTS, DIERR_INCOMPLETEEFFECT, DIERR_INPUTLOST, DIERR_INSUFFICIENTPRIVS,
+    DIERR_INVALIDPARAM, DIERR_MAPFILEFAIL, DIERR_MOREDATA, DIERR_NOAGGREGATION, DIERR_NOINTERFACE,
+    DIERR_NOTACQUIRED, DIERR_NOTBUFFERED, DIERR_NOTDOWNLOADED, DIERR_NOTEXCLUSIVEACQUIRED,
+    DIERR_NOTINITIALIZED, DIERR_OBJECTNOTFOUND, DIERR_OLDDIRECTINPUTVERSION, DIERR_OTHERAPPHASPRIO,
+    DIERR_OUTOFMEMORY, DIERR_READONLY, DIERR_REPORTFULL, DIERR_UNPLUGGED, DIERR_UNSUPPORTED,
+    DI_BUFFEROVERFLOW, DI_DOWNLOADSKIPPED, DI_EFFECTRESTARTED, DI_NOEFFECT, DI_NOTATTACHED,
+    DI_POLLEDDEVICE, DI_PROPNOEFFECT, DI_SETTINGSNOTSAVED, DI_TRUNCATED, DI_TRUNCATEDANDRESTARTED,
+    DI_WRITEPROTECT,
 };
-use windows::Win32::Foundation::{GetLastError, E_FAIL, S_OK};
+use windows::Win32::Foundation::{GetLastError, E_HANDLE, S_FALSE, S_OK};
 use windows::Win32::System::Com::Urlmon::E_PENDING;
 
+use crate::data_format::DataFormatError;
+
 pub type Result<T, E = DirectInputError> = std::result::Result<T, E>;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -42,6 +44,8 @@ pub enum DirectInputError {
     AlreadyInitialized,
     BadDriverVersion,
     BetaDirectInputVersion,
+    /// A data format was rejected before being passed to DirectInput.
+    DataFormat(DataFormatError),
     DeviceFull,
     DeviceNotReg,
     EffectPlaying,
@@ -74,11 +78,53 @@ pub enum DirectInputError {
     Unknown(HRESULT),
 }
 
+/// The DirectInput method that returned an `HRESULT`.
+///
+/// DirectInput reuses the same value for several codes: `DIERR_OTHERAPPHASPRIO`, `DIERR_READONLY`
+/// and `DIERR_HANDLEEXISTS` are all `E_ACCESSDENIED`, and `DI_NOEFFECT`, `DI_BUFFEROVERFLOW`,
+/// `DI_NOTATTACHED` and `DI_PROPNOEFFECT` are all `S_FALSE`. The method the code was returned by
+/// tells them apart.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HResultContext {
+    /// `IDirectInputDevice8::Acquire`.
+    Acquire,
+    /// `IDirectInputDevice8::BuildActionMap`.
+    BuildActionMap,
+    /// `IDirectInputEffect::Download`.
+    Download,
+    /// `IDirectInputDevice8::GetDeviceData`.
+    GetDeviceData,
+    /// `IDirectInput8::GetDeviceStatus`.
+    GetDeviceStatus,
+    /// `IDirectInputDevice8::GetProperty`.
+    GetProperty,
+    /// `IDirectInputDevice8::Poll`.
+    Poll,
+    /// `IDirectInputDevice8::SetActionMap`.
+    SetActionMap,
+    /// `IDirectInputDevice8::SetEventNotification`.
+    SetEventNotification,
+    /// `IDirectInputEffect::SetParameters`.
+    SetParameters,
+    /// `IDirectInputDevice8::SetProperty`.
+    SetProperty,
+    /// Any other method.
+    Other,
+}
+
 impl DirectInputStatus {
-    pub(crate) fn from_hresult(hr: HRESULT) -> Option<Self> {
-        // This match only contains status values that have a unique `HRESULT` value (e.g.
-        // `DI_BUFFEROVERFLOW` and `DI_NOTATTACHED` both use `S_FALSE` so they are not matched
-        // here).
+    /// Maps a success `HRESULT` returned by the method described by `context`, returning `None`
+    /// for unknown codes and failures.
+    pub fn from_hresult_with(hr: HRESULT, context: HResultContext) -> Option<Self> {
+        if hr == S_FALSE {
+            return Some(match context {
+                HResultContext::GetDeviceData => Self::BufferOverflow,
+                HResultContext::GetDeviceStatus => Self::NotAttached,
+                HResultContext::GetProperty | HResultContext::SetProperty => Self::PropNoEffect,
+                _ => Self::NoEffect,
+            });
+        }
+
         match hr {
             DI_DOWNLOADSKIPPED => Some(Self::DownloadSkipped),
             DI_EFFECTRESTARTED => Some(Self::EffectRestarted),
@@ -93,15 +139,42 @@ impl DirectInputStatus {
     }
 
     #[inline]
-    pub(crate) fn from_hresult_or_ok(hr: HRESULT) -> Self {
-        Self::from_hresult(hr).unwrap_or(Self::Ok)
+    pub(crate) fn from_hresult_or_ok(hr: HRESULT, context: HResultContext) -> Self {
+        Self::from_hresult_with(hr, context).unwrap_or(Self::Ok)
+    }
+
+    /// The `HRESULT` DirectInput uses for this status. Statuses sharing `S_FALSE` map back to
+    /// themselves with the matching [`HResultContext`].
+    pub fn into_hresult(self) -> HRESULT {
+        match self {
+            Self::BufferOverflow => HRESULT(DI_BUFFEROVERFLOW as _),
+            Self::DownloadSkipped => DI_DOWNLOADSKIPPED,
+            Self::EffectRestarted => DI_EFFECTRESTARTED,
+            Self::NoEffect => HRESULT(DI_NOEFFECT as _),
+            Self::NotAttached => HRESULT(DI_NOTATTACHED as _),
+            Self::Ok => S_OK,
+            Self::PolledDevice => DI_POLLEDDEVICE,
+            Self::PropNoEffect => HRESULT(DI_PROPNOEFFECT as _),
+            Self::SettingsNotSaved => DI_SETTINGSNOTSAVED,
+            Self::Truncated => DI_TRUNCATED,
+            Self::TruncatedAndRestarted => DI_TRUNCATEDANDRESTARTED,
+            Self::WriteProtect => DI_WRITEPROTECT,
+        }
     }
 }
 
 impl DirectInputError {
+    /// Maps a failure `HRESULT` returned by an unknown method, which resolves `E_ACCESSDENIED` to
+    /// `OtherAppHasPrio`.
     #[inline]
-    pub(crate) fn from_hresult(hr: HRESULT) -> Self {
-        Self::hresult_matches(hr).unwrap_or(Self::Unknown(hr))
+    pub fn from_hresult(hr: HRESULT) -> Self {
+        Self::from_hresult_with(hr, HResultContext::Other)
+    }
+
+    /// Maps a failure `HRESULT` returned by the method described by `context`. Codes that are not
+    /// DirectInput errors are kept as `Unknown`.
+    pub fn from_hresult_with(hr: HRESULT, context: HResultContext) -> Self {
+        Self::hresult_matches(hr, context).unwrap_or(Self::Unknown(hr))
     }
 
     pub(crate) fn from_last_error() -> Self {
@@ -110,53 +183,120 @@ impl DirectInputError {
         Self::from_hresult(HRESULT::from(err))
     }
 
-    pub(crate) fn hresult_matches(hr: HRESULT) -> Option<Self> {
+    pub(crate) fn hresult_matches(hr: HRESULT, context: HResultContext) -> Option<Self> {
+        // The `windows` crate types some of the DirectInput error codes as `HRESULT` and the rest
+        // as plain `i32`, so they are matched in two passes.
         match hr {
-            E_FAIL => return Some(Self::Generic),
+            E_HANDLE => return Some(Self::Handle),
             E_PENDING => return Some(Self::Pending),
+            DIERR_ACQUIRED => return Some(Self::Acquired),
+            DIERR_ALREADYINITIALIZED => return Some(Self::AlreadyInitialized),
+            DIERR_BADDRIVERVER => return Some(Self::BadDriverVersion),
+            DIERR_BETADIRECTINPUTVERSION => return Some(Self::BetaDirectInputVersion),
+            DIERR_INPUTLOST => return Some(Self::InputLost),
+            DIERR_NOTACQUIRED => return Some(Self::NotAcquired),
+            DIERR_NOTINITIALIZED => return Some(Self::NotInitialized),
+            DIERR_OBJECTNOTFOUND => return Some(Self::ObjectNotFound),
+            DIERR_OLDDIRECTINPUTVERSION => return Some(Self::OldDirectInputVersion),
             _ => {}
         };
         match hr.0 as i32 {
-            DIERR_ACQUIRED => Some(Self::Acquired),
-            DIERR_ALREADYINITIALIZED => Some(Self::AlreadyInitialized),
-            DIERR_BADDRIVERVER => Some(Self::BadDriverVersion),
-            DIERR_BETADIRECTINPUTVERSION => Some(Self::BetaDirectInputVersion),
             DIERR_DEVICEFULL => Some(Self::DeviceFull),
             DIERR_DEVICENOTREG => Some(Self::DeviceNotReg),
             DIERR_EFFECTPLAYING => Some(Self::EffectPlaying),
-            //DIERR_GENERIC => Some(Self::Generic),
-            //DIERR_HANDLEEXISTS => Some(Self::HandleExists),
+            DIERR_GENERIC => Some(Self::Generic),
             DIERR_HASEFFECTS => Some(Self::HasEffects),
             DIERR_INCOMPLETEEFFECT => Some(Self::IncompleteEffect),
-            DIERR_INPUTLOST => Some(Self::InputLost),
             DIERR_INVALIDPARAM => Some(Self::InvalidParam),
             DIERR_INSUFFICIENTPRIVS => Some(Self::InsufficientPrivs),
             DIERR_MAPFILEFAIL => Some(Self::MapFileFail),
             DIERR_MOREDATA => Some(Self::MoreData),
             DIERR_NOAGGREGATION => Some(Self::NoAggregation),
             DIERR_NOINTERFACE => Some(Self::NoInterface),
-            DIERR_NOTACQUIRED => Some(Self::NotAcquired),
             DIERR_NOTBUFFERED => Some(Self::NotBuffered),
             DIERR_NOTDOWNLOADED => Some(Self::NotDownloaded),
             DIERR_NOTEXCLUSIVEACQUIRED => Some(Self::NotExclusiveAcquired),
-            DIERR_NOTINITIALIZED => Some(Self::NotInitialized),
-            DIERR_OBJECTNOTFOUND => Some(Self::ObjectNotFound),
-            DIERR_OLDDIRECTINPUTVERSION => Some(Self::OldDirectInputVersion),
-            //DIERR_OTHERAPPHASPRIO => Some(Self::OtherAppHasPrio),
             DIERR_OUTOFMEMORY => Some(Self::OutOfMemory),
-            //DIERR_READONLY => Some(Self::ReadOnly),
             DIERR_REPORTFULL => Some(Self::ReportFull),
             DIERR_UNPLUGGED => Some(Self::Unplugged),
             DIERR_UNSUPPORTED => Some(Self::Unsupported),
-            //E_PENDING => Some(Self::Pending),
+            // `DIERR_HANDLEEXISTS`, `DIERR_OTHERAPPHASPRIO` and `DIERR_READONLY`
+            DIERR_OTHERAPPHASPRIO => Some(match context {
+                HResultContext::SetEventNotification => Self::HandleExists,
+                HResultContext::SetProperty => Self::ReadOnly,
+                _ => Self::OtherAppHasPrio,
+            }),
             _ => None,
         }
     }
+
+    /// The `HRESULT` DirectInput uses for this error. Errors sharing `E_ACCESSDENIED` map back to
+    /// themselves with the matching [`HResultContext`].
+    pub fn into_hresult(self) -> HRESULT {
+        let code = match self {
+            Self::Acquired => return DIERR_ACQUIRED,
+            Self::AlreadyInitialized => return DIERR_ALREADYINITIALIZED,
+            Self::BadDriverVersion => return DIERR_BADDRIVERVER,
+            Self::BetaDirectInputVersion => return DIERR_BETADIRECTINPUTVERSION,
+            Self::Handle => return E_HANDLE,
+            Self::InputLost => return DIERR_INPUTLOST,
+            Self::NotAcquired => return DIERR_NOTACQUIRED,
+            Self::NotInitialized => return DIERR_NOTINITIALIZED,
+            Self::ObjectNotFound => return DIERR_OBJECTNOTFOUND,
+            Self::OldDirectInputVersion => return DIERR_OLDDIRECTINPUTVERSION,
+            Self::Pending => return E_PENDING,
+            Self::Unknown(hr) => return hr,
+            Self::DataFormat(_) => DIERR_INVALIDPARAM,
+            Self::DeviceFull => DIERR_DEVICEFULL,
+            Self::DeviceNotReg => DIERR_DEVICENOTREG,
+            Self::EffectPlaying => DIERR_EFFECTPLAYING,
+            Self::Generic => DIERR_GENERIC,
+            Self::HandleExists => DIERR_HANDLEEXISTS,
+            Self::HasEffects => DIERR_HASEFFECTS,
+            Self::IncompleteEffect => DIERR_INCOMPLETEEFFECT,
+            Self::InvalidParam => DIERR_INVALIDPARAM,
+            Self::InsufficientPrivs => DIERR_INSUFFICIENTPRIVS,
+            Self::MapFileFail => DIERR_MAPFILEFAIL,
+            Self::MoreData => DIERR_MOREDATA,
+            Self::NoAggregation => DIERR_NOAGGREGATION,
+            Self::NoInterface => DIERR_NOINTERFACE,
+            Self::NotBuffered => DIERR_NOTBUFFERED,
+            Self::NotDownloaded => DIERR_NOTDOWNLOADED,
+            Self::NotExclusiveAcquired => DIERR_NOTEXCLUSIVEACQUIRED,
+            Self::OtherAppHasPrio => DIERR_OTHERAPPHASPRIO,
+            Self::OutOfMemory => DIERR_OUTOFMEMORY,
+            Self::ReadOnly => DIERR_READONLY,
+            Self::ReportFull => DIERR_REPORTFULL,
+            Self::Unplugged => DIERR_UNPLUGGED,
+            Self::Unsupported => DIERR_UNSUPPORTED,
+        };
+
+        HRESULT(code as _)
+    }
+
+    /// The closest `std::io::ErrorKind`, used when converting into `std::io::Error`.
+    fn io_error_kind(self) -> io::ErrorKind {
+        match self {
+            Self::DataFormat(_) | Self::InvalidParam => io::ErrorKind::InvalidInput,
+            Self::DeviceNotReg | Self::ObjectNotFound => io::ErrorKind::NotFound,
+            Self::InsufficientPrivs | Self::OtherAppHasPrio | Self::ReadOnly => {
+                io::ErrorKind::PermissionDenied
+            }
+            Self::AlreadyInitialized | Self::HandleExists => io::ErrorKind::AlreadyExists,
+            Self::Pending => io::ErrorKind::WouldBlock,
+            Self::OutOfMemory => io::ErrorKind::OutOfMemory,
+            Self::NoAggregation | Self::NoInterface | Self::Unsupported => {
+                io::ErrorKind::Unsupported
+            }
+            _ => io::ErrorKind::Other,
+        }
+    }
 }
 
 impl fmt::Display for DirectInputError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Self::DataFormat(error) => write!(f, "invalid data format: {}", error),
             Self::Unknown(hr) => fmt::Debug::fmt(hr, f),
             _ => fmt::Debug::fmt(self, f),
         }
@@ -165,9 +305,197 @@ impl fmt::Display for DirectInputError {
 
 impl Error for DirectInputError {}
 
+impl From<DataFormatError> for DirectInputError {
+    fn from(value: DataFormatError) -> Self {
+        Self::DataFormat(value)
+    }
+}
+
 impl From<windows::core::Error> for DirectInputError {
     #[inline]
     fn from(value: windows::core::Error) -> Self {
         Self::from_hresult(value.code())
     }
 }
+
+impl From<DirectInputError> for windows::core::Error {
+    #[inline]
+    fn from(value: DirectInputError) -> Self {
+        Self::from(value.into_hresult())
+    }
+}
+
+impl From<DirectInputError> for io::Error {
+    fn from(value: DirectInputError) -> Self {
+        io::Error::new(value.io_error_kind(), value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use HResultContext::*;
+
+    const ERRORS: &[(DirectInputError, u32, HResultContext)] = &[
+        (DirectInputError::Acquired, 0x800700aa, Other),
+        (DirectInputError::AlreadyInitialized, 0x800704df, Other),
+        (DirectInputError::BadDriverVersion, 0x80070077, Other),
+        (DirectInputError::BetaDirectInputVersion, 0x80070481, Other),
+        (DirectInputError::DeviceFull, 0x80040201, Other),
+        (DirectInputError::DeviceNotReg, 0x80040154, Other),
+        (DirectInputError::EffectPlaying, 0x80040208, Other),
+        (DirectInputError::Generic, 0x80004005, Other),
+        (DirectInputError::Handle, 0x80070006, Other),
+        (
+            DirectInputError::HandleExists,
+            0x80070005,
+            SetEventNotification,
+        ),
+        (DirectInputError::HasEffects, 0x80040204, Other),
+        (DirectInputError::IncompleteEffect, 0x80040206, Other),
+        (DirectInputError::InputLost, 0x8007001e, Other),
+        (DirectInputError::InvalidParam, 0x80070057, Other),
+        (DirectInputError::InsufficientPrivs, 0x80040200, Other),
+        (DirectInputError::MapFileFail, 0x8004020b, Other),
+        (DirectInputError::MoreData, 0x80040202, Other),
+        (DirectInputError::NoAggregation, 0x80040110, Other),
+        (DirectInputError::NoInterface, 0x80004002, Other),
+        (DirectInputError::NotAcquired, 0x8007000c, Other),
+        (DirectInputError::NotBuffered, 0x80040207, Other),
+        (DirectInputError::NotDownloaded, 0x80040203, Other),
+        (DirectInputError::NotExclusiveAcquired, 0x80040205, Other),
+        (DirectInputError::NotInitialized, 0x80070015, Other),
+        (DirectInputError::ObjectNotFound, 0x80070002, Other),
+        (DirectInputError::OldDirectInputVersion, 0x8007047e, Other),
+        (DirectInputError::OtherAppHasPrio, 0x80070005, Acquire),
+        (DirectInputError::OutOfMemory, 0x8007000e, Other),
+        (DirectInputError::Pending, 0x8000000a, Other),
+        (DirectInputError::ReadOnly, 0x80070005, SetProperty),
+        (DirectInputError::ReportFull, 0x8004020a, Other),
+        (DirectInputError::Unplugged, 0x80040209, Other),
+        (DirectInputError::Unsupported, 0x80004001, Other),
+    ];
+
+    const STATUSES: &[(DirectInputStatus, i32, HResultContext)] = &[
+        (DirectInputStatus::BufferOverflow, 1, GetDeviceData),
+        (DirectInputStatus::DownloadSkipped, 3, SetParameters),
+        (DirectInputStatus::EffectRestarted, 4, SetParameters),
+        (DirectInputStatus::NoEffect, 1, Poll),
+        (DirectInputStatus::NotAttached, 1, GetDeviceStatus),
+        (DirectInputStatus::Ok, 0, Other),
+        (DirectInputStatus::PolledDevice, 2, Other),
+        (DirectInputStatus::PropNoEffect, 1, SetProperty),
+        (DirectInputStatus::SettingsNotSaved, 11, SetActionMap),
+        (DirectInputStatus::Truncated, 8, SetParameters),
+        (DirectInputStatus::TruncatedAndRestarted, 12, SetParameters),
+        (DirectInputStatus::WriteProtect, 19, BuildActionMap),
+    ];
+
+    fn hresult(code: u32) -> HRESULT {
+        HRESULT(code as _)
+    }
+
+    #[test]
+    fn test_error_round_trip() {
+        for &(error, code, context) in ERRORS {
+            assert_eq!(error.into_hresult(), hresult(code), "{:?}", error);
+            assert_eq!(
+                DirectInputError::from_hresult_with(hresult(code), context),
+                error
+            );
+        }
+
+        let unknown = DirectInputError::from_hresult(hresult(0x8000ffff));
+        assert_eq!(unknown, DirectInputError::Unknown(hresult(0x8000ffff)));
+        assert_eq!(unknown.into_hresult(), hresult(0x8000ffff));
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        for &(status, code, context) in STATUSES {
+            assert_eq!(status.into_hresult(), HRESULT(code as _), "{:?}", status);
+            assert_eq!(
+                DirectInputStatus::from_hresult_with(HRESULT(code as _), context),
+                Some(status)
+            );
+        }
+
+        assert_eq!(
+            DirectInputStatus::from_hresult_with(HRESULT(5), Other),
+            None
+        );
+        assert_eq!(
+            DirectInputStatus::from_hresult_with(hresult(0x80004005), Other),
+            None
+        );
+    }
+
+    #[test]
+    fn test_aliases_by_context() {
+        let access_denied = hresult(0x80070005);
+        let resolve = |context| DirectInputError::from_hresult_with(access_denied, context);
+
+        assert_eq!(resolve(Acquire), DirectInputError::OtherAppHasPrio);
+        assert_eq!(resolve(GetDeviceData), DirectInputError::OtherAppHasPrio);
+        assert_eq!(resolve(SetProperty), DirectInputError::ReadOnly);
+        assert_eq!(
+            resolve(SetEventNotification),
+            DirectInputError::HandleExists
+        );
+        assert_eq!(
+            DirectInputError::from_hresult(access_denied),
+            DirectInputError::OtherAppHasPrio
+        );
+
+        let resolve = |context| DirectInputStatus::from_hresult_with(S_FALSE, context);
+
+        assert_eq!(
+            resolve(GetDeviceData),
+            Some(DirectInputStatus::BufferOverflow)
+        );
+        assert_eq!(resolve(GetProperty), Some(DirectInputStatus::PropNoEffect));
+        assert_eq!(
+            resolve(GetDeviceStatus),
+            Some(DirectInputStatus::NotAttached)
+        );
+        for &context in &[Acquire, BuildActionMap, Download, Poll, SetParameters] {
+            assert_eq!(resolve(context), Some(DirectInputStatus::NoEffect));
+        }
+    }
+
+    // Creating a `windows::core::Error` queries the COM error info
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_error() {
+        for &(error, code, _) in ERRORS {
+            let windows_error = windows::core::Error::from(error);
+            assert_eq!(windows_error.code(), hresult(code));
+
+            // Aliases of `E_ACCESSDENIED` lose their context
+            if windows_error.code() != hresult(0x80070005) {
+                assert_eq!(DirectInputError::from(windows_error), error);
+            }
+        }
+    }
+
+    #[test]
+    fn test_io_error() {
+        let err = io::Error::from(DirectInputError::OtherAppHasPrio);
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            err.get_ref()
+                .and_then(|e| e.downcast_ref::<DirectInputError>()),
+            Some(&DirectInputError::OtherAppHasPrio)
+        );
+
+        assert_eq!(
+            io::Error::from(DirectInputError::InvalidParam).kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            io::Error::from(DirectInputError::InputLost).kind(),
+            io::ErrorKind::Other
+        );
+    }
+}
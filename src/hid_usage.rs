@@ -0,0 +1,539 @@
+use std::fmt;
+
+/// Defines a usage enum for the usages of one page, with display names from the HID Usage
+/// Tables and an `Other` variant for usages that are not listed.
+macro_rules! usages {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($variant:ident = $value:literal => $display:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+        pub enum $name {
+            $($variant,)*
+            /// A usage that is reserved or not listed here.
+            Other(u16),
+        }
+
+        impl $name {
+            pub fn from_id(id: u16) -> Self {
+                match id {
+                    $($value => Self::$variant,)*
+                    _ => Self::Other(id),
+                }
+            }
+
+            pub fn id(self) -> u16 {
+                match self {
+                    $(Self::$variant => $value,)*
+                    Self::Other(id) => id,
+                }
+            }
+
+            /// The name of the usage in the HID Usage Tables.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(Self::$variant => Some($display),)*
+                    Self::Other(_) => None,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self.name() {
+                    Some(name) => f.write_str(name),
+                    None => write!(f, "{:#06x}", self.id()),
+                }
+            }
+        }
+    };
+}
+
+/// A HID usage page, the upper 16 bits of a usage, such as reported by
+/// [`DirectInputDeviceInfo::hid_usage`](crate::DirectInputDeviceInfo::hid_usage) and
+/// [`DeviceObject::hid_usage`](crate::DeviceObject::hid_usage).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum UsagePage {
+    GenericDesktop,
+    Simulation,
+    Vr,
+    Sport,
+    Game,
+    GenericDevice,
+    Keyboard,
+    Led,
+    Button,
+    Ordinal,
+    Telephony,
+    Consumer,
+    Digitizer,
+    Haptics,
+    /// Force feedback (Physical Input Device).
+    PhysicalInput,
+    Unicode,
+    /// Pages `0xFF00` to `0xFFFF`, whose meaning is defined by the vendor.
+    VendorDefined(u16),
+    /// A page that is reserved or not listed here.
+    Other(u16),
+}
+
+impl UsagePage {
+    pub fn from_id(id: u16) -> Self {
+        match id {
+            0x01 => Self::GenericDesktop,
+            0x02 => Self::Simulation,
+            0x03 => Self::Vr,
+            0x04 => Self::Sport,
+            0x05 => Self::Game,
+            0x06 => Self::GenericDevice,
+            0x07 => Self::Keyboard,
+            0x08 => Self::Led,
+            0x09 => Self::Button,
+            0x0a => Self::Ordinal,
+            0x0b => Self::Telephony,
+            0x0c => Self::Consumer,
+            0x0d => Self::Digitizer,
+            0x0e => Self::Haptics,
+            0x0f => Self::PhysicalInput,
+            0x10 => Self::Unicode,
+            0xff00..=0xffff => Self::VendorDefined(id),
+            _ => Self::Other(id),
+        }
+    }
+
+    pub fn id(self) -> u16 {
+        match self {
+            Self::GenericDesktop => 0x01,
+            Self::Simulation => 0x02,
+            Self::Vr => 0x03,
+            Self::Sport => 0x04,
+            Self::Game => 0x05,
+            Self::GenericDevice => 0x06,
+            Self::Keyboard => 0x07,
+            Self::Led => 0x08,
+            Self::Button => 0x09,
+            Self::Ordinal => 0x0a,
+            Self::Telephony => 0x0b,
+            Self::Consumer => 0x0c,
+            Self::Digitizer => 0x0d,
+            Self::Haptics => 0x0e,
+            Self::PhysicalInput => 0x0f,
+            Self::Unicode => 0x10,
+            Self::VendorDefined(id) | Self::Other(id) => id,
+        }
+    }
+
+    /// The name of the page in the HID Usage Tables.
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            Self::GenericDesktop => "Generic Desktop",
+            Self::Simulation => "Simulation Controls",
+            Self::Vr => "VR Controls",
+            Self::Sport => "Sport Controls",
+            Self::Game => "Game Controls",
+            Self::GenericDevice => "Generic Device Controls",
+            Self::Keyboard => "Keyboard/Keypad",
+            Self::Led => "LED",
+            Self::Button => "Button",
+            Self::Ordinal => "Ordinal",
+            Self::Telephony => "Telephony Device",
+            Self::Consumer => "Consumer",
+            Self::Digitizer => "Digitizers",
+            Self::Haptics => "Haptics",
+            Self::PhysicalInput => "Physical Input Device",
+            Self::Unicode => "Unicode",
+            Self::VendorDefined(_) => "Vendor Defined",
+            Self::Other(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for UsagePage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self, self.name()) {
+            (Self::VendorDefined(id), Some(name)) => write!(f, "{} {:#06x}", name, id),
+            (_, Some(name)) => f.write_str(name),
+            (_, None) => write!(f, "{:#06x}", self.id()),
+        }
+    }
+}
+
+usages! {
+    /// Usages of the Generic Desktop page (`0x01`).
+    pub enum GenericDesktopUsage {
+        Pointer = 0x01 => "Pointer",
+        Mouse = 0x02 => "Mouse",
+        Joystick = 0x04 => "Joystick",
+        Gamepad = 0x05 => "Game Pad",
+        Keyboard = 0x06 => "Keyboard",
+        Keypad = 0x07 => "Keypad",
+        MultiAxisController = 0x08 => "Multi-axis Controller",
+        TabletPcSystemControls = 0x09 => "Tablet PC System Controls",
+        X = 0x30 => "X",
+        Y = 0x31 => "Y",
+        Z = 0x32 => "Z",
+        Rx = 0x33 => "Rx",
+        Ry = 0x34 => "Ry",
+        Rz = 0x35 => "Rz",
+        Slider = 0x36 => "Slider",
+        Dial = 0x37 => "Dial",
+        Wheel = 0x38 => "Wheel",
+        HatSwitch = 0x39 => "Hat Switch",
+        CountedBuffer = 0x3a => "Counted Buffer",
+        ByteCount = 0x3b => "Byte Count",
+        MotionWakeup = 0x3c => "Motion Wakeup",
+        Start = 0x3d => "Start",
+        Select = 0x3e => "Select",
+        Vx = 0x40 => "Vx",
+        Vy = 0x41 => "Vy",
+        Vz = 0x42 => "Vz",
+        Vbrx = 0x43 => "Vbrx",
+        Vbry = 0x44 => "Vbry",
+        Vbrz = 0x45 => "Vbrz",
+        Vno = 0x46 => "Vno",
+        SystemControl = 0x80 => "System Control",
+        SystemPowerDown = 0x81 => "System Power Down",
+        SystemSleep = 0x82 => "System Sleep",
+        SystemWakeUp = 0x83 => "System Wake Up",
+        SystemMainMenu = 0x85 => "System Main Menu",
+        DpadUp = 0x90 => "D-pad Up",
+        DpadDown = 0x91 => "D-pad Down",
+        DpadRight = 0x92 => "D-pad Right",
+        DpadLeft = 0x93 => "D-pad Left",
+    }
+}
+
+usages! {
+    /// Usages of the Simulation Controls page (`0x02`).
+    pub enum SimulationUsage {
+        FlightSimulationDevice = 0x01 => "Flight Simulation Device",
+        AutomobileSimulationDevice = 0x02 => "Automobile Simulation Device",
+        TankSimulationDevice = 0x03 => "Tank Simulation Device",
+        SpaceshipSimulationDevice = 0x04 => "Spaceship Simulation Device",
+        SubmarineSimulationDevice = 0x05 => "Submarine Simulation Device",
+        SailingSimulationDevice = 0x06 => "Sailing Simulation Device",
+        MotorcycleSimulationDevice = 0x07 => "Motorcycle Simulation Device",
+        SportsSimulationDevice = 0x08 => "Sports Simulation Device",
+        AirplaneSimulationDevice = 0x09 => "Airplane Simulation Device",
+        HelicopterSimulationDevice = 0x0a => "Helicopter Simulation Device",
+        MagicCarpetSimulationDevice = 0x0b => "Magic Carpet Simulation Device",
+        BicycleSimulationDevice = 0x0c => "Bicycle Simulation Device",
+        FlightControlStick = 0x20 => "Flight Control Stick",
+        FlightStick = 0x21 => "Flight Stick",
+        CyclicControl = 0x22 => "Cyclic Control",
+        CyclicTrim = 0x23 => "Cyclic Trim",
+        FlightYoke = 0x24 => "Flight Yoke",
+        TrackControl = 0x25 => "Track Control",
+        Aileron = 0xb0 => "Aileron",
+        AileronTrim = 0xb1 => "Aileron Trim",
+        AntiTorqueControl = 0xb2 => "Anti-Torque Control",
+        AutopilotEnable = 0xb3 => "Autopilot Enable",
+        ChaffRelease = 0xb4 => "Chaff Release",
+        CollectiveControl = 0xb5 => "Collective Control",
+        DiveBrake = 0xb6 => "Dive Brake",
+        ElectronicCountermeasures = 0xb7 => "Electronic Countermeasures",
+        Elevator = 0xb8 => "Elevator",
+        ElevatorTrim = 0xb9 => "Elevator Trim",
+        Rudder = 0xba => "Rudder",
+        Throttle = 0xbb => "Throttle",
+        FlightCommunications = 0xbc => "Flight Communications",
+        FlareRelease = 0xbd => "Flare Release",
+        LandingGear = 0xbe => "Landing Gear",
+        ToeBrake = 0xbf => "Toe Brake",
+        Trigger = 0xc0 => "Trigger",
+        WeaponsArm = 0xc1 => "Weapons Arm",
+        WeaponsSelect = 0xc2 => "Weapons Select",
+        WingFlaps = 0xc3 => "Wing Flaps",
+        Accelerator = 0xc4 => "Accelerator",
+        Brake = 0xc5 => "Brake",
+        Clutch = 0xc6 => "Clutch",
+        Shifter = 0xc7 => "Shifter",
+        Steering = 0xc8 => "Steering",
+        TurretDirection = 0xc9 => "Turret Direction",
+        BarrelElevation = 0xca => "Barrel Elevation",
+        DivePlane = 0xcb => "Dive Plane",
+        Ballast = 0xcc => "Ballast",
+        BicycleCrank = 0xcd => "Bicycle Crank",
+        HandleBars = 0xce => "Handle Bars",
+        FrontBrake = 0xcf => "Front Brake",
+        RearBrake = 0xd0 => "Rear Brake",
+    }
+}
+
+usages! {
+    /// Usages of the Game Controls page (`0x05`).
+    pub enum GameUsage {
+        GameController3d = 0x01 => "3D Game Controller",
+        PinballDevice = 0x02 => "Pinball Device",
+        GunDevice = 0x03 => "Gun Device",
+        PointOfView = 0x20 => "Point of View",
+        TurnRightLeft = 0x21 => "Turn Right/Left",
+        PitchForwardBackward = 0x22 => "Pitch Forward/Backward",
+        RollRightLeft = 0x23 => "Roll Right/Left",
+        MoveRightLeft = 0x24 => "Move Right/Left",
+        MoveForwardBackward = 0x25 => "Move Forward/Backward",
+        MoveUpDown = 0x26 => "Move Up/Down",
+        LeanRightLeft = 0x27 => "Lean Right/Left",
+        LeanForwardBackward = 0x28 => "Lean Forward/Backward",
+        HeightOfPov = 0x29 => "Height of POV",
+        Flipper = 0x2a => "Flipper",
+        SecondaryFlipper = 0x2b => "Secondary Flipper",
+        Bump = 0x2c => "Bump",
+        NewGame = 0x2d => "New Game",
+        ShootBall = 0x2e => "Shoot Ball",
+        Player = 0x2f => "Player",
+        GunBolt = 0x30 => "Gun Bolt",
+        GunClip = 0x31 => "Gun Clip",
+        GunSelector = 0x32 => "Gun Selector",
+        GunSingleShot = 0x33 => "Gun Single Shot",
+        GunBurst = 0x34 => "Gun Burst",
+        GunAutomatic = 0x35 => "Gun Automatic",
+        GunSafety = 0x36 => "Gun Safety",
+        GamepadFireJump = 0x37 => "Gamepad Fire/Jump",
+        GamepadTrigger = 0x39 => "Gamepad Trigger",
+    }
+}
+
+usages! {
+    /// Usages of the LED page (`0x08`).
+    pub enum LedUsage {
+        NumLock = 0x01 => "Num Lock",
+        CapsLock = 0x02 => "Caps Lock",
+        ScrollLock = 0x03 => "Scroll Lock",
+        Compose = 0x04 => "Compose",
+        Kana = 0x05 => "Kana",
+        Power = 0x06 => "Power",
+        Shift = 0x07 => "Shift",
+        DoNotDisturb = 0x08 => "Do Not Disturb",
+        Mute = 0x09 => "Mute",
+        ToneEnable = 0x0a => "Tone Enable",
+        HighCutFilter = 0x0b => "High Cut Filter",
+        LowCutFilter = 0x0c => "Low Cut Filter",
+        EqualizerEnable = 0x0d => "Equalizer Enable",
+        SoundFieldOn = 0x0e => "Sound Field On",
+        SurroundOn = 0x0f => "Surround On",
+        Repeat = 0x10 => "Repeat",
+        Stereo = 0x11 => "Stereo",
+        BatteryOk = 0x1b => "Battery OK",
+        BatteryLow = 0x1d => "Battery Low",
+        Microphone = 0x21 => "Microphone",
+        Standby = 0x27 => "Stand-by",
+        CameraOn = 0x28 => "Camera On",
+        Online = 0x2a => "On-Line",
+        Busy = 0x2c => "Busy",
+        Ready = 0x2d => "Ready",
+        GenericIndicator = 0x4b => "Generic Indicator",
+        SystemSuspend = 0x4c => "System Suspend",
+        ExternalPowerConnected = 0x4d => "External Power Connected",
+    }
+}
+
+usages! {
+    /// Usages of the Consumer page (`0x0C`).
+    pub enum ConsumerUsage {
+        ConsumerControl = 0x01 => "Consumer Control",
+        NumericKeyPad = 0x02 => "Numeric Key Pad",
+        ProgrammableButtons = 0x03 => "Programmable Buttons",
+        Microphone = 0x04 => "Microphone",
+        Headphone = 0x05 => "Headphone",
+        GraphicEqualizer = 0x06 => "Graphic Equalizer",
+        Power = 0x30 => "Power",
+        Menu = 0x40 => "Menu",
+        Play = 0xb0 => "Play",
+        Pause = 0xb1 => "Pause",
+        Record = 0xb2 => "Record",
+        FastForward = 0xb3 => "Fast Forward",
+        Rewind = 0xb4 => "Rewind",
+        ScanNextTrack = 0xb5 => "Scan Next Track",
+        ScanPreviousTrack = 0xb6 => "Scan Previous Track",
+        Stop = 0xb7 => "Stop",
+        Eject = 0xb8 => "Eject",
+        PlayPause = 0xcd => "Play/Pause",
+        Volume = 0xe0 => "Volume",
+        Mute = 0xe2 => "Mute",
+        VolumeIncrement = 0xe9 => "Volume Increment",
+        VolumeDecrement = 0xea => "Volume Decrement",
+        ConsumerControlConfiguration = 0x183 => "AL Consumer Control Configuration",
+        EmailReader = 0x18a => "AL Email Reader",
+        Calculator = 0x192 => "AL Calculator",
+        LocalMachineBrowser = 0x194 => "AL Local Machine Browser",
+        Search = 0x221 => "AC Search",
+        Home = 0x223 => "AC Home",
+        Back = 0x224 => "AC Back",
+        Forward = 0x225 => "AC Forward",
+        BrowserStop = 0x226 => "AC Stop",
+        Refresh = 0x227 => "AC Refresh",
+        Bookmarks = 0x22a => "AC Bookmarks",
+    }
+}
+
+/// A HID usage page and usage ID, decoded for the pages DirectInput devices commonly report.
+///
+/// Displays as `"<page> / <usage>"`, e.g. `"Generic Desktop / Joystick"` or `"Button / Button 3"`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Usage {
+    GenericDesktop(GenericDesktopUsage),
+    Simulation(SimulationUsage),
+    Game(GameUsage),
+    Led(LedUsage),
+    /// Button number, starting at 1. Usage 0 means no button is pressed.
+    Button(u16),
+    /// Instance number, starting at 1.
+    Ordinal(u16),
+    Consumer(ConsumerUsage),
+    /// A usage on a page without a usage table here.
+    Other(UsagePage, u16),
+}
+
+impl Usage {
+    pub fn new(usage_page: u16, usage: u16) -> Self {
+        match UsagePage::from_id(usage_page) {
+            UsagePage::GenericDesktop => Self::GenericDesktop(GenericDesktopUsage::from_id(usage)),
+            UsagePage::Simulation => Self::Simulation(SimulationUsage::from_id(usage)),
+            UsagePage::Game => Self::Game(GameUsage::from_id(usage)),
+            UsagePage::Led => Self::Led(LedUsage::from_id(usage)),
+            UsagePage::Button => Self::Button(usage),
+            UsagePage::Ordinal => Self::Ordinal(usage),
+            UsagePage::Consumer => Self::Consumer(ConsumerUsage::from_id(usage)),
+            page => Self::Other(page, usage),
+        }
+    }
+
+    pub fn page(self) -> UsagePage {
+        match self {
+            Self::GenericDesktop(_) => UsagePage::GenericDesktop,
+            Self::Simulation(_) => UsagePage::Simulation,
+            Self::Game(_) => UsagePage::Game,
+            Self::Led(_) => UsagePage::Led,
+            Self::Button(_) => UsagePage::Button,
+            Self::Ordinal(_) => UsagePage::Ordinal,
+            Self::Consumer(_) => UsagePage::Consumer,
+            Self::Other(page, _) => page,
+        }
+    }
+
+    /// The usage ID within its page.
+    pub fn id(self) -> u16 {
+        match self {
+            Self::GenericDesktop(usage) => usage.id(),
+            Self::Simulation(usage) => usage.id(),
+            Self::Game(usage) => usage.id(),
+            Self::Led(usage) => usage.id(),
+            Self::Button(id) | Self::Ordinal(id) | Self::Other(_, id) => id,
+            Self::Consumer(usage) => usage.id(),
+        }
+    }
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} / ", self.page())?;
+
+        match self {
+            Self::GenericDesktop(usage) => usage.fmt(f),
+            Self::Simulation(usage) => usage.fmt(f),
+            Self::Game(usage) => usage.fmt(f),
+            Self::Led(usage) => usage.fmt(f),
+            Self::Button(0) => f.write_str("No Button Pressed"),
+            Self::Button(id) => write!(f, "Button {}", id),
+            Self::Ordinal(id) => write!(f, "Instance {}", id),
+            Self::Consumer(usage) => usage.fmt(f),
+            Self::Other(_, id) => write!(f, "{:#06x}", id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages() {
+        for id in (0..=0x20).chain(0xfeff..=0xff01).chain(Some(0xffff)) {
+            assert_eq!(UsagePage::from_id(id).id(), id);
+        }
+
+        assert_eq!(UsagePage::from_id(0x01), UsagePage::GenericDesktop);
+        assert_eq!(UsagePage::from_id(0x0f), UsagePage::PhysicalInput);
+        assert_eq!(UsagePage::from_id(0xff00), UsagePage::VendorDefined(0xff00));
+        assert_eq!(UsagePage::from_id(0x11), UsagePage::Other(0x11));
+
+        assert_eq!(UsagePage::Simulation.to_string(), "Simulation Controls");
+        assert_eq!(
+            UsagePage::VendorDefined(0xff01).to_string(),
+            "Vendor Defined 0xff01"
+        );
+        assert_eq!(UsagePage::Other(0x11).to_string(), "0x0011");
+    }
+
+    #[test]
+    fn test_usages() {
+        let cases = [
+            (
+                0x01,
+                0x04,
+                Usage::GenericDesktop(GenericDesktopUsage::Joystick),
+            ),
+            (0x01, 0x30, Usage::GenericDesktop(GenericDesktopUsage::X)),
+            (0x02, 0xc5, Usage::Simulation(SimulationUsage::Brake)),
+            (0x05, 0x20, Usage::Game(GameUsage::PointOfView)),
+            (0x08, 0x02, Usage::Led(LedUsage::CapsLock)),
+            (0x09, 0x03, Usage::Button(3)),
+            (0x0a, 0x02, Usage::Ordinal(2)),
+            (0x0c, 0xcd, Usage::Consumer(ConsumerUsage::PlayPause)),
+            (0x0c, 0x223, Usage::Consumer(ConsumerUsage::Home)),
+            (
+                0x01,
+                0x2f,
+                Usage::GenericDesktop(GenericDesktopUsage::Other(0x2f)),
+            ),
+            (0x07, 0x04, Usage::Other(UsagePage::Keyboard, 0x04)),
+            (
+                0xff00,
+                0x01,
+                Usage::Other(UsagePage::VendorDefined(0xff00), 0x01),
+            ),
+        ];
+
+        for &(page, id, usage) in &cases {
+            assert_eq!(Usage::new(page, id), usage);
+            assert_eq!((usage.page().id(), usage.id()), (page, id));
+        }
+    }
+
+    #[test]
+    fn test_usage_tables_round_trip() {
+        for id in 0..=0x300 {
+            assert_eq!(GenericDesktopUsage::from_id(id).id(), id);
+            assert_eq!(SimulationUsage::from_id(id).id(), id);
+            assert_eq!(GameUsage::from_id(id).id(), id);
+            assert_eq!(LedUsage::from_id(id).id(), id);
+            assert_eq!(ConsumerUsage::from_id(id).id(), id);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let names = [
+            (0x01, 0x04, "Generic Desktop / Joystick"),
+            (0x01, 0x39, "Generic Desktop / Hat Switch"),
+            (0x02, 0xbb, "Simulation Controls / Throttle"),
+            (0x05, 0x01, "Game Controls / 3D Game Controller"),
+            (0x08, 0x01, "LED / Num Lock"),
+            (0x09, 0x00, "Button / No Button Pressed"),
+            (0x09, 0x0c, "Button / Button 12"),
+            (0x0a, 0x01, "Ordinal / Instance 1"),
+            (0x0c, 0xe9, "Consumer / Volume Increment"),
+            (0x01, 0xff, "Generic Desktop / 0x00ff"),
+            (0x0f, 0x21, "Physical Input Device / 0x0021"),
+            (0xff00, 0x01, "Vendor Defined 0xff00 / 0x0001"),
+        ];
+
+        for &(page, id, name) in &names {
+            assert_eq!(Usage::new(page, id).to_string(), name);
+        }
+    }
+}
@@ -0,0 +1,659 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::device::{Device, FromDeviceState};
+use crate::device_capabilities::CapabilityFlags;
+use crate::device_event::DeviceEvent;
+use crate::device_set::DeviceSet;
+use crate::error::DirectInputError;
+
+/// An update published by an [`InputWorker`]. `device` is the index of the device in the order
+/// the devices were added to the [`InputWorkerBuilder`].
+#[derive(Debug)]
+pub enum InputMessage<T> {
+    /// The state read after the device signalled new input.
+    State { device: usize, state: T },
+    /// A buffered input record, if [`InputWorkerBuilder::events`] is enabled.
+    Event { device: usize, event: DeviceEvent },
+    /// The device could not be acquired or lost acquisition, e.g. to another application with
+    /// exclusive access. The worker keeps retrying with backoff.
+    Lost {
+        device: usize,
+        error: DirectInputError,
+    },
+    /// The device was unplugged. The worker keeps retrying until it is plugged back in.
+    Unplugged { device: usize },
+    /// The device was acquired again after being lost or unplugged.
+    Reacquired { device: usize },
+    /// Any other error. `device` is `None` if waiting on the devices failed.
+    Error {
+        device: Option<usize>,
+        error: DirectInputError,
+    },
+}
+
+/// Configures and spawns an [`InputWorker`].
+///
+/// Devices must be fully configured (data format, cooperative level, buffer size for events)
+/// before being added; the worker only acquires them and creates their notification events.
+pub struct InputWorkerBuilder {
+    devices: Vec<Device>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    poll_interval: Duration,
+    states: bool,
+    events: bool,
+    name: Option<String>,
+}
+
+/// Reasons an [`InputWorker`] fails to start.
+#[derive(Debug)]
+pub enum InputWorkerError {
+    /// The initial backoff is zero or longer than the maximum backoff.
+    InvalidBackoff { initial: Duration, max: Duration },
+    /// The poll interval is zero.
+    InvalidPollInterval,
+    /// Neither states nor events are read.
+    NothingToRead,
+    /// The thread could not be spawned.
+    Spawn(io::Error),
+}
+
+/// A thread owning a set of devices that reads their input as it arrives and publishes it over
+/// a channel.
+///
+/// Devices that lose acquisition (`InputLost`, `NotAcquired`, `OtherAppHasPrio`) or are
+/// unplugged are re-acquired automatically, waiting between attempts with an exponential backoff.
+/// Dropping the worker or calling [`stop`](Self::stop) stops the thread within one poll interval.
+pub struct InputWorker<T> {
+    receiver: Receiver<InputMessage<T>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Vec<Device>>>,
+}
+
+/// Acquisition state of a device owned by the worker thread.
+struct Slot {
+    acquired: bool,
+    /// `Lost` or `Unplugged` was reported and `Reacquired` is pending.
+    lost: bool,
+    unplugged: bool,
+    backoff: Duration,
+    retry_at: Instant,
+}
+
+struct Worker<T> {
+    set: DeviceSet,
+    slots: Vec<Slot>,
+    sender: Sender<InputMessage<T>>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    states: bool,
+    events: bool,
+    _state: PhantomData<fn() -> T>,
+}
+
+impl InputWorkerBuilder {
+    /// Creates a builder reading states, with a 100 ms poll interval and a backoff from 50 ms up
+    /// to 2 s.
+    pub fn new() -> Self {
+        Self {
+            devices: Vec::new(),
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            poll_interval: Duration::from_millis(100),
+            states: true,
+            events: false,
+            name: None,
+        }
+    }
+
+    pub fn device(mut self, device: Device) -> Self {
+        self.devices.push(device);
+        self
+    }
+
+    pub fn devices(mut self, devices: impl IntoIterator<Item = Device>) -> Self {
+        self.devices.extend(devices);
+        self
+    }
+
+    /// Delay before the first re-acquisition attempt, doubled after each failed attempt up to
+    /// `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Longest time the worker waits for input before polling the devices again and checking
+    /// whether it should stop.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Whether to publish the device state each time a device signals. Enabled by default.
+    pub fn states(mut self, enabled: bool) -> Self {
+        self.states = enabled;
+        self
+    }
+
+    /// Whether to publish buffered input records each time a device signals. Requires a buffer
+    /// size to have been set on every device.
+    pub fn events(mut self, enabled: bool) -> Self {
+        self.events = enabled;
+        self
+    }
+
+    /// Name of the worker thread.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), InputWorkerError> {
+        if self.initial_backoff == Duration::ZERO || self.initial_backoff > self.max_backoff {
+            return Err(InputWorkerError::InvalidBackoff {
+                initial: self.initial_backoff,
+                max: self.max_backoff,
+            });
+        }
+        if self.poll_interval == Duration::ZERO {
+            return Err(InputWorkerError::InvalidPollInterval);
+        }
+        if !self.states && !self.events {
+            return Err(InputWorkerError::NothingToRead);
+        }
+
+        Ok(())
+    }
+
+    /// Spawns the worker thread reading states of type `T`.
+    pub fn spawn<T>(self) -> Result<InputWorker<T>, InputWorkerError>
+    where
+        T: FromDeviceState + Send + 'static,
+    {
+        self.validate()?;
+
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let poll_interval = self.poll_interval;
+
+        let mut builder = thread::Builder::new();
+        if let Some(name) = self.name {
+            builder = builder.name(name);
+        }
+
+        let mut worker = Worker {
+            set: DeviceSet::new(),
+            slots: Vec::new(),
+            sender,
+            initial_backoff: self.initial_backoff,
+            max_backoff: self.max_backoff,
+            states: self.states,
+            events: self.events,
+            _state: PhantomData,
+        };
+        let devices = self.devices;
+        let thread_stop = stop.clone();
+
+        let thread = builder
+            .spawn(move || {
+                for device in devices {
+                    worker.add(device);
+                }
+                worker.run(&thread_stop, poll_interval);
+                worker.set.into_devices()
+            })
+            .map_err(InputWorkerError::Spawn)?;
+
+        Ok(InputWorker {
+            receiver,
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Default for InputWorkerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> InputWorker<T> {
+    pub fn builder() -> InputWorkerBuilder {
+        InputWorkerBuilder::new()
+    }
+
+    /// The channel receiving the worker's messages. It disconnects once the worker stopped.
+    pub fn receiver(&self) -> &Receiver<InputMessage<T>> {
+        &self.receiver
+    }
+
+    /// Stops the worker thread and returns its devices.
+    pub fn stop(mut self) -> Vec<Device> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Vec<Device> {
+        self.stop.store(true, Ordering::Relaxed);
+
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(devices)) => devices,
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl<T> Drop for InputWorker<T> {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.join();
+        }
+    }
+}
+
+impl<T: FromDeviceState> Worker<T> {
+    fn add(&mut self, device: Device) {
+        let now = Instant::now();
+        let index = self.slots.len();
+
+        self.slots.push(Slot {
+            acquired: false,
+            lost: false,
+            unplugged: false,
+            backoff: self.initial_backoff,
+            retry_at: now,
+        });
+
+        // A device whose event cannot be created is kept, never acquired, so that indices match
+        // the order the devices were added in
+        match self.set.push(device) {
+            Ok(_) => self.try_acquire(index, now),
+            Err(error) => {
+                self.slots[index].retry_at = far_future(now);
+                self.send(InputMessage::Error {
+                    device: Some(index),
+                    error,
+                });
+            }
+        }
+    }
+
+    fn run(&mut self, stop: &AtomicBool, poll_interval: Duration) {
+        while !stop.load(Ordering::Relaxed) {
+            let now = Instant::now();
+
+            for index in 0..self.slots.len() {
+                if self.slots[index].acquired {
+                    if let Err(error) = self.device(index).poll() {
+                        self.handle_error(index, error, now);
+                    }
+                } else if now >= self.slots[index].retry_at {
+                    self.try_acquire(index, now);
+                }
+            }
+
+            let timeout = self
+                .slots
+                .iter()
+                .filter(|slot| !slot.acquired)
+                .map(|slot| slot.retry_at.saturating_duration_since(now))
+                .fold(poll_interval, Duration::min);
+
+            let signalled = match self.set.wait(timeout) {
+                Ok(signalled) => signalled,
+                Err(error) => {
+                    self.send(InputMessage::Error {
+                        device: None,
+                        error,
+                    });
+                    thread::sleep(poll_interval);
+                    continue;
+                }
+            };
+
+            for index in signalled {
+                let slot = &mut self.slots[index];
+
+                if slot.acquired {
+                    self.read(index);
+                } else if slot.unplugged {
+                    // Plugging the device back in signals it, retry right away instead of waiting
+                    // for the backoff
+                    slot.retry_at = Instant::now();
+                }
+            }
+        }
+    }
+
+    fn read(&mut self, index: usize) {
+        if self.states {
+            match self.device(index).get_state::<T>() {
+                Ok(state) => self.send(InputMessage::State {
+                    device: index,
+                    state,
+                }),
+                Err(error) => return self.handle_error(index, error, Instant::now()),
+            }
+        }
+
+        if self.events {
+            match self.device(index).events() {
+                Ok(events) => {
+                    for event in events {
+                        self.send(InputMessage::Event {
+                            device: index,
+                            event,
+                        });
+                    }
+                }
+                Err(error) => self.handle_error(index, error, Instant::now()),
+            }
+        }
+    }
+
+    fn try_acquire(&mut self, index: usize, now: Instant) {
+        if let Err(error) = self.device(index).acquire() {
+            return self.handle_error(index, error, now);
+        }
+
+        let slot = &mut self.slots[index];
+        let recovered = slot.lost;
+
+        slot.acquired = true;
+        slot.lost = false;
+        slot.unplugged = false;
+        slot.backoff = self.initial_backoff;
+
+        if recovered {
+            self.send(InputMessage::Reacquired { device: index });
+        }
+    }
+
+    fn handle_error(&mut self, index: usize, error: DirectInputError, now: Instant) {
+        if !is_acquisition_error(error) {
+            return self.send(InputMessage::Error {
+                device: Some(index),
+                error,
+            });
+        }
+
+        let unplugged = error == DirectInputError::Unplugged
+            || matches!(
+                self.device(index).capabilities(),
+                Ok(caps) if !caps.flags.contains(CapabilityFlags::ATTACHED)
+            );
+
+        let slot = &mut self.slots[index];
+        let message = if unplugged && !slot.unplugged {
+            Some(InputMessage::Unplugged { device: index })
+        } else if !slot.lost {
+            Some(InputMessage::Lost {
+                device: index,
+                error,
+            })
+        } else {
+            None
+        };
+
+        // Retry right away after losing an acquired device, then back off
+        if slot.acquired {
+            slot.retry_at = now;
+        } else {
+            slot.retry_at = now + slot.backoff;
+            slot.backoff = (slot.backoff * 2).min(self.max_backoff);
+        }
+        slot.acquired = false;
+        slot.lost = true;
+        slot.unplugged |= unplugged;
+
+        if let Some(message) = message {
+            self.send(message);
+        }
+    }
+
+    fn device(&mut self, index: usize) -> &mut Device {
+        self.set.get_mut(index).unwrap()
+    }
+
+    fn send(&self, message: InputMessage<T>) {
+        // The receiver lives as long as the worker, which stops the thread before dropping it
+        let _ = self.sender.send(message);
+    }
+}
+
+/// An instant no retry is ever scheduled past, as `Instant` has no maximum.
+fn far_future(now: Instant) -> Instant {
+    now + Duration::from_secs(60 * 60 * 24 * 365)
+}
+
+fn is_acquisition_error(error: DirectInputError) -> bool {
+    matches!(
+        error,
+        DirectInputError::InputLost
+            | DirectInputError::NotAcquired
+            | DirectInputError::OtherAppHasPrio
+            | DirectInputError::Unplugged
+    )
+}
+
+impl fmt::Display for InputWorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidBackoff { initial, max } => write!(
+                f,
+                "backoff from {:?} to {:?} is empty or decreasing",
+                initial, max
+            ),
+            Self::InvalidPollInterval => f.write_str("poll interval is zero"),
+            Self::NothingToRead => f.write_str("neither states nor events are read"),
+            Self::Spawn(_) => f.write_str("failed to spawn the worker thread"),
+        }
+    }
+}
+
+impl Error for InputWorkerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Spawn(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::DIJOYSTATE2;
+
+    use super::*;
+    use crate::backend::sim::{SimulatedCall, SimulatedDevice};
+    use crate::joy_state::JoyState;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn simulated(sim: &SimulatedDevice) -> Device {
+        let mut device = Device::with_backend(sim.clone());
+        device.init_with::<JoyState>().unwrap();
+        device
+    }
+
+    fn spawn(devices: Vec<Device>) -> InputWorker<JoyState> {
+        InputWorkerBuilder::new()
+            .devices(devices)
+            .backoff(Duration::from_millis(10), Duration::from_millis(40))
+            .poll_interval(Duration::from_millis(10))
+            .spawn()
+            .unwrap()
+    }
+
+    fn recv(worker: &InputWorker<JoyState>) -> InputMessage<JoyState> {
+        worker.receiver().recv_timeout(TIMEOUT).unwrap()
+    }
+
+    /// Waits for the worker thread to acquire the devices, signals sent before are missed.
+    fn wait_acquired(sims: &[SimulatedDevice]) {
+        let start = Instant::now();
+
+        while !sims.iter().all(SimulatedDevice::is_acquired) {
+            assert!(start.elapsed() < TIMEOUT);
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_publishes_states() {
+        let sims: Vec<_> = (0..2)
+            .map(|i| SimulatedDevice::new(&format!("Simulated Pad {}", i)).with_axes(2))
+            .collect();
+        let worker = spawn(sims.iter().map(simulated).collect());
+        wait_acquired(&sims);
+
+        sims[1].push_raw(&DIJOYSTATE2 {
+            lX: 1,
+            lY: 2,
+            ..Default::default()
+        });
+        match recv(&worker) {
+            InputMessage::State { device, state } => {
+                assert_eq!(device, 1);
+                assert_eq!((state.x, state.y), (1, 2));
+            }
+            message => panic!("Unexpected message {:?}", message),
+        }
+
+        let devices = worker.stop();
+        assert_eq!(devices.len(), 2);
+        assert!(sims.iter().all(SimulatedDevice::is_acquired));
+    }
+
+    #[test]
+    fn test_reacquires_after_unplug() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_axes(2);
+        let worker = spawn(vec![simulated(&sim)]);
+        wait_acquired(std::slice::from_ref(&sim));
+
+        sim.unplug();
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::Unplugged { device: 0 }
+        ));
+
+        sim.plug();
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::Reacquired { device: 0 }
+        ));
+        assert!(sim.is_acquired());
+
+        sim.push_raw(&DIJOYSTATE2 {
+            lX: 3,
+            ..Default::default()
+        });
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::State { device: 0, state } if state.x == 3
+        ));
+    }
+
+    #[test]
+    fn test_event_failure_keeps_indices() {
+        let broken = SimulatedDevice::new("Simulated Pad 0").with_axes(2);
+        broken.fail_next(SimulatedCall::InitEvent, DirectInputError::Generic);
+        let sim = SimulatedDevice::new("Simulated Pad 1").with_axes(2);
+
+        let worker = spawn(vec![simulated(&broken), simulated(&sim)]);
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::Error {
+                device: Some(0),
+                error: DirectInputError::Generic
+            }
+        ));
+        wait_acquired(std::slice::from_ref(&sim));
+
+        sim.push_raw(&DIJOYSTATE2 {
+            lX: 4,
+            ..Default::default()
+        });
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::State { device: 1, state } if state.x == 4
+        ));
+        assert!(!broken.is_acquired());
+        assert_eq!(worker.stop().len(), 2);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_axes(2);
+        for _ in 0..3 {
+            sim.fail_next(SimulatedCall::Acquire, DirectInputError::OtherAppHasPrio);
+        }
+
+        let start = Instant::now();
+        let worker = spawn(vec![simulated(&sim)]);
+
+        // Failed attempts are only reported once
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::Lost {
+                device: 0,
+                error: DirectInputError::OtherAppHasPrio
+            }
+        ));
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::Reacquired { device: 0 }
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(10 + 20 + 40));
+
+        sim.fail_next(
+            SimulatedCall::GetDeviceState,
+            DirectInputError::InvalidParam,
+        );
+        sim.signal();
+        assert!(matches!(
+            recv(&worker),
+            InputMessage::Error {
+                device: Some(0),
+                error: DirectInputError::InvalidParam
+            }
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let validate = |builder: InputWorkerBuilder| builder.validate();
+
+        assert!(validate(InputWorker::<JoyState>::builder()).is_ok());
+        assert!(matches!(
+            validate(InputWorkerBuilder::new().backoff(Duration::ZERO, Duration::from_secs(1))),
+            Err(InputWorkerError::InvalidBackoff { .. })
+        ));
+        assert!(matches!(
+            validate(
+                InputWorkerBuilder::new().backoff(Duration::from_secs(2), Duration::from_secs(1))
+            ),
+            Err(InputWorkerError::InvalidBackoff { .. })
+        ));
+        assert!(matches!(
+            validate(InputWorkerBuilder::new().poll_interval(Duration::ZERO)),
+            Err(InputWorkerError::InvalidPollInterval)
+        ));
+        assert!(matches!(
+            validate(InputWorkerBuilder::new().states(false)),
+            Err(InputWorkerError::NothingToRead)
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 use windows::Win32::Devices::HumanInterfaceDevice::DIJOYSTATE2;
 
+use super::data_format::DataFormat;
 use super::device::FromDeviceState;
+use super::pov::Pov;
 
 #[derive(Debug)]
 pub struct JoyState {
@@ -36,9 +38,22 @@ pub struct JoyState {
     pub f_slider: [i32; 2],
 }
 
+impl JoyState {
+    /// The POV hats decoded from [`pov`](Self::pov).
+    pub fn povs(&self) -> [Pov; 4] {
+        let [a, b, c, d] = self.pov;
+
+        [Pov(a), Pov(b), Pov(c), Pov(d)]
+    }
+}
+
 impl FromDeviceState for JoyState {
     type RawState = DIJOYSTATE2;
 
+    fn data_format() -> DataFormat {
+        DataFormat::joystick2()
+    }
+
     fn from_instance(state: Self::RawState) -> Self {
         Self {
             x: state.lX,
@@ -0,0 +1,454 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Index;
+
+use crate::data_format::DataFormat;
+use crate::device::FromDeviceState;
+
+/// Keyboard scan codes as reported by DirectInput, equivalent to the `DIK_*` constants. Layout
+/// dependent keys are named after their position on a US keyboard.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(u8)]
+pub enum ScanCode {
+    Escape = 0x01,
+    Key1 = 0x02,
+    Key2 = 0x03,
+    Key3 = 0x04,
+    Key4 = 0x05,
+    Key5 = 0x06,
+    Key6 = 0x07,
+    Key7 = 0x08,
+    Key8 = 0x09,
+    Key9 = 0x0A,
+    Key0 = 0x0B,
+    Minus = 0x0C,
+    Equals = 0x0D,
+    Back = 0x0E,
+    Tab = 0x0F,
+    Q = 0x10,
+    W = 0x11,
+    E = 0x12,
+    R = 0x13,
+    T = 0x14,
+    Y = 0x15,
+    U = 0x16,
+    I = 0x17,
+    O = 0x18,
+    P = 0x19,
+    LBracket = 0x1A,
+    RBracket = 0x1B,
+    Return = 0x1C,
+    LControl = 0x1D,
+    A = 0x1E,
+    S = 0x1F,
+    D = 0x20,
+    F = 0x21,
+    G = 0x22,
+    H = 0x23,
+    J = 0x24,
+    K = 0x25,
+    L = 0x26,
+    Semicolon = 0x27,
+    Apostrophe = 0x28,
+    Grave = 0x29,
+    LShift = 0x2A,
+    Backslash = 0x2B,
+    Z = 0x2C,
+    X = 0x2D,
+    C = 0x2E,
+    V = 0x2F,
+    B = 0x30,
+    N = 0x31,
+    M = 0x32,
+    Comma = 0x33,
+    Period = 0x34,
+    Slash = 0x35,
+    RShift = 0x36,
+    Multiply = 0x37,
+    LMenu = 0x38,
+    Space = 0x39,
+    Capital = 0x3A,
+    F1 = 0x3B,
+    F2 = 0x3C,
+    F3 = 0x3D,
+    F4 = 0x3E,
+    F5 = 0x3F,
+    F6 = 0x40,
+    F7 = 0x41,
+    F8 = 0x42,
+    F9 = 0x43,
+    F10 = 0x44,
+    NumLock = 0x45,
+    Scroll = 0x46,
+    Numpad7 = 0x47,
+    Numpad8 = 0x48,
+    Numpad9 = 0x49,
+    Subtract = 0x4A,
+    Numpad4 = 0x4B,
+    Numpad5 = 0x4C,
+    Numpad6 = 0x4D,
+    Add = 0x4E,
+    Numpad1 = 0x4F,
+    Numpad2 = 0x50,
+    Numpad3 = 0x51,
+    Numpad0 = 0x52,
+    Decimal = 0x53,
+    Oem102 = 0x56,
+    F11 = 0x57,
+    F12 = 0x58,
+    F13 = 0x64,
+    F14 = 0x65,
+    F15 = 0x66,
+    Kana = 0x70,
+    AbntC1 = 0x73,
+    Convert = 0x79,
+    NoConvert = 0x7B,
+    Yen = 0x7D,
+    AbntC2 = 0x7E,
+    NumpadEquals = 0x8D,
+    PrevTrack = 0x90,
+    At = 0x91,
+    Colon = 0x92,
+    Underline = 0x93,
+    Kanji = 0x94,
+    Stop = 0x95,
+    Ax = 0x96,
+    Unlabeled = 0x97,
+    NextTrack = 0x99,
+    NumpadEnter = 0x9C,
+    RControl = 0x9D,
+    Mute = 0xA0,
+    Calculator = 0xA1,
+    PlayPause = 0xA2,
+    MediaStop = 0xA4,
+    VolumeDown = 0xAE,
+    VolumeUp = 0xB0,
+    WebHome = 0xB2,
+    NumpadComma = 0xB3,
+    Divide = 0xB5,
+    SysRq = 0xB7,
+    RMenu = 0xB8,
+    Pause = 0xC5,
+    Home = 0xC7,
+    Up = 0xC8,
+    Prior = 0xC9,
+    Left = 0xCB,
+    Right = 0xCD,
+    End = 0xCF,
+    Down = 0xD0,
+    Next = 0xD1,
+    Insert = 0xD2,
+    Delete = 0xD3,
+    LWin = 0xDB,
+    RWin = 0xDC,
+    Apps = 0xDD,
+    Power = 0xDE,
+    Sleep = 0xDF,
+    Wake = 0xE3,
+    WebSearch = 0xE5,
+    WebFavorites = 0xE6,
+    WebRefresh = 0xE7,
+    WebStop = 0xE8,
+    WebForward = 0xE9,
+    WebBack = 0xEA,
+    MyComputer = 0xEB,
+    Mail = 0xEC,
+    MediaSelect = 0xED,
+}
+
+/// State of all 256 keys of a keyboard, read with the `c_dfDIKeyboard` data format.
+#[derive(Clone, Copy)]
+pub struct KeyboardState {
+    keys: [u8; 256],
+}
+
+impl ScanCode {
+    /// The raw `DIK_*` value, which is also the offset of the key in a [`KeyboardState`] and the
+    /// object offset reported by buffered events.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+impl TryFrom<u8> for ScanCode {
+    type Error = u8;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        use ScanCode::*;
+
+        Ok(match code {
+            0x01 => Escape,
+            0x02 => Key1,
+            0x03 => Key2,
+            0x04 => Key3,
+            0x05 => Key4,
+            0x06 => Key5,
+            0x07 => Key6,
+            0x08 => Key7,
+            0x09 => Key8,
+            0x0A => Key9,
+            0x0B => Key0,
+            0x0C => Minus,
+            0x0D => Equals,
+            0x0E => Back,
+            0x0F => Tab,
+            0x10 => Q,
+            0x11 => W,
+            0x12 => E,
+            0x13 => R,
+            0x14 => T,
+            0x15 => Y,
+            0x16 => U,
+            0x17 => I,
+            0x18 => O,
+            0x19 => P,
+            0x1A => LBracket,
+            0x1B => RBracket,
+            0x1C => Return,
+            0x1D => LControl,
+            0x1E => A,
+            0x1F => S,
+            0x20 => D,
+            0x21 => F,
+            0x22 => G,
+            0x23 => H,
+            0x24 => J,
+            0x25 => K,
+            0x26 => L,
+            0x27 => Semicolon,
+            0x28 => Apostrophe,
+            0x29 => Grave,
+            0x2A => LShift,
+            0x2B => Backslash,
+            0x2C => Z,
+            0x2D => X,
+            0x2E => C,
+            0x2F => V,
+            0x30 => B,
+            0x31 => N,
+            0x32 => M,
+            0x33 => Comma,
+            0x34 => Period,
+            0x35 => Slash,
+            0x36 => RShift,
+            0x37 => Multiply,
+            0x38 => LMenu,
+            0x39 => Space,
+            0x3A => Capital,
+            0x3B => F1,
+            0x3C => F2,
+            0x3D => F3,
+            0x3E => F4,
+            0x3F => F5,
+            0x40 => F6,
+            0x41 => F7,
+            0x42 => F8,
+            0x43 => F9,
+            0x44 => F10,
+            0x45 => NumLock,
+            0x46 => Scroll,
+            0x47 => Numpad7,
+            0x48 => Numpad8,
+            0x49 => Numpad9,
+            0x4A => Subtract,
+            0x4B => Numpad4,
+            0x4C => Numpad5,
+            0x4D => Numpad6,
+            0x4E => Add,
+            0x4F => Numpad1,
+            0x50 => Numpad2,
+            0x51 => Numpad3,
+            0x52 => Numpad0,
+            0x53 => Decimal,
+            0x56 => Oem102,
+            0x57 => F11,
+            0x58 => F12,
+            0x64 => F13,
+            0x65 => F14,
+            0x66 => F15,
+            0x70 => Kana,
+            0x73 => AbntC1,
+            0x79 => Convert,
+            0x7B => NoConvert,
+            0x7D => Yen,
+            0x7E => AbntC2,
+            0x8D => NumpadEquals,
+            0x90 => PrevTrack,
+            0x91 => At,
+            0x92 => Colon,
+            0x93 => Underline,
+            0x94 => Kanji,
+            0x95 => Stop,
+            0x96 => Ax,
+            0x97 => Unlabeled,
+            0x99 => NextTrack,
+            0x9C => NumpadEnter,
+            0x9D => RControl,
+            0xA0 => Mute,
+            0xA1 => Calculator,
+            0xA2 => PlayPause,
+            0xA4 => MediaStop,
+            0xAE => VolumeDown,
+            0xB0 => VolumeUp,
+            0xB2 => WebHome,
+            0xB3 => NumpadComma,
+            0xB5 => Divide,
+            0xB7 => SysRq,
+            0xB8 => RMenu,
+            0xC5 => Pause,
+            0xC7 => Home,
+            0xC8 => Up,
+            0xC9 => Prior,
+            0xCB => Left,
+            0xCD => Right,
+            0xCF => End,
+            0xD0 => Down,
+            0xD1 => Next,
+            0xD2 => Insert,
+            0xD3 => Delete,
+            0xDB => LWin,
+            0xDC => RWin,
+            0xDD => Apps,
+            0xDE => Power,
+            0xDF => Sleep,
+            0xE3 => Wake,
+            0xE5 => WebSearch,
+            0xE6 => WebFavorites,
+            0xE7 => WebRefresh,
+            0xE8 => WebStop,
+            0xE9 => WebForward,
+            0xEA => WebBack,
+            0xEB => MyComputer,
+            0xEC => Mail,
+            0xED => MediaSelect,
+            _ => return Err(code),
+        })
+    }
+}
+
+impl KeyboardState {
+    pub fn is_pressed(&self, key: ScanCode) -> bool {
+        self.keys[key as usize] & 0x80 != 0
+    }
+
+    /// Iterates over the pressed keys in scan code order. Keys without a [`ScanCode`] variant are
+    /// skipped; use [`raw`](Self::raw) to inspect them.
+    pub fn pressed(&self) -> impl Iterator<Item = ScanCode> + '_ {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| *state & 0x80 != 0)
+            .filter_map(|(code, _)| ScanCode::try_from(code as u8).ok())
+    }
+
+    /// Raw key states indexed by scan code. The high bit is set while a key is pressed.
+    pub fn raw(&self) -> &[u8; 256] {
+        &self.keys
+    }
+}
+
+impl Index<ScanCode> for KeyboardState {
+    type Output = u8;
+
+    fn index(&self, key: ScanCode) -> &Self::Output {
+        &self.keys[key as usize]
+    }
+}
+
+impl fmt::Debug for KeyboardState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyboardState")
+            .field("pressed", &self.pressed().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl FromDeviceState for KeyboardState {
+    type RawState = [u8; 256];
+
+    fn data_format() -> DataFormat {
+        DataFormat::keyboard()
+    }
+
+    fn from_instance(state: Self::RawState) -> Self {
+        Self { keys: state }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::{
+        DI8DEVTYPEKEYBOARD_PCENH, DI8DEVTYPE_KEYBOARD,
+    };
+
+    use super::*;
+    use crate::backend::sim::SimulatedDevice;
+    use crate::device::Device;
+
+    #[test]
+    fn test_scan_code_round_trip() {
+        let mut known = 0;
+
+        for code in 0..=u8::MAX {
+            if let Ok(key) = ScanCode::try_from(code) {
+                assert_eq!(key.code(), code);
+                known += 1;
+            }
+        }
+
+        assert_eq!(known, 144);
+        assert_eq!(ScanCode::try_from(0x1e), Ok(ScanCode::A));
+        assert_eq!(ScanCode::try_from(0x00), Err(0x00));
+        assert_eq!(ScanCode::try_from(0xff), Err(0xff));
+    }
+
+    #[test]
+    fn test_keyboard_state() {
+        let mut keys = [0u8; 256];
+        keys[ScanCode::A.code() as usize] = 0x80;
+        keys[ScanCode::LShift.code() as usize] = 0x81;
+        keys[ScanCode::B.code() as usize] = 0x7f;
+        keys[0xff] = 0x80;
+
+        let state = KeyboardState::from_instance(keys);
+        assert!(state.is_pressed(ScanCode::A));
+        assert!(state.is_pressed(ScanCode::LShift));
+        assert!(!state.is_pressed(ScanCode::B));
+        assert_eq!(state[ScanCode::LShift], 0x81);
+        assert_eq!(state.raw()[0xff], 0x80);
+
+        // Unknown codes are skipped
+        assert_eq!(
+            state.pressed().collect::<Vec<_>>(),
+            [ScanCode::A, ScanCode::LShift]
+        );
+        assert_eq!(
+            format!("{:?}", state),
+            "KeyboardState { pressed: [A, LShift] }"
+        );
+        assert_eq!(KeyboardState::data_format().data_size(), 256);
+    }
+
+    #[test]
+    fn test_simulated_keyboard() {
+        let sim = SimulatedDevice::new("Simulated Keyboard")
+            .with_dev_type(DI8DEVTYPE_KEYBOARD | (DI8DEVTYPEKEYBOARD_PCENH << 8));
+        let mut device = Device::with_backend(sim.clone());
+        device.init().expect("Failed to initialize device");
+        device.acquire().expect("Failed to acquire device");
+
+        let mut keys = [0u8; 256];
+        keys[ScanCode::A.code() as usize] = 0x80;
+        keys[ScanCode::LShift.code() as usize] = 0x80;
+        keys[0xff] = 0x80;
+        sim.push_state(&keys);
+
+        let state = device
+            .get_state::<KeyboardState>()
+            .expect("Failed to get state");
+        assert_eq!(
+            state.pressed().collect::<Vec<_>>(),
+            [ScanCode::A, ScanCode::LShift]
+        );
+    }
+}
@@ -1,18 +1,108 @@
+// Allows the paths generated by `#[derive(DeviceState)]` to resolve inside this crate
+extern crate self as directinput;
+
+mod action_map;
+pub mod backend;
+mod calibration;
 mod cooperative_level;
+mod data_format;
+mod dead_zone;
 mod device;
 mod device_capabilities;
+mod device_class;
+mod device_event;
+mod device_guid;
 mod device_info;
+mod device_object;
+mod device_path;
+mod device_set;
+#[cfg(feature = "async")]
+mod device_stream;
+mod device_type;
+mod device_watcher;
+mod effect;
+mod effect_file;
+mod enum_options;
 mod error;
+mod hid_usage;
+mod input_worker;
 mod joy_state;
+mod keyboard_state;
 mod manager;
+mod mouse_state;
+mod pov;
+mod property;
+mod response_curve;
+mod state_diff;
+mod util;
 
+pub use crate::action_map::{
+    Action, ActionFlags, ActionFormat, ActionFormatBuilder, ActionFormatError, ActionMap,
+    ActionMapping, BuildActionMapFlags, MappingSource, SemanticDevice, SemanticDeviceFlags,
+    SemanticEnumFlags, SetActionMapFlags,
+};
+pub use crate::calibration::{
+    AxisCalibration, AxisPolarity, CalibrationStore, DeviceCalibration, JoyAxis, NormalizedAxes,
+    DEFAULT_AXIS_RANGE,
+};
 pub use crate::cooperative_level::CooperativeLevel;
-pub use crate::device::Device;
-pub use crate::device_capabilities::DeviceCapabilities;
+pub use crate::data_format::{
+    CustomLayout, CustomState, DataFormat, DataFormatBuilder, DataFormatError, LayoutState,
+    ObjectFormat, ObjectKind, DIDFT_OPTIONAL,
+};
+pub use crate::dead_zone::{DeadZoneMode, DeadZones, StickDeadZone};
+pub use crate::device::{Device, FromDeviceState};
+pub use crate::device_capabilities::{CapabilityFlags, DeviceCapabilities};
+pub use crate::device_class::DeviceClass;
+pub use crate::device_event::{DeviceEvent, DeviceEvents};
+pub use crate::device_guid::{DeviceGuid, ParseGuidError};
 pub use crate::device_info::DirectInputDeviceInfo;
-pub use crate::error::{DirectInputError, DirectInputStatus};
+pub use crate::device_object::DeviceObject;
+pub use crate::device_path::DevicePath;
+pub use crate::device_set::DeviceSet;
+#[cfg(feature = "async")]
+pub use crate::device_stream::{EventStream, StateStream};
+pub use crate::device_type::{
+    DeviceControlSubtype, DeviceType, DrivingSubtype, FirstPersonSubtype, FlightSubtype,
+    GamepadSubtype, JoystickSubtype, KeyboardSubtype, MouseSubtype, RemoteSubtype,
+    ScreenPointerSubtype, SupplementalSubtype,
+};
+pub use crate::device_watcher::{DeviceChange, DeviceWatcher};
+pub use crate::effect::{
+    Condition, ConditionKind, ConstantForce, CustomForce, Effect, EffectDirection, EffectHandle,
+    EffectInfo, EffectKind, EffectParams, EffectStartFlags, EffectStatus, EffectType, Envelope,
+    Periodic, RampForce, Waveform,
+};
+pub use crate::effect_file::{EffectFile, EffectFileError, FileEffect};
+pub use crate::enum_options::EnumOptions;
+pub use crate::error::{DirectInputError, DirectInputStatus, HResultContext};
+pub use crate::hid_usage::{
+    ConsumerUsage, GameUsage, GenericDesktopUsage, LedUsage, SimulationUsage, Usage, UsagePage,
+};
+pub use crate::input_worker::{InputMessage, InputWorker, InputWorkerBuilder, InputWorkerError};
 pub use crate::joy_state::JoyState;
+pub use crate::keyboard_state::{KeyboardState, ScanCode};
 pub use crate::manager::DirectInputManager;
+pub use crate::mouse_state::{MouseAxis, MouseState};
+pub use crate::pov::{Direction4, Direction8, Pov, PovButtons};
+pub use crate::property::{
+    AutoCenter, AxisMode, BufferSize, CalibrationMode, DeadZone, FfGain, Granularity, GuidAndPath,
+    InstanceName, JoystickId, ProductName, Property, PropertyTarget, Range, Saturation,
+    SettableProperty, TypeName, VidPid,
+};
+pub use crate::response_curve::{AxisCurves, CurveError, CurvePoints, ResponseCurve};
+pub use crate::state_diff::{DiffableState, StateDiff, StateEvent};
+
+#[cfg(feature = "derive")]
+pub use directinput_derive::DeviceState;
+
+/// Items used by the code generated by `#[derive(DeviceState)]`.
+#[doc(hidden)]
+pub mod __private {
+    pub use windows::Win32::Devices::HumanInterfaceDevice::{
+        GUID_RxAxis, GUID_RyAxis, GUID_RzAxis, GUID_Slider, GUID_XAxis, GUID_YAxis, GUID_ZAxis,
+    };
+}
 
 #[doc(hidden)]
 #[inline]
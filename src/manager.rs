@@ -1,19 +1,22 @@
 use std::ffi::c_void;
+use std::fmt;
+use std::ops::ControlFlow;
 
-use windows::core::Interface;
-use windows::Win32::Devices::HumanInterfaceDevice::{
-    DirectInput8Create, IDirectInput8W, IDirectInputDevice8W, DI8DEVCLASS_GAMECTRL,
-    DIDEVICEINSTANCEW, DIEDFL_ALLDEVICES, DIENUM_CONTINUE, DIRECTINPUT_VERSION,
-};
-use windows::Win32::Foundation::{BOOL, HINSTANCE};
+use windows::Win32::Foundation::HINSTANCE;
 
+use crate::action_map::{
+    ActionFormat, ActionMap, SemanticDevice, SemanticDeviceFlags, SemanticEnumFlags,
+};
+use crate::backend::{Backend, DirectInput8Backend};
 use crate::device::Device;
+use crate::device_class::DeviceClass;
+use crate::device_guid::DeviceGuid;
 use crate::device_info::DirectInputDeviceInfo;
-use crate::error::{DirectInputError, Result};
+use crate::enum_options::EnumOptions;
+use crate::error::{DirectInputStatus, Result};
 
-#[derive(Debug)]
 pub struct DirectInputManager {
-    iface: IDirectInput8W,
+    backend: Box<dyn Backend>,
 }
 
 pub trait IntoModuleInstance {
@@ -34,66 +37,131 @@ impl IntoModuleInstance for HINSTANCE {
 
 impl DirectInputManager {
     pub fn new(instance: impl IntoModuleInstance) -> Result<Self> {
-        let mut iface: Option<IDirectInput8W> = None;
-
-        unsafe {
-            DirectInput8Create(
-                instance.into_instance(),
-                DIRECTINPUT_VERSION,
-                &IDirectInput8W::IID,
-                &mut iface as *mut _ as _,
-                None,
-            )?;
-        };
+        Ok(Self::with_backend(DirectInput8Backend::new(instance)?))
+    }
 
-        match iface {
-            Some(iface) => Ok(Self { iface }),
-            None => Err(DirectInputError::BadDriverVersion),
+    /// Creates a manager on top of an alternative backend such as
+    /// [`SimulatedBackend`](crate::backend::sim::SimulatedBackend).
+    pub fn with_backend(backend: impl Backend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
         }
     }
 
+    /// Enumerates all game controllers, including detached ones. Use
+    /// [`enum_devices_with`](Self::enum_devices_with) and [`EnumOptions::attached_only`] to skip
+    /// them.
     pub fn enum_devices(&self) -> Result<Vec<DirectInputDeviceInfo>> {
-        extern "system" fn enumeration_callback(
-            device_instance: *mut DIDEVICEINSTANCEW,
-            ctx: *mut c_void,
-        ) -> BOOL {
-            let devices = unsafe { &mut *(ctx as *mut Vec<DirectInputDeviceInfo>) };
-
-            if !device_instance.is_null() {
-                devices.push(DirectInputDeviceInfo::from_instance(unsafe {
-                    &*device_instance
-                }));
+        self.enum_devices_by_class(DeviceClass::GameController)
+    }
+
+    /// Enumerates all devices of the given class, including detached ones.
+    pub fn enum_devices_by_class(&self, class: DeviceClass) -> Result<Vec<DirectInputDeviceInfo>> {
+        self.enum_devices_with(&EnumOptions::new().class(class))
+    }
+
+    /// Enumerates the devices matching `options`.
+    pub fn enum_devices_with(&self, options: &EnumOptions) -> Result<Vec<DirectInputDeviceInfo>> {
+        let mut devices = Vec::new();
+
+        self.for_each_device(options, |info| {
+            devices.push(info);
+            ControlFlow::Continue(())
+        })?;
+
+        Ok(devices)
+    }
+
+    /// Calls `f` for each device matching `options` until it returns `ControlFlow::Break`, which
+    /// stops the enumeration (`DIENUM_STOP`).
+    pub fn for_each_device<F>(&self, options: &EnumOptions, mut f: F) -> Result<()>
+    where
+        F: FnMut(DirectInputDeviceInfo) -> ControlFlow<()>,
+    {
+        self.backend
+            .enum_devices(options.filter(), options.flags(), &mut |instance| {
+                matches!(
+                    f(DirectInputDeviceInfo::from_instance(instance)),
+                    ControlFlow::Continue(())
+                )
+            })
+    }
+
+    /// Returns the first device matching `options` and `predicate`, without enumerating the
+    /// remaining devices.
+    pub fn find_device<P>(
+        &self,
+        options: &EnumOptions,
+        mut predicate: P,
+    ) -> Result<Option<DirectInputDeviceInfo>>
+    where
+        P: FnMut(&DirectInputDeviceInfo) -> bool,
+    {
+        let mut found = None;
+
+        self.for_each_device(options, |info| {
+            if predicate(&info) {
+                found = Some(info);
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
             }
+        })?;
 
-            BOOL(DIENUM_CONTINUE as _)
-        }
+        Ok(found)
+    }
 
+    /// Enumerates the devices suited to the actions of `format`, best matches first as ordered by
+    /// DirectInput. `user` selects the user whose devices are considered with
+    /// `SemanticEnumFlags::THIS_USER` and defaults to the current user.
+    pub fn enum_devices_by_semantics(
+        &self,
+        format: &ActionFormat,
+        user: Option<&str>,
+        flags: SemanticEnumFlags,
+    ) -> Result<Vec<SemanticDevice>> {
+        let mut raw = ActionMap::new(format.clone()).to_raw();
         let mut devices = Vec::new();
 
         unsafe {
-            self.iface.EnumDevices(
-                DI8DEVCLASS_GAMECTRL,
-                Some(enumeration_callback),
-                &mut devices as *mut Vec<DirectInputDeviceInfo> as _,
-                DIEDFL_ALLDEVICES,
+            self.backend.enum_devices_by_semantics(
+                user,
+                raw.as_mut_raw(),
+                flags.bits(),
+                &mut |instance, device_flags| {
+                    devices.push(SemanticDevice {
+                        info: DirectInputDeviceInfo::from_instance(instance),
+                        flags: SemanticDeviceFlags::from_bits_truncate(device_flags),
+                    });
+                    true
+                },
             )?;
-        };
+        }
 
         Ok(devices)
     }
 
     pub fn create_device(&self, device_info: &DirectInputDeviceInfo) -> Result<Device> {
-        let mut iface: Option<IDirectInputDevice8W> = None;
+        self.create_device_by_guid(device_info.guid_instance())
+    }
 
-        unsafe {
-            self.iface
-                .CreateDevice(device_info.guid_instance(), &mut iface, None)?;
-        };
+    /// Checks whether the device with the given instance GUID is still attached, returning
+    /// `DirectInputStatus::Ok` if it is and `DirectInputStatus::NotAttached` otherwise.
+    pub fn device_status(&self, guid_instance: DeviceGuid) -> Result<DirectInputStatus> {
+        self.backend.get_device_status(guid_instance.as_guid())
+    }
 
-        match iface {
-            Some(device) => Ok(Device::new(device)),
-            None => Err(DirectInputError::InputLost),
-        }
+    /// Creates a device from its instance GUID, such as one saved from an earlier enumeration.
+    pub fn create_device_by_guid(&self, guid_instance: DeviceGuid) -> Result<Device> {
+        let backend = self.backend.create_device(guid_instance.as_guid())?;
+
+        Ok(Device::new(backend))
+    }
+}
+
+impl fmt::Debug for DirectInputManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DirectInputManager").finish()
     }
 }
 
@@ -101,29 +169,71 @@ impl DirectInputManager {
 mod tests {
     use std::time::Duration;
 
+    use windows::core::GUID;
+    use windows::Win32::Devices::HumanInterfaceDevice::{
+        GUID_XAxis, DI8DEVTYPEKEYBOARD_PCENH, DI8DEVTYPEMOUSE_TRADITIONAL, DI8DEVTYPE_KEYBOARD,
+        DI8DEVTYPE_MOUSE, DIAXIS_DRIVINGR_ACCELERATE, DIAXIS_DRIVINGR_STEER,
+        DIBUTTON_DRIVINGR_PAUSE, DIBUTTON_DRIVINGR_SHIFTUP, DIDFT_ABSAXIS,
+        DIHATSWITCH_DRIVINGR_GLANCE, DIJOYSTATE2, DIKEYBOARD_A, DIVIRTUAL_DRIVING_RACE,
+    };
+
     use super::*;
-    use crate::bindings::Windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use crate::action_map::{ActionMapping, BuildActionMapFlags, MappingSource, SetActionMapFlags};
+    use crate::backend::sim::{SimulatedBackend, SimulatedCall, SimulatedDevice};
+    use crate::data_format::ObjectKind;
+    use crate::device_capabilities::CapabilityFlags;
+    use crate::device_type::{DeviceType, KeyboardSubtype, MouseSubtype};
+    use crate::error::{DirectInputError, DirectInputStatus};
+    use crate::hid_usage::{GenericDesktopUsage, Usage};
     use crate::joy_state::JoyState;
 
+    fn simulated(device: &SimulatedDevice) -> Device {
+        let backend = SimulatedBackend::new();
+        backend.add_device(device.clone());
+
+        let manager = DirectInputManager::with_backend(backend);
+        let devices = manager.enum_devices().expect("Failed to enumerate devices");
+        assert_eq!(devices.len(), 1);
+
+        let mut device = manager
+            .create_device(&devices[0])
+            .expect("Failed to create device instance");
+        device.init().expect("Failed to initialize device");
+
+        device
+    }
+
+    fn joystick(x: i32) -> DIJOYSTATE2 {
+        DIJOYSTATE2 {
+            lX: x,
+            ..Default::default()
+        }
+    }
+
+    fn axis_id(instance: u32) -> u32 {
+        DIDFT_ABSAXIS | (instance << 8)
+    }
+
+    #[cfg(windows)]
     #[test]
     fn test_create_instance() {
-        let dll_instance = unsafe { GetModuleHandleW(None) };
-
-        DirectInputManager::new(dll_instance).expect("Failed to initialize manager");
+        DirectInputManager::new(crate::current_module()).expect("Failed to initialize manager");
     }
 
+    #[cfg(windows)]
     #[test]
     fn test_enumeration() {
-        let dll_instance = unsafe { GetModuleHandleW(None) };
-        let manager = DirectInputManager::new(dll_instance).expect("Failed to initialize manager");
+        let manager =
+            DirectInputManager::new(crate::current_module()).expect("Failed to initialize manager");
 
         manager.enum_devices().expect("Failed to enumerate devices");
     }
 
+    #[cfg(windows)]
     #[test]
     fn test_create() {
-        let dll_instance = unsafe { GetModuleHandleW(None) };
-        let manager = DirectInputManager::new(dll_instance).expect("Failed to initialize manager");
+        let manager =
+            DirectInputManager::new(crate::current_module()).expect("Failed to initialize manager");
         let devices = manager.enum_devices().expect("Failed to enumerate devices");
 
         if let Some(device) = devices.first() {
@@ -143,4 +253,479 @@ mod tests {
             eprintln!("state: {:#?}", state);
         }
     }
+
+    #[test]
+    fn test_simulated_state_sequence() {
+        let sim = SimulatedDevice::new("Simulated Pad")
+            .with_axes(2)
+            .with_buttons(4);
+        let mut device = simulated(&sim);
+
+        let caps = device.capabilities().expect("Failed to get capabilities");
+        assert_eq!((caps.axes, caps.buttons, caps.povs), (2, 4, 0));
+
+        device
+            .set_axes_range(-100, 100)
+            .expect("Failed to set axes range");
+        assert_eq!(sim.range(axis_id(0)), Some((-100, 100)));
+        assert_eq!(sim.range(axis_id(1)), Some((-100, 100)));
+
+        assert_eq!(
+            device.get_state::<JoyState>().unwrap_err(),
+            DirectInputError::NotAcquired
+        );
+        device.acquire().expect("Failed to acquire device");
+
+        sim.push_raw(&joystick(1));
+        sim.push_raw(&joystick(2));
+
+        let xs: Vec<i32> = (0..3)
+            .map(|_| device.get_state::<JoyState>().unwrap().x)
+            .collect();
+        assert_eq!(xs, [1, 2, 2]);
+    }
+
+    #[test]
+    fn test_simulated_errors() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_axes(2);
+        let device = simulated(&sim);
+
+        sim.fail_next(SimulatedCall::Acquire, DirectInputError::OtherAppHasPrio);
+        assert_eq!(device.acquire(), Err(DirectInputError::OtherAppHasPrio));
+        device.acquire().expect("Failed to acquire device");
+
+        sim.push_raw(&joystick(1));
+        sim.push_error(DirectInputError::InputLost);
+        assert_eq!(device.get_state::<JoyState>().unwrap().x, 1);
+        assert_eq!(
+            device.get_state::<JoyState>().unwrap_err(),
+            DirectInputError::InputLost
+        );
+        device.acquire().expect("Failed to reacquire device");
+
+        sim.unplug();
+        assert_eq!(device.poll(), Err(DirectInputError::InputLost));
+        assert_eq!(device.poll(), Err(DirectInputError::NotAcquired));
+        assert_eq!(device.acquire(), Err(DirectInputError::Unplugged));
+
+        sim.plug();
+        device
+            .acquire()
+            .expect("Failed to acquire replugged device");
+        assert_eq!(device.get_state::<JoyState>().unwrap().x, 1);
+    }
+
+    #[test]
+    fn test_simulated_wait() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_axes(2);
+        let mut device = simulated(&sim);
+
+        assert_eq!(device.wait(Duration::ZERO), Ok(true));
+
+        device.init_event().expect("Failed to initialize event");
+        assert_eq!(device.wait(Duration::from_millis(1)), Ok(false));
+
+        sim.push_raw(&joystick(5));
+        assert_eq!(device.wait(Duration::from_secs(5)), Ok(true));
+        assert_eq!(device.wait(Duration::from_millis(1)), Ok(false));
+    }
+
+    #[test]
+    fn test_simulated_unknown_device() {
+        let manager = DirectInputManager::with_backend(SimulatedBackend::new());
+        let other = SimulatedBackend::new();
+        other.add_device(SimulatedDevice::new("Elsewhere"));
+
+        let devices = DirectInputManager::with_backend(other)
+            .enum_devices()
+            .expect("Failed to enumerate devices");
+
+        assert!(manager.enum_devices().unwrap().is_empty());
+        assert_eq!(
+            manager.create_device(&devices[0]).err(),
+            Some(DirectInputError::DeviceNotReg)
+        );
+    }
+
+    #[test]
+    fn test_simulated_buffered_events() {
+        let sim = SimulatedDevice::new("Simulated Pad").with_buttons(2);
+        let mut device = simulated(&sim);
+
+        assert_eq!(device.events().err(), Some(DirectInputError::NotBuffered));
+
+        device
+            .set_buffer_size(4)
+            .expect("Failed to set buffer size");
+        device.acquire().expect("Failed to acquire device");
+
+        sim.push_event(48, 0x80);
+        sim.push_event(48, 0x00);
+        sim.push_event(49, 0x80);
+
+        let events: Vec<_> = device.events().expect("Failed to read events").collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.object, event.pressed()))
+                .collect::<Vec<_>>(),
+            [(48, true), (48, false), (49, true)]
+        );
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[0].sequence < pair[1].sequence));
+
+        let events = device.events().expect("Failed to read events");
+        assert_eq!(events.status(), DirectInputStatus::Ok);
+        assert_eq!(events.len(), 0);
+
+        for i in 0..6 {
+            sim.push_event(48, i);
+        }
+
+        let events = device.events().expect("Failed to read events");
+        assert!(events.overflowed());
+        assert_eq!(
+            events.map(|event| event.value).collect::<Vec<_>>(),
+            [2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_simulated_objects() {
+        let sim = SimulatedDevice::new("Simulated Pad")
+            .with_axes(2)
+            .with_povs(1)
+            .with_buttons(3);
+        let device = simulated(&sim);
+
+        let objects = device.objects().expect("Failed to enumerate objects");
+        assert_eq!(objects.len(), 6);
+        assert_eq!(objects[0].name, "X Axis");
+        assert_eq!(objects[0].guid_type, GUID_XAxis);
+        assert_eq!(objects[0].kind(), Some(ObjectKind::Axis));
+        assert_eq!((objects[0].usage_page, objects[0].usage), (0x01, 0x30));
+        assert_eq!(
+            objects[0].hid_usage(),
+            Usage::GenericDesktop(GenericDesktopUsage::X)
+        );
+        assert_eq!(objects[5].hid_usage().to_string(), "Button / Button 3");
+        assert_eq!(objects[1].instance(), 1);
+
+        let buttons = device
+            .objects_of(ObjectKind::Button)
+            .expect("Failed to enumerate objects");
+        assert_eq!(
+            buttons
+                .iter()
+                .map(|object| (
+                    object.name.to_string_lossy().into_owned(),
+                    object.instance()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("Button 0".to_owned(), 0),
+                ("Button 1".to_owned(), 1),
+                ("Button 2".to_owned(), 2)
+            ]
+        );
+        assert!(buttons
+            .iter()
+            .all(|object| object.kind() == Some(ObjectKind::Button) && object.usage_page == 0x09));
+
+        let povs = device
+            .objects_of(ObjectKind::Pov)
+            .expect("Failed to enumerate objects");
+        assert_eq!(povs.len(), 1);
+        assert_eq!(povs[0].name, "Hat Switch 0");
+    }
+
+    #[test]
+    fn test_for_each_device() {
+        let wheel = SimulatedDevice::new("Simulated Wheel");
+        let unplugged = SimulatedDevice::new("Simulated Unplugged");
+        unplugged.unplug();
+
+        let backend = SimulatedBackend::new();
+        backend.add_device(SimulatedDevice::new("Simulated Pad"));
+        backend.add_device(wheel.clone());
+        backend.add_device(unplugged.clone());
+        let manager = DirectInputManager::with_backend(backend);
+        let id = |device: &SimulatedDevice| device.guid_instance().data1;
+
+        // Enumeration stops as soon as the callback breaks
+        let mut visited = 0;
+        manager
+            .for_each_device(&EnumOptions::new(), |_| {
+                visited += 1;
+                ControlFlow::Break(())
+            })
+            .expect("Failed to enumerate devices");
+        assert_eq!(visited, 1);
+
+        let found = manager
+            .find_device(&EnumOptions::new().attached_only(), |info| {
+                info.guid_instance() == wheel.guid_instance().into()
+            })
+            .expect("Failed to enumerate devices");
+        assert_eq!(
+            found.map(|info| info.guid_instance().as_guid().data1),
+            Some(id(&wheel))
+        );
+        assert!(manager
+            .find_device(&EnumOptions::new().attached_only(), |info| {
+                info.guid_instance() == unplugged.guid_instance().into()
+            })
+            .expect("Failed to enumerate devices")
+            .is_none());
+    }
+
+    #[test]
+    fn test_create_device_by_guid() {
+        let pad = SimulatedDevice::new("Simulated Pad").with_axes(2);
+        let backend = SimulatedBackend::new();
+        backend.add_device(pad.clone());
+        let manager = DirectInputManager::with_backend(backend);
+
+        // A GUID saved as a string by an earlier run
+        let saved = manager.enum_devices().unwrap()[0].guid_instance_str();
+        let guid: DeviceGuid = saved.parse().expect("Failed to parse GUID");
+        assert_eq!(guid, pad.guid_instance().into());
+
+        let mut device = manager
+            .create_device_by_guid(guid)
+            .expect("Failed to create device instance");
+        device.init().expect("Failed to initialize device");
+        assert_eq!(device.capabilities().unwrap().axes, 2);
+
+        let unknown: DeviceGuid = "{00000000-0000-0000-0000-000000000000}".parse().unwrap();
+        assert!(matches!(
+            manager.create_device_by_guid(unknown),
+            Err(DirectInputError::DeviceNotReg)
+        ));
+    }
+
+    #[test]
+    fn test_device_status() {
+        let pad = SimulatedDevice::new("Simulated Pad");
+        let backend = SimulatedBackend::new();
+        backend.add_device(pad.clone());
+        let manager = DirectInputManager::with_backend(backend);
+        let guid = pad.guid_instance().into();
+
+        assert_eq!(manager.device_status(guid), Ok(DirectInputStatus::Ok));
+
+        pad.unplug();
+        assert_eq!(
+            manager.device_status(guid),
+            Ok(DirectInputStatus::NotAttached)
+        );
+
+        pad.plug();
+        assert_eq!(manager.device_status(guid), Ok(DirectInputStatus::Ok));
+
+        let unknown = "{00000000-0000-0000-0000-000000000000}".parse().unwrap();
+        assert_eq!(
+            manager.device_status(unknown),
+            Err(DirectInputError::DeviceNotReg)
+        );
+    }
+
+    #[test]
+    fn test_vendor_and_product_ids() {
+        let pad = SimulatedDevice::new("Simulated Pad")
+            .with_vid_pid(0x0079, 0x0006)
+            .with_usage(0x01, 0x05);
+        let other = SimulatedDevice::new("Simulated Device");
+        let backend = SimulatedBackend::new();
+        backend.add_device(pad);
+        backend.add_device(other);
+        let manager = DirectInputManager::with_backend(backend);
+
+        let ids: Vec<_> = manager
+            .enum_devices()
+            .unwrap()
+            .iter()
+            .map(|info| (info.vendor_id(), info.product_id()))
+            .collect();
+        assert_eq!(ids, [(Some(0x0079), Some(0x0006)), (None, None)]);
+
+        let usages: Vec<_> = manager
+            .enum_devices()
+            .unwrap()
+            .iter()
+            .map(|info| info.hid_usage().to_string())
+            .collect();
+        assert_eq!(usages, ["Generic Desktop / Game Pad", "0x0000 / 0x0000"]);
+    }
+
+    #[test]
+    fn test_action_mapping() {
+        const STEER: usize = 1;
+        const ACCELERATE: usize = 2;
+        const SHIFT_UP: usize = 3;
+        const GLANCE: usize = 4;
+        const PAUSE: usize = 5;
+        const GUID_RACING: GUID = GUID::from_values(0x7261_6365, 0, 0, *b"RACING\0\0");
+
+        let pad = SimulatedDevice::new("Simulated Pad")
+            .with_axes(2)
+            .with_buttons(4)
+            .with_povs(1);
+        let keyboard = SimulatedDevice::new("Simulated Keyboard")
+            .with_dev_type(DI8DEVTYPE_KEYBOARD | (DI8DEVTYPEKEYBOARD_PCENH << 8))
+            .with_buttons(64);
+        let mouse = SimulatedDevice::new("Simulated Mouse")
+            .with_dev_type(DI8DEVTYPE_MOUSE | (DI8DEVTYPEMOUSE_TRADITIONAL << 8))
+            .with_buttons(2);
+        let backend = SimulatedBackend::new();
+        backend.add_device(pad.clone());
+        backend.add_device(keyboard);
+        backend.add_device(mouse);
+        let manager = DirectInputManager::with_backend(backend);
+
+        let format = ActionFormat::builder(GUID_RACING, DIVIRTUAL_DRIVING_RACE)
+            .name("Racing")
+            .action(STEER, DIAXIS_DRIVINGR_STEER, "Steer")
+            .action(ACCELERATE, DIAXIS_DRIVINGR_ACCELERATE, "Accelerate")
+            .action(SHIFT_UP, DIBUTTON_DRIVINGR_SHIFTUP, "Shift up")
+            .action(SHIFT_UP, DIKEYBOARD_A, "Shift up")
+            .action(GLANCE, DIHATSWITCH_DRIVINGR_GLANCE, "Glance")
+            .action(PAUSE, DIBUTTON_DRIVINGR_PAUSE, "Pause")
+            .buffer_size(16)
+            .axis_range(-1000, 1000)
+            .build()
+            .expect("Failed to build action format");
+
+        let devices = manager
+            .enum_devices_by_semantics(&format, None, SemanticEnumFlags::empty())
+            .expect("Failed to enumerate devices");
+        let found: Vec<_> = devices
+            .iter()
+            .map(|device| (device.info.guid_instance(), device.flags))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    pad.guid_instance().into(),
+                    SemanticDeviceFlags::MAPPED_PRI1 | SemanticDeviceFlags::MAPPED_PRI2
+                ),
+                (
+                    devices[1].info.guid_instance(),
+                    SemanticDeviceFlags::MAPPED_PRI1
+                ),
+            ]
+        );
+
+        let mut device = manager.create_device(&devices[0].info).unwrap();
+        let axes = device.objects_of(ObjectKind::Axis).unwrap();
+        let buttons = device.objects_of(ObjectKind::Button).unwrap();
+        let povs = device.objects_of(ObjectKind::Pov).unwrap();
+
+        let mut map = ActionMap::new(format);
+        assert_eq!(
+            device.build_action_map(&mut map, None, BuildActionMapFlags::empty()),
+            Ok(DirectInputStatus::Ok)
+        );
+        let mapped: Vec<_> = map
+            .mapped()
+            .map(|(action, mapping)| (action.id, mapping.object_id))
+            .collect();
+        assert_eq!(
+            mapped,
+            [
+                (STEER, axes[0].ty),
+                (ACCELERATE, axes[1].ty),
+                (SHIFT_UP, buttons[0].ty),
+                (GLANCE, povs[0].ty),
+                (PAUSE, buttons[1].ty),
+            ]
+        );
+        assert!(map.mapped().all(|(_, mapping)| mapping.device
+            == Some(pad.guid_instance().into())
+            && mapping.source == MappingSource::DEFAULT));
+
+        // A mapping chosen by the user survives rebuilding with `PRESERVE`
+        map.set_mapping(
+            2,
+            ActionMapping {
+                object_id: buttons[3].ty,
+                ..map.mappings()[2]
+            },
+        );
+        device
+            .build_action_map(&mut map, None, BuildActionMapFlags::PRESERVE)
+            .unwrap();
+        assert_eq!(map.mappings()[2].object_id, buttons[3].ty);
+
+        device
+            .set_action_map(&map, Some("Player 1"), SetActionMapFlags::empty())
+            .expect("Failed to set action map");
+        assert_eq!(pad.range(axes[0].ty), Some((-1000, 1000)));
+
+        device.acquire().unwrap();
+        pad.push_object_event(buttons[3].ty, 0x80);
+        pad.push_object_event(axes[1].ty, 500);
+        pad.push_object_event(buttons[2].ty, 0x80);
+        let events: Vec<_> = device
+            .events()
+            .unwrap()
+            .map(|event| (event.action, event.value))
+            .collect();
+        assert_eq!(events, [(SHIFT_UP, 0x80), (ACCELERATE, 500)]);
+
+        assert_eq!(
+            device.set_action_map(&map, None, SetActionMapFlags::empty()),
+            Err(DirectInputError::Acquired)
+        );
+    }
+
+    #[test]
+    fn test_enum_keyboard_and_mouse() {
+        let keyboard = SimulatedDevice::new("Simulated Keyboard")
+            .with_dev_type(DI8DEVTYPE_KEYBOARD | (DI8DEVTYPEKEYBOARD_PCENH << 8));
+        let mouse = SimulatedDevice::new("Simulated Mouse")
+            .with_dev_type(DI8DEVTYPE_MOUSE | (DI8DEVTYPEMOUSE_TRADITIONAL << 8));
+        let pad = SimulatedDevice::new("Simulated Pad");
+
+        let backend = SimulatedBackend::new();
+        backend.add_device(keyboard);
+        backend.add_device(mouse);
+        backend.add_device(pad);
+
+        let manager = DirectInputManager::with_backend(backend);
+        let count = |class| {
+            manager
+                .enum_devices_by_class(class)
+                .expect("Failed to enumerate devices")
+                .len()
+        };
+        assert_eq!(count(DeviceClass::All), 3);
+        assert_eq!(count(DeviceClass::GameController), 1);
+        assert_eq!(count(DeviceClass::Keyboard), 1);
+        assert_eq!(count(DeviceClass::Pointer), 1);
+        assert_eq!(count(DeviceClass::Device), 0);
+
+        let keyboards = manager
+            .enum_devices_by_class(DeviceClass::Keyboard)
+            .expect("Failed to enumerate devices");
+        let keyboard_device = manager
+            .create_device(&keyboards[0])
+            .expect("Failed to create device instance");
+        let caps = keyboard_device.capabilities().unwrap();
+        assert_eq!(
+            caps.dev_type,
+            DeviceType::Keyboard(KeyboardSubtype::PcEnhanced)
+        );
+        assert_eq!(caps.flags, CapabilityFlags::ATTACHED);
+        assert!(!caps.hid);
+        let pointers = manager.enum_devices_by_class(DeviceClass::Pointer).unwrap();
+        assert_eq!(
+            pointers[0].device_type(),
+            DeviceType::Mouse(MouseSubtype::Traditional)
+        );
+        assert_eq!(pointers[0].device_type().class(), DeviceClass::Pointer);
+    }
 }
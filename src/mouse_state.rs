@@ -0,0 +1,139 @@
+use windows::Win32::Devices::HumanInterfaceDevice::DIMOUSESTATE2;
+
+use crate::data_format::DataFormat;
+use crate::device::FromDeviceState;
+
+/// Mouse state read with the `c_dfDIMouse2` data format. Axes are reported relative to the
+/// previous read.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MouseState {
+    pub x: i32,
+    pub y: i32,
+    /// Wheel movement, in multiples of `WHEEL_DELTA` per notch.
+    pub wheel: i32,
+    pub buttons: [bool; 8],
+}
+
+/// An axis of a [`MouseState`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MouseAxis {
+    X,
+    Y,
+    Wheel,
+}
+
+impl MouseAxis {
+    pub const ALL: [Self; 3] = [Self::X, Self::Y, Self::Wheel];
+
+    pub fn value(self, state: &MouseState) -> i32 {
+        match self {
+            Self::X => state.x,
+            Self::Y => state.y,
+            Self::Wheel => state.wheel,
+        }
+    }
+}
+
+impl FromDeviceState for MouseState {
+    type RawState = DIMOUSESTATE2;
+
+    fn data_format() -> DataFormat {
+        DataFormat::mouse2()
+    }
+
+    fn from_instance(state: Self::RawState) -> Self {
+        let mut buttons = [false; 8];
+        for (button, raw) in buttons.iter_mut().zip(state.rgbButtons.iter()) {
+            *button = raw & 0x80 != 0;
+        }
+
+        Self {
+            x: state.lX,
+            y: state.lY,
+            wheel: state.lZ,
+            buttons,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use windows::Win32::Devices::HumanInterfaceDevice::{
+        DI8DEVTYPEMOUSE_TRADITIONAL, DI8DEVTYPE_MOUSE,
+    };
+
+    use super::*;
+    use crate::backend::sim::SimulatedDevice;
+    use crate::device::Device;
+    use crate::error::DirectInputError;
+    use crate::joy_state::JoyState;
+
+    #[test]
+    fn test_from_instance() {
+        let state = MouseState::from_instance(DIMOUSESTATE2 {
+            lX: -7,
+            lY: 12,
+            lZ: -240,
+            rgbButtons: [0x80, 0x7f, 0, 0xff, 0, 0, 0, 0x81],
+        });
+
+        assert_eq!(
+            state,
+            MouseState {
+                x: -7,
+                y: 12,
+                wheel: -240,
+                buttons: [true, false, false, true, false, false, false, true],
+            }
+        );
+        assert_eq!(
+            MouseAxis::ALL
+                .iter()
+                .map(|axis| axis.value(&state))
+                .collect::<Vec<_>>(),
+            [-7, 12, -240]
+        );
+    }
+
+    #[test]
+    fn test_data_format() {
+        assert_eq!(
+            MouseState::data_format().data_size(),
+            mem::size_of::<DIMOUSESTATE2>() as u32
+        );
+    }
+
+    #[test]
+    fn test_simulated_mouse() {
+        let sim = SimulatedDevice::new("Simulated Mouse")
+            .with_dev_type(DI8DEVTYPE_MOUSE | (DI8DEVTYPEMOUSE_TRADITIONAL << 8));
+        let mut device = Device::with_backend(sim.clone());
+        device.init().expect("Failed to initialize device");
+        device.acquire().expect("Failed to acquire device");
+
+        assert_eq!(
+            device.get_state::<JoyState>().err(),
+            Some(DirectInputError::InvalidParam)
+        );
+        sim.push_raw(&DIMOUSESTATE2 {
+            lX: 5,
+            lY: -3,
+            lZ: 120,
+            rgbButtons: [0x80, 0, 0x80, 0, 0, 0, 0, 0],
+        });
+
+        assert_eq!(
+            device
+                .get_state::<MouseState>()
+                .expect("Failed to get state"),
+            MouseState {
+                x: 5,
+                y: -3,
+                wheel: 120,
+                buttons: [true, false, true, false, false, false, false, false],
+            }
+        );
+    }
+}
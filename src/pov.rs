@@ -0,0 +1,426 @@
+use std::hash::{Hash, Hasher};
+
+/// Position of a POV hat switch, as reported in [`JoyState::pov`](crate::JoyState::pov) and
+/// [`CustomState::povs`](crate::CustomState::povs): hundredths of a degree clockwise from north,
+/// or centered.
+///
+/// Positions compare and hash by [`hundredths`](Self::hundredths), so every centered value is
+/// equal to [`Pov::CENTERED`] and angles past a full turn equal their wrapped angle.
+#[derive(Clone, Copy, Debug)]
+pub struct Pov(pub u32);
+
+/// Direction of a 4-way hat.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction4 {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+/// Direction of an 8-way hat.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction8 {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+/// A POV hat as four virtual buttons. Diagonals press two buttons.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PovButtons {
+    pub up: bool,
+    pub right: bool,
+    pub down: bool,
+    pub left: bool,
+}
+
+const FULL_CIRCLE: u32 = 36_000;
+
+impl Pov {
+    /// The value DirectInput reports for a centered hat.
+    pub const CENTERED: Self = Self(u32::MAX);
+
+    /// Whether the hat is centered. Like DirectInput recommends, only the low word is checked as
+    /// some drivers report `0xFFFF` instead of `u32::MAX`.
+    pub fn is_centered(self) -> bool {
+        self.0 & 0xffff == 0xffff
+    }
+
+    /// The angle in hundredths of a degree in `0..36000`, or `None` if the hat is centered.
+    pub fn hundredths(self) -> Option<u32> {
+        if self.is_centered() {
+            None
+        } else {
+            Some(self.0 % FULL_CIRCLE)
+        }
+    }
+
+    /// The angle in degrees clockwise from north in `[0, 360)`, or `None` if the hat is
+    /// centered.
+    pub fn angle(self) -> Option<f32> {
+        self.hundredths()
+            .map(|hundredths| hundredths as f32 / 100.0)
+    }
+
+    /// The closest of the 4 directions. Each direction covers 45° on each side, angles halfway
+    /// between two directions going to the clockwise one.
+    pub fn direction4(self) -> Option<Direction4> {
+        self.direction4_within(Direction4::FULL_TOLERANCE)
+    }
+
+    /// The closest of the 4 directions if the angle is within `tolerance` degrees of it, to
+    /// ignore diagonals on hats that report them.
+    pub fn direction4_within(self, tolerance: f32) -> Option<Direction4> {
+        let index = self.sector(Direction4::ALL.len() as u32, tolerance)?;
+
+        Some(Direction4::ALL[index])
+    }
+
+    /// The closest of the 8 directions. Each direction covers 22.5° on each side, angles halfway
+    /// between two directions going to the clockwise one.
+    pub fn direction8(self) -> Option<Direction8> {
+        self.direction8_within(Direction8::FULL_TOLERANCE)
+    }
+
+    /// The closest of the 8 directions if the angle is within `tolerance` degrees of it.
+    pub fn direction8_within(self, tolerance: f32) -> Option<Direction8> {
+        let index = self.sector(Direction8::ALL.len() as u32, tolerance)?;
+
+        Some(Direction8::ALL[index])
+    }
+
+    /// The hat as virtual buttons, from its 8-way direction.
+    pub fn buttons(self) -> PovButtons {
+        self.buttons_within(Direction8::FULL_TOLERANCE)
+    }
+
+    /// The hat as virtual buttons if the angle is within `tolerance` degrees of one of the 8
+    /// directions, with no button pressed otherwise.
+    pub fn buttons_within(self, tolerance: f32) -> PovButtons {
+        self.direction8_within(tolerance)
+            .map(Direction8::buttons)
+            .unwrap_or_default()
+    }
+
+    /// Index of the closest of `count` evenly spaced directions, if within `tolerance` degrees.
+    fn sector(self, count: u32, tolerance: f32) -> Option<usize> {
+        let hundredths = self.hundredths()?;
+        let width = FULL_CIRCLE / count;
+
+        let index = (hundredths + width / 2) / width % count;
+        let offset = (hundredths + FULL_CIRCLE - index * width) % FULL_CIRCLE;
+        let distance = offset.min(FULL_CIRCLE - offset);
+
+        if distance as f32 <= tolerance * 100.0 {
+            Some(index as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialEq for Pov {
+    fn eq(&self, other: &Self) -> bool {
+        self.hundredths() == other.hundredths()
+    }
+}
+
+impl Eq for Pov {}
+
+impl Hash for Pov {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hundredths().hash(state);
+    }
+}
+
+impl From<u32> for Pov {
+    fn from(raw: u32) -> Self {
+        Self(raw)
+    }
+}
+
+impl Direction4 {
+    /// Clockwise from north.
+    pub const ALL: [Self; 4] = [Self::Up, Self::Right, Self::Down, Self::Left];
+
+    /// Tolerance covering the whole circle.
+    pub const FULL_TOLERANCE: f32 = 45.0;
+
+    /// The angle of the direction in degrees clockwise from north.
+    pub fn angle(self) -> f32 {
+        match self {
+            Self::Up => 0.0,
+            Self::Right => 90.0,
+            Self::Down => 180.0,
+            Self::Left => 270.0,
+        }
+    }
+
+    pub fn buttons(self) -> PovButtons {
+        let mut buttons = PovButtons::default();
+        buttons.set(self, true);
+        buttons
+    }
+}
+
+impl Direction8 {
+    /// Clockwise from north.
+    pub const ALL: [Self; 8] = [
+        Self::Up,
+        Self::UpRight,
+        Self::Right,
+        Self::DownRight,
+        Self::Down,
+        Self::DownLeft,
+        Self::Left,
+        Self::UpLeft,
+    ];
+
+    /// Tolerance covering the whole circle.
+    pub const FULL_TOLERANCE: f32 = 22.5;
+
+    /// The angle of the direction in degrees clockwise from north.
+    pub fn angle(self) -> f32 {
+        match self {
+            Self::Up => 0.0,
+            Self::UpRight => 45.0,
+            Self::Right => 90.0,
+            Self::DownRight => 135.0,
+            Self::Down => 180.0,
+            Self::DownLeft => 225.0,
+            Self::Left => 270.0,
+            Self::UpLeft => 315.0,
+        }
+    }
+
+    /// The 4-way directions making up this direction, e.g. `Up` and `Right` for `UpRight`.
+    pub fn components(self) -> (Direction4, Option<Direction4>) {
+        match self {
+            Self::Up => (Direction4::Up, None),
+            Self::UpRight => (Direction4::Up, Some(Direction4::Right)),
+            Self::Right => (Direction4::Right, None),
+            Self::DownRight => (Direction4::Down, Some(Direction4::Right)),
+            Self::Down => (Direction4::Down, None),
+            Self::DownLeft => (Direction4::Down, Some(Direction4::Left)),
+            Self::Left => (Direction4::Left, None),
+            Self::UpLeft => (Direction4::Up, Some(Direction4::Left)),
+        }
+    }
+
+    pub fn buttons(self) -> PovButtons {
+        let (first, second) = self.components();
+        let mut buttons = first.buttons();
+
+        if let Some(second) = second {
+            buttons.set(second, true);
+        }
+
+        buttons
+    }
+}
+
+impl From<Direction4> for Direction8 {
+    fn from(direction: Direction4) -> Self {
+        match direction {
+            Direction4::Up => Self::Up,
+            Direction4::Right => Self::Right,
+            Direction4::Down => Self::Down,
+            Direction4::Left => Self::Left,
+        }
+    }
+}
+
+impl PovButtons {
+    pub fn is_pressed(&self, direction: Direction4) -> bool {
+        match direction {
+            Direction4::Up => self.up,
+            Direction4::Right => self.right,
+            Direction4::Down => self.down,
+            Direction4::Left => self.left,
+        }
+    }
+
+    pub fn set(&mut self, direction: Direction4, pressed: bool) {
+        let button = match direction {
+            Direction4::Up => &mut self.up,
+            Direction4::Right => &mut self.right,
+            Direction4::Down => &mut self.down,
+            Direction4::Left => &mut self.left,
+        };
+
+        *button = pressed;
+    }
+
+    /// The buttons in the order up, right, down, left.
+    pub fn to_array(self) -> [bool; 4] {
+        [self.up, self.right, self.down, self.left]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_centered() {
+        for &raw in &[u32::MAX, 0xffff, 0x1234_ffff] {
+            let pov = Pov(raw);
+
+            assert!(pov.is_centered(), "{:#x}", raw);
+            assert_eq!(pov.angle(), None);
+            assert_eq!(pov.direction4(), None);
+            assert_eq!(pov.direction8(), None);
+            assert_eq!(pov.buttons(), PovButtons::default());
+        }
+
+        assert!(!Pov(0).is_centered());
+        assert!(!Pov(0xfffe).is_centered());
+        assert_eq!(Pov::CENTERED, Pov(u32::MAX));
+    }
+
+    #[test]
+    fn test_eq_hash() {
+        use std::collections::HashSet;
+
+        assert_eq!(Pov(0xffff), Pov::CENTERED);
+        assert_eq!(Pov(0x1234_ffff), Pov::CENTERED);
+        assert_eq!(Pov(45000), Pov(9000));
+        assert_ne!(Pov(0), Pov::CENTERED);
+        assert_ne!(Pov(0), Pov(100));
+
+        let povs: HashSet<Pov> = [Pov::CENTERED, Pov(0xffff), Pov(9000), Pov(45000), Pov(0)]
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(povs.len(), 3);
+        assert!(povs.contains(&Pov(0x1234_ffff)));
+    }
+
+    #[test]
+    fn test_angle() {
+        assert_eq!(Pov(0).angle(), Some(0.0));
+        assert_eq!(Pov(4500).angle(), Some(45.0));
+        assert_eq!(Pov(35999).angle(), Some(359.99));
+        assert_eq!(Pov(36000).hundredths(), Some(0));
+        assert_eq!(Pov(45000).hundredths(), Some(9000));
+    }
+
+    #[test]
+    fn test_direction4_boundaries() {
+        let cases = [
+            (0, Direction4::Up),
+            (4499, Direction4::Up),
+            (4500, Direction4::Right),
+            (9000, Direction4::Right),
+            (13499, Direction4::Right),
+            (13500, Direction4::Down),
+            (22499, Direction4::Down),
+            (22500, Direction4::Left),
+            (31499, Direction4::Left),
+            (31500, Direction4::Up),
+            (35999, Direction4::Up),
+        ];
+
+        for &(raw, direction) in &cases {
+            assert_eq!(Pov(raw).direction4(), Some(direction), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_direction8_boundaries() {
+        let cases = [
+            (0, Direction8::Up),
+            (2249, Direction8::Up),
+            (2250, Direction8::UpRight),
+            (6749, Direction8::UpRight),
+            (6750, Direction8::Right),
+            (11250, Direction8::DownRight),
+            (15750, Direction8::Down),
+            (20250, Direction8::DownLeft),
+            (24750, Direction8::Left),
+            (29250, Direction8::UpLeft),
+            (33749, Direction8::UpLeft),
+            (33750, Direction8::Up),
+            (35999, Direction8::Up),
+        ];
+
+        for &(raw, direction) in &cases {
+            assert_eq!(Pov(raw).direction8(), Some(direction), "{}", raw);
+        }
+
+        for &direction in &Direction8::ALL {
+            let raw = (direction.angle() * 100.0) as u32;
+            assert_eq!(Pov(raw).direction8(), Some(direction));
+        }
+    }
+
+    #[test]
+    fn test_tolerance() {
+        // Within 30° of a cardinal direction, diagonals are ignored
+        assert_eq!(Pov(3000).direction4_within(30.0), Some(Direction4::Up));
+        assert_eq!(Pov(3001).direction4_within(30.0), None);
+        assert_eq!(Pov(4500).direction4_within(30.0), None);
+        assert_eq!(Pov(33000).direction4_within(30.0), Some(Direction4::Up));
+        assert_eq!(Pov(32999).direction4_within(30.0), None);
+        assert_eq!(Pov(6000).direction4_within(30.0), Some(Direction4::Right));
+
+        assert_eq!(Pov(4500).direction8_within(0.0), Some(Direction8::UpRight));
+        assert_eq!(Pov(4501).direction8_within(0.0), None);
+        assert_eq!(Pov(4000).direction8_within(5.0), Some(Direction8::UpRight));
+
+        // Tolerances above the full circle do not change the closest direction
+        assert_eq!(Pov(4499).direction4_within(90.0), Some(Direction4::Up));
+    }
+
+    #[test]
+    fn test_buttons() {
+        let cases = [
+            (0, [true, false, false, false]),
+            (4500, [true, true, false, false]),
+            (9000, [false, true, false, false]),
+            (13500, [false, true, true, false]),
+            (18000, [false, false, true, false]),
+            (22500, [false, false, true, true]),
+            (27000, [false, false, false, true]),
+            (31500, [true, false, false, true]),
+        ];
+
+        for &(raw, pressed) in &cases {
+            assert_eq!(Pov(raw).buttons().to_array(), pressed, "{}", raw);
+        }
+
+        let buttons = Direction8::DownLeft.buttons();
+        assert!(buttons.is_pressed(Direction4::Down) && buttons.is_pressed(Direction4::Left));
+        assert!(!buttons.is_pressed(Direction4::Up));
+        assert_eq!(Direction4::Left.buttons(), Direction8::Left.buttons());
+    }
+
+    #[test]
+    fn test_buttons_within() {
+        assert_eq!(
+            Pov(4000).buttons_within(5.0).to_array(),
+            [true, true, false, false]
+        );
+        assert_eq!(Pov(4000).buttons_within(4.0), PovButtons::default());
+        assert_eq!(
+            Pov(1000).buttons_within(10.0).to_array(),
+            [true, false, false, false]
+        );
+        assert_eq!(Pov::CENTERED.buttons_within(90.0), PovButtons::default());
+
+        for raw in (0..FULL_CIRCLE).step_by(250) {
+            assert_eq!(
+                Pov(raw).buttons_within(Direction8::FULL_TOLERANCE),
+                Pov(raw).buttons(),
+                "{}",
+                raw
+            );
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use std::ffi::OsString;
+use std::mem;
+
+use windows::core::GUID;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    DIPH_BYID, DIPH_BYOFFSET, DIPH_BYUSAGE, DIPH_DEVICE, DIPROPAUTOCENTER_OFF, DIPROPAUTOCENTER_ON,
+    DIPROPAXISMODE_ABS, DIPROPAXISMODE_REL, DIPROPCALIBRATIONMODE_COOKED,
+    DIPROPCALIBRATIONMODE_RAW, DIPROPDWORD, DIPROPGUIDANDPATH, DIPROPHEADER, DIPROPRANGE,
+    DIPROPSTRING,
+};
+
+use crate::device_path::DevicePath;
+use crate::util::{copy_to_wide, os_string_from_wide};
+
+/// `DIPROP_*` identifiers are defined with `MAKEDIPROP(n)` in the C headers, i.e. small integers
+/// cast to a GUID pointer, and are not exported by the `windows` crate.
+const fn make_prop(id: usize) -> *const GUID {
+    id as *const GUID
+}
+
+pub(crate) const DIPROP_BUFFERSIZE: *const GUID = make_prop(1);
+pub(crate) const DIPROP_AXISMODE: *const GUID = make_prop(2);
+pub(crate) const DIPROP_GRANULARITY: *const GUID = make_prop(3);
+pub(crate) const DIPROP_RANGE: *const GUID = make_prop(4);
+pub(crate) const DIPROP_DEADZONE: *const GUID = make_prop(5);
+pub(crate) const DIPROP_SATURATION: *const GUID = make_prop(6);
+pub(crate) const DIPROP_FFGAIN: *const GUID = make_prop(7);
+pub(crate) const DIPROP_AUTOCENTER: *const GUID = make_prop(9);
+pub(crate) const DIPROP_CALIBRATIONMODE: *const GUID = make_prop(10);
+pub(crate) const DIPROP_GUIDANDPATH: *const GUID = make_prop(12);
+pub(crate) const DIPROP_INSTANCENAME: *const GUID = make_prop(13);
+pub(crate) const DIPROP_PRODUCTNAME: *const GUID = make_prop(14);
+pub(crate) const DIPROP_JOYSTICKID: *const GUID = make_prop(15);
+pub(crate) const DIPROP_VIDPID: *const GUID = make_prop(24);
+pub(crate) const DIPROP_TYPENAME: *const GUID = make_prop(26);
+
+/// Selects what a property is read from or written to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PropertyTarget {
+    /// The device as a whole. Setting an axis property on the device applies it to every axis.
+    Device,
+    /// The object with the given `DIDFT_*` identifier, e.g. [`DeviceObject::ty`].
+    ///
+    /// [`DeviceObject::ty`]: crate::DeviceObject::ty
+    Id(u32),
+    /// The object at the given offset in the current data format.
+    Offset(u32),
+    /// The object with the given HID usage page and usage.
+    Usage { usage_page: u16, usage: u16 },
+}
+
+/// A `DIPROP_*` property that can be read with [`Device::property`].
+///
+/// Each property is its own type holding the property value, e.g. [`DeadZone`] or [`Range`].
+///
+/// [`Device::property`]: crate::Device::property
+pub trait Property: sealed::Sealed + Sized {
+    #[doc(hidden)]
+    type Raw: sealed::RawProperty;
+
+    #[doc(hidden)]
+    fn id() -> *const GUID;
+
+    #[doc(hidden)]
+    fn from_raw(raw: &Self::Raw) -> Self;
+
+    #[doc(hidden)]
+    fn to_raw(&self, raw: &mut Self::Raw);
+}
+
+/// A property that can also be written with [`Device::set_property`].
+///
+/// [`Device::set_property`]: crate::Device::set_property
+pub trait SettableProperty: Property {}
+
+mod sealed {
+    use windows::Win32::Devices::HumanInterfaceDevice::{
+        DIPROPDWORD, DIPROPGUIDANDPATH, DIPROPHEADER, DIPROPRANGE, DIPROPSTRING,
+    };
+
+    pub trait Sealed {}
+
+    /// A `DIPROP*` structure starting with a `DIPROPHEADER`.
+    pub trait RawProperty: Default {
+        fn header_mut(&mut self) -> &mut DIPROPHEADER;
+    }
+
+    macro_rules! raw_property {
+        ($($raw:ty),*) => {
+            $(
+                impl RawProperty for $raw {
+                    fn header_mut(&mut self) -> &mut DIPROPHEADER {
+                        &mut self.diph
+                    }
+                }
+            )*
+        };
+    }
+
+    raw_property!(DIPROPDWORD, DIPROPRANGE, DIPROPSTRING, DIPROPGUIDANDPATH);
+}
+
+pub(crate) use self::sealed::RawProperty;
+
+impl PropertyTarget {
+    /// Creates a zeroed `DIPROP*` structure with its header filled in for this target.
+    pub(crate) fn new_raw<R: RawProperty>(self) -> R {
+        let (how, obj) = match self {
+            Self::Device => (DIPH_DEVICE, 0),
+            Self::Id(id) => (DIPH_BYID, id),
+            Self::Offset(offset) => (DIPH_BYOFFSET, offset),
+            Self::Usage { usage_page, usage } => {
+                (DIPH_BYUSAGE, (usage_page as u32) << 16 | usage as u32)
+            }
+        };
+
+        let mut raw = R::default();
+        *raw.header_mut() = DIPROPHEADER {
+            dwSize: mem::size_of::<R>() as _,
+            dwHeaderSize: mem::size_of::<DIPROPHEADER>() as _,
+            dwHow: how,
+            dwObj: obj,
+        };
+
+        raw
+    }
+}
+
+macro_rules! dword_property {
+    ($(#[$attr:meta])* $name:ident = $id:ident, settable) => {
+        dword_property!($(#[$attr])* $name = $id);
+
+        impl SettableProperty for $name {}
+    };
+    ($(#[$attr:meta])* $name:ident = $id:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        pub struct $name(pub u32);
+
+        impl sealed::Sealed for $name {}
+
+        impl Property for $name {
+            type Raw = DIPROPDWORD;
+
+            fn id() -> *const GUID {
+                $id
+            }
+
+            fn from_raw(raw: &Self::Raw) -> Self {
+                Self(raw.dwData)
+            }
+
+            fn to_raw(&self, raw: &mut Self::Raw) {
+                raw.dwData = self.0;
+            }
+        }
+    };
+}
+
+macro_rules! string_property {
+    ($(#[$attr:meta])* $name:ident = $id:ident, settable) => {
+        string_property!($(#[$attr])* $name = $id);
+
+        impl SettableProperty for $name {}
+    };
+    ($(#[$attr:meta])* $name:ident = $id:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, Eq, PartialEq)]
+        pub struct $name(pub OsString);
+
+        impl sealed::Sealed for $name {}
+
+        impl Property for $name {
+            type Raw = DIPROPSTRING;
+
+            fn id() -> *const GUID {
+                $id
+            }
+
+            fn from_raw(raw: &Self::Raw) -> Self {
+                Self(os_string_from_wide(&raw.wsz))
+            }
+
+            fn to_raw(&self, raw: &mut Self::Raw) {
+                copy_to_wide(&mut raw.wsz, &self.0.to_string_lossy());
+            }
+        }
+    };
+}
+
+dword_property! {
+    /// `DIPROP_BUFFERSIZE`: number of buffered input records the device keeps. Device only.
+    BufferSize = DIPROP_BUFFERSIZE, settable
+}
+
+dword_property! {
+    /// `DIPROP_GRANULARITY`: smallest distance an axis or wheel reports. Read-only.
+    Granularity = DIPROP_GRANULARITY
+}
+
+dword_property! {
+    /// `DIPROP_DEADZONE`: dead zone of an axis, from 0 (none) to 10,000 (the whole range).
+    DeadZone = DIPROP_DEADZONE, settable
+}
+
+dword_property! {
+    /// `DIPROP_SATURATION`: saturation zone of an axis, from 0 to 10,000 (no saturation).
+    Saturation = DIPROP_SATURATION, settable
+}
+
+dword_property! {
+    /// `DIPROP_FFGAIN`: gain applied to all force feedback effects, from 0 to 10,000. Device
+    /// only.
+    FfGain = DIPROP_FFGAIN, settable
+}
+
+dword_property! {
+    /// `DIPROP_JOYSTICKID`: instance number of the joystick in the control panel. Read-only,
+    /// device only.
+    JoystickId = DIPROP_JOYSTICKID
+}
+
+string_property! {
+    /// `DIPROP_INSTANCENAME`: friendly name of the device instance. Device only.
+    InstanceName = DIPROP_INSTANCENAME, settable
+}
+
+string_property! {
+    /// `DIPROP_PRODUCTNAME`: friendly name of the product. Device only.
+    ProductName = DIPROP_PRODUCTNAME, settable
+}
+
+string_property! {
+    /// `DIPROP_TYPENAME`: name of the joystick type in the registry. Read-only, device only.
+    TypeName = DIPROP_TYPENAME
+}
+
+/// `DIPROP_RANGE`: range of values reported by an axis.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Range {
+    pub min: i32,
+    pub max: i32,
+}
+
+/// `DIPROP_AXISMODE`: whether axes report absolute positions or relative movement. Device only.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AxisMode {
+    Absolute,
+    Relative,
+}
+
+/// `DIPROP_CALIBRATIONMODE`: whether axis values are calibrated (cooked) or raw.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CalibrationMode {
+    Cooked,
+    Raw,
+}
+
+/// `DIPROP_AUTOCENTER`: whether the device centers itself when no force feedback effect is
+/// playing. Device only.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AutoCenter(pub bool);
+
+/// `DIPROP_VIDPID`: USB vendor and product IDs of the device. Read-only, device only.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VidPid {
+    pub vendor_id: u16,
+    pub product_id: u16,
+}
+
+/// `DIPROP_GUIDANDPATH`: device interface class and path that can be passed to `CreateFile`.
+/// Read-only, device only.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GuidAndPath {
+    pub class_guid: GUID,
+    pub path: OsString,
+}
+
+impl GuidAndPath {
+    /// Decodes the vendor and product IDs and other identifiers from the path.
+    pub fn device_path(&self) -> DevicePath {
+        DevicePath::parse(&self.path.to_string_lossy())
+    }
+}
+
+impl sealed::Sealed for Range {}
+
+impl Property for Range {
+    type Raw = DIPROPRANGE;
+
+    fn id() -> *const GUID {
+        DIPROP_RANGE
+    }
+
+    fn from_raw(raw: &Self::Raw) -> Self {
+        Self {
+            min: raw.lMin,
+            max: raw.lMax,
+        }
+    }
+
+    fn to_raw(&self, raw: &mut Self::Raw) {
+        raw.lMin = self.min;
+        raw.lMax = self.max;
+    }
+}
+
+impl SettableProperty for Range {}
+
+impl sealed::Sealed for AxisMode {}
+
+impl Property for AxisMode {
+    type Raw = DIPROPDWORD;
+
+    fn id() -> *const GUID {
+        DIPROP_AXISMODE
+    }
+
+    fn from_raw(raw: &Self::Raw) -> Self {
+        match raw.dwData {
+            DIPROPAXISMODE_REL => Self::Relative,
+            _ => Self::Absolute,
+        }
+    }
+
+    fn to_raw(&self, raw: &mut Self::Raw) {
+        raw.dwData = match self {
+            Self::Absolute => DIPROPAXISMODE_ABS,
+            Self::Relative => DIPROPAXISMODE_REL,
+        };
+    }
+}
+
+impl SettableProperty for AxisMode {}
+
+impl sealed::Sealed for CalibrationMode {}
+
+impl Property for CalibrationMode {
+    type Raw = DIPROPDWORD;
+
+    fn id() -> *const GUID {
+        DIPROP_CALIBRATIONMODE
+    }
+
+    // `Self::Raw` would be ambiguous with the `Raw` variant
+    fn from_raw(raw: &DIPROPDWORD) -> Self {
+        match raw.dwData {
+            DIPROPCALIBRATIONMODE_RAW => Self::Raw,
+            _ => Self::Cooked,
+        }
+    }
+
+    fn to_raw(&self, raw: &mut DIPROPDWORD) {
+        raw.dwData = match self {
+            Self::Cooked => DIPROPCALIBRATIONMODE_COOKED,
+            Self::Raw => DIPROPCALIBRATIONMODE_RAW,
+        };
+    }
+}
+
+impl SettableProperty for CalibrationMode {}
+
+impl sealed::Sealed for AutoCenter {}
+
+impl Property for AutoCenter {
+    type Raw = DIPROPDWORD;
+
+    fn id() -> *const GUID {
+        DIPROP_AUTOCENTER
+    }
+
+    fn from_raw(raw: &Self::Raw) -> Self {
+        Self(raw.dwData != DIPROPAUTOCENTER_OFF)
+    }
+
+    fn to_raw(&self, raw: &mut Self::Raw) {
+        raw.dwData = if self.0 {
+            DIPROPAUTOCENTER_ON
+        } else {
+            DIPROPAUTOCENTER_OFF
+        };
+    }
+}
+
+impl SettableProperty for AutoCenter {}
+
+impl sealed::Sealed for VidPid {}
+
+impl Property for VidPid {
+    type Raw = DIPROPDWORD;
+
+    fn id() -> *const GUID {
+        DIPROP_VIDPID
+    }
+
+    /// The vendor ID is in the low word and the product ID in the high word.
+    fn from_raw(raw: &Self::Raw) -> Self {
+        Self {
+            vendor_id: raw.dwData as u16,
+            product_id: (raw.dwData >> 16) as u16,
+        }
+    }
+
+    fn to_raw(&self, raw: &mut Self::Raw) {
+        raw.dwData = (self.product_id as u32) << 16 | self.vendor_id as u32;
+    }
+}
+
+impl sealed::Sealed for GuidAndPath {}
+
+impl Property for GuidAndPath {
+    type Raw = DIPROPGUIDANDPATH;
+
+    fn id() -> *const GUID {
+        DIPROP_GUIDANDPATH
+    }
+
+    fn from_raw(raw: &Self::Raw) -> Self {
+        Self {
+            class_guid: raw.guidClass,
+            path: os_string_from_wide(&raw.wszPath),
+        }
+    }
+
+    fn to_raw(&self, raw: &mut Self::Raw) {
+        raw.guidClass = self.class_guid;
+        copy_to_wide(&mut raw.wszPath, &self.path.to_string_lossy());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::DIDFT_ABSAXIS;
+
+    use super::*;
+    use crate::backend::sim::SimulatedDevice;
+    use crate::device::Device;
+    use crate::error::DirectInputError;
+
+    fn simulated(sim: &SimulatedDevice) -> Device {
+        let mut device = Device::with_backend(sim.clone());
+        device.init().unwrap();
+        device
+    }
+
+    fn axis_id(instance: u32) -> u32 {
+        DIDFT_ABSAXIS | (instance << 8)
+    }
+
+    #[test]
+    fn test_simulated_properties() {
+        let sim = SimulatedDevice::new("Simulated Pad")
+            .with_vid_pid(0x045e, 0x028e)
+            .with_joystick_id(2)
+            .with_axes(2);
+        let mut device = simulated(&sim);
+
+        let vid_pid: VidPid = device.property(PropertyTarget::Device).unwrap();
+        assert_eq!(
+            vid_pid,
+            VidPid {
+                vendor_id: 0x045e,
+                product_id: 0x028e
+            }
+        );
+        assert_eq!(
+            device.property::<JoystickId>(PropertyTarget::Device),
+            Ok(JoystickId(2))
+        );
+        assert_eq!(
+            device.property::<TypeName>(PropertyTarget::Device),
+            Ok(TypeName("VID_045E&PID_028E".into()))
+        );
+        let guid_and_path: GuidAndPath = device.property(PropertyTarget::Device).unwrap();
+        assert!(guid_and_path
+            .path
+            .to_string_lossy()
+            .starts_with("\\\\?\\hid#vid_045e&pid_028e#"));
+        let path = guid_and_path.device_path();
+        assert_eq!(
+            (path.vendor_id, path.product_id, path.is_xinput()),
+            (Some(0x045e), Some(0x028e), false)
+        );
+
+        // Names round-trip through the device instance
+        device
+            .set_property(PropertyTarget::Device, InstanceName("Player 1".into()))
+            .unwrap();
+        assert_eq!(
+            device.property::<InstanceName>(PropertyTarget::Device),
+            Ok(InstanceName("Player 1".into()))
+        );
+        assert_eq!(
+            device.property::<ProductName>(PropertyTarget::Device),
+            Ok(ProductName("Simulated Pad".into()))
+        );
+
+        // Setting an axis property on the device applies it to every axis
+        device
+            .set_property(PropertyTarget::Device, DeadZone(1_000))
+            .unwrap();
+        device
+            .set_property(PropertyTarget::Offset(4), DeadZone(2_500))
+            .unwrap();
+        device
+            .set_property(
+                PropertyTarget::Usage {
+                    usage_page: 0x01,
+                    usage: 0x30,
+                },
+                Saturation(9_000),
+            )
+            .unwrap();
+        device
+            .set_property(PropertyTarget::Id(axis_id(1)), CalibrationMode::Raw)
+            .unwrap();
+        assert_eq!(
+            device.property::<DeadZone>(PropertyTarget::Id(axis_id(0))),
+            Ok(DeadZone(1_000))
+        );
+        assert_eq!(
+            device.property::<DeadZone>(PropertyTarget::Id(axis_id(1))),
+            Ok(DeadZone(2_500))
+        );
+        assert_eq!(
+            device.property::<Saturation>(PropertyTarget::Offset(0)),
+            Ok(Saturation(9_000))
+        );
+        assert_eq!(
+            device.property::<Saturation>(PropertyTarget::Offset(4)),
+            Ok(Saturation(10_000))
+        );
+        assert_eq!(
+            device.property::<CalibrationMode>(PropertyTarget::Offset(4)),
+            Ok(CalibrationMode::Raw)
+        );
+        assert_eq!(
+            device.property::<Granularity>(PropertyTarget::Offset(0)),
+            Ok(Granularity(1))
+        );
+        assert_eq!(
+            device.property::<DeadZone>(PropertyTarget::Device),
+            Err(DirectInputError::InvalidParam)
+        );
+        assert_eq!(
+            device.property::<DeadZone>(PropertyTarget::Offset(8)),
+            Err(DirectInputError::ObjectNotFound)
+        );
+        assert_eq!(
+            device.set_property(PropertyTarget::Device, DeadZone(10_001)),
+            Err(DirectInputError::InvalidParam)
+        );
+
+        device
+            .set_property(
+                PropertyTarget::Id(axis_id(0)),
+                Range {
+                    min: -100,
+                    max: 100,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            device.property::<Range>(PropertyTarget::Id(axis_id(0))),
+            Ok(Range {
+                min: -100,
+                max: 100
+            })
+        );
+        assert_eq!(sim.range(axis_id(0)), Some((-100, 100)));
+
+        assert_eq!(
+            device.property::<AxisMode>(PropertyTarget::Device),
+            Ok(AxisMode::Absolute)
+        );
+        device
+            .set_property(PropertyTarget::Device, AxisMode::Relative)
+            .unwrap();
+        assert_eq!(
+            device.property::<AxisMode>(PropertyTarget::Device),
+            Ok(AxisMode::Relative)
+        );
+        device
+            .set_property(PropertyTarget::Device, AutoCenter(false))
+            .unwrap();
+        assert_eq!(
+            device.property::<AutoCenter>(PropertyTarget::Device),
+            Ok(AutoCenter(false))
+        );
+
+        device.acquire().unwrap();
+        assert_eq!(
+            device.set_property(PropertyTarget::Device, AutoCenter(true)),
+            Err(DirectInputError::Acquired)
+        );
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use crate::calibration::{JoyAxis, NormalizedAxes};
+
+/// Response curve of an axis, mapping a normalized value to a normalized value.
+///
+/// Curves are defined on `[0, 1]`, go through `(0, 0)` and `(1, 1)` by default, and are mirrored
+/// for negative values, so they apply to both bipolar and unipolar axes. Out of range parameters
+/// are clamped: `exponent` and `steepness` to a small positive minimum and `amount` to `[0, 1]`.
+///
+/// With the `serde` feature curves are (de)serialized as a map tagged with the curve `type`,
+/// e.g. `{"type": "power", "exponent": 2.0}` in JSON.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum ResponseCurve {
+    Linear,
+    /// `x^exponent`. Exponents above 1 reduce the sensitivity around the center.
+    Power {
+        exponent: f32,
+    },
+    /// Blend of `x` and `x^3`, the "expo" setting of RC transmitters. `amount` 0 is linear and 1
+    /// is cubic.
+    Expo {
+        amount: f32,
+    },
+    /// `x^k / (x^k + (1 - x)^k)` with `k = steepness`: less sensitive near the center and the
+    /// ends above 1, the opposite below 1.
+    SCurve {
+        steepness: f32,
+    },
+    /// Straight lines between the points.
+    PiecewiseLinear {
+        points: CurvePoints,
+    },
+    /// Monotone cubic spline (Fritsch-Carlson) through the points, which is smooth and never
+    /// overshoots between them.
+    CubicSpline {
+        points: CurvePoints,
+    },
+}
+
+/// Points of a [`ResponseCurve`], with strictly increasing `x` and non-decreasing `y` in
+/// `[0, 1]`. The curve goes through `(0, 0)`, so that it stays continuous when mirrored for
+/// negative values, and through `(1, 1)` unless a point at `x` = 1 is given.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<(f32, f32)>", into = "Vec<(f32, f32)>")
+)]
+pub struct CurvePoints {
+    points: Vec<(f32, f32)>,
+    /// `points` with the implicit end points.
+    knots: Vec<(f32, f32)>,
+    /// Spline tangent at each knot.
+    tangents: Vec<f32>,
+}
+
+/// Reasons a list of points is not a valid [`CurvePoints`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CurveError {
+    /// A coordinate is outside `[0, 1]` or not a number.
+    OutOfRange { index: usize },
+    /// The point is not to the right of the previous one.
+    NotIncreasing { index: usize },
+    /// The point is below the previous one.
+    Decreasing { index: usize },
+    /// The point is at `x` = 0 but not at `y` = 0, so the mirrored curve would jump at the
+    /// center.
+    OffCenter { index: usize },
+}
+
+/// Response curves of the axes and sliders of a [`JoyState`](crate::JoyState), applied after
+/// normalizing with a [`DeviceCalibration`](crate::DeviceCalibration) and, for sticks, after
+/// the [`DeadZones`](crate::DeadZones). Axes without a curve are linear.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct AxisCurves {
+    curves: BTreeMap<JoyAxis, ResponseCurve>,
+}
+
+const LINEAR: ResponseCurve = ResponseCurve::Linear;
+
+impl ResponseCurve {
+    pub fn piecewise_linear(points: Vec<(f32, f32)>) -> Result<Self, CurveError> {
+        Ok(Self::PiecewiseLinear {
+            points: CurvePoints::new(points)?,
+        })
+    }
+
+    pub fn cubic_spline(points: Vec<(f32, f32)>) -> Result<Self, CurveError> {
+        Ok(Self::CubicSpline {
+            points: CurvePoints::new(points)?,
+        })
+    }
+
+    /// Applies the curve to `value` in `[-1, 1]`, clamping values outside of it.
+    pub fn apply(&self, value: f32) -> f32 {
+        let x = value.abs().min(1.0);
+
+        let y = match self {
+            Self::Linear => x,
+            Self::Power { exponent } => x.powf(exponent.max(f32::EPSILON)),
+            Self::Expo { amount } => {
+                let amount = amount.clamp(0.0, 1.0);
+
+                (1.0 - amount) * x + amount * x * x * x
+            }
+            Self::SCurve { steepness } => {
+                let k = steepness.max(f32::EPSILON);
+                let rising = x.powf(k);
+
+                // Both terms are 0 only for `x` = 0 and 1 together, which cannot happen
+                rising / (rising + (1.0 - x).powf(k))
+            }
+            Self::PiecewiseLinear { points } => points.linear(x),
+            Self::CubicSpline { points } => points.spline(x),
+        };
+
+        y.copysign(value)
+    }
+}
+
+impl CurvePoints {
+    pub fn new(points: Vec<(f32, f32)>) -> Result<Self, CurveError> {
+        for (index, &(x, y)) in points.iter().enumerate() {
+            if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+                return Err(CurveError::OutOfRange { index });
+            }
+            if x == 0.0 && y != 0.0 {
+                return Err(CurveError::OffCenter { index });
+            }
+
+            if let Some(&(prev_x, prev_y)) = index.checked_sub(1).map(|prev| &points[prev]) {
+                if x <= prev_x {
+                    return Err(CurveError::NotIncreasing { index });
+                }
+                if y < prev_y {
+                    return Err(CurveError::Decreasing { index });
+                }
+            }
+        }
+
+        let mut knots = points.clone();
+        if !matches!(knots.first(), Some(&(x, _)) if x == 0.0) {
+            knots.insert(0, (0.0, 0.0));
+        }
+        if !matches!(knots.last(), Some(&(x, _)) if x == 1.0) {
+            knots.push((1.0, 1.0));
+        }
+        let tangents = tangents(&knots);
+
+        Ok(Self {
+            points,
+            knots,
+            tangents,
+        })
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// Index of the knot starting the segment containing `x`.
+    fn segment(&self, x: f32) -> usize {
+        let upper = self.knots.partition_point(|&(knot_x, _)| knot_x <= x);
+
+        upper.saturating_sub(1).min(self.knots.len() - 2)
+    }
+
+    fn linear(&self, x: f32) -> f32 {
+        let k = self.segment(x);
+        let ((x0, y0), (x1, y1)) = (self.knots[k], self.knots[k + 1]);
+
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+
+    fn spline(&self, x: f32) -> f32 {
+        let k = self.segment(x);
+        let ((x0, y0), (x1, y1)) = (self.knots[k], self.knots[k + 1]);
+        let (m0, m1) = (self.tangents[k], self.tangents[k + 1]);
+
+        // Cubic Hermite basis
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+
+        let y = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * m0
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * m1;
+
+        // Rounding can step slightly outside of the segment
+        y.clamp(y0, y1)
+    }
+}
+
+/// Tangents of the monotone cubic interpolation of `knots` with the Fritsch-Carlson method.
+fn tangents(knots: &[(f32, f32)]) -> Vec<f32> {
+    let secants: Vec<f32> = knots
+        .windows(2)
+        .map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0))
+        .collect();
+
+    let mut tangents: Vec<f32> = (0..knots.len())
+        .map(
+            |k| match (k.checked_sub(1).map(|k| secants[k]), secants.get(k)) {
+                (None, Some(&next)) => next,
+                (Some(prev), None) => prev,
+                (Some(prev), Some(&next)) if prev * next > 0.0 => (prev + next) / 2.0,
+                _ => 0.0,
+            },
+        )
+        .collect();
+
+    for (k, &secant) in secants.iter().enumerate() {
+        if secant == 0.0 {
+            tangents[k] = 0.0;
+            tangents[k + 1] = 0.0;
+            continue;
+        }
+
+        let a = tangents[k] / secant;
+        let b = tangents[k + 1] / secant;
+        let norm = a.hypot(b);
+
+        if norm > 3.0 {
+            tangents[k] = 3.0 / norm * a * secant;
+            tangents[k + 1] = 3.0 / norm * b * secant;
+        }
+    }
+
+    tangents
+}
+
+impl TryFrom<Vec<(f32, f32)>> for CurvePoints {
+    type Error = CurveError;
+
+    fn try_from(points: Vec<(f32, f32)>) -> Result<Self, Self::Error> {
+        Self::new(points)
+    }
+}
+
+impl From<CurvePoints> for Vec<(f32, f32)> {
+    fn from(points: CurvePoints) -> Self {
+        points.points
+    }
+}
+
+impl AxisCurves {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, axis: JoyAxis) -> &ResponseCurve {
+        self.curves.get(&axis).unwrap_or(&LINEAR)
+    }
+
+    pub fn set(&mut self, axis: JoyAxis, curve: ResponseCurve) {
+        self.curves.insert(axis, curve);
+    }
+
+    /// Makes `axis` linear again.
+    pub fn reset(&mut self, axis: JoyAxis) {
+        self.curves.remove(&axis);
+    }
+
+    pub fn apply(&self, axes: &NormalizedAxes) -> NormalizedAxes {
+        let curve = |axis, value| self.get(axis).apply(value);
+
+        NormalizedAxes {
+            x: curve(JoyAxis::X, axes.x),
+            y: curve(JoyAxis::Y, axes.y),
+            z: curve(JoyAxis::Z, axes.z),
+            rx: curve(JoyAxis::Rx, axes.rx),
+            ry: curve(JoyAxis::Ry, axes.ry),
+            rz: curve(JoyAxis::Rz, axes.rz),
+            slider: [
+                curve(JoyAxis::Slider0, axes.slider[0]),
+                curve(JoyAxis::Slider1, axes.slider[1]),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfRange { index } => write!(f, "point {} is outside [0, 1]", index),
+            Self::NotIncreasing { index } => {
+                write!(f, "point {} is not right of the previous point", index)
+            }
+            Self::Decreasing { index } => write!(f, "point {} is below the previous point", index),
+            Self::OffCenter { index } => write!(f, "point {} is at x = 0 but not y = 0", index),
+        }
+    }
+}
+
+impl Error for CurveError {}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn curves() -> Vec<ResponseCurve> {
+        vec![
+            ResponseCurve::Linear,
+            ResponseCurve::Power { exponent: 2.5 },
+            ResponseCurve::Power { exponent: 0.5 },
+            ResponseCurve::Expo { amount: 0.7 },
+            ResponseCurve::SCurve { steepness: 3.0 },
+            ResponseCurve::SCurve { steepness: 0.4 },
+            ResponseCurve::piecewise_linear(vec![(0.5, 0.2), (0.9, 0.9)]).unwrap(),
+            ResponseCurve::cubic_spline(vec![(0.2, 0.0), (0.5, 0.2), (0.6, 0.8)]).unwrap(),
+            ResponseCurve::piecewise_linear(vec![(0.0, 0.0), (0.1, 0.5), (1.0, 1.0)]).unwrap(),
+        ]
+    }
+
+    fn curve() -> impl Strategy<Value = ResponseCurve> {
+        let points = prop::collection::vec((0.0f32..=1.0, 0.0f32..=1.0), 0..6).prop_map(|raw| {
+            let mut xs: Vec<f32> = raw.iter().map(|&(x, _)| x).collect();
+            let mut ys: Vec<f32> = raw.iter().map(|&(_, y)| y).collect();
+            xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            xs.dedup();
+            ys.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            // A point at `x` = 0 has to be the center
+            xs.into_iter()
+                .zip(ys)
+                .map(|(x, y)| if x == 0.0 { (x, 0.0) } else { (x, y) })
+                .collect::<Vec<_>>()
+        });
+
+        prop_oneof![
+            (0.1f32..5.0).prop_map(|exponent| ResponseCurve::Power { exponent }),
+            (0.0f32..=1.0).prop_map(|amount| ResponseCurve::Expo { amount }),
+            (0.1f32..5.0).prop_map(|steepness| ResponseCurve::SCurve { steepness }),
+            points
+                .clone()
+                .prop_map(|points| ResponseCurve::piecewise_linear(points).unwrap()),
+            points.prop_map(|points| ResponseCurve::cubic_spline(points).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_endpoints() {
+        for curve in curves() {
+            assert_eq!(curve.apply(0.0), 0.0, "{:?}", curve);
+            // No jump when crossing the center
+            assert!(curve.apply(1e-6).abs() < 1e-2, "{:?}", curve);
+            assert!(curve.apply(-1e-6).abs() < 1e-2, "{:?}", curve);
+            assert_eq!(curve.apply(1.0), 1.0, "{:?}", curve);
+            assert_eq!(curve.apply(-1.0), -1.0, "{:?}", curve);
+            assert_eq!(curve.apply(2.0), 1.0, "{:?}", curve);
+        }
+    }
+
+    #[test]
+    fn test_values() {
+        let close = |curve: &ResponseCurve, x: f32, y: f32| {
+            assert!((curve.apply(x) - y).abs() < 1e-6, "{:?}({})", curve, x);
+        };
+
+        close(&ResponseCurve::Power { exponent: 2.0 }, 0.5, 0.25);
+        close(&ResponseCurve::Power { exponent: 2.0 }, -0.5, -0.25);
+        close(&ResponseCurve::Expo { amount: 1.0 }, 0.5, 0.125);
+        close(&ResponseCurve::Expo { amount: 0.5 }, 0.5, 0.3125);
+        close(&ResponseCurve::SCurve { steepness: 2.0 }, 0.5, 0.5);
+        close(&ResponseCurve::SCurve { steepness: 2.0 }, 0.25, 0.1);
+
+        let points = vec![(0.5, 0.2), (0.9, 0.9)];
+        let linear = ResponseCurve::piecewise_linear(points.clone()).unwrap();
+        close(&linear, 0.25, 0.1);
+        close(&linear, 0.7, 0.55);
+        close(&linear, 0.95, 0.95);
+
+        // The spline goes through every point and stays flat between equal points
+        let spline = ResponseCurve::cubic_spline(vec![(0.2, 0.3), (0.4, 0.3), (0.8, 0.9)]).unwrap();
+        for &(x, y) in &[(0.2, 0.3), (0.3, 0.3), (0.4, 0.3), (0.8, 0.9)] {
+            close(&spline, x, y);
+        }
+
+        // A point at the end replaces the implicit end point, and the curve stays centered
+        let offset = ResponseCurve::piecewise_linear(vec![(0.0, 0.0), (1.0, 0.8)]).unwrap();
+        close(&offset, 0.0, 0.0);
+        close(&offset, 0.5, 0.4);
+        close(&offset, -0.5, -0.4);
+        close(&offset, 1.0, 0.8);
+        close(&offset, -1.0, -0.8);
+        close(&offset, 2.0, 0.8);
+    }
+
+    #[test]
+    fn test_invalid_points() {
+        assert_eq!(
+            CurvePoints::new(vec![(0.5, 1.5)]),
+            Err(CurveError::OutOfRange { index: 0 })
+        );
+        assert_eq!(
+            CurvePoints::new(vec![(0.5, f32::NAN)]),
+            Err(CurveError::OutOfRange { index: 0 })
+        );
+        assert_eq!(
+            CurvePoints::new(vec![(0.5, 0.1), (0.5, 0.2)]),
+            Err(CurveError::NotIncreasing { index: 1 })
+        );
+        assert_eq!(
+            CurvePoints::new(vec![(0.2, 0.5), (0.4, 0.4)]),
+            Err(CurveError::Decreasing { index: 1 })
+        );
+        assert_eq!(
+            CurvePoints::new(vec![(0.0, 0.2), (1.0, 0.8)]),
+            Err(CurveError::OffCenter { index: 0 })
+        );
+        assert_eq!(
+            ResponseCurve::cubic_spline(vec![(0.0, 0.1)]),
+            Err(CurveError::OffCenter { index: 0 })
+        );
+        assert_eq!(CurvePoints::new(Vec::new()).unwrap().points(), []);
+    }
+
+    #[test]
+    fn test_axis_curves() {
+        let mut curves = AxisCurves::new();
+        curves.set(JoyAxis::Y, ResponseCurve::Power { exponent: 2.0 });
+        curves.set(JoyAxis::Slider1, ResponseCurve::Expo { amount: 1.0 });
+
+        let axes = NormalizedAxes {
+            x: 0.5,
+            y: -0.5,
+            slider: [0.5, 0.5],
+            ..NormalizedAxes::default()
+        };
+        let applied = curves.apply(&axes);
+        assert_eq!((applied.x, applied.y), (0.5, -0.25));
+        assert_eq!(applied.slider, [0.5, 0.125]);
+
+        curves.reset(JoyAxis::Y);
+        assert_eq!(curves.get(JoyAxis::Y), &ResponseCurve::Linear);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+        assert_tokens(
+            &ResponseCurve::Power { exponent: 2.0 },
+            &[
+                Token::Struct {
+                    name: "ResponseCurve",
+                    len: 2,
+                },
+                Token::Str("type"),
+                Token::Str("power"),
+                Token::Str("exponent"),
+                Token::F32(2.0),
+                Token::StructEnd,
+            ],
+        );
+        assert_tokens(
+            &ResponseCurve::piecewise_linear(vec![(0.5, 0.25)]).unwrap(),
+            &[
+                Token::Struct {
+                    name: "ResponseCurve",
+                    len: 2,
+                },
+                Token::Str("type"),
+                Token::Str("piecewise_linear"),
+                Token::Str("points"),
+                Token::Seq { len: Some(1) },
+                Token::Tuple { len: 2 },
+                Token::F32(0.5),
+                Token::F32(0.25),
+                Token::TupleEnd,
+                Token::SeqEnd,
+                Token::StructEnd,
+            ],
+        );
+        assert_de_tokens_error::<CurvePoints>(
+            &[
+                Token::Seq { len: Some(1) },
+                Token::Tuple { len: 2 },
+                Token::F32(2.0),
+                Token::F32(0.0),
+                Token::TupleEnd,
+                Token::SeqEnd,
+            ],
+            "point 0 is outside [0, 1]",
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_monotonic(curve in curve(), a in -1.0f32..=1.0, b in -1.0f32..=1.0) {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+            let (low_y, high_y) = (curve.apply(low), curve.apply(high));
+
+            prop_assert!(low_y <= high_y + 1e-6, "{:?}: {} -> {}", curve, low_y, high_y);
+        }
+
+        #[test]
+        fn prop_bounded(curve in curve(), x in -1.0f32..=1.0) {
+            let y = curve.apply(x);
+
+            prop_assert!((-1.0..=1.0).contains(&y));
+            prop_assert!(y == 0.0 || y.signum() == x.signum());
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use crate::calibration::JoyAxis;
+use crate::data_format::CustomState;
+use crate::joy_state::JoyState;
+use crate::keyboard_state::{KeyboardState, ScanCode};
+use crate::mouse_state::{MouseAxis, MouseState};
+use crate::pov::Pov;
+
+/// A device state whose buttons, axes and POV hats can be compared by [`StateDiff`].
+///
+/// Objects are addressed by index so two states can be walked side by side. States with a
+/// variable number of objects, like [`CustomState`], are compared up to the shorter of the two.
+pub trait DiffableState {
+    /// Identifies a button in [`StateEvent::Pressed`] and [`StateEvent::Released`].
+    type Button: Copy;
+    /// Identifies an axis in [`StateEvent::AxisMoved`] and per-axis thresholds.
+    type Axis: Copy + Ord;
+
+    fn button_count(&self) -> usize;
+
+    /// The button at `index` and whether it is pressed, or `None` if the button has no
+    /// identifier and is not reported.
+    fn button(&self, index: usize) -> Option<(Self::Button, bool)>;
+
+    fn axis_count(&self) -> usize;
+
+    /// The axis at `index` and its value.
+    fn axis(&self, index: usize) -> (Self::Axis, i32);
+
+    /// Raw POV values, as in [`JoyState::pov`].
+    fn povs(&self) -> &[u32] {
+        &[]
+    }
+}
+
+/// A change between two states of a device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StateEvent<B, A> {
+    Pressed(B),
+    Released(B),
+    AxisMoved { axis: A, old: i32, new: i32 },
+    PovChanged { index: usize, old: Pov, new: Pov },
+}
+
+/// Turns successive device states into [`StateEvent`]s, to get event semantics from
+/// immediate-mode polling.
+///
+/// An axis is reported once it has moved by at least its threshold, 1 by default. With
+/// [`update`](Self::update), the movement is measured from the last reported value so slow drift
+/// is eventually reported instead of being lost between polls.
+///
+/// ```ignore
+/// let mut diff = StateDiff::new().threshold(JoyAxis::X, 256);
+///
+/// loop {
+///     for event in diff.update(device.get_state::<JoyState>()?) {
+///         println!("{:?}", event);
+///     }
+/// }
+/// ```
+pub struct StateDiff<S: DiffableState> {
+    thresholds: BTreeMap<S::Axis, u32>,
+    default_threshold: u32,
+    previous: Option<S>,
+    reported: Vec<i32>,
+}
+
+impl<S: DiffableState> StateDiff<S> {
+    pub fn new() -> Self {
+        Self {
+            thresholds: BTreeMap::new(),
+            default_threshold: 1,
+            previous: None,
+            reported: Vec::new(),
+        }
+    }
+
+    /// Sets the minimum change of `axis` that is reported. A threshold of 0 behaves like 1.
+    pub fn threshold(mut self, axis: S::Axis, threshold: u32) -> Self {
+        self.thresholds.insert(axis, threshold);
+        self
+    }
+
+    /// Sets the threshold of the axes without their own [`threshold`](Self::threshold).
+    pub fn default_threshold(mut self, threshold: u32) -> Self {
+        self.default_threshold = threshold;
+        self
+    }
+
+    /// The threshold applying to `axis`.
+    pub fn threshold_of(&self, axis: S::Axis) -> u32 {
+        self.thresholds
+            .get(&axis)
+            .copied()
+            .unwrap_or(self.default_threshold)
+    }
+
+    /// The events leading from `old` to `new`: buttons first, then axes, then POV hats, each in
+    /// index order.
+    pub fn diff(&self, old: &S, new: &S) -> Vec<StateEvent<S::Button, S::Axis>> {
+        let mut events = Vec::new();
+
+        self.diff_buttons(old, new, &mut events);
+
+        for index in 0..old.axis_count().min(new.axis_count()) {
+            let (_, old) = old.axis(index);
+            let (axis, new) = new.axis(index);
+
+            if self.moved(axis, old, new) {
+                events.push(StateEvent::AxisMoved { axis, old, new });
+            }
+        }
+
+        diff_povs(old, new, &mut events);
+
+        events
+    }
+
+    /// Compares `state` with the previous one and keeps it for the next call. The first state
+    /// only sets the baseline and yields no events, so buttons held at that point are never
+    /// reported as pressed.
+    pub fn update(&mut self, state: S) -> Vec<StateEvent<S::Button, S::Axis>> {
+        let mut events = Vec::new();
+
+        if let Some(previous) = &self.previous {
+            self.diff_buttons(previous, &state, &mut events);
+
+            let count = self.reported.len().min(state.axis_count());
+            for index in 0..count {
+                let old = self.reported[index];
+                let (axis, new) = state.axis(index);
+
+                if self.moved(axis, old, new) {
+                    events.push(StateEvent::AxisMoved { axis, old, new });
+                    self.reported[index] = new;
+                }
+            }
+
+            diff_povs(previous, &state, &mut events);
+        }
+
+        // Axes seen for the first time start from their current value
+        let known = self.reported.len();
+        self.reported.truncate(state.axis_count());
+        for index in known..state.axis_count() {
+            self.reported.push(state.axis(index).1);
+        }
+
+        self.previous = Some(state);
+        events
+    }
+
+    /// The state passed to the last [`update`](Self::update).
+    pub fn previous(&self) -> Option<&S> {
+        self.previous.as_ref()
+    }
+
+    /// Forgets the previous state, so the next [`update`](Self::update) sets a new baseline.
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.reported.clear();
+    }
+
+    fn diff_buttons(&self, old: &S, new: &S, events: &mut Vec<StateEvent<S::Button, S::Axis>>) {
+        for index in 0..old.button_count().min(new.button_count()) {
+            let (old, (button, new)) = match (old.button(index), new.button(index)) {
+                (Some((_, old)), Some(new)) => (old, new),
+                _ => continue,
+            };
+
+            match (old, new) {
+                (false, true) => events.push(StateEvent::Pressed(button)),
+                (true, false) => events.push(StateEvent::Released(button)),
+                _ => {}
+            }
+        }
+    }
+
+    fn moved(&self, axis: S::Axis, old: i32, new: i32) -> bool {
+        let change = (i64::from(new) - i64::from(old)).unsigned_abs();
+
+        change > 0 && change >= u64::from(self.threshold_of(axis))
+    }
+}
+
+impl<S: DiffableState> Default for StateDiff<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn diff_povs<S: DiffableState>(old: &S, new: &S, events: &mut Vec<StateEvent<S::Button, S::Axis>>) {
+    for (index, (&old, &new)) in old.povs().iter().zip(new.povs()).enumerate() {
+        let (old, new) = (Pov(old), Pov(new));
+
+        if old != new {
+            events.push(StateEvent::PovChanged { index, old, new });
+        }
+    }
+}
+
+impl DiffableState for JoyState {
+    type Button = usize;
+    type Axis = JoyAxis;
+
+    fn button_count(&self) -> usize {
+        self.buttons.len()
+    }
+
+    fn button(&self, index: usize) -> Option<(usize, bool)> {
+        Some((index, self.buttons[index] & 0x80 != 0))
+    }
+
+    fn axis_count(&self) -> usize {
+        JoyAxis::ALL.len()
+    }
+
+    fn axis(&self, index: usize) -> (JoyAxis, i32) {
+        let axis = JoyAxis::ALL[index];
+
+        (axis, axis.value(self))
+    }
+
+    fn povs(&self) -> &[u32] {
+        &self.pov
+    }
+}
+
+impl DiffableState for MouseState {
+    type Button = usize;
+    type Axis = MouseAxis;
+
+    fn button_count(&self) -> usize {
+        self.buttons.len()
+    }
+
+    fn button(&self, index: usize) -> Option<(usize, bool)> {
+        Some((index, self.buttons[index]))
+    }
+
+    fn axis_count(&self) -> usize {
+        MouseAxis::ALL.len()
+    }
+
+    fn axis(&self, index: usize) -> (MouseAxis, i32) {
+        let axis = MouseAxis::ALL[index];
+
+        (axis, axis.value(self))
+    }
+}
+
+impl DiffableState for KeyboardState {
+    type Button = ScanCode;
+    type Axis = usize;
+
+    fn button_count(&self) -> usize {
+        self.raw().len()
+    }
+
+    fn button(&self, index: usize) -> Option<(ScanCode, bool)> {
+        let key = ScanCode::try_from(index as u8).ok()?;
+
+        Some((key, self.is_pressed(key)))
+    }
+
+    fn axis_count(&self) -> usize {
+        0
+    }
+
+    fn axis(&self, index: usize) -> (usize, i32) {
+        panic!("keyboards have no axes, got index {}", index)
+    }
+}
+
+impl DiffableState for CustomState {
+    type Button = usize;
+    type Axis = usize;
+
+    fn button_count(&self) -> usize {
+        self.buttons.len()
+    }
+
+    fn button(&self, index: usize) -> Option<(usize, bool)> {
+        Some((index, self.buttons[index] & 0x80 != 0))
+    }
+
+    fn axis_count(&self) -> usize {
+        self.axes.len()
+    }
+
+    fn axis(&self, index: usize) -> (usize, i32) {
+        (index, self.axes[index])
+    }
+
+    fn povs(&self) -> &[u32] {
+        &self.povs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Devices::HumanInterfaceDevice::DIJOYSTATE2;
+
+    use super::*;
+    use crate::device::FromDeviceState;
+
+    fn joy_state(f: impl FnOnce(&mut DIJOYSTATE2)) -> JoyState {
+        let mut raw = DIJOYSTATE2 {
+            rgdwPOV: [u32::MAX; 4],
+            ..Default::default()
+        };
+        f(&mut raw);
+
+        JoyState::from_instance(raw)
+    }
+
+    #[test]
+    fn test_buttons() {
+        let diff = StateDiff::new();
+        let old = joy_state(|raw| {
+            raw.rgbButtons[0] = 0x80;
+            raw.rgbButtons[3] = 0x80;
+        });
+        let new = joy_state(|raw| {
+            raw.rgbButtons[3] = 0x80;
+            raw.rgbButtons[5] = 0x80;
+            // Only the high bit counts
+            raw.rgbButtons[7] = 0x7f;
+        });
+
+        assert_eq!(
+            diff.diff(&old, &new),
+            [StateEvent::Released(0), StateEvent::Pressed(5)]
+        );
+        assert_eq!(diff.diff(&new, &new), []);
+    }
+
+    #[test]
+    fn test_axis_thresholds() {
+        let diff = StateDiff::new()
+            .default_threshold(100)
+            .threshold(JoyAxis::Y, 10)
+            .threshold(JoyAxis::Z, 0);
+        let old = joy_state(|_| {});
+        let new = joy_state(|raw| {
+            raw.lX = 99;
+            raw.lY = -10;
+            raw.lZ = 1;
+            raw.lRx = 100;
+        });
+
+        assert_eq!(
+            diff.diff(&old, &new),
+            [
+                StateEvent::AxisMoved {
+                    axis: JoyAxis::Y,
+                    old: 0,
+                    new: -10
+                },
+                StateEvent::AxisMoved {
+                    axis: JoyAxis::Z,
+                    old: 0,
+                    new: 1
+                },
+                StateEvent::AxisMoved {
+                    axis: JoyAxis::Rx,
+                    old: 0,
+                    new: 100
+                },
+            ]
+        );
+
+        let extremes = joy_state(|raw| raw.lX = i32::MIN);
+        let full = joy_state(|raw| raw.lX = i32::MAX);
+        assert_eq!(diff.diff(&extremes, &full).len(), 1);
+    }
+
+    #[test]
+    fn test_povs() {
+        let diff = StateDiff::new();
+        let old = joy_state(|raw| raw.rgdwPOV[1] = 9000);
+        let new = joy_state(|raw| {
+            raw.rgdwPOV[0] = 0xffff;
+            raw.rgdwPOV[1] = 18000;
+            raw.rgdwPOV[2] = 0;
+        });
+
+        assert_eq!(
+            diff.diff(&old, &new),
+            [
+                StateEvent::PovChanged {
+                    index: 1,
+                    old: Pov(9000),
+                    new: Pov(18000)
+                },
+                StateEvent::PovChanged {
+                    index: 2,
+                    old: Pov::CENTERED,
+                    new: Pov(0)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_accumulates_drift() {
+        let mut diff = StateDiff::new().threshold(JoyAxis::X, 10);
+
+        assert_eq!(diff.update(joy_state(|raw| raw.rgbButtons[0] = 0x80)), []);
+
+        for x in 1..10 {
+            assert_eq!(
+                diff.update(joy_state(|raw| {
+                    raw.lX = x;
+                    raw.rgbButtons[0] = 0x80;
+                })),
+                []
+            );
+        }
+
+        assert_eq!(
+            diff.update(joy_state(|raw| raw.lX = 10)),
+            [
+                StateEvent::Released(0),
+                StateEvent::AxisMoved {
+                    axis: JoyAxis::X,
+                    old: 0,
+                    new: 10
+                },
+            ]
+        );
+        assert_eq!(diff.update(joy_state(|raw| raw.lX = 15)), []);
+        assert_eq!(diff.previous().map(|state| state.x), Some(15));
+
+        diff.reset();
+        assert_eq!(diff.update(joy_state(|raw| raw.lX = 100)), []);
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let mut old = [0; 256];
+        old[ScanCode::A as usize] = 0x80;
+        // No scan code, never reported
+        old[0x00] = 0x80;
+
+        let mut new = [0; 256];
+        new[ScanCode::Space as usize] = 0x80;
+
+        let old = KeyboardState::from_instance(old);
+        let new = KeyboardState::from_instance(new);
+
+        assert_eq!(
+            StateDiff::new().diff(&old, &new),
+            [
+                StateEvent::Released(ScanCode::A),
+                StateEvent::Pressed(ScanCode::Space)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_state_lengths() {
+        let old = CustomState {
+            axes: vec![0, 0],
+            buttons: vec![0, 0x80],
+            povs: vec![],
+        };
+        let new = CustomState {
+            axes: vec![5],
+            buttons: vec![0x80],
+            povs: vec![0],
+        };
+
+        assert_eq!(
+            StateDiff::new().diff(&old, &new),
+            [
+                StateEvent::Pressed(0),
+                StateEvent::AxisMoved {
+                    axis: 0,
+                    old: 0,
+                    new: 5
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use std::ffi::OsString;
+
+/// Decodes a NUL-terminated UTF-16 buffer embedded in a DirectInput structure.
+pub(crate) fn os_string_from_wide(buf: &[u16]) -> OsString {
+    let end = buf.iter().position(|&ch| ch == 0).unwrap_or(buf.len());
+
+    #[cfg(windows)]
+    {
+        std::os::windows::ffi::OsStringExt::from_wide(&buf[..end])
+    }
+    #[cfg(not(windows))]
+    {
+        OsString::from(String::from_utf16_lossy(&buf[..end]))
+    }
+}
+
+/// Encodes `value` into a fixed-size UTF-16 buffer, truncating it if needed so the buffer is
+/// always NUL-terminated.
+pub(crate) fn copy_to_wide(buf: &mut [u16], value: &str) {
+    let max = buf.len().saturating_sub(1);
+    let mut len = 0;
+
+    for (dst, ch) in buf.iter_mut().zip(value.encode_utf16().take(max)) {
+        *dst = ch;
+        len += 1;
+    }
+    for dst in &mut buf[len..] {
+        *dst = 0;
+    }
+}
+
+/// Encodes `value` as a NUL-terminated UTF-16 string for parameters taking a `PWSTR`.
+pub(crate) fn to_wide_nul(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(Some(0)).collect()
+}